@@ -1,29 +1,121 @@
-use axum::{extract::{ws::{Message, WebSocket}, State, WebSocketUpgrade}, response::IntoResponse, routing::get, Router};
+use axum::{extract::{ws::{Message, WebSocket}, Path, Query, State, WebSocketUpgrade}, http::StatusCode, response::IntoResponse, routing::{get, post, put}, Router};
+use std::{collections::HashMap, path::PathBuf, time::Duration};
+
 use serde::{Deserialize, Serialize};
-use tokio::sync::{broadcast, mpsc, watch};
+use tokio::{sync::{broadcast, mpsc, oneshot, watch}, time::Instant};
+use uuid::Uuid;
+
+use sbsp_backend::{controller::{ControllerCommand, CueHistory, HistoryEntry, ShowState}, diagnostics::{ChannelDepth, ChannelMonitor}, event::UiEvent, executor::ExecutorCommand, input::{InputSource, ReplayGuard}, manager::{ModelCommand, ShowModelHandle, ValidationIssue}, media::{probe_audio_info, AudioInfo}, model::{cue::CueKind, easing::{supported_easings, EasingKind}, timing::CueSheetEntry, ShowModel}};
 
-use crate::{controller::{ControllerCommand, ShowState}, event::UiEvent, manager::{ModelCommand, ShowModelHandle}, model::ShowModel};
+/// ShowStateをクライアントに送信する最小間隔。再生中はProgressごとに状態が変わるため、この間隔でまとめて送信する
+const STATE_SEND_INTERVAL: Duration = Duration::from_millis(50);
+/// 処理を終えたコマンドの応答を、クライアントへの送信まで保持するチャネルの容量
+const ACK_CAPACITY: usize = 32;
 
 #[derive(Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(tag = "type", content = "data", rename_all = "camelCase")]
 enum WsMessage {
-    Event(UiEvent),
+    Event(Box<UiEvent>),
     State(ShowState),
+    FullState(Box<FullShowState>), // 接続直後に送信するショー全体の状態
+    #[serde(rename_all = "camelCase")]
+    Ack {
+        request_id: Option<String>, // クライアントがコマンドに付けたID
+        ok: bool,
+        error: Option<String>,
+    },
 }
 
 #[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(tag = "type", rename_all = "camelCase")]
 enum ApiCommand {
     Controll(ControllerCommand),
     Model(Box<ModelCommand>)
 }
 
+#[derive(Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+struct ApiRequest {
+    #[serde(default)]
+    request_id: Option<String>,
+    #[serde(flatten)]
+    command: ApiCommand,
+}
+
+/// WebSocketの死活監視の設定
+#[derive(Debug, Clone, Copy)]
+pub struct WsHeartbeat {
+    pub interval: Duration, // Pingを送信する間隔
+    pub timeout: Duration,  // Pingを送信してからPongを待つ時間
+}
+
+impl Default for WsHeartbeat {
+    fn default() -> Self {
+        Self { interval: Duration::from_secs(15), timeout: Duration::from_secs(10) }
+    }
+}
+
+/// クライアントからの入力と接続の扱いに関する設定
+#[derive(Clone)]
+pub struct ApiOptions {
+    replay_guard: ReplayGuard,
+    heartbeat: WsHeartbeat,
+    media_root: PathBuf,
+    executor_tx: Option<mpsc::Sender<ExecutorCommand>>,
+}
+
+impl Default for ApiOptions {
+    fn default() -> Self {
+        Self {
+            replay_guard: ReplayGuard::default(),
+            heartbeat: WsHeartbeat::default(),
+            media_root: PathBuf::from("."),
+            executor_tx: None,
+        }
+    }
+}
+
+impl ApiOptions {
+    /// 入力元ごとの再送防止を設定します。
+    pub fn with_replay_guard(mut self, replay_guard: ReplayGuard) -> Self {
+        self.replay_guard = replay_guard;
+        self
+    }
+
+    /// WebSocketの死活監視を設定します。
+    pub fn with_heartbeat(mut self, heartbeat: WsHeartbeat) -> Self {
+        self.heartbeat = heartbeat;
+        self
+    }
+
+    /// 音声ファイルの情報を取得できるディレクトリを設定します。このディレクトリの外のファイルは拒否します。
+    pub fn with_media_root(mut self, media_root: PathBuf) -> Self {
+        self.media_root = media_root;
+        self
+    }
+
+    /// デバッグ用にExecutorへ問い合わせるチャネルを設定します。設定しない場合、デバッグ用のAPIは503を返します。
+    pub fn with_executor(mut self, executor_tx: mpsc::Sender<ExecutorCommand>) -> Self {
+        self.executor_tx = Some(executor_tx);
+        self
+    }
+}
+
 #[derive(Clone)]
 struct ApiState {
     controller_tx: mpsc::Sender<ControllerCommand>,
     state_rx: watch::Receiver<ShowState>,
     event_rx_factory: broadcast::Sender<UiEvent>,
     model_handle: ShowModelHandle,
+    replay_guard: ReplayGuard,
+    channel_monitor: ChannelMonitor,
+    history: CueHistory,
+    heartbeat: WsHeartbeat,
+    media_root: PathBuf,
+    executor_tx: Option<mpsc::Sender<ExecutorCommand>>,
 }
 
 pub async fn create_api_router(
@@ -31,12 +123,21 @@ pub async fn create_api_router(
     state_rx: watch::Receiver<ShowState>,
     event_rx_factory: broadcast::Sender<UiEvent>,
     model_handle: ShowModelHandle,
+    channel_monitor: ChannelMonitor,
+    history: CueHistory,
+    options: ApiOptions,
 ) -> Router {
     let state = ApiState {
         controller_tx,
         state_rx,
         event_rx_factory,
         model_handle,
+        replay_guard: options.replay_guard,
+        channel_monitor,
+        history,
+        heartbeat: options.heartbeat,
+        media_root: options.media_root,
+        executor_tx: options.executor_tx,
     };
 
     Router::new()
@@ -44,28 +145,209 @@ pub async fn create_api_router(
         .route("/ws", get(websocket_handler))
         // 初回接続時にショー全体の状態を取得するエンドポイント
         .route("/api/show/full_state", get(get_full_state_handler))
+        // キューリスト全体の事前チェックを行うエンドポイント
+        .route("/api/show/validate", get(get_validate_handler))
+        // 各キューの開始時刻を計算したキューシートを取得するエンドポイント
+        .route("/api/show/timing_sheet", get(get_timing_sheet_handler))
+        // キューの実行履歴を取得するエンドポイント
+        .route("/api/show/history", get(get_history_handler))
+        // フェードで使用できるイージングの一覧を取得するエンドポイント
+        .route("/api/easings", get(get_easings_handler))
+        // キューに設定する前の音声ファイルのヘッダー情報を取得するエンドポイント
+        .route("/api/audio/probe", get(get_audio_probe_handler))
+        // 名前・番号・メモでキューを検索するエンドポイント
+        .route("/api/cues/search", get(get_search_cues_handler))
+        // 指定したキューを参照しているキューの一覧を取得するエンドポイント
+        .route("/api/cues/{id}/referenced_by", get(get_referenced_by_handler))
+        // 内部チャネルにたまっているメッセージ数を取得するエンドポイント
+        .route("/api/diagnostics/channels", get(get_channel_depths_handler))
+        // 内部チャネルの詰まりを警告するしきい値を変更するエンドポイント
+        .route("/api/diagnostics/channels/{name}/warning_threshold", put(put_warning_threshold_handler))
+        // Executorが再生中とみなしているインスタンスの一覧を取得するエンドポイント
+        .route("/api/debug/active", get(get_debug_active_handler))
+        // 再生カーソルのキューをGOするエンドポイント
+        .route("/api/go", post(post_go_handler))
+        // 指定したキューから再生カーソルを移動してGOするエンドポイント
+        .route("/api/go/{cue_id}", post(post_go_from_cue_handler))
+        // WebSocketと同じControllerCommandを送信するエンドポイント
+        .route("/api/controll", post(post_controll_handler))
         .with_state(state) // ルーター全体で状態を共有
 }
 
 #[derive(Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 struct FullShowState {
     show_model: ShowModel,
     show_state: ShowState,
+    durations: HashMap<Uuid, f64>, // オーディオキューとWaitキューの長さ(秒)。音声ファイルを読み込めないキューは含めない
 }
 
 async fn get_full_state_handler(
     State(state): State<ApiState>,
 ) -> axum::Json<FullShowState> {
+    axum::Json(full_show_state(&state).await)
+}
 
-    let show_model = state.model_handle.read().await.clone();    
+async fn full_show_state(state: &ApiState) -> FullShowState {
+    let show_model = state.model_handle.read().await.clone();
     let show_state = state.state_rx.borrow().clone();
+    let durations = state.model_handle.cue_durations().await;
 
-    let full_state = FullShowState {
+    FullShowState {
         show_model,
         show_state,
+        durations,
+    }
+}
+
+async fn get_validate_handler(
+    State(state): State<ApiState>,
+) -> axum::Json<Vec<ValidationIssue>> {
+    axum::Json(state.model_handle.validate_show().await)
+}
+
+async fn get_timing_sheet_handler(
+    State(state): State<ApiState>,
+) -> axum::Json<Vec<CueSheetEntry>> {
+    axum::Json(state.model_handle.timing_sheet().await)
+}
+
+/// limitを指定しない場合に返す履歴の件数
+const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+#[derive(Deserialize)]
+struct HistoryQuery {
+    limit: Option<usize>,
+}
+
+async fn get_history_handler(
+    State(state): State<ApiState>,
+    Query(query): Query<HistoryQuery>,
+) -> axum::Json<Vec<HistoryEntry>> {
+    axum::Json(state.history.recent(query.limit.unwrap_or(DEFAULT_HISTORY_LIMIT)).await)
+}
+
+async fn get_easings_handler() -> axum::Json<Vec<EasingKind>> {
+    axum::Json(supported_easings())
+}
+
+#[derive(Deserialize)]
+struct ProbeQuery {
+    path: PathBuf, // 相対パスの場合はショーファイルのディレクトリからの位置
+}
+
+async fn get_audio_probe_handler(
+    State(state): State<ApiState>,
+    Query(query): Query<ProbeQuery>,
+) -> Result<axum::Json<AudioInfo>, (StatusCode, String)> {
+    let resolved = state.model_handle.resolve_path(&query.path).await;
+    let path = tokio::fs::canonicalize(&resolved).await.map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => (StatusCode::NOT_FOUND, format!("File not found: {}", query.path.display())),
+        _ => (StatusCode::BAD_REQUEST, format!("Failed to open {}: {}", query.path.display(), e)),
+    })?;
+    let media_root = tokio::fs::canonicalize(&state.media_root).await.map_err(|e| {
+        log::error!("Media root '{}' is unavailable: {:?}", state.media_root.display(), e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Media root is unavailable.".to_string())
+    })?;
+    // シンボリックリンクや".."を解決した上で、メディアのディレクトリの外にあるファイルは読み込まない
+    if !path.starts_with(&media_root) {
+        return Err((StatusCode::FORBIDDEN, format!("{} is outside the media root.", query.path.display())));
+    }
+    match tokio::task::spawn_blocking(move || probe_audio_info(&path)).await {
+        Ok(Ok(info)) => Ok(axum::Json(info)),
+        Ok(Err(e)) => Err((StatusCode::UNPROCESSABLE_ENTITY, format!("{:#}", e))),
+        Err(e) => {
+            log::error!("Audio probe task failed: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to read audio file.".to_string()))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    #[serde(default)]
+    q: String,
+    #[serde(rename = "type")]
+    kind: Option<CueKind>,
+}
+
+async fn get_search_cues_handler(
+    State(state): State<ApiState>,
+    Query(query): Query<SearchQuery>,
+) -> axum::Json<Vec<Uuid>> {
+    axum::Json(state.model_handle.search_cues(&query.q, query.kind).await)
+}
+
+async fn get_referenced_by_handler(
+    State(state): State<ApiState>,
+    Path(cue_id): Path<Uuid>,
+) -> axum::Json<Vec<Uuid>> {
+    axum::Json(state.model_handle.get_cues_referencing(&cue_id).await)
+}
+
+async fn get_channel_depths_handler(
+    State(state): State<ApiState>,
+) -> axum::Json<Vec<ChannelDepth>> {
+    axum::Json(state.channel_monitor.snapshot())
+}
+
+/// Executorが再生中とみなしているインスタンスの一覧(instance_id → cue_id)を返します。
+/// ShowStateとAudioEngineの状態が食い違う場合の調査に使います。
+async fn get_debug_active_handler(
+    State(state): State<ApiState>,
+) -> Result<axum::Json<HashMap<Uuid, Uuid>>, (StatusCode, String)> {
+    let Some(executor_tx) = &state.executor_tx else {
+        return Err((StatusCode::SERVICE_UNAVAILABLE, "Executor is not available.".to_string()));
     };
-    
-    axum::Json(full_state)
+    let (reply_tx, reply_rx) = oneshot::channel();
+    if executor_tx.send(ExecutorCommand::QueryActive { reply: reply_tx }).await.is_err() {
+        return Err((StatusCode::SERVICE_UNAVAILABLE, "Executor is not running.".to_string()));
+    }
+    match reply_rx.await {
+        Ok(active_instances) => Ok(axum::Json(active_instances)),
+        Err(_) => Err((StatusCode::SERVICE_UNAVAILABLE, "Executor is not running.".to_string())),
+    }
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+struct WarningThresholdRequest {
+    threshold: usize,
+}
+
+async fn put_warning_threshold_handler(
+    State(state): State<ApiState>,
+    Path(name): Path<String>,
+    axum::Json(request): axum::Json<WarningThresholdRequest>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    state
+        .channel_monitor
+        .set_warning_threshold(&name, request.threshold)
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(|message| (StatusCode::BAD_REQUEST, message))
+}
+
+async fn post_go_handler(
+    State(state): State<ApiState>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    send_controller_command(&state, InputSource::Rest, ControllerCommand::Go).await?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+async fn post_go_from_cue_handler(
+    State(state): State<ApiState>,
+    Path(cue_id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    send_controller_command(&state, InputSource::Rest, ControllerCommand::GoFromCue { cue_id }).await?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+async fn post_controll_handler(
+    State(state): State<ApiState>,
+    axum::Json(command): axum::Json<ControllerCommand>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    send_controller_command(&state, InputSource::Rest, command).await?;
+    Ok(StatusCode::ACCEPTED)
 }
 
 async fn websocket_handler(
@@ -78,53 +360,103 @@ async fn websocket_handler(
 async fn handle_socket(mut socket: WebSocket, state: ApiState) {
     let mut state_rx = state.state_rx.clone();
     let mut event_rx = state.event_rx_factory.subscribe();
+    // 前回の送信から間隔が空いていない状態の変更は、次の送信時刻までまとめる
+    let mut next_state_send = Instant::now();
+    let mut state_pending = false;
+    // プロキシに切断されないよう定期的にPingを送り、Pongが届かないクライアントは切断する
+    let mut ping_timer = tokio::time::interval_at(Instant::now() + state.heartbeat.interval, state.heartbeat.interval);
+    let mut pong_deadline: Option<Instant> = None;
+    // 時間のかかるコマンドの処理中も配信を止めないよう、コマンドは別のタスクで処理し、応答だけをここで送信する
+    let (ack_tx, mut ack_rx) = mpsc::channel::<WsMessage>(ACK_CAPACITY);
 
     log::info!("New WebSocket client connected.");
 
+    // ショーの途中で接続した場合でも再生中のキューを表示できるよう、最初に現在の状態を送信する
+    state_rx.mark_unchanged();
+    let ws_message = WsMessage::FullState(Box::new(full_show_state(&state).await));
+    if let Ok(payload) = serde_json::to_string(&ws_message)
+        && socket.send(Message::Text(payload.into())).await.is_err()
+    {
+        log::info!("WebSocket client disconnected (send error).");
+        return;
+    }
+
     loop {
         tokio::select! {
-            Ok(event) = event_rx.recv() => {
-                let ws_message = WsMessage::Event(event);
-
-                if let Ok(payload) = serde_json::to_string(&ws_message) {
-                    if socket.send(Message::Text(payload.into())).await.is_err() {
-                        log::info!("WebSocket client disconnected (send error).");
-                        break;
-                    }
+            Some(ws_message) = next_event_message(&state, &mut event_rx) => {
+                if let Ok(payload) = serde_json::to_string(&ws_message)
+                    && socket.send(Message::Text(payload.into())).await.is_err()
+                {
+                    log::info!("WebSocket client disconnected (send error).");
+                    break;
                 }
             }
-            Ok(_) = state_rx.changed() => {
-                let new_state = state_rx.borrow().clone();
+            Ok(_) = state_rx.changed(), if !state_pending => {
+                state_pending = true;
+            }
+            _ = tokio::time::sleep_until(next_state_send), if state_pending => {
+                state_pending = false;
+                next_state_send = Instant::now() + STATE_SEND_INTERVAL;
+                let new_state = state_rx.borrow_and_update().clone();
                 let ws_message = WsMessage::State(new_state);
                 
-                if let Ok(payload) = serde_json::to_string(&ws_message) {
-                    if socket.send(Message::Text(payload.into())).await.is_err() {
-                        log::info!("WebSocket client disconnected (send error).");
-                        break;
-                    }
+                if let Ok(payload) = serde_json::to_string(&ws_message)
+                    && socket.send(Message::Text(payload.into())).await.is_err()
+                {
+                    log::info!("WebSocket client disconnected (send error).");
+                    break;
                 }
             }
             
+            _ = ping_timer.tick() => {
+                if socket.send(Message::Ping(Default::default())).await.is_err() {
+                    log::info!("WebSocket client disconnected (send error).");
+                    break;
+                }
+                pong_deadline.get_or_insert(Instant::now() + state.heartbeat.timeout);
+            }
+            _ = async { tokio::time::sleep_until(pong_deadline.unwrap()).await }, if pong_deadline.is_some() => {
+                log::info!("WebSocket client disconnected (pong timeout).");
+                break;
+            }
+            Some(ws_message) = ack_rx.recv() => {
+                if let Ok(payload) = serde_json::to_string(&ws_message)
+                    && socket.send(Message::Text(payload.into())).await.is_err()
+                {
+                    log::info!("WebSocket client disconnected (send error).");
+                    break;
+                }
+            }
+
             Some(Ok(msg)) = socket.recv() => {
+                // クライアントから届いたメッセージは、Pongに限らず接続が生きている証拠として扱う
+                pong_deadline = None;
                 if let Message::Text(text) = msg {
-                    if let Ok(command_request) = serde_json::from_str::<ApiCommand>(&text) {
-                        match command_request {
-                            ApiCommand::Controll(controller_command) => {
-                                if state.controller_tx.send(controller_command).await.is_err() {
-                                    log::error!("Failed to send Go command to CueController.");
-                                    break;
-                                }
-                            },
-                            ApiCommand::Model(model_command) => {
-                                if state.model_handle.send_command(*model_command).await.is_err() {
-                                    log::error!("Failed to send Model command to ShowModelManager.");
-                                    break;
-                                }
-                            },
+                    let request = match serde_json::from_str::<ApiRequest>(&text) {
+                        Ok(request) => request,
+                        Err(e) => {
+                            log::error!("Invalid command received: {}", e);
+                            // 解析できないコマンドでも、requestIdが読み取れれば応答に含める
+                            let request_id = serde_json::from_str::<serde_json::Value>(&text)
+                                .ok()
+                                .and_then(|value| value.get("requestId")?.as_str().map(str::to_string));
+                            let ws_message = ack_message(request_id, Err(anyhow::anyhow!("Invalid command: {}", e)));
+                            if let Ok(payload) = serde_json::to_string(&ws_message)
+                                && socket.send(Message::Text(payload.into())).await.is_err()
+                            {
+                                log::info!("WebSocket client disconnected (send error).");
+                                break;
+                            }
+                            continue;
                         }
-                    } else {
-                        log::error!("Invalid command received.")
-                    }
+                    };
+                    let state = state.clone();
+                    let ack_tx = ack_tx.clone();
+                    tokio::spawn(async move {
+                        let result = handle_api_command(&state, request.command).await;
+                        // 応答を送る前にクライアントが切断した場合は、応答を捨てる
+                        let _ = ack_tx.send(ack_message(request.request_id, result)).await;
+                    });
                 } else if let Message::Close(_) = msg {
                     log::info!("WebSocket client sent close message.");
                     break;
@@ -135,3 +467,469 @@ async fn handle_socket(mut socket: WebSocket, state: ApiState) {
         }
     }
 }
+
+/// コマンドの処理結果をクライアントへの応答に変換します。
+fn ack_message(request_id: Option<String>, result: anyhow::Result<()>) -> WsMessage {
+    WsMessage::Ack {
+        request_id,
+        ok: result.is_ok(),
+        error: result.err().map(|e| format!("{:#}", e)),
+    }
+}
+
+/// クライアントに送信する次のイベントを受信します。チャネルが閉じた場合はNoneを返します。
+///
+/// 送信が遅いクライアントはブロードキャストチャネル上で自分の分だけ古いイベントを取りこぼし、
+/// 他のクライアントには影響しません。取りこぼした場合は、ショー全体の状態を送り直して同期させます。
+async fn next_event_message(state: &ApiState, event_rx: &mut broadcast::Receiver<UiEvent>) -> Option<WsMessage> {
+    match event_rx.recv().await {
+        Ok(event) => Some(WsMessage::Event(Box::new(event))),
+        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+            log::warn!("WebSocket client is too slow. Skipped {} events and resyncing.", skipped);
+            Some(WsMessage::FullState(Box::new(full_show_state(state).await)))
+        }
+        Err(broadcast::error::RecvError::Closed) => None,
+    }
+}
+
+/// WebSocketで受信したコマンドを処理します。
+async fn handle_api_command(state: &ApiState, command: ApiCommand) -> anyhow::Result<()> {
+    match command {
+        ApiCommand::Controll(controller_command) => {
+            send_controller_command(state, InputSource::WebSocket, controller_command)
+                .await
+                .map_err(|(_, message)| anyhow::anyhow!(message))?;
+        }
+        ApiCommand::Model(model_command) => {
+            state.model_handle.execute_command(*model_command).await?;
+        }
+    }
+    Ok(())
+}
+
+/// 対象のキューの存在と再送を確認し、CueControllerにコマンドを送信します。
+/// WebSocketとRESTの両方から使用します。
+async fn send_controller_command(
+    state: &ApiState,
+    source: InputSource,
+    command: ControllerCommand,
+) -> Result<(), (StatusCode, String)> {
+    for cue_id in command.cue_ids() {
+        if state.model_handle.get_cue_by_id(&cue_id).await.is_none() {
+            return Err((StatusCode::NOT_FOUND, format!("Cue '{}' not found.", cue_id)));
+        }
+    }
+    if !state.replay_guard.check(source, &command) {
+        return Err((StatusCode::CONFLICT, "Ignored replayed trigger.".to_string()));
+    }
+    state.controller_tx.send(command).await.map_err(|_| {
+        log::error!("Failed to send command to CueController.");
+        (StatusCode::SERVICE_UNAVAILABLE, "CueController is not running.".to_string())
+    })
+}
+
+/// WebSocketとREST APIで送受信する型のJSON Schemaを`dir`に書き出します。
+#[cfg(feature = "schema")]
+pub fn export_schema(dir: &std::path::Path) -> anyhow::Result<()> {
+    use sbsp_backend::schema::write_schema;
+
+    std::fs::create_dir_all(dir)?;
+    // WebSocket
+    write_schema::<WsMessage>(dir, "ws_message")?;
+    write_schema::<ApiRequest>(dir, "api_request")?;
+    // REST API
+    write_schema::<FullShowState>(dir, "full_state")?;
+    write_schema::<Vec<ValidationIssue>>(dir, "validate")?;
+    write_schema::<Vec<CueSheetEntry>>(dir, "timing_sheet")?;
+    write_schema::<Vec<HistoryEntry>>(dir, "history")?;
+    write_schema::<Vec<EasingKind>>(dir, "easings")?;
+    write_schema::<AudioInfo>(dir, "audio_probe")?;
+    write_schema::<Vec<ChannelDepth>>(dir, "channels")?;
+    write_schema::<WarningThresholdRequest>(dir, "warning_threshold")?;
+    write_schema::<HashMap<Uuid, Uuid>>(dir, "debug_active")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request};
+    use sbsp_backend::{controller::CueController, diagnostics::ChannelMonitor, manager::ShowModelManager, model::cue::{Cue, CueParam, CueSequence, RetriggerMode}};
+    use tokio_util::sync::CancellationToken;
+    use tower::ServiceExt;
+
+    use crate::test_util::write_wav_samples;
+
+    use super::*;
+
+    /// 指定したキューを持つショーでルーターを生成し、CueControllerが受け取るチャネルと共に返します。
+    async fn setup_router(cues: Vec<Cue>) -> (Router, mpsc::Receiver<ControllerCommand>) {
+        setup_router_with_options(cues, ApiOptions::default()).await
+    }
+
+    async fn setup_router_with_options(cues: Vec<Cue>, options: ApiOptions) -> (Router, mpsc::Receiver<ControllerCommand>) {
+        let (controller_tx, controller_rx) = mpsc::channel::<ControllerCommand>(32);
+        let (_state_tx, state_rx) = watch::channel(ShowState::new());
+        let (event_tx, _) = broadcast::channel::<UiEvent>(32);
+        let (manager, model_handle) = ShowModelManager::new(event_tx.clone());
+        manager.write_with(|model| model.cues = cues).await;
+        let router = create_api_router(
+            controller_tx,
+            state_rx,
+            event_tx,
+            model_handle,
+            ChannelMonitor::new(),
+            CueHistory::default(),
+            options,
+        )
+        .await;
+        (router, controller_rx)
+    }
+
+    fn make_wait_cue(cue_id: Uuid) -> Cue {
+        Cue {
+            id: cue_id,
+            number: "1".to_string(),
+            name: "Wait".to_string(),
+            notes: "".to_string(),
+            pre_wait: 0.0,
+            post_wait: 0.0,
+            sequence: CueSequence::DoNotContinue,
+            follow_offset: None,
+            armed: true,
+            retrigger_mode: RetriggerMode::Restart,
+            color: None,
+            order: 0.0,
+            schedule: None,
+            param: CueParam::Wait { duration: 1.0 },
+        }
+    }
+
+    fn post(uri: &str) -> Request<Body> {
+        Request::post(uri).body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn rest_go() {
+        let (router, mut controller_rx) = setup_router(vec![]).await;
+
+        let response = router.oneshot(post("/api/go")).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        assert!(matches!(controller_rx.try_recv(), Ok(ControllerCommand::Go)));
+    }
+
+    #[tokio::test]
+    async fn rest_go_on_empty_show() {
+        let (controller_tx, controller_rx) = mpsc::channel::<ControllerCommand>(32);
+        let (exec_tx, _exec_rx) = mpsc::channel::<ExecutorCommand>(32);
+        let (_executor_event_tx, executor_event_rx) = mpsc::channel(32);
+        let (state_tx, mut state_rx) = watch::channel(ShowState::new());
+        let (event_tx, _) = broadcast::channel::<UiEvent>(32);
+        let (_manager, model_handle) = ShowModelManager::new(event_tx.clone());
+        let controller = CueController::new(model_handle.clone(), exec_tx, controller_rx, executor_event_rx, state_tx, event_tx.clone()).await;
+        let controller_task = tokio::spawn(controller.run(CancellationToken::new()));
+        let router = create_api_router(
+            controller_tx.clone(),
+            state_rx.clone(),
+            event_tx,
+            model_handle,
+            ChannelMonitor::new(),
+            CueHistory::default(),
+            ApiOptions::default(),
+        )
+        .await;
+
+        // 再生カーソルがない状態のGOは無視され、CueControllerは後続のコマンドを処理し続ける
+        let response = router.oneshot(post("/api/go")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        controller_tx.send(ControllerCommand::SetPreviewMode { enabled: true }).await.unwrap();
+        state_rx.wait_for(|state| state.preview).await.unwrap();
+        assert!(!controller_task.is_finished());
+    }
+
+    #[tokio::test]
+    async fn rest_go_from_cue() {
+        let cue_id = Uuid::new_v4();
+        let (router, mut controller_rx) = setup_router(vec![make_wait_cue(cue_id)]).await;
+
+        let response = router.clone().oneshot(post(&format!("/api/go/{}", cue_id))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        assert!(matches!(controller_rx.try_recv(), Ok(ControllerCommand::GoFromCue { cue_id: sent_id }) if sent_id == cue_id));
+
+        let response = router.oneshot(post(&format!("/api/go/{}", Uuid::new_v4()))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(controller_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn rest_controll() {
+        let cue_id = Uuid::new_v4();
+        let (router, mut controller_rx) = setup_router(vec![make_wait_cue(cue_id)]).await;
+        let json_post = |body: String| {
+            Request::post("/api/controll")
+                .header("content-type", "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let body = serde_json::to_string(&ControllerCommand::GoFromCue { cue_id }).unwrap();
+        let response = router.clone().oneshot(json_post(body)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        assert!(matches!(controller_rx.try_recv(), Ok(ControllerCommand::GoFromCue { cue_id: sent_id }) if sent_id == cue_id));
+
+        let body = serde_json::to_string(&ControllerCommand::GoFromCue { cue_id: Uuid::new_v4() }).unwrap();
+        let response = router.clone().oneshot(json_post(body)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = router.oneshot(json_post("{\"command\":\"unknown\"}".to_string())).await.unwrap();
+        assert!(response.status().is_client_error());
+        assert!(controller_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn search_cues() {
+        let wait_id = Uuid::new_v4();
+        let mut audio_cue = make_wait_cue(Uuid::new_v4());
+        audio_cue.number = "2".to_string();
+        audio_cue.name = "Opening".to_string();
+        audio_cue.notes = "Start after the WAIT cue".to_string();
+        audio_cue.param = CueParam::Audio {
+            target: "opening.wav".into(),
+            start_time: None,
+            fade_in_param: None,
+            end_time: None,
+            fade_out_param: None,
+            levels: sbsp_backend::model::cue::AudioCueLevels { master: 0.0 },
+            loop_region: None,
+            loop_count: None,
+            stop_fade_param: None,
+            output_bus: None,
+            streaming: false,
+            normalize_to: None,
+            automation: None,
+        };
+        let (router, _) = setup_router(vec![make_wait_cue(wait_id), audio_cue.clone()]).await;
+        let search = |uri: &str| {
+            let router = router.clone();
+            let request = Request::get(uri).body(Body::empty()).unwrap();
+            async move {
+                let response = router.oneshot(request).await.unwrap();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                serde_json::from_slice::<Vec<Uuid>>(&body).unwrap()
+            }
+        };
+
+        // 名前・メモを大文字小文字を区別せずに検索する
+        assert_eq!(search("/api/cues/search?q=wAiT").await, vec![wait_id, audio_cue.id]);
+        assert_eq!(search("/api/cues/search?q=wait&type=audio").await, vec![audio_cue.id]);
+        assert_eq!(search("/api/cues/search?q=2").await, vec![audio_cue.id]);
+        assert_eq!(search("/api/cues/search?type=wait").await, vec![wait_id]);
+
+        let request = Request::get("/api/cues/search?type=video").body(Body::empty()).unwrap();
+        assert_eq!(router.oneshot(request).await.unwrap().status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn full_state_durations() {
+        let wait_id = Uuid::new_v4();
+        let mut missing_cue = make_wait_cue(Uuid::new_v4());
+        missing_cue.param = CueParam::Audio {
+            target: "missing.wav".into(),
+            start_time: None,
+            fade_in_param: None,
+            end_time: None,
+            fade_out_param: None,
+            levels: sbsp_backend::model::cue::AudioCueLevels { master: 0.0 },
+            loop_region: None,
+            loop_count: None,
+            stop_fade_param: None,
+            output_bus: None,
+            streaming: false,
+            normalize_to: None,
+            automation: None,
+        };
+        let (router, _) = setup_router(vec![make_wait_cue(wait_id), missing_cue]).await;
+
+        let response = router.oneshot(Request::get("/api/show/full_state").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let full_state: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        // 読み込めない音声ファイルのキューは含めない
+        assert_eq!(full_state["durations"], serde_json::json!({ wait_id.to_string(): 1.0 }));
+    }
+
+    #[tokio::test]
+    async fn slow_client_does_not_stall_others() {
+        let (controller_tx, _controller_rx) = mpsc::channel::<ControllerCommand>(32);
+        let (_state_tx, state_rx) = watch::channel(ShowState::new());
+        let (event_tx, _) = broadcast::channel::<UiEvent>(4);
+        let (_, model_handle) = ShowModelManager::new(event_tx.clone());
+        let state = ApiState {
+            controller_tx,
+            state_rx,
+            event_rx_factory: event_tx.clone(),
+            model_handle,
+            replay_guard: ReplayGuard::new(),
+            channel_monitor: ChannelMonitor::new(),
+            history: CueHistory::default(),
+            heartbeat: WsHeartbeat::default(),
+            media_root: PathBuf::from("."),
+            executor_tx: None,
+        };
+        let mut fast_rx = event_tx.subscribe();
+        let mut slow_rx = event_tx.subscribe();
+
+        let cue_ids: Vec<Uuid> = (0..10).map(|_| Uuid::new_v4()).collect();
+        for sent_id in &cue_ids {
+            event_tx.send(UiEvent::CueStarted { cue_id: *sent_id }).unwrap();
+            let message = next_event_message(&state, &mut fast_rx).await;
+            assert!(matches!(message, Some(WsMessage::Event(event)) if matches!(*event, UiEvent::CueStarted { cue_id } if cue_id == *sent_id)));
+        }
+
+        // 遅いクライアントは状態を送り直した上で、残っている最新のイベントから受信を続ける
+        assert!(matches!(next_event_message(&state, &mut slow_rx).await, Some(WsMessage::FullState(_))));
+        let message = next_event_message(&state, &mut slow_rx).await;
+        assert!(matches!(message, Some(WsMessage::Event(event)) if matches!(*event, UiEvent::CueStarted { cue_id } if cue_id == cue_ids[6])));
+    }
+
+    #[tokio::test]
+    async fn audio_probe() {
+        let media_root = std::env::temp_dir().join(format!("sbsp_media_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&media_root).unwrap();
+        // 8000Hz・モノラル・16bitで4000フレームのWAVファイル
+        let wav_path = media_root.join("tone.wav");
+        write_wav_samples(&wav_path, 8000, &[0; 4000]);
+        let (router, _) = setup_router_with_options(vec![], ApiOptions::default().with_media_root(media_root.clone())).await;
+        let probe = |path: &std::path::Path| {
+            let router = router.clone();
+            let uri = format!("/api/audio/probe?path={}", path.display());
+            async move { router.oneshot(Request::get(uri).body(Body::empty()).unwrap()).await.unwrap() }
+        };
+
+        let response = probe(&wav_path).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let info: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(info, serde_json::json!({ "duration": 0.5, "sampleRate": 8000, "channels": 1, "format": "pcm_s16le" }));
+
+        assert_eq!(probe(&media_root.join("missing.wav")).await.status(), StatusCode::NOT_FOUND);
+        assert_eq!(probe(&media_root.join("../../etc/passwd")).await.status(), StatusCode::FORBIDDEN);
+        std::fs::remove_dir_all(media_root).unwrap();
+    }
+
+    #[cfg(feature = "schema")]
+    #[test]
+    fn schema_is_up_to_date() {
+        let dir = std::env::temp_dir().join(format!("sbsp_schema_{}", Uuid::new_v4()));
+        export_schema(&dir).unwrap();
+
+        // スキーマを更新する場合は`cargo run --features schema -- --export-schema`を実行する
+        let committed_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("schema");
+        for entry in std::fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            let committed = std::fs::read_to_string(committed_dir.join(path.file_name().unwrap())).unwrap_or_default();
+            assert_eq!(std::fs::read_to_string(&path).unwrap(), committed, "{} is out of date.", path.display());
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn websocket_heartbeat() {
+        use futures_util::StreamExt;
+        use tokio_tungstenite::tungstenite;
+
+        let heartbeat = WsHeartbeat { interval: Duration::from_millis(30), timeout: Duration::from_millis(60) };
+        let (router, _) = setup_router_with_options(vec![], ApiOptions::default().with_heartbeat(heartbeat)).await;
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/ws", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        // 受信を続けるクライアントはPongを自動で返すため、接続が維持される
+        let (mut client, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        let mut pings = 0;
+        let deadline = Instant::now() + Duration::from_millis(300);
+        while let Ok(message) = tokio::time::timeout_at(deadline, client.next()).await {
+            match message {
+                Some(Ok(tungstenite::Message::Ping(_))) => pings += 1,
+                Some(Ok(_)) => {}
+                other => panic!("WebSocket closed unexpectedly: {:?}", other),
+            }
+        }
+        assert!(pings >= 3);
+
+        // Pongを返さないクライアントは切断される
+        let (mut silent_client, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let closed = tokio::time::timeout(Duration::from_secs(1), async {
+            loop {
+                match silent_client.next().await {
+                    Some(Ok(tungstenite::Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                }
+            }
+        })
+        .await;
+        assert!(closed.is_ok());
+    }
+
+    #[tokio::test]
+    async fn websocket_command_does_not_block_others() {
+        use futures_util::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite;
+
+        // CueControllerが受信しないため、チャネルの容量を超えたGOは送信を待ち続ける
+        let (router, _controller_rx) = setup_router(vec![]).await;
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/ws", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        let (mut client, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        for _ in 0..33 {
+            let request = r#"{"type":"controll","command":"go"}"#;
+            client.send(tungstenite::Message::Text(request.into())).await.unwrap();
+        }
+        let request = format!(
+            r#"{{"type":"model","command":"setArmed","params":{{"cueId":"{}","armed":false}},"requestId":"model"}}"#,
+            Uuid::new_v4()
+        );
+        client.send(tungstenite::Message::Text(request.into())).await.unwrap();
+
+        // 処理が終わらないコマンドがあっても、後から届いたコマンドの応答は返る
+        let acked = tokio::time::timeout(Duration::from_secs(1), async {
+            while let Some(Ok(message)) = client.next().await {
+                if let tungstenite::Message::Text(text) = message
+                    && text.contains(r#""requestId":"model""#)
+                {
+                    return true;
+                }
+            }
+            false
+        })
+        .await;
+        assert_eq!(acked, Ok(true));
+    }
+
+    #[tokio::test]
+    async fn debug_active_instances() {
+        let (router, _) = setup_router(vec![]).await;
+        let response = router.oneshot(Request::get("/api/debug/active").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let (executor_tx, mut executor_rx) = mpsc::channel::<ExecutorCommand>(4);
+        let (router, _) = setup_router_with_options(vec![], ApiOptions::default().with_executor(executor_tx)).await;
+        let (instance_id, cue_id) = (Uuid::new_v4(), Uuid::new_v4());
+        tokio::spawn(async move {
+            if let Some(ExecutorCommand::QueryActive { reply }) = executor_rx.recv().await {
+                reply.send(HashMap::from([(instance_id, cue_id)])).unwrap();
+            }
+        });
+
+        let response = router.oneshot(Request::get("/api/debug/active").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let active: HashMap<Uuid, Uuid> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(active, HashMap::from([(instance_id, cue_id)]));
+    }
+
+}
@@ -1,15 +1,26 @@
-use std::{collections::HashMap};
+use std::{collections::{HashMap, HashSet, VecDeque}, path::PathBuf, sync::{Arc, Mutex}, time::{Duration, SystemTime, UNIX_EPOCH}};
 
+use kira::Easing;
 use serde::{Deserialize, Serialize};
-use tokio::sync::{broadcast, mpsc, watch};
+use tokio::{sync::{broadcast, mpsc, watch, RwLock}, time::Instant};
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
 use crate::{
-    event::UiEvent, executor::{ExecutorCommand, ExecutorEvent}, manager::ShowModelHandle
+    event::{CueSkipReason, UiEvent}, executor::{ExecutorCommand, ExecutorEvent}, manager::ShowModelHandle, model::{cue::{AudioCueLevels, Cue, CueParam, CueSequence, RetriggerMode}, easing::FadeCurve, settings::MissedSchedulePolicy, ShowModel}
 };
 
+/// 実行履歴に保持する最大件数
+const HISTORY_CAPACITY: usize = 1000;
+/// RevertLevelsで保存されたレベルに戻すフェードの時間(秒)
+const REVERT_LEVELS_DURATION: f64 = 0.5;
+/// ShowState.upcomingに含めるキューの数
+const UPCOMING_CUE_COUNT: usize = 3;
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum PlaybackStatus {
+    Loading, // 音声ファイルを読み込んでいる。読み込みが終わるとPlayingになる
     Playing,
     Paused,
     Completed,
@@ -17,28 +28,205 @@ pub enum PlaybackStatus {
 }
 
 #[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ActiveCue {
     pub cue_id: Uuid,
-    pub position: f64,
-    pub duration: f64,
+    pub position: f64, // 再生範囲(start_time〜end_time)の先頭からの秒数
+    pub duration: f64, // 再生範囲の長さ(秒)
+    pub remaining: Option<f64>, // 終了までの秒数。ループ中や再生開始前など、終了時刻が決まらない場合はNone
+    pub progress: f64, // 再生範囲内の進捗(0〜1)
     pub status: PlaybackStatus,
+    pub fire_order: u64, // 発火順。値が小さいほど先に発火したキュー
+}
+
+impl PlaybackStatus {
+    /// 読み込み中・再生中・一時停止中のいずれかであるかを返します。
+    pub fn is_running(&self) -> bool {
+        matches!(self, PlaybackStatus::Loading | PlaybackStatus::Playing | PlaybackStatus::Paused)
+    }
+}
+
+impl ActiveCue {
+    pub fn new(cue_id: Uuid, status: PlaybackStatus, fire_order: u64) -> Self {
+        Self { cue_id, position: 0.0, duration: 0.0, remaining: None, progress: 0.0, status, fire_order }
+    }
+
+    /// 再生位置を更新し、残り時間と進捗を求め直します。`looping`がtrueの場合、残り時間は不定とします。
+    fn set_position(&mut self, position: f64, duration: f64, looping: bool) {
+        self.position = position;
+        self.duration = duration;
+        self.remaining = (!looping).then(|| (duration - position).max(0.0));
+        self.progress = if duration > 0.0 { (position / duration).clamp(0.0, 1.0) } else { 0.0 };
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(tag = "command", content = "params", rename_all = "camelCase", rename_all_fields = "camelCase")]
 pub enum ControllerCommand {
     Go,
+    /// 指定したキューに再生カーソルを移動してGOします。
+    GoFromCue {
+        cue_id: Uuid,
+    },
+    /// 再生カーソルのキューから有効なキューを`count`個、完了を待たずに順に実行し、再生カーソルを最後に実行したキューの次の有効なキューに進めます。
+    /// 残りの有効なキューが`count`個より少ない場合は、最後のキューまで実行します。
+    GoMultiple {
+        count: usize,
+    },
     StopAll,
+    /// 再生中・読み込み中の全てのキューを一時停止します。既に一時停止しているキューには何もしません。
+    PauseAll,
+    /// 一時停止中の全てのキューを再開します。再生中のキューには何もしません。
+    ResumeAll,
+    /// 再生中・読み込み中のキューを一時停止します。
+    PauseCue {
+        cue_id: Uuid,
+    },
+    /// 一時停止中のキューを再開します。
+    ResumeCue {
+        cue_id: Uuid,
+    },
+    /// フェードを使わずに全てのキューをすぐに停止します。停止の完了を待たずに再生中のキューを消去します。
+    Panic,
+    /// 全てのキューを1つのカーブで`duration`秒かけて無音までフェードし、停止します。
+    /// フェード中にGOした場合はフェードを打ち切ります。
+    FadeOutAll {
+        duration: f64,
+        easing: FadeCurve,
+    },
+    /// `from_cue_id`のキューを`duration`秒かけて無音までフェードして停止し、同時に`to_cue_id`のオーディオキューを同じ長さでフェードインして開始します。
+    /// `from_cue_id`のキューが再生中でない場合は、`to_cue_id`のキューのフェードインのみ行います。
+    Crossfade {
+        from_cue_id: Uuid,
+        to_cue_id: Uuid,
+        duration: f64,
+        easing: FadeCurve,
+    },
     SetPlaybackCursor {
         cue_id: Uuid,
     },
+    /// 再生カーソルをキューリストの最初のキューに戻します。再生中のキューは停止しません。
+    /// キューがない場合は再生カーソルを解除します。
+    ResetCursor,
+    ToggleCue {
+        cue_id: Uuid,
+    },
+    GoAtTimecode {
+        cue_id: Uuid,
+        frame: u64,
+    },
+    HoldAudio,
+    ReleaseAudio,
+    SetLevelsMany {
+        changes: Vec<(Uuid, AudioCueLevels, f64, FadeCurve)>, // (cue_id, levels, duration, easing)
+    },
+    /// 再生中のキューのレベルを、モデルに保存されているレベルに戻します。
+    RevertLevels {
+        cue_id: Uuid,
+    },
+    /// 実行中のフェードインを中断し、すぐに目標のレベルにします。
+    CancelFade {
+        cue_id: Uuid,
+    },
+    /// 再生中・一時停止中のキューの再生位置(秒)を移動します。
+    SeekCue {
+        cue_id: Uuid,
+        position: f64,
+    },
+    /// 再生中のキューのループを解除します。ループ領域を抜けて再生範囲の終端まで再生し、終了します。
+    ReleaseLoop {
+        cue_id: Uuid,
+    },
+    /// 再生中のキューの音声ファイルを差し替えます。キューの設定は変更しません。
+    ReplacePlayingMedia {
+        cue_id: Uuid,
+        new_target: PathBuf,
+    },
+    /// プレビューモードを切り替えます。プレビュー中はキューの進行だけを行い、音声は再生しません。
+    SetPreviewMode {
+        enabled: bool,
+    },
+    /// オーディオキューを下げた音量で数秒間だけ試聴し、キューのフェードアウトで停止します。
+    /// 再生カーソルと再生中のキューには影響しません。
+    AuditionCue {
+        cue_id: Uuid,
+    },
+    /// オーディオキューの音声ファイルを先にメモリに読み込みます。次にGOした際は読み込みを待たずに再生を開始します。
+    /// 先読みしたサウンドはメモリの上限を超えると、最も長く使われていないものから解放されます。
+    Preload {
+        cue_id: Uuid,
+    },
+    /// 先読みしたオーディオキューの音声ファイルをメモリから解放します。
+    EvictPreload {
+        cue_id: Uuid,
+    },
+    /// 全体の音量(dB)を変更します。再生中と今後再生する全てのキューに反映されます。
+    SetMasterVolume {
+        db: f64,
+        duration: f64,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::EasingDef"))]
+        easing: Easing,
+    },
+    /// バスの音量(dB)を変更します。バスで再生中と今後再生する全てのキューに反映されます。
+    SetBusVolume {
+        bus: String,
+        db: f64,
+        duration: f64,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::EasingDef"))]
+        easing: Easing,
+    },
+}
+
+impl ControllerCommand {
+    /// コマンドが対象とするキューのIDを返します。
+    pub fn cue_ids(&self) -> Vec<Uuid> {
+        match self {
+            ControllerCommand::SetPlaybackCursor { cue_id }
+            | ControllerCommand::GoFromCue { cue_id }
+            | ControllerCommand::ToggleCue { cue_id }
+            | ControllerCommand::PauseCue { cue_id }
+            | ControllerCommand::ResumeCue { cue_id }
+            | ControllerCommand::GoAtTimecode { cue_id, .. }
+            | ControllerCommand::RevertLevels { cue_id }
+            | ControllerCommand::CancelFade { cue_id }
+            | ControllerCommand::SeekCue { cue_id, .. }
+            | ControllerCommand::ReleaseLoop { cue_id }
+            | ControllerCommand::AuditionCue { cue_id }
+            | ControllerCommand::Preload { cue_id }
+            | ControllerCommand::EvictPreload { cue_id }
+            | ControllerCommand::ReplacePlayingMedia { cue_id, .. } => vec![*cue_id],
+            ControllerCommand::SetLevelsMany { changes } => changes.iter().map(|(cue_id, ..)| *cue_id).collect(),
+            ControllerCommand::Crossfade { from_cue_id, to_cue_id, .. } => vec![*from_cue_id, *to_cue_id],
+            ControllerCommand::Go
+            | ControllerCommand::GoMultiple { .. }
+            | ControllerCommand::ResetCursor
+            | ControllerCommand::StopAll
+            | ControllerCommand::PauseAll
+            | ControllerCommand::ResumeAll
+            | ControllerCommand::Panic
+            | ControllerCommand::FadeOutAll { .. }
+            | ControllerCommand::HoldAudio
+            | ControllerCommand::ReleaseAudio
+            | ControllerCommand::SetPreviewMode { .. }
+            | ControllerCommand::SetMasterVolume { .. }
+            | ControllerCommand::SetBusVolume { .. } => vec![],
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct ShowState {
     pub playback_cursor: Option<Uuid>,
     pub active_cues: HashMap<Uuid, ActiveCue>,
+    pub audio_held: bool, // HoldAudioで全体の音声を一時停止中かどうか
+    pub upcoming: Vec<Uuid>, // 再生カーソルから順に、次にGOで実行される有効なキュー
+    pub master_volume: f64, // 全体の音量(dB)
+    pub bus_volumes: HashMap<String, f64>, // 音量を変更したバスの音量(dB)。含まれないバスは0dB
+    pub preview: bool, // プレビューモード中かどうか。音声を再生せずにキューの進行だけを確認する
+    pub last_played: HashMap<Uuid, u64>, // キューが最後に再生を開始した時刻(UNIXエポックからのミリ秒)。起動してから再生したキューのみ含む
 }
 
 impl ShowState {
@@ -46,10 +234,106 @@ impl ShowState {
         Self {
             playback_cursor: None,
             active_cues: HashMap::new(),
+            audio_held: false,
+            upcoming: Vec::new(),
+            master_volume: 0.0,
+            bus_volumes: HashMap::new(),
+            preview: false,
+            last_played: HashMap::new(),
+        }
+    }
+
+    /// 再生中のキューを発火順に並べて返します。
+    pub fn active_cues_in_fire_order(&self) -> Vec<&ActiveCue> {
+        let mut active_cues: Vec<&ActiveCue> = self.active_cues.values().collect();
+        active_cues.sort_by_key(|active_cue| active_cue.fire_order);
+        active_cues
+    }
+
+    fn next_fire_order(&self) -> u64 {
+        self.active_cues
+            .values()
+            .map(|active_cue| active_cue.fire_order + 1)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum HistoryEventKind {
+    Started,
+    Paused,
+    Resumed,
+    Completed,
+    Error,
+}
+
+impl HistoryEventKind {
+    /// 履歴に残すイベントの種類を返します。Progressは頻度が高いため、LoadingはStartedの直前に必ず届くため残しません。
+    fn from_event(event: &ExecutorEvent) -> Option<Self> {
+        match event {
+            ExecutorEvent::Started { .. } => Some(Self::Started),
+            ExecutorEvent::Loading { .. }
+            | ExecutorEvent::Progress { .. }
+            | ExecutorEvent::Meter { .. }
+            | ExecutorEvent::VoiceStolen { .. }
+            | ExecutorEvent::AuditionStarted { .. }
+            | ExecutorEvent::AuditionCompleted { .. } => None,
+            ExecutorEvent::Paused { .. } => Some(Self::Paused),
+            ExecutorEvent::Resumed { .. } => Some(Self::Resumed),
+            ExecutorEvent::Completed { .. } => Some(Self::Completed),
+            ExecutorEvent::Error { .. } => Some(Self::Error),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    pub timestamp: u64, // UNIXエポックからのミリ秒
+    pub cue_id: Uuid,
+    pub event_kind: HistoryEventKind,
+}
+
+/// キューの実行履歴。古いものから`HISTORY_CAPACITY`件を超えた分を破棄します。
+#[derive(Clone, Default)]
+pub struct CueHistory(Arc<RwLock<VecDeque<HistoryEntry>>>);
+
+impl CueHistory {
+    async fn push(&self, entry: HistoryEntry) {
+        let mut entries = self.0.write().await;
+        if entries.len() >= HISTORY_CAPACITY {
+            entries.pop_front();
         }
+        entries.push_back(entry);
+    }
+
+    /// 最新の`limit`件を古い順に返します。
+    pub async fn recent(&self, limit: usize) -> Vec<HistoryEntry> {
+        let entries = self.0.read().await;
+        entries.iter().skip(entries.len().saturating_sub(limit)).cloned().collect()
     }
 }
 
+/// 再生カーソルから順に、GOで実行される有効なキューを最大`UPCOMING_CUE_COUNT`個返します。
+fn upcoming_cues(cues: &[Cue], cursor: Uuid) -> Vec<Uuid> {
+    let Some(index) = cues.iter().position(|cue| cue.id == cursor) else {
+        return Vec::new();
+    };
+    cues[index..].iter().filter(|cue| cue.armed).take(UPCOMING_CUE_COUNT).map(|cue| cue.id).collect()
+}
+
+/// ショーを開いた際の再生カーソルの位置を返します。保存されたカーソルのキューが存在しない場合は最初のキューを返します。
+fn initial_cursor(model: &ShowModel) -> Option<Uuid> {
+    model
+        .last_cursor
+        .filter(|cursor| model.cues.iter().any(|cue| cue.id == *cursor))
+        .or_else(|| model.cues.first().map(|cue| cue.id))
+}
+
 pub struct CueController {
     model_handle: ShowModelHandle,
     executor_tx: mpsc::Sender<ExecutorCommand>, // Executorへの指示用チャネル
@@ -58,6 +342,14 @@ pub struct CueController {
     executor_event_rx: mpsc::Receiver<ExecutorEvent>,
     state_tx: watch::Sender<ShowState>,
     event_tx: broadcast::Sender<UiEvent>,
+    history: CueHistory,
+    followed_cues: Mutex<HashSet<Uuid>>, // 再生中に`follow_offset`の位置で次のキューを実行したキュー
+    released_loops: Mutex<HashSet<Uuid>>, // 再生中にReleaseLoopでループを解除したキュー
+    pending_schedules: Mutex<HashMap<Uuid, u64>>, // 実行時刻より前に確認した、キューの実行時刻
+    handled_schedules: Mutex<HashMap<Uuid, u64>>, // 実行または見送り済みの、キューの実行時刻
+    held_cues: Mutex<Vec<Uuid>>, // HoldAudioで一時停止したキュー
+    hold_started: Mutex<Option<Instant>>, // HoldAudioで一時停止した時刻
+    pending_continues: Mutex<HashMap<Uuid, Instant>>, // AutoContinue・AutoFollowで、post_waitの後に実行する次のキューと実行時刻
 }
 
 impl CueController {
@@ -70,8 +362,12 @@ impl CueController {
         event_tx: broadcast::Sender<UiEvent>,
     ) -> Self {
         let manager = model_handle.read().await;
-        let show_state = if let Some(first_cue) = manager.cues.first() {
-            ShowState { playback_cursor: Some(first_cue.id), ..Default::default() }
+        let show_state = if let Some(cursor) = initial_cursor(&manager) {
+            ShowState {
+                playback_cursor: Some(cursor),
+                upcoming: upcoming_cues(&manager.cues, cursor),
+                ..Default::default()
+            }
         } else {
             ShowState::new()
         };
@@ -87,13 +383,48 @@ impl CueController {
             executor_event_rx,
             state_tx,
             event_tx,
+            history: CueHistory::default(),
+            followed_cues: Mutex::new(HashSet::new()),
+            released_loops: Mutex::new(HashSet::new()),
+            pending_schedules: Mutex::new(HashMap::new()),
+            handled_schedules: Mutex::new(HashMap::new()),
+            held_cues: Mutex::new(Vec::new()),
+            hold_started: Mutex::new(None),
+            pending_continues: Mutex::new(HashMap::new()),
         }
     }
 
-    pub async fn run(mut self) {
+    /// 実行履歴を参照するためのハンドルを返します。
+    pub fn history(&self) -> CueHistory {
+        self.history.clone()
+    }
+
+    pub async fn run(mut self, cancel_token: CancellationToken) {
         log::info!("CueController run loop started.");
+        // ショーの読み込みを検知するため、自身が送信するものも含めてUIイベントを購読する
+        let mut ui_event_rx = self.event_tx.subscribe();
         loop {
+            // キューの編集をすぐに反映するため、毎回実行時刻を確認する
+            let next_schedule = self.run_schedules().await.unwrap_or_else(|e| {
+                log::error!("Error running scheduled cues: {:?}", e);
+                None
+            });
+            let next_continue = self.run_continues().await.unwrap_or_else(|e| {
+                log::error!("Error running continued cues: {:?}", e);
+                None
+            });
+            let next_wake = next_schedule.into_iter().chain(next_continue).min();
             tokio::select! {
+                _ = cancel_token.cancelled() => break,
+                _ = async { tokio::time::sleep(next_wake.unwrap()).await }, if next_wake.is_some() => {},
+                result = ui_event_rx.recv() => {
+                    // 受信が遅れた場合は読み込みを見逃した可能性があるため、同様に照合する
+                    if let Ok(UiEvent::ShowModelLoaded { .. } | UiEvent::ShowModelCreated) | Err(broadcast::error::RecvError::Lagged(_)) = result
+                        && let Err(e) = self.reconcile_with_model().await
+                    {
+                        log::error!("Error reconciling playback with show model: {:?}", e);
+                    }
+                },
                 Some(command) = self.command_rx.recv() => {
                     if let Err(e) = self.handle_command(command).await {
                         log::error!("Error handling controller command: {:?}", e);
@@ -107,175 +438,862 @@ impl CueController {
                 else => break,
             }
         }
+        // Executorが終了するまで残りのイベントを処理してUIに届ける
+        while let Some(event) = self.executor_event_rx.recv().await {
+            if let Err(e) = self.handle_executor_event(event).await {
+                log::error!("Error handling playback event: {:?}", e);
+            }
+        }
         log::info!("CueController run loop finished.");
     }
 
     async fn handle_command(&self, command: ControllerCommand) -> Result<(), anyhow::Error> {
         match command {
             ControllerCommand::Go => {
-                let state = self.state_tx.borrow().clone();
-                let cue_id = state.playback_cursor.expect("Playback Cursor is unavailable.");
-                self.handle_go(cue_id).await
+                let Some(cursor) = self.state_tx.borrow().playback_cursor else {
+                    log::warn!("GO: Playback cursor is unavailable.");
+                    return Ok(());
+                };
+                self.handle_go_from(cursor).await
             },
-            ControllerCommand::StopAll => Ok(()), /* TODO */
+            ControllerCommand::GoFromCue { cue_id } => self.handle_go_from(cue_id).await,
+            ControllerCommand::GoMultiple { count } => self.handle_go_multiple(count).await,
+            ControllerCommand::StopAll => {
+                self.pending_continues.lock().unwrap().clear();
+                self.executor_tx.send(ExecutorCommand::StopAll).await?;
+                Ok(())
+            }
+            ControllerCommand::PauseAll => {
+                for cue_id in self.active_cue_ids_with_status(&[PlaybackStatus::Loading, PlaybackStatus::Playing]) {
+                    self.executor_tx.send(ExecutorCommand::PauseCue(cue_id)).await?;
+                }
+                Ok(())
+            }
+            ControllerCommand::ResumeAll => {
+                for cue_id in self.active_cue_ids_with_status(&[PlaybackStatus::Paused]) {
+                    self.executor_tx.send(ExecutorCommand::ResumeCue(cue_id)).await?;
+                }
+                Ok(())
+            }
+            ControllerCommand::PauseCue { cue_id } => {
+                self.executor_tx.send(ExecutorCommand::PauseCue(cue_id)).await?;
+                Ok(())
+            }
+            ControllerCommand::ResumeCue { cue_id } => {
+                self.executor_tx.send(ExecutorCommand::ResumeCue(cue_id)).await?;
+                Ok(())
+            }
+            ControllerCommand::Panic => self.handle_panic().await,
+            ControllerCommand::FadeOutAll { duration, easing } => {
+                if !(duration.is_finite() && duration >= 0.0) {
+                    log::warn!("FadeOutAll: Invalid fade duration {}.", duration);
+                    return Ok(());
+                }
+                self.pending_continues.lock().unwrap().clear();
+                self.executor_tx.send(ExecutorCommand::FadeOutAll { duration, easing }).await?;
+                Ok(())
+            }
+            ControllerCommand::Crossfade { from_cue_id, to_cue_id, duration, easing } => {
+                if !(duration.is_finite() && duration >= 0.0) {
+                    log::warn!("Crossfade: Invalid fade duration {}.", duration);
+                    return Ok(());
+                }
+                if from_cue_id == to_cue_id {
+                    log::warn!("Crossfade: Cue '{}' can't be crossfaded to itself.", from_cue_id);
+                    return Ok(());
+                }
+                if self.skip_if_held(to_cue_id) {
+                    return Ok(());
+                }
+                if let Some(to_cue) = self.model_handle.get_cue_by_id(&to_cue_id).await
+                    && self.ignores_retrigger(&to_cue)
+                {
+                    return Ok(());
+                }
+                self.executor_tx.send(ExecutorCommand::Crossfade { from_cue_id, to_cue_id, duration, easing }).await?;
+                Ok(())
+            }
+            ControllerCommand::AuditionCue { cue_id } => {
+                self.executor_tx.send(ExecutorCommand::AuditionCue(cue_id)).await?;
+                Ok(())
+            }
+            ControllerCommand::Preload { cue_id } => {
+                self.executor_tx.send(ExecutorCommand::Preload(cue_id)).await?;
+                Ok(())
+            }
+            ControllerCommand::EvictPreload { cue_id } => {
+                self.executor_tx.send(ExecutorCommand::EvictPreload(cue_id)).await?;
+                Ok(())
+            }
+            ControllerCommand::SetPreviewMode { enabled } => {
+                self.executor_tx.send(ExecutorCommand::SetPreviewMode(enabled)).await?;
+                self.state_tx.send_if_modified(|state| std::mem::replace(&mut state.preview, enabled) != enabled);
+                Ok(())
+            }
             ControllerCommand::SetPlaybackCursor { cue_id } => {
                 if self.model_handle.get_cue_by_id(&cue_id).await.is_some() {
+                    let upcoming = upcoming_cues(&self.model_handle.read().await.cues, cue_id);
                     self.state_tx.send_modify(|state| {
                         if state.playback_cursor.ne(&Some(cue_id)) {
                             state.playback_cursor = Some(cue_id);
+                            state.upcoming = upcoming;
                             if self.event_tx.send(UiEvent::PlaybackCursorMoved { cue_id }).is_err() {
                                 log::trace!("No UI clients are listening to playback events.");
                             }
                         }
                     });
+                    self.model_handle.set_last_cursor(Some(cue_id)).await;
+                } else {
+                    log::warn!("SetPlaybackCursor: Cue with id '{}' not found.", cue_id);
                 }
                 Ok(())
             }
-        }
-    }
-
-    async fn handle_go(&self, cue_id: Uuid) -> Result<(), anyhow::Error> {
-        let model = self.model_handle.read().await;
-
-        if model.cues.iter().any(|cue| cue.id.eq(&cue_id)) {
-            let command = ExecutorCommand::ExecuteCue(cue_id);
-            self.executor_tx.send(command).await?;
-        } else {
-            log::warn!("GO: Reached end of cue list.");
-        }
-        Ok(())
-    }
-
-    /// Executorからの再生イベントを処理します
-    async fn handle_executor_event(&self, event: ExecutorEvent) -> Result<(), anyhow::Error> {
-        let mut show_state = self.state_tx.borrow().clone();
-        let mut state_changed = false;
-
-        match &event {
-            ExecutorEvent::Started { cue_id } => {
-                let active_cue = ActiveCue {
-                    cue_id: *cue_id,
-                    position: 0.0,
-                    duration: 0.0,
-                    status: PlaybackStatus::Playing,
-                };
-                show_state.active_cues.insert(*cue_id, active_cue);
-                state_changed = true;
+            ControllerCommand::ResetCursor => {
+                let model = self.model_handle.read().await;
+                let cursor = model.cues.first().map(|cue| cue.id);
+                let upcoming = cursor.map_or_else(Vec::new, |cursor| upcoming_cues(&model.cues, cursor));
+                drop(model);
+                let previous_cursor = self.state_tx.borrow().playback_cursor;
+                self.state_tx.send_if_modified(|state| {
+                    let changed = state.playback_cursor != cursor || state.upcoming != upcoming;
+                    state.playback_cursor = cursor;
+                    state.upcoming = upcoming;
+                    changed
+                });
+                self.model_handle.set_last_cursor(cursor).await;
+                if let Some(cue_id) = cursor
+                    && previous_cursor != Some(cue_id)
+                    && self.event_tx.send(UiEvent::PlaybackCursorMoved { cue_id }).is_err()
+                {
+                    log::trace!("No UI clients are listening to playback events.");
+                }
+                Ok(())
             }
-            ExecutorEvent::Progress {
-                cue_id,
-                position,
-                duration,
-                ..
-            } => {
-                if let Some(active_cue) = show_state.active_cues.get_mut(cue_id) {
-                    active_cue.position = *position;
-                    active_cue.duration = *duration;
-                    active_cue.status = PlaybackStatus::Playing
+            ControllerCommand::ToggleCue { cue_id } => {
+                let is_active = self.state_tx.borrow().active_cues.get(&cue_id).is_some_and(|active_cue| active_cue.status.is_running());
+                if is_active {
+                    self.executor_tx.send(ExecutorCommand::StopCue(cue_id)).await?;
+                    Ok(())
                 } else {
-                    show_state.active_cues.insert(
-                        *cue_id,
-                        ActiveCue {
-                            cue_id: *cue_id,
-                            position: *position,
-                            duration: *duration,
-                            status: PlaybackStatus::Playing,
-                        },
-                    );
+                    self.handle_go(cue_id).await
                 }
-                state_changed = true;
             }
-            ExecutorEvent::Paused {
-                cue_id,
-                position,
-                duration,
-            } => {
-                if let Some(active_cue) = show_state.active_cues.get_mut(cue_id) {
-                    if !active_cue.status.eq(&PlaybackStatus::Paused) {
-                        active_cue.position = *position;
-                        active_cue.duration = *duration;
-                        active_cue.status = PlaybackStatus::Paused;
-                        state_changed = true;
-                    }
-                } else {
-                    show_state.active_cues.insert(
-                        *cue_id,
-                        ActiveCue {
-                            cue_id: *cue_id,
-                            position: *position,
-                            duration: *duration,
-                            status: PlaybackStatus::Paused,
-                        },
-                    );
-                    state_changed = true;
+            ControllerCommand::GoAtTimecode { cue_id, frame } => {
+                if self.skip_if_held(cue_id) {
+                    return Ok(());
+                }
+                match self.model_handle.get_cue_by_id(&cue_id).await {
+                    Some(cue) if self.ignores_retrigger(&cue) => {}
+                    Some(_) => self.executor_tx.send(ExecutorCommand::ExecuteCueAtFrame { cue_id, frame }).await?,
+                    None => log::warn!("GoAtTimecode: Cue with id '{}' not found.", cue_id),
                 }
+                Ok(())
             }
-            ExecutorEvent::Resumed { cue_id } => {
-                if let Some(active_cue) = show_state.active_cues.get_mut(cue_id) {
-                    if !active_cue.status.eq(&PlaybackStatus::Playing) {
-                        active_cue.status = PlaybackStatus::Playing;
-                        state_changed = true;
-                    }
+            ControllerCommand::HoldAudio => {
+                if self.state_tx.borrow().audio_held {
+                    return Ok(());
+                }
+                // 解除時に再開するのは、ここで一時停止したキューだけにする
+                let cue_ids = self.active_cue_ids_with_status(&[PlaybackStatus::Loading, PlaybackStatus::Playing]);
+                for cue_id in &cue_ids {
+                    self.executor_tx.send(ExecutorCommand::PauseCue(*cue_id)).await?;
                 }
+                *self.held_cues.lock().unwrap() = cue_ids;
+                *self.hold_started.lock().unwrap() = Some(Instant::now());
+                self.state_tx.send_modify(|state| state.audio_held = true);
+                Ok(())
             }
-            ExecutorEvent::Completed { cue_id, .. } => {
-                if let Some(mut active_cue) = show_state.active_cues.remove(cue_id) {
-                    active_cue.status = PlaybackStatus::Completed;
-                    state_changed = true;
-                    // TODO: Auto-Followロジックをここでトリガー
+            ControllerCommand::ReleaseAudio => {
+                if !self.state_tx.borrow().audio_held {
+                    return Ok(());
+                }
+                let held_cues = std::mem::take(&mut *self.held_cues.lock().unwrap());
+                let paused_cues = self.active_cue_ids_with_status(&[PlaybackStatus::Paused]);
+                for cue_id in held_cues.into_iter().filter(|cue_id| paused_cues.contains(cue_id)) {
+                    self.executor_tx.send(ExecutorCommand::ResumeCue(cue_id)).await?;
                 }
+                // 一時停止中に過ぎた実行時刻は、初めて確認した時点で過ぎていたものとして`missed_schedule_policy`に従う
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_millis() as u64);
+                self.pending_schedules.lock().unwrap().retain(|_, schedule| *schedule > now);
+                // post_waitは一時停止していた時間だけ遅らせる
+                if let Some(hold_started) = self.hold_started.lock().unwrap().take() {
+                    let held_duration = hold_started.elapsed();
+                    for continue_at in self.pending_continues.lock().unwrap().values_mut() {
+                        *continue_at += held_duration;
+                    }
+                }
+                self.state_tx.send_modify(|state| state.audio_held = false);
+                Ok(())
             }
-            ExecutorEvent::Error { cue_id, error, .. } => {
-                if let Some(active_cue) = show_state.active_cues.get_mut(cue_id) {
-                    active_cue.status = PlaybackStatus::Error;
-                    state_changed = true;
-                    log::error!("State: Cue error on '{}': {}", active_cue.cue_id, error);
+            ControllerCommand::RevertLevels { cue_id } => {
+                let Some(cue) = self.model_handle.get_cue_by_id(&cue_id).await else {
+                    log::warn!("RevertLevels: Cue with id '{}' not found.", cue_id);
+                    return Ok(());
+                };
+                let CueParam::Audio { levels, .. } = cue.param else {
+                    log::warn!("RevertLevels: Cue with id '{}' is not an audio cue.", cue_id);
+                    return Ok(());
+                };
+                let changes = vec![(cue_id, levels, REVERT_LEVELS_DURATION, Easing::Linear.into())];
+                self.executor_tx.send(ExecutorCommand::SetLevelsMany { changes }).await?;
+                Ok(())
+            }
+            ControllerCommand::CancelFade { cue_id } => {
+                self.executor_tx.send(ExecutorCommand::CancelFade(cue_id)).await?;
+                Ok(())
+            }
+            ControllerCommand::SeekCue { cue_id, position } => {
+                self.executor_tx.send(ExecutorCommand::Seek { cue_id, position }).await?;
+                Ok(())
+            }
+            ControllerCommand::ReleaseLoop { cue_id } => {
+                self.executor_tx.send(ExecutorCommand::ReleaseLoop(cue_id)).await?;
+                self.released_loops.lock().unwrap().insert(cue_id);
+                Ok(())
+            }
+            ControllerCommand::ReplacePlayingMedia { cue_id, new_target } => {
+                if self.state_tx.borrow().active_cues.contains_key(&cue_id) {
+                    self.executor_tx.send(ExecutorCommand::ReplaceMedia { cue_id, new_target }).await?;
+                } else {
+                    log::warn!("ReplacePlayingMedia: Cue with id '{}' is not playing.", cue_id);
                 }
+                Ok(())
+            }
+            ControllerCommand::SetLevelsMany { changes } => {
+                self.executor_tx.send(ExecutorCommand::SetLevelsMany { changes }).await?;
+                Ok(())
+            }
+            ControllerCommand::SetMasterVolume { db, duration, easing } => {
+                self.executor_tx.send(ExecutorCommand::SetMasterVolume { db, duration, easing }).await?;
+                self.state_tx.send_modify(|state| state.master_volume = db);
+                Ok(())
+            }
+            ControllerCommand::SetBusVolume { bus, db, duration, easing } => {
+                self.executor_tx.send(ExecutorCommand::SetBusVolume { bus: bus.clone(), db, duration, easing }).await?;
+                self.state_tx.send_modify(|state| {
+                    state.bus_volumes.insert(bus, db);
+                });
+                Ok(())
             }
         }
+    }
 
-        if state_changed && self.state_tx.send(show_state).is_err() {
-            log::trace!("No UI clients are listening to state updates.");
+    /// `cue_id`から順に探し、最初の有効なキューのIDを返します。飛ばした無効なキューはUIに通知します。
+    async fn next_armed_cue(&self, cue_id: Uuid) -> Option<Uuid> {
+        let model = self.model_handle.read().await;
+        let Some(index) = model.cues.iter().position(|cue| cue.id == cue_id) else {
+            return Some(cue_id);
+        };
+        for cue in &model.cues[index..] {
+            if cue.armed {
+                return Some(cue.id);
+            }
+            self.send_skipped(cue.id, CueSkipReason::Disarmed);
         }
+        self.send_skipped(cue_id, CueSkipReason::EndOfList);
+        None
+    }
 
-        match &event {
-            ExecutorEvent::Started { .. } |
-            ExecutorEvent::Paused { .. } |
-            ExecutorEvent::Resumed { .. } |
-            ExecutorEvent::Completed { .. } |
-            ExecutorEvent::Error { .. } => {
-                if self.event_tx.send(UiEvent::from(event)).is_err() {
-                    log::trace!("No UI clients are listening to playback events.");
+    /// HoldAudioで一時停止中の場合、キューを実行せずにUIに通知してtrueを返します。
+    fn skip_if_held(&self, cue_id: Uuid) -> bool {
+        if !self.state_tx.borrow().audio_held {
+            return false;
+        }
+        self.send_skipped(cue_id, CueSkipReason::AudioHeld);
+        true
+    }
+
+    /// 再トリガーを無視するキューが実行中の場合、trueを返します。
+    fn ignores_retrigger(&self, cue: &Cue) -> bool {
+        if cue.retrigger_mode != RetriggerMode::Ignore
+            || !self.state_tx.borrow().active_cues.get(&cue.id).is_some_and(|active_cue| active_cue.status.is_running())
+        {
+            return false;
+        }
+        log::info!("GO: Cue '{}' is already active. Ignored retrigger.", cue.id);
+        true
+    }
+
+    /// キューを実行しなかったことをUIに通知します。
+    fn send_skipped(&self, cue_id: Uuid, reason: CueSkipReason) {
+        log::info!("Skipped cue '{}': {:?}", cue_id, reason);
+        if self.event_tx.send(UiEvent::CueSkipped { cue_id, reason }).is_err() {
+            log::trace!("No UI clients are listening to playback events.");
+        }
+    }
+
+    /// 子キューの進捗から、それを含む再生中のグループキューの進捗を更新します。
+    /// グループの進捗は、再生中の子キューのうち最も長いものの進捗になります。
+    async fn update_group_progress(&self, show_state: &mut ShowState, cue_id: Uuid) {
+        let model = self.model_handle.read().await;
+        let mut visited = HashSet::from([cue_id]);
+        let mut updated = vec![cue_id];
+        while let Some(child_id) = updated.pop() {
+            for group_cue in &model.cues {
+                let CueParam::Group { children } = &group_cue.param else {
+                    continue;
+                };
+                if !children.contains(&child_id)
+                    || !show_state.active_cues.contains_key(&group_cue.id)
+                    || !visited.insert(group_cue.id)
+                {
+                    continue;
                 }
-            },
-            _ => ()
+                let longest_child = children
+                    .iter()
+                    .filter_map(|id| show_state.active_cues.get(id))
+                    .max_by(|a, b| a.duration.total_cmp(&b.duration))
+                    .map(|active_cue| (active_cue.position, active_cue.duration));
+                // ループ中の子キュー(再生位置が分かっていて残り時間が決まらないキュー)がある場合、グループの終了時刻も決まらない
+                let looping = children
+                    .iter()
+                    .filter_map(|id| show_state.active_cues.get(id))
+                    .any(|active_cue| active_cue.remaining.is_none() && active_cue.duration > 0.0);
+                if let (Some((position, duration)), Some(group)) = (longest_child, show_state.active_cues.get_mut(&group_cue.id)) {
+                    group.set_position(position, duration, looping);
+                    updated.push(group_cue.id);
+                }
+            }
         }
-        // TODO: ApiServerに状態変更を通知する
-        Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
+    /// ループ領域を繰り返し再生中のキューかどうかを返します。
+    async fn is_looping(&self, cue_id: &Uuid) -> bool {
+        if self.released_loops.lock().unwrap().contains(cue_id) {
+            return false;
+        }
+        self.model_handle
+            .get_cue_by_id(cue_id)
+            .await
+            .is_some_and(|cue| matches!(cue.param, CueParam::Audio { loop_region: Some(_), .. }))
+    }
 
-    use std::path::PathBuf;
+    /// 指定したいずれかの状態のキューのIDを発火順に返します。
+    fn active_cue_ids_with_status(&self, statuses: &[PlaybackStatus]) -> Vec<Uuid> {
+        self.state_tx
+            .borrow()
+            .active_cues_in_fire_order()
+            .into_iter()
+            .filter(|active_cue| statuses.contains(&active_cue.status))
+            .map(|active_cue| active_cue.cue_id)
+            .collect()
+    }
 
-    use crate::{manager::ShowModelManager, model::{
-        self,
-        cue::{AudioCueFadeParam, AudioCueLevels, Cue},
-    }};
+    /// 全てのキューをすぐに停止し、AudioEngineからの停止の通知を待たずにShowStateから取り除きます。
+    async fn handle_panic(&self) -> Result<(), anyhow::Error> {
+        log::warn!("PANIC: Stopping all cues immediately.");
+        self.pending_continues.lock().unwrap().clear();
+        self.executor_tx.send(ExecutorCommand::Panic).await?;
+        let mut cue_ids = Vec::new();
+        self.state_tx.send_if_modified(|state| {
+            cue_ids = state.active_cues_in_fire_order().into_iter().map(|active_cue| active_cue.cue_id).collect();
+            state.active_cues.clear();
+            !cue_ids.is_empty()
+        });
+        for cue_id in &cue_ids {
+            if self.event_tx.send(UiEvent::CueCompleted { cue_id: *cue_id }).is_err() {
+                log::trace!("No UI clients are listening to playback events.");
+            }
+        }
+        if self.event_tx.send(UiEvent::PanicTriggered { cue_ids }).is_err() {
+            log::trace!("No UI clients are listening to playback events.");
+        }
+        Ok(())
+    }
 
-    use super::*;
+    /// 読み込み直したショーに存在しないキューを停止し、ShowStateから取り除きます。
+    /// IDが一致するキューはそのまま再生を続けます。再生カーソルはショーに保存された位置に戻します。
+    async fn reconcile_with_model(&self) -> Result<(), anyhow::Error> {
+        let model = self.model_handle.read().await;
+        let cue_ids: HashSet<Uuid> = model.cues.iter().map(|cue| cue.id).collect();
+        let show_state = self.state_tx.borrow().clone();
+        let stopped: Vec<Uuid> = show_state
+            .active_cues_in_fire_order()
+            .into_iter()
+            .map(|active_cue| active_cue.cue_id)
+            .filter(|cue_id| !cue_ids.contains(cue_id))
+            .collect();
+        let cursor = initial_cursor(&model);
+        let upcoming = cursor.map_or_else(Vec::new, |cursor| upcoming_cues(&model.cues, cursor));
+        drop(model);
 
-    use kira::sound::Region;
-    use tokio::sync::{
-        mpsc::{self, Receiver, Sender},
-        watch,
-    };
+        for cue_id in &stopped {
+            self.executor_tx.send(ExecutorCommand::StopCue(*cue_id)).await?;
+        }
+        self.model_handle.set_last_cursor(cursor).await;
+        self.state_tx.send_if_modified(|state| {
+            let changed = !stopped.is_empty() || state.playback_cursor != cursor || state.upcoming != upcoming;
+            state.active_cues.retain(|cue_id, _| !stopped.contains(cue_id));
+            // 読み込み直したショーに存在しないキューの再生時刻は残さない
+            let last_played_count = state.last_played.len();
+            state.last_played.retain(|cue_id, _| cue_ids.contains(cue_id));
+            state.playback_cursor = cursor;
+            state.upcoming = upcoming;
+            changed || state.last_played.len() != last_played_count
+        });
+        if let Some(cue_id) = cursor
+            && show_state.playback_cursor != Some(cue_id)
+            && self.event_tx.send(UiEvent::PlaybackCursorMoved { cue_id }).is_err()
+        {
+            log::trace!("No UI clients are listening to playback events.");
+        }
+        if !stopped.is_empty() {
+            log::warn!("Stopped {} cues that no longer exist in the show.", stopped.len());
+            if self.event_tx.send(UiEvent::VanishedCuesStopped { cue_ids: stopped }).is_err() {
+                log::trace!("No UI clients are listening to playback events.");
+            }
+        }
+        Ok(())
+    }
 
-    async fn setup_controller(
-        cue_ids: &[Uuid],
-    ) -> (
+    /// `cursor`以降で最初の有効なキューまで再生カーソルを進め、そのキューを実行します。
+    async fn handle_go_from(&self, cursor: Uuid) -> Result<(), anyhow::Error> {
+        // 一時停止中は再生カーソルも進めない
+        if self.skip_if_held(cursor) {
+            return Ok(());
+        }
+        // 無効化されたキューは飛ばし、カーソルを次に実行するキューへ進める
+        let Some(cue_id) = self.next_armed_cue(cursor).await else {
+            log::warn!("GO: No armed cue after the playback cursor.");
+            return Ok(());
+        };
+        self.move_cursor(cue_id).await;
+        self.handle_go(cue_id).await
+    }
+
+    /// 再生カーソルを移動し、UIに通知します。既にそのキューにある場合は何もしません。
+    async fn move_cursor(&self, cue_id: Uuid) {
+        if self.state_tx.borrow().playback_cursor == Some(cue_id) {
+            return;
+        }
+        let upcoming = upcoming_cues(&self.model_handle.read().await.cues, cue_id);
+        self.state_tx.send_modify(|state| {
+            state.playback_cursor = Some(cue_id);
+            state.upcoming = upcoming;
+        });
+        self.model_handle.set_last_cursor(Some(cue_id)).await;
+        if self.event_tx.send(UiEvent::PlaybackCursorMoved { cue_id }).is_err() {
+            log::trace!("No UI clients are listening to playback events.");
+        }
+    }
+
+    /// 再生カーソルのキューから有効なキューを`count`個まで順に実行し、再生カーソルを次の有効なキューに進めます。
+    /// 次の有効なキューがない場合は、最後に実行したキューに再生カーソルを置きます。
+    async fn handle_go_multiple(&self, count: usize) -> Result<(), anyhow::Error> {
+        if count == 0 {
+            log::warn!("GO MULTIPLE: Count must be at least 1.");
+            return Ok(());
+        }
+        let Some(cursor) = self.state_tx.borrow().playback_cursor else {
+            log::warn!("GO MULTIPLE: Playback cursor is unavailable.");
+            return Ok(());
+        };
+        if self.skip_if_held(cursor) {
+            return Ok(());
+        }
+        let (targets, next_cursor) = {
+            let model = self.model_handle.read().await;
+            let Some(index) = model.cues.iter().position(|cue| cue.id == cursor) else {
+                drop(model);
+                log::warn!("GO MULTIPLE: Cue '{}' not found.", cursor);
+                self.send_skipped(cursor, CueSkipReason::Removed);
+                return Ok(());
+            };
+            let mut targets = Vec::with_capacity(count);
+            let mut next_cursor = None;
+            for cue in &model.cues[index..] {
+                if targets.len() == count {
+                    if cue.armed {
+                        next_cursor = Some(cue.id);
+                        break;
+                    }
+                } else if cue.armed {
+                    targets.push(cue.id);
+                } else {
+                    self.send_skipped(cue.id, CueSkipReason::Disarmed);
+                }
+            }
+            (targets, next_cursor)
+        };
+        if targets.len() < count {
+            log::info!("GO MULTIPLE: Reached end of cue list after {} of {} cues.", targets.len(), count);
+            self.send_skipped(targets.last().copied().unwrap_or(cursor), CueSkipReason::EndOfList);
+        }
+        for cue_id in &targets {
+            self.handle_go(*cue_id).await?;
+        }
+        if let Some(cue_id) = next_cursor.or(targets.last().copied()) {
+            self.move_cursor(cue_id).await;
+        }
+        Ok(())
+    }
+
+    /// 実行時刻に達したキューを実行し、次の実行時刻までの時間を返します。
+    ///
+    /// 初めて確認した時点で既に過ぎていた実行時刻は、ショー設定の`missed_schedule_policy`に従って見送るか、すぐに実行します。
+    /// 無効化されたキューの実行時刻は確認しません。
+    async fn run_schedules(&self) -> Result<Option<Duration>, anyhow::Error> {
+        // 一時停止中は実行時刻を確認せず、解除後に改めて確認する
+        if self.state_tx.borrow().audio_held {
+            return Ok(None);
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_millis() as u64);
+        let (due_cue_ids, next_schedule) = {
+            let model = self.model_handle.read().await;
+            let policy = model.settings.general.missed_schedule_policy;
+            let mut pending_schedules = self.pending_schedules.lock().unwrap();
+            let mut handled_schedules = self.handled_schedules.lock().unwrap();
+            let mut due_cue_ids = Vec::new();
+            let mut next_schedule: Option<u64> = None;
+            for cue in model.cues.iter().filter(|cue| cue.armed) {
+                let Some(schedule) = cue.schedule else {
+                    continue;
+                };
+                if handled_schedules.get(&cue.id) == Some(&schedule) {
+                    continue;
+                }
+                if schedule > now {
+                    pending_schedules.insert(cue.id, schedule);
+                    next_schedule = Some(next_schedule.map_or(schedule, |next| next.min(schedule)));
+                    continue;
+                }
+                handled_schedules.insert(cue.id, schedule);
+                if pending_schedules.remove(&cue.id) == Some(schedule) || policy == MissedSchedulePolicy::FireImmediately {
+                    due_cue_ids.push(cue.id);
+                } else {
+                    log::warn!("SCHEDULE: Scheduled time of cue '{}' has already passed. Skipped.", cue.id);
+                }
+            }
+            (due_cue_ids, next_schedule)
+        };
+        for cue_id in due_cue_ids {
+            log::info!("SCHEDULE: Firing cue '{}'.", cue_id);
+            self.handle_go_from(cue_id).await?;
+        }
+        Ok(next_schedule.map(|schedule| Duration::from_millis(schedule - now)))
+    }
+
+    async fn handle_go(&self, cue_id: Uuid) -> Result<(), anyhow::Error> {
+        if self.skip_if_held(cue_id) {
+            return Ok(());
+        }
+        let model = self.model_handle.read().await;
+
+        if let Some(index) = model.cues.iter().position(|cue| cue.id.eq(&cue_id)) {
+            let cue = &model.cues[index];
+            if self.ignores_retrigger(cue) {
+                return Ok(());
+            }
+            let command = ExecutorCommand::ExecuteCue(cue_id);
+            self.executor_tx.send(command).await?;
+            // AutoContinueのキューは、実行してからpre_waitとpost_waitの後に次のキューを実行する
+            if cue.sequence == CueSequence::AutoContinue {
+                match model.cues.get(index + 1) {
+                    Some(next_cue) => self.continue_to(next_cue.id, cue.pre_wait + cue.post_wait),
+                    None => self.send_skipped(cue_id, CueSkipReason::EndOfList),
+                }
+            }
+        } else {
+            log::warn!("GO: Cue '{}' not found.", cue_id);
+            self.send_skipped(cue_id, CueSkipReason::Removed);
+        }
+        Ok(())
+    }
+
+    /// `wait`秒後に`cue_id`から実行するよう予約します。同じキューの予約は置き換えます。
+    fn continue_to(&self, cue_id: Uuid, wait: f64) {
+        let continue_at = Instant::now() + Duration::from_secs_f64(wait.max(0.0));
+        self.pending_continues.lock().unwrap().insert(cue_id, continue_at);
+    }
+
+    /// `continue_to`で予約した時刻に達したキューを実行し、次の予約までの時間を返します。
+    /// HoldAudioで一時停止中は実行しません。
+    async fn run_continues(&self) -> Result<Option<Duration>, anyhow::Error> {
+        if self.state_tx.borrow().audio_held {
+            return Ok(None);
+        }
+        let now = Instant::now();
+        let due_cue_ids: Vec<Uuid> = {
+            let mut pending_continues = self.pending_continues.lock().unwrap();
+            let due_cue_ids = pending_continues.iter().filter(|(_, continue_at)| **continue_at <= now).map(|(cue_id, _)| *cue_id).collect();
+            pending_continues.retain(|_, continue_at| *continue_at > now);
+            due_cue_ids
+        };
+        for cue_id in due_cue_ids {
+            log::info!("CONTINUE: Firing cue '{}'.", cue_id);
+            self.handle_go_from(cue_id).await?;
+        }
+        let next_continue = self.pending_continues.lock().unwrap().values().min().copied();
+        Ok(next_continue.map(|continue_at| continue_at.saturating_duration_since(Instant::now())))
+    }
+
+    /// AutoFollowのキューが次のキューを実行する時点に達した場合、次のキューのIDとキューの`post_wait`を返します。
+    ///
+    /// `follow_offset`が指定されている場合は再生位置がキューの終了から`follow_offset`秒の位置を超えた時点、
+    /// 指定されていない場合は完了した時点です。手動で停止したキューは次のキューを実行しません。
+    async fn auto_follow_target(&self, event: &ExecutorEvent) -> Option<(Uuid, f64)> {
+        let (cue_id, progress) = match event {
+            ExecutorEvent::Started { cue_id } => {
+                self.followed_cues.lock().unwrap().remove(cue_id);
+                return None;
+            }
+            ExecutorEvent::Progress { cue_id, position, duration } => (*cue_id, Some((*position, *duration))),
+            ExecutorEvent::Completed { cue_id, report } => {
+                // 再生中に実行済みの場合は、完了時に再び実行しない
+                if self.followed_cues.lock().unwrap().remove(cue_id) || report.was_stopped_manually {
+                    return None;
+                }
+                (*cue_id, None)
+            }
+            _ => return None,
+        };
+        let model = self.model_handle.read().await;
+        let index = model.cues.iter().position(|cue| cue.id == cue_id)?;
+        let cue = &model.cues[index];
+        if cue.sequence != CueSequence::AutoFollow {
+            return None;
+        }
+        if let Some((position, duration)) = progress {
+            let follow_offset = cue.follow_offset?;
+            if position < (duration + follow_offset).max(0.0) || !self.followed_cues.lock().unwrap().insert(cue_id) {
+                return None;
+            }
+            if -follow_offset > duration {
+                log::warn!(
+                    "Follow offset {}s of cue '{}' exceeds its duration {}s. Following from the start.",
+                    follow_offset, cue_id, duration
+                );
+            }
+        }
+        let Some(next_cue) = model.cues.get(index + 1) else {
+            self.send_skipped(cue_id, CueSkipReason::EndOfList);
+            return None;
+        };
+        let next_cue_id = next_cue.id;
+        log::info!("AUTO FOLLOW: {} -> {}", cue_id, next_cue_id);
+        Some((next_cue_id, cue.post_wait))
+    }
+
+    /// Executorからの再生イベントを処理します
+    async fn handle_executor_event(&self, event: ExecutorEvent) -> Result<(), anyhow::Error> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_millis() as u64);
+        if let Some(event_kind) = HistoryEventKind::from_event(&event) {
+            self.history.push(HistoryEntry { timestamp, cue_id: event.cue_id(), event_kind }).await;
+        }
+        let mut show_state = self.state_tx.borrow().clone();
+        let mut state_changed = false;
+
+        match &event {
+            ExecutorEvent::Loading { cue_id } => {
+                let active_cue = ActiveCue::new(*cue_id, PlaybackStatus::Loading, show_state.next_fire_order());
+                show_state.active_cues.insert(*cue_id, active_cue);
+                state_changed = true;
+            }
+            ExecutorEvent::Started { cue_id } => {
+                self.released_loops.lock().unwrap().remove(cue_id);
+                // 読み込み中のキューは、発火順を引き継ぐ
+                let fire_order = match show_state.active_cues.get(cue_id) {
+                    Some(active_cue) if active_cue.status == PlaybackStatus::Loading => active_cue.fire_order,
+                    _ => show_state.next_fire_order(),
+                };
+                let active_cue = ActiveCue::new(*cue_id, PlaybackStatus::Playing, fire_order);
+                show_state.active_cues.insert(*cue_id, active_cue);
+                show_state.last_played.insert(*cue_id, timestamp);
+                state_changed = true;
+            }
+            ExecutorEvent::Progress {
+                cue_id,
+                position,
+                duration,
+                ..
+            } => {
+                let looping = self.is_looping(cue_id).await;
+                if let Some(active_cue) = show_state.active_cues.get_mut(cue_id) {
+                    active_cue.set_position(*position, *duration, looping);
+                    active_cue.status = PlaybackStatus::Playing
+                } else {
+                    let mut active_cue = ActiveCue::new(*cue_id, PlaybackStatus::Playing, show_state.next_fire_order());
+                    active_cue.set_position(*position, *duration, looping);
+                    show_state.active_cues.insert(*cue_id, active_cue);
+                }
+                self.update_group_progress(&mut show_state, *cue_id).await;
+                state_changed = true;
+            }
+            ExecutorEvent::Paused {
+                cue_id,
+                position,
+                duration,
+            } => {
+                let looping = self.is_looping(cue_id).await;
+                if let Some(active_cue) = show_state.active_cues.get_mut(cue_id) {
+                    if !active_cue.status.eq(&PlaybackStatus::Paused) {
+                        active_cue.set_position(*position, *duration, looping);
+                        active_cue.status = PlaybackStatus::Paused;
+                        state_changed = true;
+                    }
+                } else {
+                    let mut active_cue = ActiveCue::new(*cue_id, PlaybackStatus::Paused, show_state.next_fire_order());
+                    active_cue.set_position(*position, *duration, looping);
+                    show_state.active_cues.insert(*cue_id, active_cue);
+                    state_changed = true;
+                }
+            }
+            ExecutorEvent::Meter { .. } => {}
+            ExecutorEvent::VoiceStolen { cue_id } => {
+                // 停止の完了はCompletedで反映する
+                log::warn!("State: Voice limit reached. Stopping cue '{}'.", cue_id);
+            }
+            // 試聴は再生中のキューとして扱わない
+            ExecutorEvent::AuditionStarted { .. } | ExecutorEvent::AuditionCompleted { .. } => {}
+            ExecutorEvent::Resumed { cue_id } => {
+                if let Some(active_cue) = show_state.active_cues.get_mut(cue_id)
+                    && !active_cue.status.eq(&PlaybackStatus::Playing)
+                {
+                    active_cue.status = PlaybackStatus::Playing;
+                    state_changed = true;
+                }
+            }
+            ExecutorEvent::Completed { cue_id, .. } => {
+                self.released_loops.lock().unwrap().remove(cue_id);
+                if show_state.active_cues.remove(cue_id).is_some() {
+                    state_changed = true;
+                }
+            }
+            ExecutorEvent::Error { cue_id, error, .. } => {
+                // 失敗したインスタンスはExecutorで破棄され、Completedは届かないため取り除く。エラーはUiEvent::CueErrorで通知する
+                if show_state.active_cues.remove(cue_id).is_some() {
+                    state_changed = true;
+                }
+                log::error!("State: Cue error on '{}': {}", cue_id, error);
+            }
+        }
+
+        if state_changed && self.state_tx.send(show_state).is_err() {
+            log::trace!("No UI clients are listening to state updates.");
+        }
+        let follow_target = self.auto_follow_target(&event).await;
+
+        let report_event = if let ExecutorEvent::Completed { cue_id, report } = &event {
+            Some(UiEvent::CuePlaybackReport {
+                cue_id: *cue_id,
+                played_duration: report.played_duration,
+                peak_level: report.peak_level,
+                was_stopped_manually: report.was_stopped_manually,
+            })
+        } else {
+            None
+        };
+        if !matches!(event, ExecutorEvent::Progress { .. })
+            && self.event_tx.send(UiEvent::from(event)).is_err()
+        {
+            log::trace!("No UI clients are listening to playback events.");
+        }
+        if let Some(report_event) = report_event
+            && self.event_tx.send(report_event).is_err()
+        {
+            log::trace!("No UI clients are listening to playback events.");
+        }
+        match follow_target {
+            Some((next_cue_id, post_wait)) if post_wait > 0.0 => self.continue_to(next_cue_id, post_wait),
+            Some((next_cue_id, _)) => self.handle_go_from(next_cue_id).await?,
+            None => {}
+        }
+        // TODO: ApiServerに状態変更を通知する
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::PathBuf;
+
+    use crate::{event::CueErrorKind, executor::PlaybackReport, manager::ShowModelManager, model::{
+        self,
+        cue::{AudioCueFadeParam, AudioCueLevels, Cue},
+        settings::ShowSettings,
+    }};
+
+    use super::*;
+
+    use kira::sound::Region;
+    use tokio::sync::{
+        mpsc::{self, Receiver, Sender},
+        watch,
+    };
+
+    fn make_cue(cue_id: Uuid) -> Cue {
+        Cue {
+            id: cue_id,
+            number: "1".to_string(),
+            name: "Play IGY".to_string(),
+            notes: "".to_string(),
+            pre_wait: 0.0,
+            post_wait: 0.0,
+            sequence: model::cue::CueSequence::DoNotContinue,
+            follow_offset: None,
+            armed: true,
+            retrigger_mode: RetriggerMode::Restart,
+            color: None,
+            order: 0.0,
+            schedule: None,
+            param: model::cue::CueParam::Audio {
+                target: PathBuf::from("./I.G.Y.flac"),
+                start_time: Some(5.0),
+                fade_in_param: Some(AudioCueFadeParam {
+                    duration: 2.0,
+                    easing: kira::Easing::Linear.into(),
+                }),
+                end_time: Some(50.0),
+                fade_out_param: Some(AudioCueFadeParam {
+                    duration: 5.0,
+                    easing: kira::Easing::InPowi(2).into(),
+                }),
+                levels: AudioCueLevels { master: 0.0 },
+                loop_region: Some(Region {
+                    start: kira::sound::PlaybackPosition::Seconds(2.0),
+                    end: kira::sound::EndPosition::EndOfAudio,
+                }),
+                loop_count: None,
+                stop_fade_param: None,
+                output_bus: None,
+                streaming: false,
+                normalize_to: None,
+                automation: None,
+            },
+        }
+    }
+
+    async fn setup_controller(
+        cue_ids: &[Uuid],
+    ) -> (
+        CueController,
+        Sender<ControllerCommand>,
+        Receiver<ExecutorCommand>,
+        Sender<ExecutorEvent>,
+        watch::Receiver<ShowState>,
+        broadcast::Receiver<UiEvent>,
+    ) {
+        setup_controller_with_cues(cue_ids.iter().map(|cue_id| make_cue(*cue_id)).collect()).await
+    }
+
+    async fn setup_controller_with_cues(
+        cues: Vec<Cue>,
+    ) -> (
+        CueController,
+        Sender<ControllerCommand>,
+        Receiver<ExecutorCommand>,
+        Sender<ExecutorEvent>,
+        watch::Receiver<ShowState>,
+        broadcast::Receiver<UiEvent>,
+    ) {
+        setup_controller_with_settings(cues, ShowSettings::default()).await
+    }
+
+    async fn setup_controller_with_settings(
+        cues: Vec<Cue>,
+        settings: ShowSettings,
+    ) -> (
         CueController,
         Sender<ControllerCommand>,
         Receiver<ExecutorCommand>,
@@ -293,88 +1311,407 @@ mod tests {
         manager
             .write_with(|model| {
                 model.name = "TestShowModel".to_string();
-                for cue_id in cue_ids {
-                    model.cues.push(Cue {
-                        id: *cue_id,
-                        number: "1".to_string(),
-                        name: "Play IGY".to_string(),
-                        notes: "".to_string(),
-                        pre_wait: 0.0,
-                        post_wait: 0.0,
-                        sequence: model::cue::CueSequence::DoNotContinue,
-                        param: model::cue::CueParam::Audio {
-                            target: PathBuf::from("./I.G.Y.flac"),
-                            start_time: Some(5.0),
-                            fade_in_param: Some(AudioCueFadeParam {
-                                duration: 2.0,
-                                easing: kira::Easing::Linear,
-                            }),
-                            end_time: Some(50.0),
-                            fade_out_param: Some(AudioCueFadeParam {
-                                duration: 5.0,
-                                easing: kira::Easing::InPowi(2),
-                            }),
-                            levels: AudioCueLevels { master: 0.0 },
-                            loop_region: Some(Region {
-                                start: kira::sound::PlaybackPosition::Seconds(2.0),
-                                end: kira::sound::EndPosition::EndOfAudio,
-                            }),
-                        },
-                    });
-                }
+                model.cues = cues;
+                model.settings = settings;
             })
             .await;
 
-        let controller = CueController::new(
-            handle.clone(),
-            exec_tx,
-            ctrl_rx,
-            playback_event_rx,
-            state_tx,
-            event_tx,
-        ).await;
+        let controller = CueController::new(
+            handle.clone(),
+            exec_tx,
+            ctrl_rx,
+            playback_event_rx,
+            state_tx,
+            event_tx,
+        ).await;
+
+        (controller, ctrl_tx, exec_rx, playback_event_tx, state_rx, event_rx)
+    }
+
+    #[tokio::test]
+    async fn go_command() {
+        let cue_id = Uuid::new_v4();
+        let (controller, ctrl_tx, mut exec_rx, _, _, _) = setup_controller(&[cue_id]).await;
+
+        tokio::spawn(controller.run(CancellationToken::new()));
+
+        ctrl_tx
+            .send(ControllerCommand::Go)
+            .await
+            .unwrap();
+
+        if let Some(ExecutorCommand::ExecuteCue(id)) = exec_rx.recv().await {
+            assert_eq!(id, cue_id);
+        } else {
+            unreachable!();
+        }
+    }
+
+    #[tokio::test]
+    async fn go_skips_disarmed_cue() {
+        let disarmed_cue_id = Uuid::new_v4();
+        let cue_id = Uuid::new_v4();
+        let mut disarmed_cue = make_cue(disarmed_cue_id);
+        disarmed_cue.armed = false;
+        let (controller, ctrl_tx, mut exec_rx, _, state_rx, mut event_rx) =
+            setup_controller_with_cues(vec![disarmed_cue, make_cue(cue_id)]).await;
+
+        tokio::spawn(controller.run(CancellationToken::new()));
+
+        ctrl_tx.send(ControllerCommand::Go).await.unwrap();
+
+        if let Some(ExecutorCommand::ExecuteCue(id)) = exec_rx.recv().await {
+            assert_eq!(id, cue_id);
+        } else {
+            unreachable!();
+        }
+        assert_eq!(
+            event_rx.recv().await.unwrap(),
+            UiEvent::CueSkipped { cue_id: disarmed_cue_id, reason: CueSkipReason::Disarmed }
+        );
+        assert_eq!(event_rx.recv().await.unwrap(), UiEvent::PlaybackCursorMoved { cue_id });
+        assert_eq!(state_rx.borrow().playback_cursor, Some(cue_id));
+    }
+
+    #[tokio::test]
+    async fn go_reports_skipped_cue() {
+        let cue_id = Uuid::new_v4();
+        let disarmed_cue_id = Uuid::new_v4();
+        let mut disarmed_cue = make_cue(disarmed_cue_id);
+        disarmed_cue.armed = false;
+        let (controller, ctrl_tx, mut exec_rx, _, _, mut event_rx) =
+            setup_controller_with_cues(vec![make_cue(cue_id), disarmed_cue]).await;
+
+        tokio::spawn(controller.run(CancellationToken::new()));
+
+        // 無効なキューより後に有効なキューがない
+        ctrl_tx.send(ControllerCommand::GoFromCue { cue_id: disarmed_cue_id }).await.unwrap();
+        assert_eq!(
+            event_rx.recv().await.unwrap(),
+            UiEvent::CueSkipped { cue_id: disarmed_cue_id, reason: CueSkipReason::Disarmed }
+        );
+        assert_eq!(
+            event_rx.recv().await.unwrap(),
+            UiEvent::CueSkipped { cue_id: disarmed_cue_id, reason: CueSkipReason::EndOfList }
+        );
+
+        // 削除されたキュー
+        let removed_cue_id = Uuid::new_v4();
+        ctrl_tx.send(ControllerCommand::GoFromCue { cue_id: removed_cue_id }).await.unwrap();
+        assert_eq!(
+            event_rx.recv().await.unwrap(),
+            UiEvent::PlaybackCursorMoved { cue_id: removed_cue_id }
+        );
+        assert_eq!(
+            event_rx.recv().await.unwrap(),
+            UiEvent::CueSkipped { cue_id: removed_cue_id, reason: CueSkipReason::Removed }
+        );
+        assert!(exec_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn go_multiple() {
+        let cue_ids: Vec<Uuid> = (0..5).map(|_| Uuid::new_v4()).collect();
+        let mut cues: Vec<Cue> = cue_ids.iter().map(|cue_id| make_cue(*cue_id)).collect();
+        cues[1].armed = false;
+        let (controller, ctrl_tx, mut exec_rx, _, mut state_rx, mut event_rx) = setup_controller_with_cues(cues).await;
+
+        tokio::spawn(controller.run(CancellationToken::new()));
+
+        // 無効なキューを飛ばして2個実行し、次の有効なキューに再生カーソルを進める
+        ctrl_tx.send(ControllerCommand::GoMultiple { count: 2 }).await.unwrap();
+        for cue_id in [cue_ids[0], cue_ids[2]] {
+            assert!(matches!(exec_rx.recv().await, Some(ExecutorCommand::ExecuteCue(id)) if id == cue_id));
+        }
+        assert_eq!(
+            event_rx.recv().await.unwrap(),
+            UiEvent::CueSkipped { cue_id: cue_ids[1], reason: CueSkipReason::Disarmed }
+        );
+        assert_eq!(event_rx.recv().await.unwrap(), UiEvent::PlaybackCursorMoved { cue_id: cue_ids[3] });
+        state_rx.wait_for(|state| state.playback_cursor == Some(cue_ids[3])).await.unwrap();
+
+        // 残りのキューが足りない場合は最後のキューまで実行する
+        ctrl_tx.send(ControllerCommand::GoMultiple { count: 3 }).await.unwrap();
+        for cue_id in [cue_ids[3], cue_ids[4]] {
+            assert!(matches!(exec_rx.recv().await, Some(ExecutorCommand::ExecuteCue(id)) if id == cue_id));
+        }
+        assert_eq!(
+            event_rx.recv().await.unwrap(),
+            UiEvent::CueSkipped { cue_id: cue_ids[4], reason: CueSkipReason::EndOfList }
+        );
+        assert_eq!(event_rx.recv().await.unwrap(), UiEvent::PlaybackCursorMoved { cue_id: cue_ids[4] });
+        assert!(exec_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn retrigger_ignored_while_active() {
+        let cue_id = Uuid::new_v4();
+        let mut cue = make_cue(cue_id);
+        cue.retrigger_mode = RetriggerMode::Ignore;
+        let (controller, ctrl_tx, mut exec_rx, playback_event_tx, mut state_rx, _) = setup_controller_with_cues(vec![cue]).await;
+
+        tokio::spawn(controller.run(CancellationToken::new()));
+
+        ctrl_tx.send(ControllerCommand::GoFromCue { cue_id }).await.unwrap();
+        assert!(matches!(exec_rx.recv().await, Some(ExecutorCommand::ExecuteCue(id)) if id == cue_id));
+        playback_event_tx.send(ExecutorEvent::Started { cue_id }).await.unwrap();
+        state_rx.wait_for(|state| state.active_cues.contains_key(&cue_id)).await.unwrap();
+
+        // 再生中は同じキューを実行しない。タイムコードやクロスフェードからの実行も同様
+        ctrl_tx.send(ControllerCommand::GoFromCue { cue_id }).await.unwrap();
+        ctrl_tx.send(ControllerCommand::GoAtTimecode { cue_id, frame: 0 }).await.unwrap();
+        ctrl_tx
+            .send(ControllerCommand::Crossfade { from_cue_id: Uuid::new_v4(), to_cue_id: cue_id, duration: 1.0, easing: FadeCurve::default() })
+            .await
+            .unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        assert!(exec_rx.try_recv().is_err());
+
+        // 完了後は再び実行できる
+        let report = PlaybackReport { played_duration: 1.0, peak_level: -6.0, was_stopped_manually: false };
+        playback_event_tx.send(ExecutorEvent::Completed { cue_id, report }).await.unwrap();
+        state_rx.wait_for(|state| !state.active_cues.contains_key(&cue_id)).await.unwrap();
+        ctrl_tx.send(ControllerCommand::GoFromCue { cue_id }).await.unwrap();
+        assert!(matches!(exec_rx.recv().await, Some(ExecutorCommand::ExecuteCue(id)) if id == cue_id));
+    }
+
+    #[tokio::test]
+    async fn go_from_cue_command() {
+        let cue_id = Uuid::new_v4();
+        let cue_id_next = Uuid::new_v4();
+        let (controller, ctrl_tx, mut exec_rx, _, state_rx, mut event_rx) = setup_controller(&[cue_id, cue_id_next]).await;
+
+        tokio::spawn(controller.run(CancellationToken::new()));
+
+        ctrl_tx.send(ControllerCommand::GoFromCue { cue_id: cue_id_next }).await.unwrap();
+
+        if let Some(ExecutorCommand::ExecuteCue(id)) = exec_rx.recv().await {
+            assert_eq!(id, cue_id_next);
+        } else {
+            unreachable!();
+        }
+        assert_eq!(event_rx.recv().await.unwrap(), UiEvent::PlaybackCursorMoved { cue_id: cue_id_next });
+        assert_eq!(state_rx.borrow().playback_cursor, Some(cue_id_next));
+    }
+
+    #[tokio::test]
+    async fn set_playback_cursor() {
+        let cue_id = Uuid::new_v4();
+        println!("{}", cue_id);
+        let cue_id_next = Uuid::new_v4();
+        println!("{}", cue_id_next);
+        let (controller, ctrl_tx, _, _, state_rx, mut event_rx) = setup_controller(&[cue_id, cue_id_next]).await;
+
+        tokio::spawn(controller.run(CancellationToken::new()));
+
+        assert_eq!(state_rx.borrow().playback_cursor, Some(cue_id));
+
+        ctrl_tx.send(ControllerCommand::SetPlaybackCursor { cue_id: cue_id_next }).await.unwrap();
+
+        let event = event_rx.recv().await.unwrap();
+        assert_eq!(event, UiEvent::PlaybackCursorMoved { cue_id: cue_id_next });
+        if let Some(playback_cursor) = state_rx.borrow().playback_cursor {
+            assert_eq!(playback_cursor, cue_id_next);
+        }
+    }
+
+    #[tokio::test]
+    async fn set_playback_cursor_to_missing_cue() {
+        let cue_id = Uuid::new_v4();
+        let cue_id_next = Uuid::new_v4();
+        let (controller, ctrl_tx, _, _, state_rx, mut event_rx) = setup_controller(&[cue_id, cue_id_next]).await;
+
+        tokio::spawn(controller.run(CancellationToken::new()));
+
+        ctrl_tx.send(ControllerCommand::SetPlaybackCursor { cue_id: Uuid::new_v4() }).await.unwrap();
+        ctrl_tx.send(ControllerCommand::SetPlaybackCursor { cue_id: cue_id_next }).await.unwrap();
+
+        // 存在しないキューにはカーソルを移動せず、イベントも送信しない
+        assert_eq!(event_rx.recv().await.unwrap(), UiEvent::PlaybackCursorMoved { cue_id: cue_id_next });
+        assert_eq!(state_rx.borrow().playback_cursor, Some(cue_id_next));
+    }
+
+    #[tokio::test]
+    async fn toggle_cue_command() {
+        let cue_id = Uuid::new_v4();
+        let (controller, ctrl_tx, mut exec_rx, playback_event_tx, _state_rx, mut event_rx) = setup_controller(&[cue_id]).await;
+
+        tokio::spawn(controller.run(CancellationToken::new()));
+
+        ctrl_tx
+            .send(ControllerCommand::ToggleCue { cue_id })
+            .await
+            .unwrap();
+
+        if let Some(ExecutorCommand::ExecuteCue(id)) = exec_rx.recv().await {
+            assert_eq!(id, cue_id);
+        } else {
+            unreachable!();
+        }
+
+        playback_event_tx
+            .send(ExecutorEvent::Started { cue_id })
+            .await
+            .unwrap();
+        assert_eq!(event_rx.recv().await.unwrap(), UiEvent::CueStarted { cue_id });
+
+        ctrl_tx
+            .send(ControllerCommand::ToggleCue { cue_id })
+            .await
+            .unwrap();
+
+        if let Some(ExecutorCommand::StopCue(id)) = exec_rx.recv().await {
+            assert_eq!(id, cue_id);
+        } else {
+            unreachable!();
+        }
+    }
+
+    #[tokio::test]
+    async fn hold_n_release_audio_command() {
+        let cue_id = Uuid::new_v4();
+        let paused_cue_id = Uuid::new_v4();
+        let (controller, ctrl_tx, mut exec_rx, playback_event_tx, mut state_rx, mut event_rx) =
+            setup_controller(&[cue_id, paused_cue_id]).await;
+
+        tokio::spawn(controller.run(CancellationToken::new()));
+
+        for cue_id in [cue_id, paused_cue_id] {
+            playback_event_tx
+                .send(ExecutorEvent::Progress { cue_id, position: 12.0, duration: 50.0 })
+                .await
+                .unwrap();
+        }
+        playback_event_tx
+            .send(ExecutorEvent::Paused { cue_id: paused_cue_id, position: 12.0, duration: 50.0 })
+            .await
+            .unwrap();
+        assert_eq!(event_rx.recv().await.unwrap(), UiEvent::CuePaused { cue_id: paused_cue_id });
+        state_rx.mark_unchanged();
+
+        // 既に一時停止しているキューは対象にしない
+        ctrl_tx.send(ControllerCommand::HoldAudio).await.unwrap();
+        assert!(matches!(exec_rx.recv().await, Some(ExecutorCommand::PauseCue(id)) if id == cue_id));
+        state_rx.changed().await.unwrap();
+        assert!(state_rx.borrow().audio_held);
+
+        playback_event_tx
+            .send(ExecutorEvent::Paused { cue_id, position: 12.0, duration: 50.0 })
+            .await
+            .unwrap();
+        assert_eq!(event_rx.recv().await.unwrap(), UiEvent::CuePaused { cue_id });
+        state_rx.mark_unchanged();
+
+        // HoldAudioで一時停止したキューだけを再開する
+        ctrl_tx.send(ControllerCommand::ReleaseAudio).await.unwrap();
+        assert!(matches!(exec_rx.recv().await, Some(ExecutorCommand::ResumeCue(id)) if id == cue_id));
+        state_rx.changed().await.unwrap();
+        assert!(exec_rx.try_recv().is_err());
+        let state = state_rx.borrow().clone();
+        assert!(!state.audio_held);
+        assert_eq!(state.active_cues.get(&paused_cue_id).unwrap().status, PlaybackStatus::Paused);
+        let active_cue = state.active_cues.get(&cue_id).unwrap();
+        assert_eq!(active_cue.position, 12.0);
+        assert_eq!(active_cue.duration, 50.0);
+    }
+
+    #[tokio::test]
+    async fn go_while_audio_held() {
+        let cue_ids = [Uuid::new_v4(), Uuid::new_v4()];
+        let (controller, _, mut exec_rx, _, state_rx, mut event_rx) = setup_controller(&cue_ids).await;
 
-        (controller, ctrl_tx, exec_rx, playback_event_tx, state_rx, event_rx)
+        controller.handle_command(ControllerCommand::HoldAudio).await.unwrap();
+        controller.handle_command(ControllerCommand::Go).await.unwrap();
+        assert!(exec_rx.try_recv().is_err());
+        assert_eq!(event_rx.try_recv().unwrap(), UiEvent::CueSkipped { cue_id: cue_ids[0], reason: CueSkipReason::AudioHeld });
+        assert_eq!(state_rx.borrow().playback_cursor, Some(cue_ids[0]));
+
+        controller.handle_command(ControllerCommand::ReleaseAudio).await.unwrap();
+        controller.handle_command(ControllerCommand::Go).await.unwrap();
+        assert!(matches!(exec_rx.try_recv(), Ok(ExecutorCommand::ExecuteCue(id)) if id == cue_ids[0]));
     }
 
     #[tokio::test]
-    async fn go_command() {
+    async fn auto_follow_while_audio_held() {
         let cue_id = Uuid::new_v4();
-        let (controller, ctrl_tx, mut exec_rx, _, _, _) = setup_controller(&[cue_id]).await;
+        let next_cue_id = Uuid::new_v4();
+        let mut cue = make_cue(cue_id);
+        cue.sequence = CueSequence::AutoFollow;
+        let (controller, _, mut exec_rx, _, _state_rx, mut event_rx) =
+            setup_controller_with_cues(vec![cue, make_cue(next_cue_id)]).await;
+
+        controller.handle_executor_event(ExecutorEvent::Started { cue_id }).await.unwrap();
+        controller.handle_command(ControllerCommand::HoldAudio).await.unwrap();
+        assert!(matches!(exec_rx.try_recv(), Ok(ExecutorCommand::PauseCue(id)) if id == cue_id));
+        let report = PlaybackReport { played_duration: 10.0, peak_level: -6.0, was_stopped_manually: false };
+        controller.handle_executor_event(ExecutorEvent::Completed { cue_id, report }).await.unwrap();
+        assert!(exec_rx.try_recv().is_err());
+        let skipped = std::iter::from_fn(|| event_rx.try_recv().ok())
+            .any(|event| event == UiEvent::CueSkipped { cue_id: next_cue_id, reason: CueSkipReason::AudioHeld });
+        assert!(skipped);
+    }
 
-        tokio::spawn(controller.run());
+    #[tokio::test]
+    async fn schedule_while_audio_held() {
+        let cue_id = Uuid::new_v4();
+        let mut cue = make_cue(cue_id);
+        cue.schedule = Some(now_millis() + 50);
+        let (controller, ctrl_tx, mut exec_rx, _, mut state_rx, _) =
+            setup_controller_with_cues(vec![make_cue(Uuid::new_v4()), cue]).await;
 
-        ctrl_tx
-            .send(ControllerCommand::Go)
-            .await
-            .unwrap();
+        tokio::spawn(controller.run(CancellationToken::new()));
 
-        if let Some(ExecutorCommand::ExecuteCue(id)) = exec_rx.recv().await {
-            assert_eq!(id, cue_id);
-        } else {
-            unreachable!();
-        }
+        ctrl_tx.send(ControllerCommand::HoldAudio).await.unwrap();
+        state_rx.changed().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(exec_rx.try_recv().is_err());
+
+        // 一時停止中に過ぎた実行時刻は、`missed_schedule_policy`に従って見送る
+        ctrl_tx.send(ControllerCommand::ReleaseAudio).await.unwrap();
+        state_rx.changed().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(exec_rx.try_recv().is_err());
     }
 
     #[tokio::test]
-    async fn set_playback_cursor() {
-        let cue_id = Uuid::new_v4();
-        println!("{}", cue_id);
-        let cue_id_next = Uuid::new_v4();
-        println!("{}", cue_id_next);
-        let (controller, ctrl_tx, _, _, state_rx, mut event_rx) = setup_controller(&[cue_id, cue_id_next]).await;
+    async fn pause_n_resume_all_command() {
+        let cue_ids = [Uuid::new_v4(), Uuid::new_v4()];
+        let (controller, ctrl_tx, mut exec_rx, playback_event_tx, mut state_rx, mut event_rx) = setup_controller(&cue_ids).await;
 
-        tokio::spawn(controller.run());
+        tokio::spawn(controller.run(CancellationToken::new()));
 
-        assert_eq!(state_rx.borrow().playback_cursor, Some(cue_id));
+        for cue_id in cue_ids {
+            playback_event_tx
+                .send(ExecutorEvent::Progress { cue_id, position: 12.0, duration: 50.0 })
+                .await
+                .unwrap();
+        }
+        playback_event_tx
+            .send(ExecutorEvent::Paused { cue_id: cue_ids[1], position: 12.0, duration: 50.0 })
+            .await
+            .unwrap();
+        assert_eq!(event_rx.recv().await.unwrap(), UiEvent::CuePaused { cue_id: cue_ids[1] });
+        state_rx.mark_unchanged();
 
-        ctrl_tx.send(ControllerCommand::SetPlaybackCursor { cue_id: cue_id_next }).await.unwrap();
+        // 既に一時停止しているキューは対象にしない
+        ctrl_tx.send(ControllerCommand::PauseAll).await.unwrap();
+        assert!(matches!(exec_rx.recv().await, Some(ExecutorCommand::PauseCue(id)) if id == cue_ids[0]));
+        playback_event_tx
+            .send(ExecutorEvent::Paused { cue_id: cue_ids[0], position: 12.5, duration: 50.0 })
+            .await
+            .unwrap();
+        assert_eq!(event_rx.recv().await.unwrap(), UiEvent::CuePaused { cue_id: cue_ids[0] });
 
-        let event = event_rx.recv().await.unwrap();
-        assert_eq!(event, UiEvent::PlaybackCursorMoved { cue_id: cue_id_next });
-        if let Some(playback_cursor) = state_rx.borrow().playback_cursor {
-            assert_eq!(playback_cursor, cue_id_next);
+        ctrl_tx.send(ControllerCommand::ResumeAll).await.unwrap();
+        for cue_id in cue_ids {
+            assert!(matches!(exec_rx.recv().await, Some(ExecutorCommand::ResumeCue(id)) if id == cue_id));
         }
+        playback_event_tx.send(ExecutorEvent::Resumed { cue_id: cue_ids[0] }).await.unwrap();
+        assert_eq!(event_rx.recv().await.unwrap(), UiEvent::CueResumed { cue_id: cue_ids[0] });
+
+        // 再生中のキューは対象にしない
+        ctrl_tx.send(ControllerCommand::ResumeAll).await.unwrap();
+        assert!(matches!(exec_rx.recv().await, Some(ExecutorCommand::ResumeCue(id)) if id == cue_ids[1]));
     }
 
     #[tokio::test]
@@ -382,7 +1719,7 @@ mod tests {
         let cue_id = Uuid::new_v4();
         let (controller, _, _, playback_event_tx, state_rx, mut event_rx) = setup_controller(&[cue_id]).await;
 
-        tokio::spawn(controller.run());
+        tokio::spawn(controller.run(CancellationToken::new()));
 
         playback_event_tx
             .send(ExecutorEvent::Started { cue_id })
@@ -401,13 +1738,84 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn last_played_on_started() {
+        let cue_ids = [Uuid::new_v4(), Uuid::new_v4()];
+        let (controller, _, _, playback_event_tx, mut state_rx, _) = setup_controller(&cue_ids).await;
+
+        tokio::spawn(controller.run(CancellationToken::new()));
+
+        let before = now_millis();
+        playback_event_tx.send(ExecutorEvent::Started { cue_id: cue_ids[0] }).await.unwrap();
+        let first_played = state_rx.wait_for(|state| state.last_played.contains_key(&cue_ids[0])).await.unwrap().last_played[&cue_ids[0]];
+        assert!(first_played >= before && first_played <= now_millis());
+        assert!(!state_rx.borrow().last_played.contains_key(&cue_ids[1]));
+
+        // 完了後も残り、再び開始した時刻で更新する
+        let report = PlaybackReport { played_duration: 1.0, peak_level: -6.0, was_stopped_manually: false };
+        playback_event_tx.send(ExecutorEvent::Completed { cue_id: cue_ids[0], report }).await.unwrap();
+        state_rx.wait_for(|state| state.active_cues.is_empty()).await.unwrap();
+        assert_eq!(state_rx.borrow().last_played[&cue_ids[0]], first_played);
+
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        playback_event_tx.send(ExecutorEvent::Started { cue_id: cue_ids[0] }).await.unwrap();
+        state_rx.wait_for(|state| state.last_played[&cue_ids[0]] > first_played).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn loading_event() {
+        let cue_ids = [Uuid::new_v4(), Uuid::new_v4()];
+        let (controller, _, _, playback_event_tx, state_rx, mut event_rx) = setup_controller(&cue_ids).await;
+
+        tokio::spawn(controller.run(CancellationToken::new()));
+
+        playback_event_tx.send(ExecutorEvent::Loading { cue_id: cue_ids[0] }).await.unwrap();
+        assert_eq!(event_rx.recv().await.unwrap(), UiEvent::CueLoading { cue_id: cue_ids[0] });
+        assert_eq!(state_rx.borrow().active_cues[&cue_ids[0]].status, PlaybackStatus::Loading);
+
+        // 後から発火したキューが先に読み込みを終えても、発火順は変わらない
+        playback_event_tx.send(ExecutorEvent::Started { cue_id: cue_ids[1] }).await.unwrap();
+        assert_eq!(event_rx.recv().await.unwrap(), UiEvent::CueStarted { cue_id: cue_ids[1] });
+        playback_event_tx.send(ExecutorEvent::Started { cue_id: cue_ids[0] }).await.unwrap();
+        assert_eq!(event_rx.recv().await.unwrap(), UiEvent::CueStarted { cue_id: cue_ids[0] });
+
+        let state = state_rx.borrow().clone();
+        assert_eq!(state.active_cues[&cue_ids[0]].status, PlaybackStatus::Playing);
+        let order: Vec<Uuid> = state.active_cues_in_fire_order().iter().map(|active_cue| active_cue.cue_id).collect();
+        assert_eq!(order, cue_ids.to_vec());
+    }
+
+    #[tokio::test]
+    async fn active_cues_fire_order() {
+        let cue_ids = [Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4()];
+        let (controller, _, _, playback_event_tx, state_rx, mut event_rx) = setup_controller(&cue_ids).await;
+
+        tokio::spawn(controller.run(CancellationToken::new()));
+
+        for cue_id in [cue_ids[2], cue_ids[0], cue_ids[1]] {
+            playback_event_tx
+                .send(ExecutorEvent::Started { cue_id })
+                .await
+                .unwrap();
+            assert_eq!(event_rx.recv().await.unwrap(), UiEvent::CueStarted { cue_id });
+        }
+
+        let order: Vec<Uuid> = state_rx
+            .borrow()
+            .active_cues_in_fire_order()
+            .iter()
+            .map(|active_cue| active_cue.cue_id)
+            .collect();
+        assert_eq!(order, vec![cue_ids[2], cue_ids[0], cue_ids[1]]);
+    }
+
     #[tokio::test]
     async fn progress_event() {
         let cue_id = Uuid::new_v4();
         let (controller, _, _, playback_event_tx, mut state_rx, event_rx) = setup_controller(&[cue_id]).await;
         state_rx.mark_unchanged();
 
-        tokio::spawn(controller.run());
+        tokio::spawn(controller.run(CancellationToken::new()));
 
         playback_event_tx
             .send(ExecutorEvent::Progress {
@@ -430,12 +1838,41 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn group_progress_tracks_longest_child() {
+        let short_cue_id = Uuid::new_v4();
+        let long_cue_id = Uuid::new_v4();
+        let group_id = Uuid::new_v4();
+        let mut group_cue = make_cue(group_id);
+        group_cue.param = CueParam::Group { children: vec![short_cue_id, long_cue_id] };
+        let (controller, _, _, playback_event_tx, mut state_rx, _) =
+            setup_controller_with_cues(vec![group_cue, make_cue(short_cue_id), make_cue(long_cue_id)]).await;
+
+        tokio::spawn(controller.run(CancellationToken::new()));
+
+        for cue_id in [group_id, short_cue_id, long_cue_id] {
+            playback_event_tx.send(ExecutorEvent::Started { cue_id }).await.unwrap();
+        }
+        let progress = [(short_cue_id, 5.0, 10.0), (long_cue_id, 7.0, 30.0), (short_cue_id, 6.0, 10.0)];
+        for (cue_id, position, duration) in progress {
+            playback_event_tx.send(ExecutorEvent::Progress { cue_id, position, duration }).await.unwrap();
+        }
+
+        state_rx
+            .wait_for(|state| state.active_cues.get(&short_cue_id).is_some_and(|active_cue| active_cue.position == 6.0))
+            .await
+            .unwrap();
+        let group = state_rx.borrow().active_cues.get(&group_id).unwrap().clone();
+        assert_eq!(group.position, 7.0);
+        assert_eq!(group.duration, 30.0);
+    }
+
     #[tokio::test]
     async fn pause_n_resume_event() {
         let cue_id = Uuid::new_v4();
         let (controller, _, _, playback_event_tx, state_rx, mut event_rx) = setup_controller(&[cue_id]).await;
 
-        tokio::spawn(controller.run());
+        tokio::spawn(controller.run(CancellationToken::new()));
 
         playback_event_tx
             .send(ExecutorEvent::Paused {
@@ -479,15 +1916,474 @@ mod tests {
         let cue_id = Uuid::new_v4();
         let (controller, _, _, playback_event_tx, state_rx, mut event_rx) = setup_controller(&[cue_id]).await;
 
-        tokio::spawn(controller.run());
+        tokio::spawn(controller.run(CancellationToken::new()));
 
+        let report = PlaybackReport { played_duration: 12.5, peak_level: -3.0, was_stopped_manually: true };
         playback_event_tx
-            .send(ExecutorEvent::Completed { cue_id })
+            .send(ExecutorEvent::Completed { cue_id, report })
             .await
             .unwrap();
 
         let event = event_rx.recv().await.unwrap();
         assert!(event.eq(&UiEvent::CueCompleted { cue_id }));
+        let event = event_rx.recv().await.unwrap();
+        assert_eq!(event, UiEvent::CuePlaybackReport { cue_id, played_duration: 12.5, peak_level: -3.0, was_stopped_manually: true });
         assert!(!state_rx.borrow().active_cues.contains_key(&cue_id));
     }
+
+    #[tokio::test]
+    async fn panic_clears_active_cues() {
+        let cue_ids = [Uuid::new_v4(), Uuid::new_v4()];
+        let (controller, ctrl_tx, mut exec_rx, playback_event_tx, state_rx, mut event_rx) = setup_controller(&cue_ids).await;
+
+        tokio::spawn(controller.run(CancellationToken::new()));
+
+        for cue_id in cue_ids {
+            playback_event_tx.send(ExecutorEvent::Started { cue_id }).await.unwrap();
+            assert_eq!(event_rx.recv().await.unwrap(), UiEvent::CueStarted { cue_id });
+        }
+
+        ctrl_tx.send(ControllerCommand::Panic).await.unwrap();
+        assert!(matches!(exec_rx.recv().await, Some(ExecutorCommand::Panic)));
+        // 停止の完了を待たずに、発火順に終了を通知する
+        for cue_id in cue_ids {
+            assert_eq!(event_rx.recv().await.unwrap(), UiEvent::CueCompleted { cue_id });
+        }
+        assert_eq!(event_rx.recv().await.unwrap(), UiEvent::PanicTriggered { cue_ids: cue_ids.to_vec() });
+        assert!(state_rx.borrow().active_cues.is_empty());
+    }
+
+    #[tokio::test]
+    async fn set_preview_mode() {
+        let (controller, ctrl_tx, mut exec_rx, _, mut state_rx, _) = setup_controller(&[Uuid::new_v4()]).await;
+
+        tokio::spawn(controller.run(CancellationToken::new()));
+
+        ctrl_tx.send(ControllerCommand::SetPreviewMode { enabled: true }).await.unwrap();
+        assert!(matches!(exec_rx.recv().await, Some(ExecutorCommand::SetPreviewMode(true))));
+        state_rx.wait_for(|state| state.preview).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn error_event() {
+        let cue_id = Uuid::new_v4();
+        let (controller, _, _, playback_event_tx, mut state_rx, mut event_rx) = setup_controller(&[cue_id]).await;
+
+        tokio::spawn(controller.run(CancellationToken::new()));
+
+        playback_event_tx.send(ExecutorEvent::Started { cue_id }).await.unwrap();
+        state_rx.wait_for(|state| state.active_cues.contains_key(&cue_id)).await.unwrap();
+        assert!(matches!(event_rx.recv().await.unwrap(), UiEvent::CueStarted { .. }));
+        playback_event_tx
+            .send(ExecutorEvent::Error { cue_id, error: "File not found.".to_string(), kind: CueErrorKind::MediaUnavailable })
+            .await
+            .unwrap();
+
+        let event = event_rx.recv().await.unwrap();
+        assert!(event.eq(&UiEvent::CueError { cue_id, error: "File not found.".to_string(), kind: CueErrorKind::MediaUnavailable }));
+        // 失敗したキューは再生中のキューに残さない
+        assert!(state_rx.borrow().active_cues.is_empty());
+    }
+
+    #[tokio::test]
+    async fn toggle_after_error() {
+        let cue_id = Uuid::new_v4();
+        let (controller, ctrl_tx, mut exec_rx, playback_event_tx, _, mut event_rx) = setup_controller(&[cue_id]).await;
+
+        tokio::spawn(controller.run(CancellationToken::new()));
+
+        // 開始前に失敗したキューは、トグルで停止せずに再び実行する
+        playback_event_tx
+            .send(ExecutorEvent::Error { cue_id, error: "File not found.".to_string(), kind: CueErrorKind::MediaUnavailable })
+            .await
+            .unwrap();
+        assert!(matches!(event_rx.recv().await.unwrap(), UiEvent::CueError { .. }));
+        ctrl_tx.send(ControllerCommand::ToggleCue { cue_id }).await.unwrap();
+        assert!(matches!(exec_rx.recv().await, Some(ExecutorCommand::ExecuteCue(id)) if id == cue_id));
+    }
+
+    #[tokio::test]
+    async fn history() {
+        let cue_id = Uuid::new_v4();
+        let (controller, _, _, playback_event_tx, _, mut event_rx) = setup_controller(&[cue_id]).await;
+        let history = controller.history();
+
+        tokio::spawn(controller.run(CancellationToken::new()));
+
+        playback_event_tx.send(ExecutorEvent::Started { cue_id }).await.unwrap();
+        playback_event_tx.send(ExecutorEvent::Progress { cue_id, position: 1.0, duration: 2.0 }).await.unwrap();
+        playback_event_tx.send(ExecutorEvent::Completed { cue_id, report: PlaybackReport { played_duration: 2.0, peak_level: -6.0, was_stopped_manually: false } }).await.unwrap();
+        while !matches!(event_rx.recv().await.unwrap(), UiEvent::CuePlaybackReport { .. }) {}
+
+        let kinds: Vec<HistoryEventKind> = history.recent(10).await.iter().map(|entry| entry.event_kind).collect();
+        assert_eq!(kinds, vec![HistoryEventKind::Started, HistoryEventKind::Completed]);
+        let kinds: Vec<HistoryEventKind> = history.recent(1).await.iter().map(|entry| entry.event_kind).collect();
+        assert_eq!(kinds, vec![HistoryEventKind::Completed]);
+    }
+
+    #[tokio::test]
+    async fn history_is_bounded() {
+        let history = CueHistory::default();
+        for timestamp in 0..(HISTORY_CAPACITY as u64 + 10) {
+            history.push(HistoryEntry { timestamp, cue_id: Uuid::nil(), event_kind: HistoryEventKind::Started }).await;
+        }
+
+        let entries = history.recent(usize::MAX).await;
+        assert_eq!(entries.len(), HISTORY_CAPACITY);
+        assert_eq!(entries[0].timestamp, 10);
+    }
+
+    #[tokio::test]
+    async fn upcoming_cues_from_cursor() {
+        let cue_ids: Vec<Uuid> = (0..6).map(|_| Uuid::new_v4()).collect();
+        let mut cues: Vec<Cue> = cue_ids.iter().map(|cue_id| make_cue(*cue_id)).collect();
+        cues[2].armed = false;
+        let (controller, ctrl_tx, _, _, state_rx, mut event_rx) = setup_controller_with_cues(cues).await;
+
+        assert_eq!(state_rx.borrow().upcoming, vec![cue_ids[0], cue_ids[1], cue_ids[3]]);
+
+        tokio::spawn(controller.run(CancellationToken::new()));
+
+        ctrl_tx.send(ControllerCommand::SetPlaybackCursor { cue_id: cue_ids[1] }).await.unwrap();
+        assert_eq!(event_rx.recv().await.unwrap(), UiEvent::PlaybackCursorMoved { cue_id: cue_ids[1] });
+        assert_eq!(state_rx.borrow().upcoming, vec![cue_ids[1], cue_ids[3], cue_ids[4]]);
+
+        ctrl_tx.send(ControllerCommand::SetPlaybackCursor { cue_id: cue_ids[4] }).await.unwrap();
+        assert_eq!(event_rx.recv().await.unwrap(), UiEvent::PlaybackCursorMoved { cue_id: cue_ids[4] });
+        assert_eq!(state_rx.borrow().upcoming, vec![cue_ids[4], cue_ids[5]]);
+    }
+
+    fn now_millis() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64
+    }
+
+    #[tokio::test]
+    async fn scheduled_cue() {
+        let cue_id = Uuid::new_v4();
+        let mut cue = make_cue(cue_id);
+        cue.schedule = Some(now_millis() + 50);
+        let (controller, _, mut exec_rx, _, state_rx, _) = setup_controller_with_cues(vec![make_cue(Uuid::new_v4()), cue]).await;
+
+        tokio::spawn(controller.run(CancellationToken::new()));
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(exec_rx.try_recv().is_err());
+        assert!(matches!(exec_rx.recv().await, Some(ExecutorCommand::ExecuteCue(id)) if id == cue_id));
+        assert_eq!(state_rx.borrow().playback_cursor, Some(cue_id));
+
+        // 同じ実行時刻では再び実行しない
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(exec_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn missed_schedule() {
+        let cue_id = Uuid::new_v4();
+        let mut cue = make_cue(cue_id);
+        cue.schedule = Some(now_millis() - 1000);
+        let (controller, _, mut exec_rx, _, _, _) = setup_controller_with_cues(vec![cue.clone()]).await;
+
+        tokio::spawn(controller.run(CancellationToken::new()));
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(exec_rx.try_recv().is_err());
+
+        let mut settings = ShowSettings::default();
+        settings.general.missed_schedule_policy = MissedSchedulePolicy::FireImmediately;
+        let (controller, _, mut exec_rx, _, _, _) = setup_controller_with_settings(vec![cue], settings).await;
+
+        tokio::spawn(controller.run(CancellationToken::new()));
+
+        assert!(matches!(exec_rx.recv().await, Some(ExecutorCommand::ExecuteCue(id)) if id == cue_id));
+    }
+
+    #[tokio::test]
+    async fn reset_cursor() {
+        let cue_ids = [Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4()];
+        let (controller, _, _, _, state_rx, mut event_rx) = setup_controller(&cue_ids).await;
+        controller.handle_command(ControllerCommand::SetPlaybackCursor { cue_id: cue_ids[2] }).await.unwrap();
+        assert_eq!(event_rx.try_recv().unwrap(), UiEvent::PlaybackCursorMoved { cue_id: cue_ids[2] });
+
+        controller.handle_command(ControllerCommand::ResetCursor).await.unwrap();
+        assert_eq!(event_rx.try_recv().unwrap(), UiEvent::PlaybackCursorMoved { cue_id: cue_ids[0] });
+        assert_eq!(state_rx.borrow().playback_cursor, Some(cue_ids[0]));
+        assert_eq!(state_rx.borrow().upcoming, cue_ids.to_vec());
+
+        // キューがない場合はカーソルを解除する
+        let (controller, _, _, _, state_rx, mut event_rx) = setup_controller(&[]).await;
+        controller.handle_command(ControllerCommand::ResetCursor).await.unwrap();
+        assert!(event_rx.try_recv().is_err());
+        assert_eq!(state_rx.borrow().playback_cursor, None);
+        assert!(state_rx.borrow().upcoming.is_empty());
+    }
+
+    #[tokio::test]
+    async fn revert_levels() {
+        let cue_id = Uuid::new_v4();
+        let mut cue = make_cue(cue_id);
+        if let CueParam::Audio { levels, .. } = &mut cue.param {
+            *levels = AudioCueLevels { master: -3.0 };
+        }
+        let (controller, ctrl_tx, mut exec_rx, _, _, _) = setup_controller_with_cues(vec![cue]).await;
+
+        tokio::spawn(controller.run(CancellationToken::new()));
+
+        ctrl_tx
+            .send(ControllerCommand::SetLevelsMany { changes: vec![(cue_id, AudioCueLevels { master: -20.0 }, 0.0, Easing::Linear.into())] })
+            .await
+            .unwrap();
+        assert!(matches!(exec_rx.recv().await.unwrap(), ExecutorCommand::SetLevelsMany { .. }));
+
+        ctrl_tx.send(ControllerCommand::RevertLevels { cue_id }).await.unwrap();
+        if let Some(ExecutorCommand::SetLevelsMany { changes }) = exec_rx.recv().await {
+            assert_eq!(changes, vec![(cue_id, AudioCueLevels { master: -3.0 }, REVERT_LEVELS_DURATION, Easing::Linear.into())]);
+        } else {
+            panic!("Wrong Executor Command emitted.");
+        }
+    }
+
+    #[tokio::test]
+    async fn set_master_volume() {
+        let (controller, ctrl_tx, mut exec_rx, _, mut state_rx, _) = setup_controller_with_cues(vec![]).await;
+
+        tokio::spawn(controller.run(CancellationToken::new()));
+
+        ctrl_tx
+            .send(ControllerCommand::SetMasterVolume { db: -10.0, duration: 2.0, easing: Easing::Linear })
+            .await
+            .unwrap();
+        if let Some(ExecutorCommand::SetMasterVolume { db, duration, .. }) = exec_rx.recv().await {
+            assert_eq!((db, duration), (-10.0, 2.0));
+        } else {
+            panic!("Wrong Executor Command emitted.");
+        }
+        state_rx.changed().await.unwrap();
+        assert_eq!(state_rx.borrow().master_volume, -10.0);
+    }
+
+    #[tokio::test]
+    async fn set_bus_volume() {
+        let (controller, ctrl_tx, mut exec_rx, _, mut state_rx, _) = setup_controller_with_cues(vec![]).await;
+
+        tokio::spawn(controller.run(CancellationToken::new()));
+
+        ctrl_tx
+            .send(ControllerCommand::SetBusVolume { bus: "Music".to_string(), db: -12.0, duration: 1.0, easing: Easing::Linear })
+            .await
+            .unwrap();
+        if let Some(ExecutorCommand::SetBusVolume { bus, db, .. }) = exec_rx.recv().await {
+            assert_eq!((bus.as_str(), db), ("Music", -12.0));
+        } else {
+            panic!("Wrong Executor Command emitted.");
+        }
+        state_rx.changed().await.unwrap();
+        assert_eq!(state_rx.borrow().bus_volumes["Music"], -12.0);
+    }
+
+    #[tokio::test]
+    async fn reload_stops_vanished_cues() {
+        let kept_id = Uuid::new_v4();
+        let vanished_id = Uuid::new_v4();
+        let (exec_tx, mut exec_rx) = mpsc::channel::<ExecutorCommand>(32);
+        let (_ctrl_tx, ctrl_rx) = mpsc::channel::<ControllerCommand>(32);
+        let (playback_event_tx, playback_event_rx) = mpsc::channel::<ExecutorEvent>(32);
+        let (state_tx, mut state_rx) = watch::channel::<ShowState>(ShowState::new());
+        let (event_tx, mut event_rx) = broadcast::channel::<UiEvent>(32);
+        let (manager, handle) = ShowModelManager::new(event_tx.clone());
+        manager.write_with(|model| model.cues = vec![make_cue(vanished_id), make_cue(kept_id)]).await;
+        let controller = CueController::new(handle, exec_tx, ctrl_rx, playback_event_rx, state_tx, event_tx.clone()).await;
+
+        tokio::spawn(controller.run(CancellationToken::new()));
+
+        for cue_id in [vanished_id, kept_id] {
+            playback_event_tx.send(ExecutorEvent::Started { cue_id }).await.unwrap();
+            state_rx.changed().await.unwrap();
+        }
+        manager.write_with(|model| model.cues.retain(|cue| cue.id == kept_id)).await;
+        event_tx.send(UiEvent::ShowModelLoaded { path: PathBuf::from("show.sbsp") }).unwrap();
+
+        assert!(matches!(exec_rx.recv().await, Some(ExecutorCommand::StopCue(cue_id)) if cue_id == vanished_id));
+        state_rx.changed().await.unwrap();
+        let state = state_rx.borrow().clone();
+        assert_eq!(state.active_cues.keys().collect::<Vec<_>>(), vec![&kept_id]);
+        assert_eq!(state.playback_cursor, Some(kept_id));
+        let mut cursor_moved = false;
+        loop {
+            match event_rx.recv().await.unwrap() {
+                UiEvent::PlaybackCursorMoved { cue_id } => cursor_moved = cue_id == kept_id,
+                UiEvent::VanishedCuesStopped { cue_ids } => {
+                    assert_eq!(cue_ids, vec![vanished_id]);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        assert!(cursor_moved);
+    }
+
+    #[tokio::test]
+    async fn restore_last_cursor() {
+        let cue_ids = [Uuid::new_v4(), Uuid::new_v4()];
+        let (exec_tx, _exec_rx) = mpsc::channel::<ExecutorCommand>(32);
+        let (_ctrl_tx, ctrl_rx) = mpsc::channel::<ControllerCommand>(32);
+        let (_playback_event_tx, playback_event_rx) = mpsc::channel::<ExecutorEvent>(32);
+        let (state_tx, state_rx) = watch::channel::<ShowState>(ShowState::new());
+        let (event_tx, _event_rx) = broadcast::channel::<UiEvent>(32);
+        let (manager, handle) = ShowModelManager::new(event_tx.clone());
+        manager
+            .write_with(|model| {
+                model.cues = cue_ids.iter().map(|cue_id| make_cue(*cue_id)).collect();
+                model.last_cursor = Some(cue_ids[1]);
+            })
+            .await;
+        let controller = CueController::new(handle, exec_tx, ctrl_rx, playback_event_rx, state_tx, event_tx).await;
+        assert_eq!(state_rx.borrow().playback_cursor, Some(cue_ids[1]));
+
+        // カーソルを移動するとショーに記録する
+        controller.handle_command(ControllerCommand::SetPlaybackCursor { cue_id: cue_ids[0] }).await.unwrap();
+        assert_eq!(manager.read().await.last_cursor, Some(cue_ids[0]));
+
+        // 保存されたカーソルのキューが存在しない場合は最初のキューに戻す
+        controller.handle_command(ControllerCommand::SetPlaybackCursor { cue_id: cue_ids[1] }).await.unwrap();
+        manager.write_with(|model| model.last_cursor = Some(Uuid::new_v4())).await;
+        controller.reconcile_with_model().await.unwrap();
+        assert_eq!(state_rx.borrow().playback_cursor, Some(cue_ids[0]));
+        assert_eq!(manager.read().await.last_cursor, Some(cue_ids[0]));
+    }
+
+    #[tokio::test]
+    async fn auto_follow_with_offset() {
+        let cue_id = Uuid::new_v4();
+        let next_cue_id = Uuid::new_v4();
+        let last_cue_id = Uuid::new_v4();
+        let mut cue = make_cue(cue_id);
+        cue.sequence = CueSequence::AutoFollow;
+        cue.follow_offset = Some(-3.0);
+        let mut next_cue = make_cue(next_cue_id);
+        next_cue.sequence = CueSequence::AutoFollow;
+        next_cue.follow_offset = Some(-20.0);
+        let (controller, _, mut exec_rx, _, state_rx, _) =
+            setup_controller_with_cues(vec![cue, next_cue, make_cue(last_cue_id)]).await;
+
+        controller.handle_executor_event(ExecutorEvent::Started { cue_id }).await.unwrap();
+        controller.handle_executor_event(ExecutorEvent::Progress { cue_id, position: 6.5, duration: 10.0 }).await.unwrap();
+        assert!(exec_rx.try_recv().is_err());
+
+        // 終了の3秒前を超えた時点で1度だけ次のキューを実行し、完了時には実行しない
+        controller.handle_executor_event(ExecutorEvent::Progress { cue_id, position: 7.1, duration: 10.0 }).await.unwrap();
+        assert!(matches!(exec_rx.try_recv(), Ok(ExecutorCommand::ExecuteCue(id)) if id == next_cue_id));
+        assert_eq!(state_rx.borrow().playback_cursor, Some(next_cue_id));
+        controller.handle_executor_event(ExecutorEvent::Progress { cue_id, position: 8.0, duration: 10.0 }).await.unwrap();
+        let report = PlaybackReport { played_duration: 10.0, peak_level: -6.0, was_stopped_manually: false };
+        controller.handle_executor_event(ExecutorEvent::Completed { cue_id, report }).await.unwrap();
+        assert!(exec_rx.try_recv().is_err());
+
+        // キューの長さを超えるオフセットは、再生開始直後に次のキューを実行する
+        controller.handle_executor_event(ExecutorEvent::Started { cue_id: next_cue_id }).await.unwrap();
+        controller
+            .handle_executor_event(ExecutorEvent::Progress { cue_id: next_cue_id, position: 0.0, duration: 10.0 })
+            .await
+            .unwrap();
+        assert!(matches!(exec_rx.try_recv(), Ok(ExecutorCommand::ExecuteCue(id)) if id == last_cue_id));
+    }
+
+    #[tokio::test]
+    async fn progress_remaining_time() {
+        let cue_id = Uuid::new_v4();
+        let looping_cue_id = Uuid::new_v4();
+        let mut cue = make_cue(cue_id);
+        if let CueParam::Audio { loop_region, .. } = &mut cue.param {
+            *loop_region = None;
+        }
+        let (controller, _, mut exec_rx, _, state_rx, _) = setup_controller_with_cues(vec![cue, make_cue(looping_cue_id)]).await;
+
+        controller.handle_executor_event(ExecutorEvent::Started { cue_id }).await.unwrap();
+        assert_eq!(state_rx.borrow().active_cues[&cue_id].remaining, None);
+        controller.handle_executor_event(ExecutorEvent::Progress { cue_id, position: 12.0, duration: 50.0 }).await.unwrap();
+        let active_cue = state_rx.borrow().active_cues[&cue_id].clone();
+        assert_eq!(active_cue.remaining, Some(38.0));
+        assert_eq!(active_cue.progress, 0.24);
+
+        // ループ中は終了時刻が決まらない
+        controller.handle_executor_event(ExecutorEvent::Progress { cue_id: looping_cue_id, position: 25.0, duration: 50.0 }).await.unwrap();
+        let active_cue = state_rx.borrow().active_cues[&looping_cue_id].clone();
+        assert_eq!(active_cue.remaining, None);
+        assert_eq!(active_cue.progress, 0.5);
+
+        controller.handle_command(ControllerCommand::ReleaseLoop { cue_id: looping_cue_id }).await.unwrap();
+        assert!(matches!(exec_rx.try_recv(), Ok(ExecutorCommand::ReleaseLoop(id)) if id == looping_cue_id));
+        controller.handle_executor_event(ExecutorEvent::Progress { cue_id: looping_cue_id, position: 30.0, duration: 50.0 }).await.unwrap();
+        assert_eq!(state_rx.borrow().active_cues[&looping_cue_id].remaining, Some(20.0));
+    }
+
+    #[tokio::test]
+    async fn auto_continue_after_post_wait() {
+        let cue_id = Uuid::new_v4();
+        let next_cue_id = Uuid::new_v4();
+        let mut cue = make_cue(cue_id);
+        cue.sequence = CueSequence::AutoContinue;
+        cue.pre_wait = 0.02;
+        cue.post_wait = 0.03;
+        let (controller, ctrl_tx, mut exec_rx, _, _state_rx, _) = setup_controller_with_cues(vec![cue, make_cue(next_cue_id)]).await;
+
+        tokio::spawn(controller.run(CancellationToken::new()));
+
+        let started_at = Instant::now();
+        ctrl_tx.send(ControllerCommand::Go).await.unwrap();
+        assert!(matches!(exec_rx.recv().await, Some(ExecutorCommand::ExecuteCue(id)) if id == cue_id));
+        assert!(matches!(exec_rx.recv().await, Some(ExecutorCommand::ExecuteCue(id)) if id == next_cue_id));
+        assert!(started_at.elapsed() >= Duration::from_millis(50));
+
+        // StopAllで、まだ実行していない次のキューを取り消す
+        ctrl_tx.send(ControllerCommand::GoFromCue { cue_id }).await.unwrap();
+        assert!(matches!(exec_rx.recv().await, Some(ExecutorCommand::ExecuteCue(id)) if id == cue_id));
+        ctrl_tx.send(ControllerCommand::StopAll).await.unwrap();
+        assert!(matches!(exec_rx.recv().await, Some(ExecutorCommand::StopAll)));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(exec_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn auto_follow_after_post_wait() {
+        let cue_id = Uuid::new_v4();
+        let next_cue_id = Uuid::new_v4();
+        let mut cue = make_cue(cue_id);
+        cue.sequence = CueSequence::AutoFollow;
+        cue.post_wait = 0.05;
+        let (controller, _, mut exec_rx, playback_event_tx, _state_rx, _) =
+            setup_controller_with_cues(vec![cue, make_cue(next_cue_id)]).await;
+
+        tokio::spawn(controller.run(CancellationToken::new()));
+
+        playback_event_tx.send(ExecutorEvent::Started { cue_id }).await.unwrap();
+        let report = PlaybackReport { played_duration: 10.0, peak_level: -6.0, was_stopped_manually: false };
+        let completed_at = Instant::now();
+        playback_event_tx.send(ExecutorEvent::Completed { cue_id, report }).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(exec_rx.try_recv().is_err());
+        assert!(matches!(exec_rx.recv().await, Some(ExecutorCommand::ExecuteCue(id)) if id == next_cue_id));
+        assert!(completed_at.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn auto_follow_on_completion() {
+        let cue_id = Uuid::new_v4();
+        let next_cue_id = Uuid::new_v4();
+        let mut cue = make_cue(cue_id);
+        cue.sequence = CueSequence::AutoFollow;
+        let (controller, _, mut exec_rx, _, _, _) = setup_controller_with_cues(vec![cue, make_cue(next_cue_id)]).await;
+
+        controller.handle_executor_event(ExecutorEvent::Started { cue_id }).await.unwrap();
+        controller.handle_executor_event(ExecutorEvent::Progress { cue_id, position: 9.9, duration: 10.0 }).await.unwrap();
+        assert!(exec_rx.try_recv().is_err());
+        let report = PlaybackReport { played_duration: 10.0, peak_level: -6.0, was_stopped_manually: false };
+        controller.handle_executor_event(ExecutorEvent::Completed { cue_id, report }).await.unwrap();
+        assert!(matches!(exec_rx.try_recv(), Ok(ExecutorCommand::ExecuteCue(id)) if id == next_cue_id));
+
+        // 手動で停止した場合は次のキューを実行しない
+        controller.handle_executor_event(ExecutorEvent::Started { cue_id }).await.unwrap();
+        let report = PlaybackReport { played_duration: 2.0, peak_level: -6.0, was_stopped_manually: true };
+        controller.handle_executor_event(ExecutorEvent::Completed { cue_id, report }).await.unwrap();
+        assert!(exec_rx.try_recv().is_err());
+    }
 }
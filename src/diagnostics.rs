@@ -0,0 +1,144 @@
+use std::{collections::HashMap, sync::{Arc, Mutex}, time::Duration};
+
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+/// 詰まりの警告を確認する間隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// 既定の警告のしきい値(容量に対する割合)
+const DEFAULT_WARNING_RATIO: f64 = 0.8;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelDepth {
+    pub name: String,
+    pub len: usize,               // 未処理のメッセージ数
+    pub capacity: usize,
+    pub warning_threshold: usize, // この数以上たまった場合に警告する
+}
+
+impl ChannelDepth {
+    pub fn is_over_threshold(&self) -> bool {
+        self.len >= self.warning_threshold
+    }
+}
+
+/// チャネルの(未処理数, 容量)を返す関数。チャネルが閉じている場合はNone
+type DepthProbe = Box<dyn Fn() -> Option<(usize, usize)> + Send + Sync>;
+
+struct MonitoredChannel {
+    name: String,
+    probe: DepthProbe,
+}
+
+/// 内部のmpscチャネルにたまっているメッセージ数を監視します。
+///
+/// 監視のためにチャネルを開いたままにしないよう、送信側は弱参照で保持します。
+#[derive(Clone, Default)]
+pub struct ChannelMonitor {
+    channels: Arc<Vec<MonitoredChannel>>,
+    thresholds: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl ChannelMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 監視するチャネルを追加します。
+    pub fn with_channel<T: Send + 'static>(mut self, name: &str, sender: &mpsc::Sender<T>) -> Self {
+        let weak_sender = sender.downgrade();
+        let probe: DepthProbe = Box::new(move || {
+            let sender = weak_sender.upgrade()?;
+            Some((sender.max_capacity() - sender.capacity(), sender.max_capacity()))
+        });
+        Arc::get_mut(&mut self.channels)
+            .expect("ChannelMonitor must be configured before it is shared.")
+            .push(MonitoredChannel { name: name.to_string(), probe });
+        self
+    }
+
+    /// 各チャネルの現在の状態を返します。閉じたチャネルは含みません。
+    pub fn snapshot(&self) -> Vec<ChannelDepth> {
+        let thresholds = self.thresholds.lock().unwrap();
+        self.channels
+            .iter()
+            .filter_map(|channel| {
+                let (len, capacity) = (channel.probe)()?;
+                let warning_threshold = thresholds
+                    .get(&channel.name)
+                    .copied()
+                    .unwrap_or_else(|| (capacity as f64 * DEFAULT_WARNING_RATIO).ceil() as usize);
+                Some(ChannelDepth { name: channel.name.clone(), len, capacity, warning_threshold })
+            })
+            .collect()
+    }
+
+    /// チャネルの警告のしきい値を変更します。
+    pub fn set_warning_threshold(&self, name: &str, threshold: usize) -> Result<(), String> {
+        if !self.channels.iter().any(|channel| channel.name == name) {
+            return Err(format!("Channel '{}' is not monitored.", name));
+        }
+        if threshold == 0 {
+            return Err("Warning threshold must be greater than 0.".to_string());
+        }
+        self.thresholds.lock().unwrap().insert(name.to_string(), threshold);
+        Ok(())
+    }
+
+    /// 定期的にチャネルを確認し、しきい値を超えている場合に警告を出力します。
+    pub async fn run(self, cancel_token: CancellationToken) {
+        let mut check_timer = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = cancel_token.cancelled() => break,
+                _ = check_timer.tick() => {
+                    for depth in self.snapshot().iter().filter(|depth| depth.is_over_threshold()) {
+                        log::warn!(
+                            "Channel '{}' is backed up: {}/{} messages pending.",
+                            depth.name, depth.len, depth.capacity
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn occupancy_rises_when_flooded() {
+        let (tx, mut rx) = mpsc::channel::<u32>(8);
+        let monitor = ChannelMonitor::new().with_channel("test", &tx);
+        assert_eq!(monitor.snapshot()[0].len, 0);
+
+        for i in 0..6 {
+            tx.send(i).await.unwrap();
+        }
+        let depth = &monitor.snapshot()[0];
+        assert_eq!((depth.len, depth.capacity, depth.warning_threshold), (6, 8, 7));
+        assert!(!depth.is_over_threshold());
+
+        monitor.set_warning_threshold("test", 4).unwrap();
+        assert!(monitor.snapshot()[0].is_over_threshold());
+        assert!(monitor.set_warning_threshold("unknown", 4).is_err());
+
+        rx.recv().await.unwrap();
+        assert_eq!(monitor.snapshot()[0].len, 5);
+    }
+
+    #[tokio::test]
+    async fn does_not_keep_channel_open() {
+        let (tx, mut rx) = mpsc::channel::<u32>(8);
+        let monitor = ChannelMonitor::new().with_channel("test", &tx);
+        drop(tx);
+
+        assert!(rx.recv().await.is_none());
+        assert!(monitor.snapshot().is_empty());
+    }
+}
@@ -1 +1,2 @@
 pub mod audio_engine;
+pub mod timecode;
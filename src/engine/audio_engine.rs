@@ -1,16 +1,22 @@
 use anyhow::{Context, Result};
+use cpal::traits::{DeviceTrait, HostTrait};
 use kira::{
     clock::{ClockHandle, ClockSpeed, ClockTime}, sound::{
-        static_sound::{StaticSoundData, StaticSoundHandle}, EndPosition, PlaybackPosition, PlaybackState, Region
-    }, AudioManager, AudioManagerSettings, Decibels, DefaultBackend, Easing, StartTime, Tween
+        static_sound::{StaticSoundData, StaticSoundHandle, StaticSoundSettings}, streaming::{StreamingSoundData, StreamingSoundHandle}, EndPosition, FromFileError, PlaybackPosition, PlaybackState, Region
+    }, backend::{cpal::CpalBackendSettings, Backend}, track::{TrackBuilder, TrackHandle}, AudioManager, AudioManagerSettings, Decibels, DefaultBackend, Easing, StartTime, Tween
 };
-use std::{collections::HashMap, path::PathBuf, time::Duration};
+use std::{collections::{HashMap, VecDeque}, path::{Path, PathBuf}, sync::{atomic::{AtomicU64, Ordering}, Arc, Mutex}, time::Duration};
+use symphonia::core::errors::Error as SymphoniaError;
 use tokio::{sync::mpsc, time};
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
 use uuid::Uuid;
 
 use crate::{
-    executor::EngineEvent,
-    model::cue::{AudioCueFadeParam, AudioCueLevels},
+    event::CueErrorKind,
+    executor::{EngineEvent, PlaybackReport},
+    media::LoudnessCache,
+    model::{cue::{AudioCueFadeParam, AudioCueLevels}, easing::FadeCurve},
 };
 
 #[derive(Debug, Clone)]
@@ -28,11 +34,66 @@ pub enum AudioCommand {
     Stop {
         id: Uuid,
         fade_out: Duration,
+        easing: Easing,
     },
     SetLevels {
         id: Uuid,
         levels: AudioCueLevels,
         duration: f64,
+        easing: FadeCurve,
+    },
+    /// 複数のサウンドのレベルを同時に変更します。(id, levels, duration, easing)
+    SetLevelsMany {
+        changes: Vec<(Uuid, AudioCueLevels, f64, FadeCurve)>,
+    },
+    /// 実行中のフェードインを中断し、すぐに目標のレベルにします。
+    CancelFade {
+        id: Uuid,
+    },
+    /// 再生中のサウンドの音声ファイルを差し替え、同じ位置から再生を続けます。
+    ReplaceMedia {
+        id: Uuid,
+        filepath: PathBuf,
+    },
+    /// 再生位置(秒)を移動します。一時停止中はスクラブのプレビューを再生します。
+    Seek {
+        id: Uuid,
+        position: f64,
+    },
+    /// ループを解除し、再生範囲の終端(end_time)まで再生して終了させます。
+    ReleaseLoop {
+        id: Uuid,
+    },
+    /// 音声ファイルを先にメモリに読み込みます。同じファイル・ラウドネスの目標のPlayは、読み込みを待たずにすぐに再生を開始します。
+    /// `id`は先読みを識別するIDで、同じIDで先読みし直すと前の先読みを置き換えます。ストリーミング再生の指定は先読みしません。
+    Preload {
+        id: Uuid,
+        data: PlayCommandData,
+    },
+    /// 先読みしたサウンドをメモリから解放します。
+    EvictPreload {
+        id: Uuid,
+    },
+    /// 出力デバイスを切り替えます。再生中のサウンドがある場合は全て終了してから切り替えます。
+    SetOutputDevice {
+        device: Option<String>,
+    },
+    /// 同時に再生できるサウンドの数を設定します。Noneの場合は制限しません。
+    /// 上限に達した状態で再生を開始すると、最も古いサウンドを短いフェードで停止します。
+    SetMaxVoices {
+        limit: Option<usize>,
+    },
+    /// メイントラックの音量を変更します。再生中と今後再生する全てのサウンドに反映されます。
+    SetMasterVolume {
+        db: f64,
+        duration: f64,
+        easing: Easing,
+    },
+    /// バスの音量を変更します。バスで再生中と今後再生する全てのサウンドに反映されます。
+    SetBusVolume {
+        bus: String,
+        db: f64,
+        duration: f64,
         easing: Easing,
     },
 }
@@ -46,20 +107,563 @@ pub struct PlayCommandData {
     pub end_time: Option<f64>,
     pub fade_out_param: Option<AudioCueFadeParam>,
     pub loop_region: Option<Region>,
+    pub loop_count: Option<u32>,
+    pub start_delay: f64, // 再生開始までの秒数
+    pub output_bus: Option<String>, // 再生するバス。Noneの場合はメイントラック
+    pub streaming: bool, // ファイル全体を読み込まずに再生しながら読み込むかどうか
+    pub normalize_to: Option<f64>, // 目標のラウドネス(LUFS)。Noneの場合はラウドネスを揃えない
+    pub automation: Option<Vec<(f64, f64)>>, // 音量のオートメーションの(キューの開始からの秒数, levels.masterに加える音量(dB))
+}
+
+/// 再生前のサウンド。短い効果音はメモリに読み込み、長いファイルは再生しながら読み込む
+enum LoadedSound {
+    Static(StaticSoundData),
+    Streaming(StreamingSoundData<FromFileError>),
+}
+
+impl LoadedSound {
+    fn slice(self, region: Region) -> Self {
+        match self {
+            Self::Static(data) => Self::Static(data.slice(region)),
+            Self::Streaming(data) => Self::Streaming(data.slice(region)),
+        }
+    }
+
+    fn volume(self, volume: Decibels) -> Self {
+        match self {
+            Self::Static(data) => Self::Static(data.volume(volume)),
+            Self::Streaming(data) => Self::Streaming(data.volume(volume)),
+        }
+    }
+
+    fn start_time(self, start_time: StartTime) -> Self {
+        match self {
+            Self::Static(data) => Self::Static(data.start_time(start_time)),
+            Self::Streaming(data) => Self::Streaming(data.start_time(start_time)),
+        }
+    }
+
+    fn start_position(self, start_position: f64) -> Self {
+        match self {
+            Self::Static(data) => Self::Static(data.start_position(start_position)),
+            Self::Streaming(data) => Self::Streaming(data.start_position(start_position)),
+        }
+    }
+
+    fn loop_region(self, loop_region: Option<Region>) -> Self {
+        match self {
+            Self::Static(data) => Self::Static(data.loop_region(loop_region)),
+            Self::Streaming(data) => Self::Streaming(data.loop_region(loop_region)),
+        }
+    }
+
+    fn fade_in_tween(self, tween: Tween) -> Self {
+        match self {
+            Self::Static(data) => Self::Static(data.fade_in_tween(tween)),
+            Self::Streaming(data) => Self::Streaming(data.fade_in_tween(tween)),
+        }
+    }
+
+    /// 再生範囲の長さ(秒)
+    fn duration(&self) -> f64 {
+        match self {
+            Self::Static(data) => data.duration().as_secs_f64(),
+            Self::Streaming(data) => data.duration().as_secs_f64(),
+        }
+    }
+
+    fn sample_rate(&self) -> f64 {
+        match self {
+            Self::Static(data) => data.sample_rate as f64,
+            // ストリーミングのデータはサンプルレートを公開していないため、フレーム数と長さから求める
+            Self::Streaming(data) => {
+                let duration = data.duration().as_secs_f64();
+                if duration > 0.0 { (data.num_frames() as f64 / duration).round() } else { 1.0 }
+            }
+        }
+    }
+
+    /// メモリに読み込んだデータ。ストリーミングの場合はNone
+    fn as_static(&self) -> Option<&StaticSoundData> {
+        match self {
+            Self::Static(data) => Some(data),
+            Self::Streaming(_) => None,
+        }
+    }
+}
+
+/// 再生中のサウンドの操作。`LoadedSound`の種類に対応する
+enum SoundHandle {
+    Static(StaticSoundHandle),
+    Streaming(StreamingSoundHandle<FromFileError>),
+}
+
+impl SoundHandle {
+    fn state(&self) -> PlaybackState {
+        match self {
+            Self::Static(handle) => handle.state(),
+            Self::Streaming(handle) => handle.state(),
+        }
+    }
+
+    fn position(&self) -> f64 {
+        match self {
+            Self::Static(handle) => handle.position(),
+            Self::Streaming(handle) => handle.position(),
+        }
+    }
+
+    fn set_volume(&mut self, volume: impl Into<Decibels>, tween: Tween) {
+        let volume = volume.into();
+        match self {
+            Self::Static(handle) => handle.set_volume(volume, tween),
+            Self::Streaming(handle) => handle.set_volume(volume, tween),
+        }
+    }
+
+    fn pause(&mut self, tween: Tween) {
+        match self {
+            Self::Static(handle) => handle.pause(tween),
+            Self::Streaming(handle) => handle.pause(tween),
+        }
+    }
+
+    fn resume(&mut self, tween: Tween) {
+        match self {
+            Self::Static(handle) => handle.resume(tween),
+            Self::Streaming(handle) => handle.resume(tween),
+        }
+    }
+
+    fn stop(&mut self, tween: Tween) {
+        match self {
+            Self::Static(handle) => handle.stop(tween),
+            Self::Streaming(handle) => handle.stop(tween),
+        }
+    }
+
+    fn seek_to(&mut self, position: f64) {
+        match self {
+            Self::Static(handle) => handle.seek_to(position),
+            Self::Streaming(handle) => handle.seek_to(position),
+        }
+    }
+
+    fn set_loop_region(&mut self, loop_region: Option<Region>) {
+        match self {
+            Self::Static(handle) => handle.set_loop_region(loop_region),
+            Self::Streaming(handle) => handle.set_loop_region(loop_region),
+        }
+    }
+
+    /// 再生中にファイルの読み込みで発生したエラーを取り出します。メモリ上のサウンドではエラーは発生しません。
+    fn pop_error(&mut self) -> Option<FromFileError> {
+        match self {
+            Self::Static(_) => None,
+            Self::Streaming(handle) => handle.pop_error(),
+        }
+    }
 }
 
 struct PlayingSound {
     duration: f64,
-    handle: StaticSoundHandle,
+    handle: SoundHandle,
     last_state: PlaybackState,
+    levels: AudioCueLevels, // 現在の目標レベル
+    gain_offset: f64,       // ラウドネスを揃えるためにlevels.masterに加える音量(dB)
+    loop_counter: Option<LoopCounter>,
+    tracker: PlaybackTracker,
+    data: PlayCommandData, // 再生開始時の指定。メディアの差し替えに使用する
+    fade_stages: VecDeque<FadeStage>, // 折れ線のフェードのうち、まだ開始していない区間
+    breakpoint_fade_in_end: Option<f64>, // 折れ線のフェードインが終わる再生位置
+    voice_order: u64, // 再生を開始した順番。同時に再生できる数を超えた際に最も古いサウンドを選ぶ
     _clock: ClockHandle,
 }
 
-pub struct AudioEngine {
-    manager: Option<AudioManager>,
+impl PlayingSound {
+    /// ラウドネスの補正を含めた、実際に適用するレベルを返します。
+    fn output_levels(&self) -> AudioCueLevels {
+        AudioCueLevels { master: self.levels.master + self.gain_offset }
+    }
+
+    /// 再生位置が開始位置に達した区間のうち最後のものについて、区間の終了位置までに音量を変化させます。
+    fn advance_fade_stages(&mut self, position: f64, start_time: StartTime) {
+        let mut due_stage = None;
+        while let Some(stage) = self.fade_stages.front()
+            && stage.position <= position
+        {
+            due_stage = self.fade_stages.pop_front();
+        }
+        if let Some(stage) = due_stage {
+            self.handle.set_volume(
+                stage.volume,
+                Tween {
+                    start_time,
+                    duration: Duration::from_secs_f64((stage.end_position - position).max(0.0)),
+                    easing: stage.easing,
+                },
+            );
+        }
+    }
+}
+
+/// 段階的に行うフェードの1区間。kiraは音量の変化を1つしか予約できないため、再生位置に応じて順に指示します。
+struct FadeStage {
+    position: f64,     // 変化を始める再生位置(秒)
+    end_position: f64, // 目標の音量に達する再生位置(秒)
+    volume: Decibels,
+    easing: Easing,
+    fade_in: bool,
+}
+
+/// 折れ線のフェードを区間に分割します。変化量は`from`から`to`への振幅の割合です。
+fn breakpoint_stages(points: &[(f64, f64)], start_position: f64, duration: f64, from: Decibels, to: Decibels, fade_in: bool) -> VecDeque<FadeStage> {
+    let (from_amplitude, to_amplitude) = (from.as_amplitude(), to.as_amplitude());
+    let mut last_time = points.first().map_or(0.0, |(time, _)| *time);
+    points
+        .iter()
+        .map(|(time, amount)| {
+            let amplitude = from_amplitude + (to_amplitude - from_amplitude) * *amount as f32;
+            let stage = FadeStage {
+                position: start_position + last_time * duration,
+                end_position: start_position + time * duration,
+                volume: Decibels(amplitude_to_db(amplitude) as f32),
+                easing: Easing::Linear,
+                fade_in,
+            };
+            last_time = *time;
+            stage
+        })
+        .collect()
+}
+
+/// 音量のオートメーションを区間に分割します。最初のブレークポイントまではキューのレベルから変化させ、最後のブレークポイント以降はその音量を保ちます。
+fn automation_stages(points: &[(f64, f64)], master: Decibels) -> VecDeque<FadeStage> {
+    let mut last_time = 0.0;
+    points
+        .iter()
+        .map(|(time, db)| {
+            let stage = FadeStage {
+                position: last_time,
+                end_position: *time,
+                volume: Decibels(master.0 + *db as f32),
+                easing: Easing::Linear,
+                fade_in: false,
+            };
+            last_time = *time;
+            stage
+        })
+        .collect()
+}
+
+/// 再生レポートのため、再生した時間とピークレベルを集計します。
+struct PlaybackTracker {
+    sound_data: Option<StaticSoundData>, // ストリーミング再生の場合はNoneで、ピークレベルとメーターは求めない
+    loop_bounds: Option<(f64, f64)>, // ループ領域の(開始, 終了)位置(秒)
+    last_position: f64,
+    pending_seek_from: Option<f64>, // シークが反映される前の位置。一時停止中は再開するまで反映されない
+    played_duration: f64,
+    peak: f32, // キューのレベルを含む振幅。フェードは考慮しない
+    stopped_manually: bool,
+}
+
+impl PlaybackTracker {
+    /// `from`から`to`へのシークを記録します。移動した区間は再生した時間に含めません。
+    fn seek(&mut self, from: f64, to: f64) {
+        self.pending_seek_from = Some(from);
+        self.last_position = to;
+    }
+
+    /// 前回の位置から`position`までを再生したものとして集計します。
+    fn update(&mut self, position: f64, levels: &AudioCueLevels) {
+        if let Some(seek_from) = self.pending_seek_from {
+            if position == seek_from {
+                return;
+            }
+            self.pending_seek_from = None;
+        }
+        let last_position = std::mem::replace(&mut self.last_position, position);
+        let gain = Decibels(levels.master as f32).as_amplitude();
+        match self.loop_bounds {
+            Some((loop_start, loop_end)) if position < last_position => {
+                // ループ領域の先頭に戻った
+                self.add_range(last_position, loop_end, gain);
+                self.add_range(loop_start, position, gain);
+            }
+            _ => self.add_range(last_position, position, gain),
+        }
+    }
+
+    fn add_range(&mut self, from: f64, to: f64, gain: f32) {
+        if to <= from {
+            return;
+        }
+        self.played_duration += to - from;
+        let Some(sound_data) = &self.sound_data else {
+            return;
+        };
+        let sample_rate = sound_data.sample_rate as f64;
+        let start_index = (from * sample_rate) as usize;
+        let end_index = ((to * sample_rate) as usize).min(sound_data.num_frames());
+        for index in start_index..end_index {
+            if let Some(frame) = sound_data.frame_at_index(index) {
+                self.peak = self.peak.max(frame.left.abs().max(frame.right.abs()) * gain);
+            }
+        }
+    }
+
+    fn report(&self) -> PlaybackReport {
+        PlaybackReport {
+            played_duration: self.played_duration,
+            peak_level: amplitude_to_db(self.peak),
+            was_stopped_manually: self.stopped_manually,
+        }
+    }
+
+    /// `position`までの`METER_WINDOW`の区間の(ピーク, RMS)をdBFSで返します。キューのレベルを含み、フェードは含みません。
+    /// ストリーミング再生の場合はNoneを返します。
+    fn meter(&self, position: f64, levels: &AudioCueLevels) -> Option<(f64, f64)> {
+        let sound_data = self.sound_data.as_ref()?;
+        let gain = Decibels(levels.master as f32).as_amplitude();
+        let sample_rate = sound_data.sample_rate as f64;
+        let end_index = ((position * sample_rate) as usize).min(sound_data.num_frames());
+        let start_index = end_index.saturating_sub((METER_WINDOW.as_secs_f64() * sample_rate) as usize);
+        let mut peak: f32 = 0.0;
+        let mut sum_squares = 0.0;
+        for index in start_index..end_index {
+            if let Some(frame) = sound_data.frame_at_index(index) {
+                let amplitude = frame.left.abs().max(frame.right.abs()) * gain;
+                peak = peak.max(amplitude);
+                sum_squares += (amplitude * amplitude) as f64;
+            }
+        }
+        let frames = (end_index - start_index).max(1);
+        Some((amplitude_to_db(peak), amplitude_to_db((sum_squares / frames as f64).sqrt() as f32)))
+    }
+}
+
+/// キューの音声のうち`position`から`SCRUB_PREVIEW_DURATION`の区間を、フェードやループなしで再生するデータを返します。
+fn scrub_preview_data(sound_data: &StaticSoundData, position: f64, levels: &AudioCueLevels) -> StaticSoundData {
+    let (slice_start, slice_end) = sound_data.slice.unwrap_or((0, sound_data.frames.len()));
+    let sample_rate = sound_data.sample_rate as f64;
+    let start = (slice_start + (position * sample_rate) as usize).min(slice_end);
+    let end = (start + (SCRUB_PREVIEW_DURATION.as_secs_f64() * sample_rate) as usize).min(slice_end);
+    sound_data
+        .with_settings(StaticSoundSettings::new().volume(Decibels(levels.master as f32)))
+        .slice(Region {
+            start: PlaybackPosition::Samples(start),
+            end: EndPosition::Custom(PlaybackPosition::Samples(end)),
+        })
+}
+
+/// 振幅をdBFSに変換します。無音はDecibels::SILENCEになります。
+fn amplitude_to_db(amplitude: f32) -> f64 {
+    if amplitude > 0.0 {
+        (20.0 * amplitude.log10()).max(Decibels::SILENCE.0) as f64
+    } else {
+        Decibels::SILENCE.0 as f64
+    }
+}
+
+/// 有限回数のループを管理します。kiraのループは無限のため、再生位置の巻き戻りでループ回数を数えます。
+/// 状態の検出間隔(50ms)より短いループ領域は正しく数えられません。
+struct LoopCounter {
+    remaining_loops: u32,
+    last_position: f64,
+    pending_seek_from: Option<f64>, // シークが反映される前の位置
+    loop_end: Option<f64>, // ループ領域の終了位置(秒)。音声の終端の場合はNone
+    final_pass: bool,
+}
+
+impl LoopCounter {
+    /// `from`から`to`へのシークを記録します。シークによる巻き戻りはループとして数えません。
+    fn seek(&mut self, from: f64, to: f64) {
+        self.pending_seek_from = Some(from);
+        self.last_position = to;
+    }
+
+    /// 再生位置を更新します。最後のループ領域の再生が終わった場合は`true`を返します。
+    fn update(&mut self, handle: &mut SoundHandle) -> bool {
+        let position = handle.position();
+        if let Some(seek_from) = self.pending_seek_from {
+            if position == seek_from {
+                return false;
+            }
+            self.pending_seek_from = None;
+        }
+        let last_position = std::mem::replace(&mut self.last_position, position);
+        if self.final_pass {
+            return self.loop_end.is_some_and(|loop_end| position >= loop_end);
+        }
+        if position < last_position {
+            self.remaining_loops = self.remaining_loops.saturating_sub(1);
+            if self.remaining_loops == 0 {
+                // 最後の1回はループせずに終端まで再生する
+                handle.set_loop_region(None);
+                self.final_pass = true;
+            }
+        }
+        false
+    }
+}
+
+/// 一時停止・停止の検出と停止済みサウンドの削除を行う間隔
+const STATE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// フェードを中断した際に目標のレベルに達するまでの時間。クリックノイズを避けるため0にはしない
+const FADE_CANCEL_DURATION: Duration = Duration::from_millis(10);
+/// 一時停止中のシーク時に再生するプレビューの長さ
+const SCRUB_PREVIEW_DURATION: Duration = Duration::from_millis(80);
+/// 終了時のフェードアウト時間
+const SHUTDOWN_FADE_OUT: Duration = Duration::from_secs(1);
+/// メーターのレベルを求める区間の長さ
+const METER_WINDOW: Duration = Duration::from_millis(50);
+/// メーターの既定の通知間隔
+const DEFAULT_METER_INTERVAL: Duration = Duration::from_millis(100);
+/// 進捗・メーターの通知間隔の許容範囲
+const MIN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+const MAX_POLL_INTERVAL: Duration = Duration::from_millis(1000);
+/// ラウドネスを揃える際に上げる音量の上限(dB)。ほぼ無音のファイルが大音量にならないようにする
+const MAX_NORMALIZE_GAIN: f64 = 20.0;
+/// 同時に再生できる数を超えた際に、古いサウンドを停止するフェードの長さ
+const VOICE_STEAL_FADE_OUT: Duration = Duration::from_millis(50);
+/// 先読みしたサウンドが使用するメモリの既定の上限(バイト)
+const DEFAULT_PRELOAD_BUDGET: usize = 512 * 1024 * 1024;
+
+/// 登録前のサウンド。`register`されずに破棄された場合はサウンドを停止します。
+struct PendingSound(Option<PlayingSound>);
+
+impl PendingSound {
+    fn register(mut self) -> PlayingSound {
+        self.0.take().unwrap()
+    }
+}
+
+impl Drop for PendingSound {
+    fn drop(&mut self) {
+        if let Some(playing_sound) = self.0.as_mut() {
+            playing_sound.handle.stop(Tween {
+                start_time: StartTime::Immediate,
+                duration: Duration::ZERO,
+                easing: Easing::Linear,
+            });
+        }
+    }
+}
+
+/// 読み込み中のサウンド
+struct PendingLoad {
+    cancel_token: CancellationToken,  // 再生開始前に停止された場合に読み込みを打ち切る
+    deferred_commands: Vec<AudioCommand>, // 読み込み中に届いたコマンド。再生開始後に実行する
+}
+
+/// 先読みしたサウンド
+struct PreloadedSound {
+    filepath: PathBuf,
+    normalize_to: Option<f64>,
+    sound: StaticSoundData,
+    gain_offset: f64, // ラウドネスの補正(dB)
+    size: usize,      // 音声データのバイト数
+    last_used: u64,   // 最後に先読み・再生した順番。上限を超えた際に最も古いものから解放する
+}
+
+/// 先読みしたサウンドのキャッシュ。合計のサイズが上限を超える場合は、最も長く使われていないものから解放します。
+#[derive(Clone)]
+struct PreloadCache {
+    sounds: Arc<Mutex<HashMap<Uuid, PreloadedSound>>>,
+    budget: usize,
+    clock: Arc<AtomicU64>,
+}
+
+impl PreloadCache {
+    fn new(budget: usize) -> Self {
+        Self { sounds: Arc::default(), budget, clock: Arc::default() }
+    }
+
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// サウンドを追加します。上限より大きいサウンドは追加せずにfalseを返します。
+    fn insert(&self, id: Uuid, filepath: PathBuf, normalize_to: Option<f64>, sound: StaticSoundData, gain_offset: f64) -> bool {
+        let size = sound.frames.len() * std::mem::size_of::<kira::Frame>();
+        if size > self.budget {
+            return false;
+        }
+        let last_used = self.tick();
+        let mut sounds = self.sounds.lock().unwrap();
+        sounds.remove(&id);
+        while sounds.values().map(|preloaded| preloaded.size).sum::<usize>() + size > self.budget {
+            let (&oldest_id, _) = sounds.iter().min_by_key(|(_, preloaded)| preloaded.last_used).unwrap();
+            let evicted = sounds.remove(&oldest_id).unwrap();
+            tracing::info!(preload_id = %oldest_id, file = %evicted.filepath.display(), "Preload budget exceeded. Evicting the least recently used sound.");
+        }
+        sounds.insert(id, PreloadedSound { filepath, normalize_to, sound, gain_offset, size, last_used });
+        true
+    }
+
+    /// 同じファイル・ラウドネスの目標で先読みしたサウンドを返します。
+    fn get(&self, filepath: &Path, normalize_to: Option<f64>) -> Option<(StaticSoundData, f64)> {
+        let last_used = self.tick();
+        let mut sounds = self.sounds.lock().unwrap();
+        let preloaded = sounds
+            .values_mut()
+            .find(|preloaded| preloaded.filepath == filepath && preloaded.normalize_to == normalize_to)?;
+        preloaded.last_used = last_used;
+        Some((preloaded.sound.clone(), preloaded.gain_offset))
+    }
+
+    fn remove(&self, id: &Uuid) -> bool {
+        self.sounds.lock().unwrap().remove(id).is_some()
+    }
+}
+
+/// 読み込みを終えたサウンド
+struct LoadedMessage {
+    id: Uuid,
+    data: PlayCommandData,
+    result: Result<(LoadedSound, f64)>, // (サウンド, ラウドネスの補正(dB))
+}
+
+/// 差し替え用に読み込みを終えたサウンド
+struct ReplacedMessage {
+    id: Uuid,
+    data: PlayCommandData,
+    cancel_token: CancellationToken, // 差し替え前に停止・再度差し替えされた場合はキャンセル済み
+    result: Result<(LoadedSound, f64)>,
+}
+
+/// 出力デバイス名からAudioManagerを生成する関数
+type ManagerFactory<B> = fn(Option<&str>) -> Result<AudioManager<B>>;
+
+/// 音声ファイルを読み込む関数
+type SoundLoader = fn(&Path) -> Result<StaticSoundData, FromFileError>;
+
+pub struct AudioEngine<B: Backend = DefaultBackend> {
+    manager: Option<AudioManager<B>>,
+    manager_factory: Option<ManagerFactory<B>>,
+    sound_loader: SoundLoader,
+    output_device: Option<String>,
+    pending_output_device: Option<Option<String>>, // 再生終了後に切り替える出力デバイス
+    master_volume: f64,                            // メイントラックの音量(dB)
+    buses: HashMap<String, TrackHandle>,           // 名前付きのバス。最初に使われたときに作成する
+    bus_volumes: HashMap<String, f64>,             // バスの音量(dB)。出力デバイスを切り替えてバスを作り直す際に引き継ぐ
+    scrub_preview: bool,                           // 一時停止中のシークでプレビューを再生するかどうか
+    scrub_previews: Vec<SoundHandle>,              // 再生中のプレビュー。PlayingSoundとは別に管理する
+    loudness_cache: LoudnessCache,
+    preload_cache: PreloadCache,
+    pending_loads: HashMap<Uuid, PendingLoad>,
+    loaded_tx: mpsc::Sender<LoadedMessage>,
+    loaded_rx: mpsc::Receiver<LoadedMessage>,
+    pending_replacements: HashMap<Uuid, CancellationToken>, // 差し替え用に読み込み中のサウンド
+    replaced_tx: mpsc::Sender<ReplacedMessage>,
+    replaced_rx: mpsc::Receiver<ReplacedMessage>,
     command_rx: mpsc::Receiver<AudioCommand>,
     event_tx: mpsc::Sender<EngineEvent>,
     playing_sounds: HashMap<Uuid, PlayingSound>,
+    max_voices: Option<usize>, // 同時に再生できるサウンドの数。Noneの場合は制限しない
+    next_voice_order: u64,
+    poll_interval: Duration,
+    meter_interval: Duration,
 }
 
 impl AudioEngine {
@@ -67,169 +671,661 @@ impl AudioEngine {
         command_rx: mpsc::Receiver<AudioCommand>,
         event_tx: mpsc::Sender<EngineEvent>,
     ) -> Result<Self> {
-        let manager = AudioManager::<DefaultBackend>::new(AudioManagerSettings::default())
-            .context("Failed to initialize AudioManager")?;
+        Self::with_output_device(command_rx, event_tx, None)
+    }
+
+    /// 指定した名前の出力デバイスを使用するAudioEngineを生成します。Noneの場合は既定のデバイスを使用します。
+    pub fn with_output_device(
+        command_rx: mpsc::Receiver<AudioCommand>,
+        event_tx: mpsc::Sender<EngineEvent>,
+        output_device: Option<String>,
+    ) -> Result<Self> {
+        let manager = Self::create_manager(output_device.as_deref())?;
+
+        let mut engine = Self::with_manager(manager, command_rx, event_tx);
+        engine.manager_factory = Some(Self::create_manager);
+        engine.output_device = output_device;
+        Ok(engine)
+    }
+
+    /// 指定した名前の出力デバイスを使用するAudioManagerを生成します。Noneの場合は既定のデバイスを使用します。
+    fn create_manager(device_name: Option<&str>) -> Result<AudioManager> {
+        let device = match device_name {
+            Some(device_name) => Some(
+                cpal::default_host()
+                    .output_devices()
+                    .context("Failed to enumerate output devices")?
+                    .find(|device| device.name().is_ok_and(|name| name == device_name))
+                    .with_context(|| OutputDeviceUnavailable(Some(device_name.to_string())))?,
+            ),
+            None => None,
+        };
+        AudioManager::<DefaultBackend>::new(AudioManagerSettings {
+            backend_settings: CpalBackendSettings { device, ..Default::default() },
+            ..Default::default()
+        })
+        .context(OutputDeviceUnavailable(device_name.map(str::to_string)))
+    }
+}
 
-        Ok(Self {
+impl<B: Backend> AudioEngine<B> {
+    /// 初期化済みのAudioManagerを使用してAudioEngineを生成します。
+    pub fn with_manager(
+        manager: AudioManager<B>,
+        command_rx: mpsc::Receiver<AudioCommand>,
+        event_tx: mpsc::Sender<EngineEvent>,
+    ) -> Self {
+        let (loaded_tx, loaded_rx) = mpsc::channel(32);
+        let (replaced_tx, replaced_rx) = mpsc::channel(32);
+        Self {
             manager: Some(manager),
+            manager_factory: None,
+            sound_loader: |path| StaticSoundData::from_file(path),
+            output_device: None,
+            pending_output_device: None,
+            master_volume: 0.0,
+            buses: HashMap::new(),
+            bus_volumes: HashMap::new(),
+            scrub_preview: false,
+            scrub_previews: Vec::new(),
+            loudness_cache: LoudnessCache::default(),
+            preload_cache: PreloadCache::new(DEFAULT_PRELOAD_BUDGET),
+            pending_loads: HashMap::new(),
+            loaded_tx,
+            loaded_rx,
+            pending_replacements: HashMap::new(),
+            replaced_tx,
+            replaced_rx,
             command_rx,
             event_tx,
             playing_sounds: HashMap::new(),
-        })
+            max_voices: None,
+            next_voice_order: 0,
+            poll_interval: STATE_POLL_INTERVAL,
+            meter_interval: DEFAULT_METER_INTERVAL,
+        }
+    }
+
+    /// Progressイベントの送信間隔を設定します。10ms〜1000msの範囲に丸められます。
+    ///
+    /// 間隔を短くすると再生位置の表示は滑らかになりますが、再生中のサウンドごとに
+    /// 間隔ごと1つのイベントが流れるため、CPU負荷とUIへの送信量が増えます。
+    /// 一時停止・停止の検出はこの設定に関わらず50ms間隔で行われます。
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval.clamp(MIN_POLL_INTERVAL, MAX_POLL_INTERVAL);
+        self
     }
 
-    pub async fn run(mut self) {
-        let mut poll_timer = time::interval(Duration::from_millis(50));
-        log::info!("AudioEngine run loop started");
+    /// Meterイベントの送信間隔を設定します。Progressとは独立しており、10ms〜1000msの範囲に丸められます。
+    pub fn with_meter_interval(mut self, meter_interval: Duration) -> Self {
+        self.meter_interval = meter_interval.clamp(MIN_POLL_INTERVAL, MAX_POLL_INTERVAL);
+        self
+    }
+
+    /// 先読みしたサウンドが使用するメモリの上限(バイト)を設定します。
+    /// 上限を超える場合は、最も長く再生・先読みされていないサウンドから解放します。
+    pub fn with_preload_budget(mut self, budget: usize) -> Self {
+        self.preload_cache = PreloadCache::new(budget);
+        self
+    }
+
+    /// 一時停止中のキューをシークした際に、移動先の音声を短く再生するかどうかを設定します。
+    /// プレビューはキューの再生状態や再生レポートに影響しません。
+    pub fn with_scrub_preview(mut self, enabled: bool) -> Self {
+        self.scrub_preview = enabled;
+        self
+    }
+
+    pub async fn run(mut self, cancel_token: CancellationToken) {
+        let mut progress_timer = time::interval(self.poll_interval);
+        let mut state_timer = time::interval(STATE_POLL_INTERVAL);
+        let mut meter_timer = time::interval(self.meter_interval);
+        tracing::info!("AudioEngine run loop started.");
         loop {
             tokio::select! {
+                _ = cancel_token.cancelled() => {
+                    self.fade_out_all().await;
+                    break;
+                },
                 Some(command) = self.command_rx.recv() => {
-                    log::debug!("AudioEngine received command: {:?}", command);
-
-                    let result = match command {
-                        // TODO: output is ignored. AudioEngine should have AudioManager for enabled devices
-                        AudioCommand::Play {id, data} => {
-                            self.handle_play(id, data)
-                                .await
-                        }
-                        AudioCommand::Pause { id } => self.handle_pause(id).await,
-                        AudioCommand::Resume { id } => self.handle_resume(id).await,
-                        AudioCommand::Stop { id, fade_out } => self.handle_stop(id, fade_out),
-                        AudioCommand::SetLevels {id,levels, duration, easing } => self.handle_set_levels(id, levels, duration, easing),
-                    };
-                    if let Err(e) = result {
-                        log::error!("Error processing audio_engine command: {:?}", e);
-                    }
+                    tracing::debug!(instance_id = command.target().map(tracing::field::display), ?command, "AudioEngine received command.");
+                    self.handle_command(command).await;
                 },
-                _ = poll_timer.tick() => {
-                    let keys = self.playing_sounds.keys().clone();
-                    for id in keys {
-                        let Some(playing_sound) = self.playing_sounds.get(id) else {
-                            log::warn!("Received event for unknown instance_id: {}", id);
-                            continue;
-                        };
-                        let playback_state = playing_sound.handle.state();
-                        let event = match playback_state {
-                            kira::sound::PlaybackState::Playing => {
-                                EngineEvent::Audio(AudioEngineEvent::Progress { instance_id: *id, position: playing_sound.handle.position(), duration: playing_sound.duration })
-                            },
-                            kira::sound::PlaybackState::Pausing => {
-                                EngineEvent::Audio(AudioEngineEvent::Progress { instance_id: *id, position: playing_sound.handle.position(), duration: playing_sound.duration })
-                            },
-                            kira::sound::PlaybackState::Paused => {
-                                if playing_sound.last_state.eq(&PlaybackState::Paused) {
-                                    continue;
-                                }
-                                log::info!("PAUSE: id={}", *id);
-                                EngineEvent::Audio(AudioEngineEvent::Paused { instance_id: *id, position: playing_sound.handle.position(), duration: playing_sound.duration })
-                            },
-                            kira::sound::PlaybackState::WaitingToResume => {
-                                continue
-                            },
-                            kira::sound::PlaybackState::Resuming => {
-                                EngineEvent::Audio(AudioEngineEvent::Progress { instance_id: *id, position: playing_sound.handle.position(), duration: playing_sound.duration })
-                            },
-                            kira::sound::PlaybackState::Stopping => {
-                                EngineEvent::Audio(AudioEngineEvent::Progress { instance_id: *id, position: playing_sound.handle.position(), duration: playing_sound.duration })
-                            },
-                            kira::sound::PlaybackState::Stopped => {
-                                if playing_sound.last_state.eq(&PlaybackState::Stopped) {
-                                    continue;
-                                }
-                                log::info!("STOP: id={}", *id);
-                                EngineEvent::Audio(AudioEngineEvent::Completed { instance_id: *id })
-                            },
-                        };
+                Some(loaded) = self.loaded_rx.recv() => {
+                    let id = loaded.id;
+                    if let Err(e) = self.finish_load(loaded).await {
+                        tracing::error!(instance_id = %id, error = ?e, "Failed to start loaded sound.");
+                        let event = EngineEvent::Audio(AudioEngineEvent::Error {
+                            instance_id: id,
+                            error: format!("{:#}", e),
+                            kind: error_kind(&e),
+                        });
                         if let Err(e) = self.event_tx.send(event).await {
-                            log::error!("Error polling Sound status: {:?}", e);
+                            tracing::error!(instance_id = %id, error = ?e, "Failed to send Error event.");
                         }
                     }
-                    for playing_sound in self.playing_sounds.values_mut() {
-                        playing_sound.last_state = playing_sound.handle.state();
+                },
+                Some(replaced) = self.replaced_rx.recv() => {
+                    let id = replaced.id;
+                    if let Err(e) = self.finish_replace(replaced).await {
+                        tracing::error!(instance_id = %id, error = ?e, "Failed to replace media.");
                     }
-                    // 停止状態のPlayingSoundを削除
-                    self.playing_sounds.retain(|_, value| !matches!(value.handle.state(), kira::sound::PlaybackState::Stopped));
                 },
+                _ = progress_timer.tick() => self.emit_progress().await,
+                _ = state_timer.tick() => self.sweep_states().await,
+                _ = meter_timer.tick() => self.emit_meter().await,
                 else => break
             }
         }
-        log::info!("AudioEngine run loop finished.");
+        tracing::info!("AudioEngine run loop finished.");
     }
 
-    async fn handle_play(&mut self, id: Uuid, data: PlayCommandData) -> Result<()> {
-        let manager = self.manager.as_mut().unwrap();
-        let mut clock = manager.add_clock(ClockSpeed::SecondsPerTick(1.0)).unwrap();
-
-        let filepath_clone = data.filepath.clone();
-        let mut sound_data =
-            tokio::task::spawn_blocking(move || StaticSoundData::from_file(filepath_clone))
-                .await?
-                .with_context(|| {
-                    format!(
-                        "Failed to load sound data from: {}",
-                        data.filepath.display()
-                    )
-                })?
-                .slice(Region {
-                    start: PlaybackPosition::Seconds(data.start_time.unwrap_or(0.0)),
-                    end: if let Some(end_time) = data.end_time {
-                        EndPosition::Custom(PlaybackPosition::Seconds(end_time))
-                    } else {
-                        EndPosition::EndOfAudio
-                    },
-                })
-                .volume(Decibels::from(data.levels.master as f32))
-                .start_time(StartTime::ClockTime(ClockTime::from_ticks_f64(&clock, 0.0)))
-                .loop_region(data.loop_region);
+    async fn handle_command(&mut self, command: AudioCommand) {
+        // 読み込み中のサウンドへのコマンドは、再生を開始するまで保留する
+        if let Some(id) = command.deferrable_target()
+            && let Some(pending_load) = self.pending_loads.get_mut(&id)
+        {
+            tracing::debug!(instance_id = %id, ?command, "Deferring command until the sound is loaded.");
+            pending_load.deferred_commands.push(command);
+            return;
+        }
+        let target = command.target();
+        let result = match command {
+            // TODO: output is ignored. AudioEngine should have AudioManager for enabled devices
+            AudioCommand::Play { id, data } => self.handle_play(id, data).await,
+            AudioCommand::Pause { id } => self.handle_pause(id).await,
+            AudioCommand::Resume { id } => self.handle_resume(id).await,
+            AudioCommand::Stop { id, fade_out, easing } => self.handle_stop(id, fade_out, easing).await,
+            AudioCommand::SetLevels {id,levels, duration, easing } => self.handle_set_levels(id, levels, duration, easing),
+            AudioCommand::SetLevelsMany { changes } => self.handle_set_levels_many(changes),
+            AudioCommand::CancelFade { id } => self.handle_cancel_fade(id),
+            AudioCommand::ReplaceMedia { id, filepath } => self.handle_replace_media(id, filepath),
+            AudioCommand::Seek { id, position } => self.handle_seek(id, position),
+            AudioCommand::ReleaseLoop { id } => self.handle_release_loop(id),
+            AudioCommand::Preload { id, data } => {
+                self.handle_preload(id, data);
+                Ok(())
+            }
+            AudioCommand::EvictPreload { id } => {
+                if !self.preload_cache.remove(&id) {
+                    tracing::warn!(preload_id = %id, "Cannot evict preload: Sound is not preloaded.");
+                }
+                Ok(())
+            }
+            AudioCommand::SetOutputDevice { device } => self.handle_set_output_device(device),
+            AudioCommand::SetMaxVoices { limit } => {
+                self.max_voices = limit;
+                Ok(())
+            }
+            AudioCommand::SetMasterVolume { db, duration, easing } => self.handle_set_master_volume(db, duration, easing),
+            AudioCommand::SetBusVolume { bus, db, duration, easing } => self.handle_set_bus_volume(bus, db, duration, easing),
+        };
+        if let Err(e) = result {
+            tracing::error!(instance_id = target.map(tracing::field::display), error = ?e, "Error processing audio_engine command.");
+        }
+    }
 
-        if let Some(fade_in_param) = data.fade_in_param {
-            sound_data = sound_data.fade_in_tween(Tween {
+    /// 終了時に全てのサウンドをフェードアウトし、停止を通知します。
+    async fn fade_out_all(&mut self) {
+        for pending_load in self.pending_loads.values() {
+            pending_load.cancel_token.cancel();
+        }
+        for cancel_token in self.pending_replacements.values() {
+            cancel_token.cancel();
+        }
+        if self.playing_sounds.is_empty() {
+            return;
+        }
+        tracing::info!(count = self.playing_sounds.len(), "Fading out sounds for shutdown.");
+        for playing_sound in self.playing_sounds.values_mut() {
+            playing_sound.handle.stop(Tween {
                 start_time: StartTime::Immediate,
-                duration: Duration::from_secs_f64(fade_in_param.duration),
-                easing: fade_in_param.easing,
+                duration: SHUTDOWN_FADE_OUT,
+                easing: Easing::default(),
             });
+            playing_sound.tracker.stopped_manually = true;
         }
+        time::sleep(SHUTDOWN_FADE_OUT + STATE_POLL_INTERVAL).await;
+        self.sweep_states().await;
+    }
 
-        let duration = sound_data.duration().as_secs_f64();
-
-        log::info!("PLAY: id={}, file={}", id, data.filepath.display());
-        let mut handle = manager.play(sound_data)?;
-        clock.start();
+    /// 再生中のサウンドの進捗を通知します。
+    async fn emit_progress(&self) {
+        for (id, playing_sound) in &self.playing_sounds {
+            if !matches!(
+                playing_sound.handle.state(),
+                PlaybackState::Playing | PlaybackState::Pausing | PlaybackState::Resuming | PlaybackState::Stopping
+            ) {
+                continue;
+            }
+            let event = EngineEvent::Audio(AudioEngineEvent::Progress { instance_id: *id, position: playing_sound.handle.position(), duration: playing_sound.duration });
+            if let Err(e) = self.event_tx.send(event).await {
+                tracing::error!(instance_id = %id, error = ?e, "Failed to send Progress event.");
+            }
+        }
+    }
 
-        if let Some(fade_out_param) = data.fade_out_param {
-            handle.set_volume(
-                Decibels::SILENCE,
-                Tween {
-                    start_time: StartTime::ClockTime(ClockTime::from_ticks_f64(
-                        &clock,
-                        duration - fade_out_param.duration,
-                    )),
-                    duration: Duration::from_secs_f64(fade_out_param.duration),
-                    easing: fade_out_param.easing,
-                },
-            );
+    /// 再生中のサウンドのレベルを通知します。
+    async fn emit_meter(&self) {
+        for (id, playing_sound) in &self.playing_sounds {
+            if !matches!(playing_sound.handle.state(), PlaybackState::Playing | PlaybackState::Resuming) {
+                continue;
+            }
+            let Some((peak, rms)) = playing_sound.tracker.meter(playing_sound.handle.position(), &playing_sound.output_levels()) else {
+                continue;
+            };
+            let event = EngineEvent::Audio(AudioEngineEvent::Meter { instance_id: *id, peak, rms });
+            if let Err(e) = self.event_tx.send(event).await {
+                tracing::error!(instance_id = %id, error = ?e, "Failed to send Meter event.");
+            }
         }
+    }
 
-        self.event_tx
+    /// 一時停止・停止への状態遷移を検出して通知し、停止したサウンドを削除します。
+    async fn sweep_states(&mut self) {
+        let mut failed_ids = Vec::new();
+        for (id, playing_sound) in self.playing_sounds.iter_mut() {
+            // 再生中にファイルを読めなくなった場合(リムーバブルメディアが外された場合など)は、サウンドを破棄する
+            if let Some(error) = playing_sound.handle.pop_error() {
+                tracing::error!(instance_id = %id, %error, "Failed to read media during playback.");
+                playing_sound.handle.stop(Tween::default());
+                failed_ids.push(*id);
+                let event = EngineEvent::Audio(AudioEngineEvent::Error {
+                    instance_id: *id,
+                    error: format!("Failed to read media during playback: {}", error),
+                    kind: CueErrorKind::MediaUnavailable,
+                });
+                if let Err(e) = self.event_tx.send(event).await {
+                    tracing::error!(instance_id = %id, error = ?e, "Failed to send Error event.");
+                }
+                continue;
+            }
+            let playback_state = playing_sound.handle.state();
+            let last_state = std::mem::replace(&mut playing_sound.last_state, playback_state);
+            // 終端まで再生して停止した場合、最後に取得できる位置は終端より手前になる
+            let reached_end = playback_state.eq(&PlaybackState::Stopped)
+                && !playing_sound.tracker.stopped_manually
+                && playing_sound.loop_counter.as_ref().is_none_or(|loop_counter| loop_counter.loop_end.is_none());
+            let position = if reached_end { playing_sound.duration } else { playing_sound.handle.position() };
+            let output_levels = playing_sound.output_levels();
+            playing_sound.tracker.update(position, &output_levels);
+            if matches!(playback_state, PlaybackState::Playing | PlaybackState::Resuming) {
+                playing_sound.advance_fade_stages(position, StartTime::Immediate);
+            }
+            if let Some(loop_counter) = playing_sound.loop_counter.as_mut()
+                && playback_state.eq(&PlaybackState::Playing)
+                && loop_counter.update(&mut playing_sound.handle)
+            {
+                tracing::info!(instance_id = %id, "LOOP END");
+                playing_sound.handle.stop(Tween::default());
+            }
+            let event = match playback_state {
+                PlaybackState::Paused if last_state.ne(&PlaybackState::Paused) => {
+                    tracing::info!(instance_id = %id, "PAUSE");
+                    EngineEvent::Audio(AudioEngineEvent::Paused { instance_id: *id, position: playing_sound.handle.position(), duration: playing_sound.duration })
+                },
+                PlaybackState::Stopped => {
+                    tracing::info!(instance_id = %id, "STOP");
+                    EngineEvent::Audio(AudioEngineEvent::Completed { instance_id: *id, report: playing_sound.tracker.report() })
+                },
+                _ => continue,
+            };
+            if let Err(e) = self.event_tx.send(event).await {
+                tracing::error!(instance_id = %id, error = ?e, "Failed to send playback state event.");
+            }
+        }
+        // 停止状態のPlayingSoundを削除
+        self.playing_sounds.retain(|id, value| value.last_state.ne(&PlaybackState::Stopped) && !failed_ids.contains(id));
+        self.scrub_previews.retain(|handle| handle.state().ne(&PlaybackState::Stopped));
+        if let Err(e) = self.apply_output_device() {
+            tracing::error!(error = ?e, "Failed to switch output device.");
+        }
+    }
+
+    async fn handle_play(&mut self, id: Uuid, data: PlayCommandData) -> Result<()> {
+        tracing::info!(instance_id = %id, file = %data.filepath.display(), "PLAY");
+        // 先読みしたサウンドは読み込みを待たずに再生を開始する
+        if !data.streaming
+            && let Some((sound, gain_offset)) = self.preload_cache.get(&data.filepath, data.normalize_to)
+        {
+            tracing::debug!(instance_id = %id, "Playing preloaded sound.");
+            self.pending_loads.insert(id, PendingLoad { cancel_token: CancellationToken::new(), deferred_commands: Vec::new() });
+            // finish_loadは保留したコマンドの実行でhandle_commandを呼ぶため、再帰の呼び出しをBoxにする
+            return Box::pin(self.finish_load(LoadedMessage { id, data, result: Ok((LoadedSound::Static(sound), gain_offset)) })).await;
+        }
+        // 大きなファイルはデコードに時間がかかるため、読み込みの開始を先に通知する
+        self.event_tx
+            .send(EngineEvent::Audio(AudioEngineEvent::Loading {
+                instance_id: id,
+            }))
+            .await?;
+        // 読み込み中もコマンドを受け付けるため、読み込みは別のタスクで行う
+        let cancel_token = CancellationToken::new();
+        self.pending_loads.insert(id, PendingLoad { cancel_token: cancel_token.clone(), deferred_commands: Vec::new() });
+        let sound_loader = self.sound_loader;
+        let loudness_cache = self.loudness_cache.clone();
+        let loaded_tx = self.loaded_tx.clone();
+        tokio::spawn(
+            async move {
+                let result = tokio::select! {
+                    _ = cancel_token.cancelled() => return,
+                    result = load_sound(sound_loader, &loudness_cache, &data) => result,
+                };
+                if loaded_tx.send(LoadedMessage { id, data, result }).await.is_err() {
+                    tracing::debug!("AudioEngine finished before the sound was loaded.");
+                }
+            }
+            .in_current_span(),
+        );
+        Ok(())
+    }
+
+    /// 音声ファイルを別のタスクで読み込み、先読みのキャッシュに追加します。
+    fn handle_preload(&mut self, id: Uuid, data: PlayCommandData) {
+        if data.streaming {
+            tracing::warn!(preload_id = %id, file = %data.filepath.display(), "Streaming sounds are not preloaded.");
+            return;
+        }
+        tracing::info!(preload_id = %id, file = %data.filepath.display(), "PRELOAD");
+        let sound_loader = self.sound_loader;
+        let loudness_cache = self.loudness_cache.clone();
+        let preload_cache = self.preload_cache.clone();
+        tokio::spawn(
+            async move {
+                match load_sound(sound_loader, &loudness_cache, &data).await {
+                    Ok((LoadedSound::Static(sound), gain_offset)) => {
+                        if !preload_cache.insert(id, data.filepath.clone(), data.normalize_to, sound, gain_offset) {
+                            tracing::warn!(file = %data.filepath.display(), "Sound is larger than the preload budget. Not preloaded.");
+                        }
+                    }
+                    Ok((LoadedSound::Streaming(_), _)) => unreachable!(),
+                    Err(e) => tracing::warn!(error = ?e, "Failed to preload sound."),
+                }
+            }
+            .in_current_span(),
+        );
+    }
+
+    /// 読み込みを終えたサウンドの再生を開始し、読み込み中に届いたコマンドを実行します。
+    /// 読み込み中に停止されたサウンドは再生しません。
+    async fn finish_load(&mut self, loaded: LoadedMessage) -> Result<()> {
+        let LoadedMessage { id, data, result } = loaded;
+        let Some(pending_load) = self.pending_loads.remove(&id) else {
+            tracing::info!(instance_id = %id, "Sound was stopped while loading. Discarding.");
+            return Ok(());
+        };
+        let (sound, gain_offset) = result?;
+        self.steal_voices().await?;
+        let pending_sound = self.start_sound(data, sound, gain_offset, 0.0)?;
+
+        self.event_tx
             .send(EngineEvent::Audio(AudioEngineEvent::Started {
                 instance_id: id,
             }))
             .await?;
 
-        self.playing_sounds.insert(
-            id,
-            PlayingSound {
-                duration,
-                handle,
-                last_state: PlaybackState::Playing,
-                _clock: clock,
-            },
+        self.playing_sounds.insert(id, pending_sound.register());
+        for command in pending_load.deferred_commands {
+            self.handle_command(command).await;
+        }
+        Ok(())
+    }
+
+    /// 同時に再生できる数に達している場合、新しいサウンドの分を空けるため、古いサウンドから短いフェードで停止します。
+    /// 停止中のサウンドは数えません。
+    async fn steal_voices(&mut self) -> Result<()> {
+        let Some(max_voices) = self.max_voices else {
+            return Ok(());
+        };
+        let mut voices: Vec<(u64, Uuid)> = self
+            .playing_sounds
+            .iter()
+            .filter(|(_, playing_sound)| {
+                !playing_sound.tracker.stopped_manually
+                    && !matches!(playing_sound.handle.state(), PlaybackState::Stopping | PlaybackState::Stopped)
+            })
+            .map(|(id, playing_sound)| (playing_sound.voice_order, *id))
+            .collect();
+        let excess = (voices.len() + 1).saturating_sub(max_voices);
+        if excess == 0 {
+            return Ok(());
+        }
+        voices.sort_unstable();
+        for (_, id) in voices.into_iter().take(excess) {
+            tracing::warn!(instance_id = %id, max_voices, "Voice limit reached. Stopping the oldest sound.");
+            self.handle_stop(id, VOICE_STEAL_FADE_OUT, Easing::Linear).await?;
+            self.event_tx.send(EngineEvent::Audio(AudioEngineEvent::VoiceStolen { instance_id: id })).await?;
+        }
+        Ok(())
+    }
+
+    /// 再生中のサウンドの音声ファイルを別のタスクで読み込みます。
+    /// 読み込み中は元のサウンドの再生を続け、読み込みを終えた時点の位置から差し替えます。
+    fn handle_replace_media(&mut self, id: Uuid, filepath: PathBuf) -> Result<()> {
+        let Some(playing_sound) = self.playing_sounds.get(&id) else {
+            tracing::warn!(instance_id = %id, "ReplaceMedia command received for non-existent ID.");
+            return Err(anyhow::anyhow!("Sound with ID {} not found for replace media.", id));
+        };
+        tracing::info!(instance_id = %id, file = %filepath.display(), "REPLACE MEDIA");
+        let data = PlayCommandData {
+            filepath,
+            fade_in_param: None,
+            start_delay: 0.0,
+            ..playing_sound.data.clone()
+        };
+        // 読み込み中の差し替えは新しい差し替えで置き換える
+        let cancel_token = CancellationToken::new();
+        if let Some(previous) = self.pending_replacements.insert(id, cancel_token.clone()) {
+            previous.cancel();
+        }
+        let sound_loader = self.sound_loader;
+        let loudness_cache = self.loudness_cache.clone();
+        let replaced_tx = self.replaced_tx.clone();
+        tokio::spawn(
+            async move {
+                let result = tokio::select! {
+                    _ = cancel_token.cancelled() => return,
+                    result = load_sound(sound_loader, &loudness_cache, &data) => result,
+                };
+                if replaced_tx.send(ReplacedMessage { id, data, cancel_token, result }).await.is_err() {
+                    tracing::debug!("AudioEngine finished before the replacement was loaded.");
+                }
+            }
+            .in_current_span(),
         );
         Ok(())
     }
 
+    /// 読み込みを終えたサウンドに差し替え、元のサウンドの現在の位置・音量・一時停止状態を引き継ぎます。
+    /// 読み込み中に停止・再度差し替えされた場合は破棄します。
+    async fn finish_replace(&mut self, replaced: ReplacedMessage) -> Result<()> {
+        let ReplacedMessage { id, mut data, cancel_token, result } = replaced;
+        if cancel_token.is_cancelled() {
+            tracing::info!(instance_id = %id, "Replacement was superseded while loading. Discarding.");
+            return Ok(());
+        }
+        self.pending_replacements.remove(&id);
+        let (sound, gain_offset) = result?;
+        let Some(playing_sound) = self.playing_sounds.get(&id) else {
+            tracing::info!(instance_id = %id, "Sound finished while loading the replacement. Discarding.");
+            return Ok(());
+        };
+        let position = playing_sound.handle.position();
+        data.levels = playing_sound.levels.clone();
+        let mut pending_sound = self.start_sound(data, sound, gain_offset, position)?;
+
+        let new_sound = pending_sound.0.as_mut().unwrap();
+        let old_sound = self.playing_sounds.get_mut(&id).unwrap();
+        new_sound.tracker.played_duration = old_sound.tracker.played_duration;
+        new_sound.tracker.peak = old_sound.tracker.peak;
+        new_sound.voice_order = old_sound.voice_order;
+        if old_sound.handle.state() == PlaybackState::Paused {
+            new_sound.handle.pause(Tween::default());
+        }
+        old_sound.handle.stop(Tween {
+            start_time: StartTime::Immediate,
+            duration: Duration::ZERO,
+            easing: Easing::Linear,
+        });
+        self.playing_sounds.insert(id, pending_sound.register());
+        Ok(())
+    }
+
+    /// 読み込んだサウンドを`start_position`秒の位置から再生を開始します。
+    fn start_sound(&mut self, data: PlayCommandData, sound: LoadedSound, gain_offset: f64, start_position: f64) -> Result<PendingSound> {
+        if let Err(e) = self.apply_output_device() {
+            tracing::error!(file = %data.filepath.display(), error = ?e, "Failed to switch output device.");
+        }
+        let manager = self.manager.as_mut().unwrap();
+        let mut clock = manager
+            .add_clock(ClockSpeed::SecondsPerTick(1.0))
+            .context("Failed to add clock")?;
+
+        let mut sound_data = sound
+            .slice(Region {
+                start: PlaybackPosition::Seconds(data.start_time.unwrap_or(0.0)),
+                end: if let Some(end_time) = data.end_time {
+                    EndPosition::Custom(PlaybackPosition::Seconds(end_time))
+                } else {
+                    EndPosition::EndOfAudio
+                },
+            })
+            .volume(Decibels::from((data.levels.master + gain_offset) as f32))
+            .start_time(StartTime::ClockTime(ClockTime::from_ticks_f64(&clock, data.start_delay)))
+            .start_position(start_position);
+        let loop_region = data.loop_region.and_then(|loop_region| clamp_loop_region(loop_region, &sound_data));
+        sound_data = sound_data.loop_region(loop_region);
+
+        let duration = sound_data.duration();
+        let sample_rate = sound_data.sample_rate();
+        let master = Decibels((data.levels.master + gain_offset) as f32);
+        let mut fade_stages = VecDeque::new();
+        let mut breakpoint_fade_in_end = None;
+        if let Some(fade_in_param) = &data.fade_in_param {
+            if let Some(points) = fade_in_param.easing.breakpoints() {
+                fade_stages = breakpoint_stages(&points, start_position, fade_in_param.duration, Decibels::SILENCE, master, true);
+                sound_data = sound_data.volume(fade_stages[0].volume);
+                breakpoint_fade_in_end = Some(start_position + fade_in_param.duration);
+            } else {
+                sound_data = sound_data.fade_in_tween(Tween {
+                    start_time: StartTime::ClockTime(ClockTime::from_ticks_f64(&clock, data.start_delay)),
+                    duration: Duration::from_secs_f64(fade_in_param.duration),
+                    easing: fade_in_param.easing.easing(),
+                });
+            }
+        }
+        let loop_bounds = loop_region.map(|loop_region| {
+            let to_seconds = |position| match position {
+                PlaybackPosition::Seconds(seconds) => seconds,
+                PlaybackPosition::Samples(samples) => samples as f64 / sample_rate,
+            };
+            let loop_end = match loop_region.end {
+                EndPosition::EndOfAudio => duration,
+                EndPosition::Custom(position) => to_seconds(position),
+            };
+            (to_seconds(loop_region.start), loop_end)
+        });
+
+        let loop_counter = match (loop_region, data.loop_count) {
+            (Some(loop_region), Some(loop_count)) => {
+                let loop_end = match loop_region.end {
+                    EndPosition::EndOfAudio => None,
+                    EndPosition::Custom(PlaybackPosition::Seconds(seconds)) => Some(seconds),
+                    EndPosition::Custom(PlaybackPosition::Samples(samples)) => {
+                        Some(samples as f64 / sample_rate)
+                    }
+                };
+                if loop_count <= 1 {
+                    sound_data = sound_data.loop_region(None);
+                }
+                Some(LoopCounter {
+                    remaining_loops: loop_count.saturating_sub(1),
+                    last_position: start_position,
+                    pending_seek_from: None,
+                    loop_end,
+                    final_pass: loop_count <= 1,
+                })
+            }
+            _ => None,
+        };
+
+        let tracker = PlaybackTracker {
+            sound_data: sound_data.as_static().cloned(),
+            loop_bounds,
+            last_position: start_position,
+            pending_seek_from: None,
+            played_duration: 0.0,
+            peak: 0.0,
+            stopped_manually: false,
+        };
+
+        let handle = self.play_on_bus(data.output_bus.as_deref(), sound_data)?;
+        clock.start();
+        let voice_order = self.next_voice_order;
+        self.next_voice_order += 1;
+        // ここから登録までの間に失敗・キャンセルされた場合はガードがサウンドを停止する
+        let mut pending_sound = PendingSound(Some(PlayingSound {
+            duration,
+            handle,
+            last_state: PlaybackState::Playing,
+            levels: data.levels.clone(),
+            gain_offset,
+            loop_counter,
+            tracker,
+            data: data.clone(),
+            fade_stages,
+            breakpoint_fade_in_end,
+            voice_order,
+            _clock: clock,
+        }));
+        let playing_sound = pending_sound.0.as_mut().unwrap();
+
+        if let Some(points) = &data.automation {
+            let mut stages = automation_stages(points, master);
+            // フェードアウトの区間ではオートメーションより先にフェードアウトを優先する
+            if let Some(fade_out_param) = &data.fade_out_param {
+                stages.retain(|stage| stage.position < duration - fade_out_param.duration);
+            }
+            playing_sound.fade_stages.extend(stages);
+        }
+        if let Some(fade_out_param) = &data.fade_out_param {
+            let fade_out_position = duration - fade_out_param.duration;
+            if let Some(points) = fade_out_param.easing.breakpoints() {
+                let stages = breakpoint_stages(&points, fade_out_position, fade_out_param.duration, master, Decibels::SILENCE, false);
+                playing_sound.fade_stages.extend(stages);
+            } else if !playing_sound.fade_stages.is_empty() {
+                // 段階的なフェードインがフェードアウトの予約を上書きしないよう、フェードアウトも再生位置に応じて指示する
+                playing_sound.fade_stages.push_back(FadeStage {
+                    position: fade_out_position,
+                    end_position: duration,
+                    volume: Decibels::SILENCE,
+                    easing: fade_out_param.easing.easing(),
+                    fade_in: false,
+                });
+            } else {
+                let clock = &playing_sound._clock;
+                playing_sound.handle.set_volume(
+                    Decibels::SILENCE,
+                    Tween {
+                        start_time: StartTime::ClockTime(ClockTime::from_ticks_f64(
+                            clock,
+                            data.start_delay + fade_out_position - start_position,
+                        )),
+                        duration: Duration::from_secs_f64(fade_out_param.duration),
+                        easing: fade_out_param.easing.easing(),
+                    },
+                );
+            }
+        }
+        playing_sound.fade_stages.make_contiguous().sort_by(|a, b| a.position.total_cmp(&b.position));
+        let start_time = StartTime::ClockTime(ClockTime::from_ticks_f64(&playing_sound._clock, data.start_delay));
+        playing_sound.advance_fade_stages(start_position, start_time);
+
+        Ok(pending_sound)
+    }
+
     async fn handle_pause(&mut self, id: Uuid) -> Result<()> {
-        log::info!("PAUSE: id={}", id);
+        tracing::info!(instance_id = %id, "PAUSE");
         if let Some(playing_sound) = self.playing_sounds.get_mut(&id) {
             playing_sound.handle.pause(Tween::default());
             self.event_tx
@@ -241,13 +1337,13 @@ impl AudioEngine {
                 .await?;
             Ok(())
         } else {
-            log::warn!("Pause command received for non-existent ID: {}", id);
+            tracing::warn!(instance_id = %id, "Pause command received for non-existent ID.");
             Err(anyhow::anyhow!("Sound with ID {} not found for pause.", id))
         }
     }
 
     async fn handle_resume(&mut self, id: Uuid) -> Result<()> {
-        log::info!("RESUME: id={}", id);
+        tracing::info!(instance_id = %id, "RESUME");
         if let Some(playing_sound) = self.playing_sounds.get_mut(&id) {
             if playing_sound
                 .handle
@@ -263,7 +1359,7 @@ impl AudioEngine {
             }
             Ok(())
         } else {
-            log::warn!("Resume command received for non-existent ID: {}", id);
+            tracing::warn!(instance_id = %id, "Resume command received for non-existent ID.");
             Err(anyhow::anyhow!(
                 "Sound with ID {} not found for resume.",
                 id
@@ -271,45 +1367,263 @@ impl AudioEngine {
         }
     }
 
-    fn handle_stop(&mut self, id: Uuid, fade_out: Duration) -> Result<()> {
-        log::info!("STOP: id={}, fade_out={:?}", id, fade_out);
-        if let Some(mut playing_sound) = self.playing_sounds.remove(&id) {
+    async fn handle_stop(&mut self, id: Uuid, fade_out: Duration, easing: Easing) -> Result<()> {
+        tracing::info!(instance_id = %id, ?fade_out, "STOP");
+        // 読み込み中のサウンドは再生を開始せずに終了する
+        if let Some(pending_load) = self.pending_loads.remove(&id) {
+            pending_load.cancel_token.cancel();
+            let report = PlaybackReport { was_stopped_manually: true, ..PlaybackReport::empty() };
+            self.event_tx.send(EngineEvent::Audio(AudioEngineEvent::Completed { instance_id: id, report })).await?;
+            return Ok(());
+        }
+        // 差し替え用の読み込みは破棄する
+        if let Some(cancel_token) = self.pending_replacements.remove(&id) {
+            cancel_token.cancel();
+        }
+        if let Some(playing_sound) = self.playing_sounds.get_mut(&id) {
             let fade_tween = Tween {
                 start_time: StartTime::Immediate,
                 duration: fade_out,
-                easing: Easing::default(),
+                easing,
             };
             playing_sound.handle.stop(fade_tween);
+            playing_sound.tracker.stopped_manually = true;
             Ok(())
         } else {
-            log::warn!("Stop command received for non-existent ID: {}", id);
+            tracing::warn!(instance_id = %id, "Stop command received for non-existent ID.");
             Err(anyhow::anyhow!("Sound with ID {} not found for stop.", id))
         }
     }
 
+    fn handle_cancel_fade(&mut self, id: Uuid) -> Result<()> {
+        let Some(playing_sound) = self.playing_sounds.get_mut(&id) else {
+            tracing::warn!(instance_id = %id, "CancelFade command received for non-existent ID.");
+            return Err(anyhow::anyhow!("Sound with ID {} not found for cancel fade.", id));
+        };
+        // 一時停止・停止中のフェードは中断しない
+        if !matches!(playing_sound.handle.state(), PlaybackState::Playing | PlaybackState::Resuming) {
+            return Ok(());
+        }
+        tracing::info!(instance_id = %id, "CANCEL FADE");
+        // フェードインはkiraのフェード用パラメータで行われるため、短いresumeで上書きする
+        playing_sound.handle.resume(Tween {
+            start_time: StartTime::Immediate,
+            duration: FADE_CANCEL_DURATION,
+            easing: Easing::Linear,
+        });
+        // 折れ線のフェードインは音量の変化で行われるため、残りの区間を破棄して元の音量に戻す
+        if playing_sound.breakpoint_fade_in_end.is_some_and(|fade_in_end| playing_sound.handle.position() < fade_in_end) {
+            playing_sound.fade_stages.retain(|stage| !stage.fade_in);
+            playing_sound.handle.set_volume(
+                playing_sound.output_levels().master as f32,
+                Tween {
+                    start_time: StartTime::Immediate,
+                    duration: FADE_CANCEL_DURATION,
+                    easing: Easing::Linear,
+                },
+            );
+        }
+        Ok(())
+    }
+
     fn handle_set_levels(
         &mut self,
         id: Uuid,
         levels: AudioCueLevels,
         duration: f64,
-        easing: Easing,
+        easing: FadeCurve,
     ) -> Result<()> {
-        log::info!("SET LEVELS: id={}, levels={:?}", id, levels);
         if let Some(playing_sound) = self.playing_sounds.get_mut(&id) {
-            playing_sound.handle.set_volume(
-                levels.master as f32,
+            tracing::info!(instance_id = %id, from = ?playing_sound.levels, to = ?levels, "SET LEVELS");
+            playing_sound.fade_stages.clear();
+            if let Some(points) = easing.breakpoints() {
+                let position = playing_sound.handle.position();
+                playing_sound.fade_stages = breakpoint_stages(
+                    &points,
+                    position,
+                    duration,
+                    Decibels(playing_sound.output_levels().master as f32),
+                    Decibels((levels.master + playing_sound.gain_offset) as f32),
+                    false,
+                );
+                playing_sound.advance_fade_stages(position, StartTime::Immediate);
+            } else {
+                playing_sound.handle.set_volume(
+                    (levels.master + playing_sound.gain_offset) as f32,
+                    Tween {
+                        start_time: StartTime::Immediate,
+                        duration: Duration::from_secs_f64(duration),
+                        easing: easing.easing(),
+                    },
+                );
+            }
+            playing_sound.levels = levels;
+            Ok(())
+        } else {
+            tracing::warn!(instance_id = %id, "SetLevels command received for non-existent ID.");
+            Err(anyhow::anyhow!(
+                "Sound with ID {} not found for set levels.",
+                id
+            ))
+        }
+    }
+
+    fn handle_set_output_device(&mut self, device: Option<String>) -> Result<()> {
+        if self.manager_factory.is_none() {
+            return Err(anyhow::anyhow!("Output device cannot be changed for this audio backend."));
+        }
+        if self.pending_output_device.is_none() && device == self.output_device {
+            return Ok(());
+        }
+        tracing::info!(?device, "SET OUTPUT DEVICE");
+        self.pending_output_device = Some(device);
+        self.apply_output_device()
+    }
+
+    /// 保留中の出力デバイスがあれば切り替えます。再生中のサウンドは切り替えると失われるため、全て終了するまで待ちます。
+    fn apply_output_device(&mut self) -> Result<()> {
+        if !self.playing_sounds.is_empty() {
+            return Ok(());
+        }
+        let (Some(device), Some(manager_factory)) = (self.pending_output_device.take(), self.manager_factory) else {
+            return Ok(());
+        };
+        let mut manager = manager_factory(device.as_deref())?;
+        // 新しいAudioManagerのメイントラックにも音量を引き継ぐ
+        manager.main_track().set_volume(
+            Decibels(self.master_volume as f32),
+            Tween {
+                start_time: StartTime::Immediate,
+                duration: Duration::ZERO,
+                easing: Easing::Linear,
+            },
+        );
+        self.manager = Some(manager);
+        // バスは古いAudioManagerのトラックのため、次に使われたときに作り直す
+        self.buses.clear();
+        tracing::info!(?device, "OUTPUT DEVICE SWITCHED");
+        self.output_device = device;
+        Ok(())
+    }
+
+    fn handle_seek(&mut self, id: Uuid, position: f64) -> Result<()> {
+        let Some(playing_sound) = self.playing_sounds.get_mut(&id) else {
+            tracing::warn!(instance_id = %id, "Seek command received for non-existent ID.");
+            return Err(anyhow::anyhow!("Sound with ID {} not found for seek.", id));
+        };
+        let position = position.clamp(0.0, playing_sound.duration);
+        tracing::info!(instance_id = %id, position, "SEEK");
+        let from = playing_sound.handle.position();
+        playing_sound.handle.seek_to(position);
+        // 移動した区間は再生した時間やループ回数に含めない
+        playing_sound.tracker.seek(from, position);
+        if let Some(loop_counter) = playing_sound.loop_counter.as_mut() {
+            loop_counter.seek(from, position);
+        }
+        // ストリーミング再生のサウンドはメモリに読み込んでいないため、プレビューを再生しない
+        if self.scrub_preview
+            && playing_sound.handle.state() == PlaybackState::Paused
+            && let Some(sound_data) = &playing_sound.tracker.sound_data
+        {
+            let preview = LoadedSound::Static(scrub_preview_data(sound_data, position, &playing_sound.output_levels()));
+            let output_bus = playing_sound.data.output_bus.clone();
+            // ドラッグ中は直前のプレビューを止め、最新の位置だけを鳴らす
+            for handle in self.scrub_previews.iter_mut() {
+                handle.stop(Tween {
+                    start_time: StartTime::Immediate,
+                    duration: FADE_CANCEL_DURATION,
+                    easing: Easing::Linear,
+                });
+            }
+            let handle = self.play_on_bus(output_bus.as_deref(), preview)?;
+            self.scrub_previews.push(handle);
+        }
+        Ok(())
+    }
+
+    fn handle_release_loop(&mut self, id: Uuid) -> Result<()> {
+        let Some(playing_sound) = self.playing_sounds.get_mut(&id) else {
+            tracing::warn!(instance_id = %id, "ReleaseLoop command received for non-existent ID.");
+            return Err(anyhow::anyhow!("Sound with ID {} not found for release loop.", id));
+        };
+        tracing::info!(instance_id = %id, "RELEASE LOOP");
+        // 再生範囲はend_timeで切り出しているため、ループを外すとend_timeで終了する
+        playing_sound.handle.set_loop_region(None);
+        playing_sound.loop_counter = None;
+        playing_sound.tracker.loop_bounds = None;
+        Ok(())
+    }
+
+    fn handle_set_master_volume(&mut self, db: f64, duration: f64, easing: Easing) -> Result<()> {
+        tracing::info!(from = self.master_volume, to = db, duration, "SET MASTER VOLUME");
+        self.manager.as_mut().unwrap().main_track().set_volume(
+            Decibels(db as f32),
+            Tween {
+                start_time: StartTime::Immediate,
+                duration: Duration::from_secs_f64(duration),
+                easing,
+            },
+        );
+        self.master_volume = db;
+        Ok(())
+    }
+
+    fn handle_set_bus_volume(&mut self, bus: String, db: f64, duration: f64, easing: Easing) -> Result<()> {
+        tracing::info!(%bus, from = self.bus_volumes.get(&bus).copied().unwrap_or(0.0), to = db, duration, "SET BUS VOLUME");
+        // まだ作成されていないバスは、作成時にこの音量を使う
+        if let Some(track) = self.buses.get_mut(&bus) {
+            track.set_volume(
+                Decibels(db as f32),
                 Tween {
                     start_time: StartTime::Immediate,
                     duration: Duration::from_secs_f64(duration),
                     easing,
                 },
             );
+        }
+        self.bus_volumes.insert(bus, db);
+        Ok(())
+    }
+
+    /// サウンドを`bus`で再生します。バスがまだない場合は作成し、Noneの場合はメイントラックで再生します。
+    fn play_on_bus(&mut self, bus: Option<&str>, sound_data: LoadedSound) -> Result<SoundHandle> {
+        let manager = self.manager.as_mut().unwrap();
+        let Some(bus) = bus else {
+            return Ok(match sound_data {
+                LoadedSound::Static(data) => SoundHandle::Static(manager.play(data)?),
+                LoadedSound::Streaming(data) => SoundHandle::Streaming(manager.play(data)?),
+            });
+        };
+        if !self.buses.contains_key(bus) {
+            let volume = self.bus_volumes.get(bus).copied().unwrap_or(0.0);
+            let track = manager
+                .add_sub_track(TrackBuilder::new().volume(Decibels(volume as f32)))
+                .with_context(|| format!("Failed to create bus '{}'", bus))?;
+            tracing::info!(%bus, "BUS CREATED");
+            self.buses.insert(bus.to_string(), track);
+        }
+        let track = self.buses.get_mut(bus).unwrap();
+        Ok(match sound_data {
+            LoadedSound::Static(data) => SoundHandle::Static(track.play(data)?),
+            LoadedSound::Streaming(data) => SoundHandle::Streaming(track.play(data)?),
+        })
+    }
+
+    /// 全ての変更を同じオーディオ処理の周期で反映させ、フェードが揃うようにします。
+    /// 存在しないIDがあっても他のサウンドには反映します。
+    fn handle_set_levels_many(&mut self, changes: Vec<(Uuid, AudioCueLevels, f64, FadeCurve)>) -> Result<()> {
+        let mut missing_ids = Vec::new();
+        for (id, levels, duration, easing) in changes {
+            if self.handle_set_levels(id, levels, duration, easing).is_err() {
+                missing_ids.push(id);
+            }
+        }
+        if missing_ids.is_empty() {
             Ok(())
         } else {
-            log::warn!("SetLevels command received for non-existent ID: {}", id);
             Err(anyhow::anyhow!(
-                "Sound with ID {} not found for set levels.",
-                id
+                "Sounds with IDs {:?} not found for set levels.",
+                missing_ids
             ))
         }
     }
@@ -317,6 +1631,10 @@ impl AudioEngine {
 
 #[derive(Debug)]
 pub enum AudioEngineEvent {
+    /// 音声ファイルの読み込みを開始した。読み込みが終わるとStartedを送信する
+    Loading {
+        instance_id: Uuid,
+    },
     Started {
         instance_id: Uuid,
     },
@@ -330,27 +1648,1062 @@ pub enum AudioEngineEvent {
         position: f64,
         duration: f64,
     },
+    Meter {
+        instance_id: Uuid,
+        peak: f64, // dBFS
+        rms: f64,  // dBFS
+    },
+    /// 同時に再生できる数を超えたため、サウンドを停止した。停止の完了はCompletedで通知する
+    VoiceStolen {
+        instance_id: Uuid,
+    },
     Resumed {
         instance_id: Uuid,
     },
     Completed {
         instance_id: Uuid,
+        report: PlaybackReport,
     },
     Error {
         instance_id: Uuid,
         error: String,
+        kind: CueErrorKind,
     },
 }
 
+/// 音声ファイルを読み込み、ラウドネスの補正(dB)と合わせて返します。
+async fn load_sound(sound_loader: SoundLoader, loudness_cache: &LoudnessCache, data: &PlayCommandData) -> Result<(LoadedSound, f64)> {
+    let gain_offset = match data.normalize_to {
+        Some(target) => normalize_gain(loudness_cache, &data.filepath, target).await,
+        None => 0.0,
+    };
+    let filepath = data.filepath.clone();
+    let streaming = data.streaming;
+    let sound = tokio::task::spawn_blocking(move || {
+        if streaming {
+            StreamingSoundData::from_file(&filepath).map(LoadedSound::Streaming)
+        } else {
+            sound_loader(&filepath).map(LoadedSound::Static)
+        }
+    })
+    .await?
+    .with_context(|| format!("Failed to load sound data from: {}", data.filepath.display()))?;
+    Ok((sound, gain_offset))
+}
+
+/// ファイルのラウドネスを`target`(LUFS)に揃えるための音量の補正(dB)を返します。
+/// 初回はファイル全体を測定するため、長いファイルでは再生の開始が遅れます。測定できない場合は補正しません。
+async fn normalize_gain(loudness_cache: &LoudnessCache, filepath: &Path, target: f64) -> f64 {
+    match loudness_cache.loudness(filepath).await {
+        Some(loudness) => (target - loudness).min(MAX_NORMALIZE_GAIN),
+        None => {
+            tracing::warn!(file = %filepath.display(), "Loudness is unknown. Playing without normalization.");
+            0.0
+        }
+    }
+}
+
+impl AudioCommand {
+    /// コマンドが対象とする再生中のサウンドのIDを返します。ログに付けるために使います。
+    fn target(&self) -> Option<Uuid> {
+        match self {
+            Self::Play { id, .. } | Self::Stop { id, .. } => Some(*id),
+            _ => self.deferrable_target(),
+        }
+    }
+
+    /// 1つのサウンドを対象とし、読み込みが終わるまで保留できるコマンドの対象のIDを返します。
+    fn deferrable_target(&self) -> Option<Uuid> {
+        match self {
+            Self::Pause { id }
+            | Self::Resume { id }
+            | Self::SetLevels { id, .. }
+            | Self::CancelFade { id }
+            | Self::ReplaceMedia { id, .. }
+            | Self::Seek { id, .. }
+            | Self::ReleaseLoop { id } => Some(*id),
+            _ => None,
+        }
+    }
+}
+
+/// ループ領域を再生範囲(start_time〜end_time)に収めます。
+///
+/// end_timeがループ領域より優先され、ループの終了位置が再生範囲を超える場合は再生範囲の終端までをループします。
+/// ループの開始位置が再生範囲外の場合はループせずに再生します。
+fn clamp_loop_region(loop_region: Region, sound_data: &LoadedSound) -> Option<Region> {
+    let to_seconds = |position| match position {
+        PlaybackPosition::Seconds(seconds) => seconds,
+        PlaybackPosition::Samples(samples) => samples as f64 / sound_data.sample_rate(),
+    };
+    let duration = sound_data.duration();
+    if to_seconds(loop_region.start) >= duration {
+        tracing::warn!(loop_start = to_seconds(loop_region.start), duration, "Loop region starts after the end of playback range. Loop is ignored.");
+        return None;
+    }
+    let end = match loop_region.end {
+        EndPosition::Custom(position) if to_seconds(position) < duration => loop_region.end,
+        _ => EndPosition::EndOfAudio,
+    };
+    Some(Region { start: loop_region.start, end })
+}
+
+/// 出力デバイスを開けなかったことを表すエラーの文脈
+#[derive(Debug)]
+struct OutputDeviceUnavailable(Option<String>);
+
+impl std::fmt::Display for OutputDeviceUnavailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            Some(device) => write!(f, "Output device '{}' is unavailable", device),
+            None => write!(f, "Default output device is unavailable"),
+        }
+    }
+}
+
+/// エラーの原因から、UIに表示するエラーの種類を判定します。
+fn error_kind(error: &anyhow::Error) -> CueErrorKind {
+    if error.downcast_ref::<OutputDeviceUnavailable>().is_some() {
+        return CueErrorKind::DeviceUnavailable;
+    }
+    for cause in error.chain() {
+        if let Some(from_file_error) = cause.downcast_ref::<FromFileError>() {
+            return match from_file_error {
+                FromFileError::NoDefaultTrack
+                | FromFileError::UnknownSampleRate
+                | FromFileError::UnknownDuration
+                | FromFileError::UnsupportedChannelConfiguration => CueErrorKind::UnsupportedFormat,
+                FromFileError::IoError(io_error) => io_error_kind(io_error),
+                FromFileError::SymphoniaError(symphonia_error) => match symphonia_error {
+                    SymphoniaError::IoError(io_error) => io_error_kind(io_error),
+                    SymphoniaError::Unsupported(_) => CueErrorKind::UnsupportedFormat,
+                    _ => CueErrorKind::DecodeFailure,
+                },
+            };
+        }
+        if let Some(io_error) = cause.downcast_ref::<std::io::Error>() {
+            return io_error_kind(io_error);
+        }
+    }
+    CueErrorKind::Playback
+}
+
+fn io_error_kind(error: &std::io::Error) -> CueErrorKind {
+    match error.kind() {
+        std::io::ErrorKind::NotFound => CueErrorKind::FileNotFound,
+        // 読み込み途中でファイルが終わっている場合は、ファイルが壊れている
+        std::io::ErrorKind::UnexpectedEof => CueErrorKind::DecodeFailure,
+        _ => CueErrorKind::MediaUnavailable,
+    }
+}
+
 impl AudioEngineEvent {
     pub fn instance_id(&self) -> Uuid {
         match self {
+            Self::Loading { instance_id } => *instance_id,
             Self::Started { instance_id } => *instance_id,
             Self::Progress { instance_id, .. } => *instance_id,
             Self::Paused { instance_id, .. } => *instance_id,
+            Self::Meter { instance_id, .. } => *instance_id,
+            Self::VoiceStolen { instance_id } => *instance_id,
             Self::Resumed { instance_id } => *instance_id,
-            Self::Completed { instance_id } => *instance_id,
+            Self::Completed { instance_id, .. } => *instance_id,
             Self::Error { instance_id, .. } => *instance_id,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use kira::{backend::mock::{MockBackend, MockBackendSettings}, sound::streaming::Decoder, Frame};
+
+    use crate::test_util::write_wav_samples;
+
+    use super::*;
+
+    /// テスト用の音声の振幅(約-6dBFS)
+    const TEST_AMPLITUDE: i16 = 16384;
+
+    /// 振幅一定の16bitモノラルWAVファイルを書き出します。
+    fn write_test_wav(path: &Path, sample_rate: u32, num_frames: u32) {
+        write_wav_samples(path, sample_rate, &vec![TEST_AMPLITUDE; num_frames as usize]);
+    }
+
+    /// `sample_rate`で動作するMockBackendと、同じサンプルレートで`num_frames`フレームのWAVファイルを用意します。
+    /// `process`1回で128フレーム分の再生が進みます。
+    fn setup_engine(sample_rate: u32, num_frames: u32) -> (AudioEngine<MockBackend>, mpsc::Sender<AudioCommand>, mpsc::Receiver<EngineEvent>, PathBuf) {
+        let (audio_tx, audio_rx) = mpsc::channel::<AudioCommand>(32);
+        let (event_tx, event_rx) = mpsc::channel::<EngineEvent>(32);
+        let manager = AudioManager::<MockBackend>::new(AudioManagerSettings {
+            backend_settings: MockBackendSettings { sample_rate },
+            ..Default::default()
+        })
+        .unwrap();
+
+        let filepath = std::env::temp_dir().join(format!("sbsp_test_{}.wav", Uuid::new_v4()));
+        write_test_wav(&filepath, sample_rate, num_frames);
+
+        (AudioEngine::with_manager(manager, audio_rx, event_tx), audio_tx, event_rx, filepath)
+    }
+
+    fn play_data(filepath: &Path) -> PlayCommandData {
+        PlayCommandData {
+            filepath: filepath.to_path_buf(),
+            levels: AudioCueLevels { master: 0.0 },
+            start_time: None,
+            fade_in_param: None,
+            end_time: None,
+            fade_out_param: None,
+            loop_region: None,
+            loop_count: None,
+            start_delay: 0.0,
+            output_bus: None,
+            streaming: false,
+            normalize_to: None,
+            automation: None,
+        }
+    }
+
+    /// 読み込みの完了を待って再生を開始します。
+    async fn play(engine: &mut AudioEngine<MockBackend>, id: Uuid, data: PlayCommandData) -> Result<()> {
+        engine.handle_play(id, data).await?;
+        let loaded = engine.loaded_rx.recv().await.unwrap();
+        engine.finish_load(loaded).await
+    }
+
+    fn process(engine: &mut AudioEngine<MockBackend>) {
+        let backend = engine.manager.as_mut().unwrap().backend_mut();
+        backend.on_start_processing();
+        backend.process();
+        backend.on_start_processing();
+    }
+
+    #[tokio::test]
+    async fn play_registers_sound() {
+        let (mut engine, _audio_tx, mut event_rx, filepath) = setup_engine(48000, 48000);
+        let id = Uuid::now_v7();
+
+        play(&mut engine, id, play_data(&filepath)).await.unwrap();
+        process(&mut engine);
+
+        assert!(matches!(event_rx.recv().await, Some(EngineEvent::Audio(AudioEngineEvent::Loading { instance_id })) if instance_id == id));
+        assert!(matches!(event_rx.recv().await, Some(EngineEvent::Audio(AudioEngineEvent::Started { instance_id })) if instance_id == id));
+        assert!(engine.playing_sounds.contains_key(&id));
+        assert_eq!(engine.manager.as_mut().unwrap().main_track().num_sounds(), 1);
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[tokio::test]
+    async fn play_stops_sound_when_event_send_fails() {
+        let (mut engine, _audio_tx, event_rx, filepath) = setup_engine(48000, 48000);
+        drop(event_rx);
+
+        let id = Uuid::now_v7();
+        let data = play_data(&filepath);
+        engine.pending_loads.insert(id, PendingLoad { cancel_token: CancellationToken::new(), deferred_commands: Vec::new() });
+        let result = load_sound(engine.sound_loader, &engine.loudness_cache, &data).await;
+        let result = engine.finish_load(LoadedMessage { id, data, result }).await;
+        process(&mut engine);
+
+        assert!(result.is_err());
+        assert!(engine.playing_sounds.is_empty());
+        assert_eq!(engine.manager.as_mut().unwrap().main_track().num_sounds(), 0);
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    /// Completedが届くまで再生を進め、経過した再生時間(秒)を返します。
+    async fn run_until_completed(engine: &mut AudioEngine<MockBackend>, event_rx: &mut mpsc::Receiver<EngineEvent>, sample_rate: u32, limit: f64) -> Option<f64> {
+        let mut elapsed = 0.0;
+        while elapsed < limit {
+            process(engine);
+            elapsed += 128.0 / sample_rate as f64;
+            // ストリーミング再生の読み込みスレッドが追いつくのを待つ
+            std::thread::sleep(Duration::from_micros(100));
+            engine.sweep_states().await;
+            while let Ok(event) = event_rx.try_recv() {
+                if let EngineEvent::Audio(AudioEngineEvent::Completed { .. }) = event {
+                    return Some(elapsed);
+                }
+            }
+        }
+        None
+    }
+
+    #[tokio::test]
+    async fn finite_loop_count() {
+        let (mut engine, _audio_tx, mut event_rx, filepath) = setup_engine(1000, 1000);
+        let mut data = play_data(&filepath);
+        data.loop_region = Some(Region { start: PlaybackPosition::Seconds(0.5), end: EndPosition::EndOfAudio });
+        data.loop_count = Some(2);
+
+        play(&mut engine, Uuid::now_v7(), data).await.unwrap();
+        let elapsed = run_until_completed(&mut engine, &mut event_rx, 1000, 5.0).await.unwrap();
+
+        // 0.0-1.0秒を再生した後、0.5-1.0秒をもう1回再生して終了する
+        assert!((1.5..1.8).contains(&elapsed), "completed after {}s", elapsed);
+        assert!(engine.playing_sounds.is_empty());
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[tokio::test]
+    async fn finite_loop_count_with_custom_end() {
+        let (mut engine, _audio_tx, mut event_rx, filepath) = setup_engine(1000, 2000);
+        let mut data = play_data(&filepath);
+        data.loop_region = Some(Region { start: PlaybackPosition::Seconds(0.5), end: EndPosition::Custom(PlaybackPosition::Seconds(1.0)) });
+        data.loop_count = Some(2);
+
+        play(&mut engine, Uuid::now_v7(), data).await.unwrap();
+        let elapsed = run_until_completed(&mut engine, &mut event_rx, 1000, 5.0).await.unwrap();
+
+        assert!((1.5..1.8).contains(&elapsed), "completed after {}s", elapsed);
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[tokio::test]
+    async fn finite_loop_count_with_end_time() {
+        let (mut engine, _audio_tx, mut event_rx, filepath) = setup_engine(1000, 2000);
+        let mut data = play_data(&filepath);
+        data.end_time = Some(1.5);
+        data.loop_region = Some(Region { start: PlaybackPosition::Seconds(0.5), end: EndPosition::Custom(PlaybackPosition::Seconds(1.8)) });
+        data.loop_count = Some(2);
+
+        play(&mut engine, Uuid::now_v7(), data).await.unwrap();
+        let elapsed = run_until_completed(&mut engine, &mut event_rx, 1000, 5.0).await.unwrap();
+
+        // ループ領域はend_timeまでに切り詰められ、0.0-1.5秒を再生した後、0.5-1.5秒をもう1回再生して終了する
+        assert!((2.5..2.8).contains(&elapsed), "completed after {}s", elapsed);
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[tokio::test]
+    async fn infinite_loop_with_end_time() {
+        let (mut engine, _audio_tx, mut event_rx, filepath) = setup_engine(1000, 2000);
+        let mut data = play_data(&filepath);
+        data.end_time = Some(1.5);
+        data.loop_region = Some(Region { start: PlaybackPosition::Seconds(0.5), end: EndPosition::Custom(PlaybackPosition::Seconds(1.8)) });
+
+        play(&mut engine, Uuid::now_v7(), data).await.unwrap();
+
+        assert_eq!(run_until_completed(&mut engine, &mut event_rx, 1000, 5.0).await, None);
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[tokio::test]
+    async fn infinite_loop() {
+        let (mut engine, _audio_tx, mut event_rx, filepath) = setup_engine(1000, 1000);
+        let mut data = play_data(&filepath);
+        data.loop_region = Some(Region { start: PlaybackPosition::Seconds(0.5), end: EndPosition::EndOfAudio });
+
+        play(&mut engine, Uuid::now_v7(), data).await.unwrap();
+
+        assert_eq!(run_until_completed(&mut engine, &mut event_rx, 1000, 5.0).await, None);
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[tokio::test]
+    async fn set_levels_many() {
+        let (mut engine, _audio_tx, _event_rx, filepath) = setup_engine(48000, 48000);
+        let first_id = Uuid::now_v7();
+        let second_id = Uuid::now_v7();
+        let missing_id = Uuid::now_v7();
+        play(&mut engine, first_id, play_data(&filepath)).await.unwrap();
+        play(&mut engine, second_id, play_data(&filepath)).await.unwrap();
+
+        let result = engine.handle_set_levels_many(vec![
+            (first_id, AudioCueLevels { master: -6.0 }, 1.0, Easing::Linear.into()),
+            (missing_id, AudioCueLevels { master: -6.0 }, 1.0, Easing::Linear.into()),
+            (second_id, AudioCueLevels { master: -12.0 }, 1.0, Easing::Linear.into()),
+        ]);
+
+        assert!(result.is_err());
+        assert_eq!(engine.playing_sounds[&first_id].levels, AudioCueLevels { master: -6.0 });
+        assert_eq!(engine.playing_sounds[&second_id].levels, AudioCueLevels { master: -12.0 });
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    fn create_mock_manager(_device_name: Option<&str>) -> Result<AudioManager<MockBackend>> {
+        Ok(AudioManager::<MockBackend>::new(AudioManagerSettings {
+            backend_settings: MockBackendSettings { sample_rate: 48000 },
+            ..Default::default()
+        })
+        .unwrap())
+    }
+
+    #[tokio::test]
+    async fn set_output_device_after_playback() {
+        let (mut engine, _audio_tx, mut event_rx, filepath) = setup_engine(48000, 48000);
+        assert!(engine.handle_set_output_device(Some("Main Out".to_string())).is_err());
+        engine.manager_factory = Some(create_mock_manager);
+
+        let id = Uuid::now_v7();
+        play(&mut engine, id, play_data(&filepath)).await.unwrap();
+        engine.handle_set_output_device(Some("Main Out".to_string())).unwrap();
+        assert_eq!(engine.output_device, None);
+
+        engine.handle_stop(id, Duration::ZERO, Easing::Linear).await.unwrap();
+        process(&mut engine);
+        engine.sweep_states().await;
+        while let Ok(event) = event_rx.try_recv() {
+            if let EngineEvent::Audio(AudioEngineEvent::Completed { instance_id, .. }) = event {
+                assert_eq!(instance_id, id);
+            }
+        }
+        assert_eq!(engine.output_device, Some("Main Out".to_string()));
+        assert!(engine.pending_output_device.is_none());
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[tokio::test]
+    async fn master_volume_survives_output_device_switch() {
+        let (mut engine, _audio_tx, _event_rx, filepath) = setup_engine(48000, 48000);
+        engine.manager_factory = Some(create_mock_manager);
+
+        engine.handle_set_master_volume(-6.0, 0.0, Easing::Linear).unwrap();
+        engine.handle_set_output_device(Some("Main Out".to_string())).unwrap();
+        process(&mut engine);
+
+        assert_eq!(engine.output_device, Some("Main Out".to_string()));
+        assert_eq!(engine.master_volume, -6.0);
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[tokio::test]
+    async fn play_on_bus() {
+        let (mut engine, _audio_tx, _event_rx, filepath) = setup_engine(48000, 48000);
+        engine.manager_factory = Some(create_mock_manager);
+
+        // 作成前に設定した音量はバスの作成時に使う
+        engine.handle_set_bus_volume("Music".to_string(), -6.0, 0.0, Easing::Linear).unwrap();
+        assert!(engine.buses.is_empty());
+        let ids = [Uuid::now_v7(), Uuid::now_v7(), Uuid::now_v7()];
+        play(&mut engine, ids[0], PlayCommandData { output_bus: Some("Music".to_string()), ..play_data(&filepath) }).await.unwrap();
+        play(&mut engine, ids[1], PlayCommandData { output_bus: Some("Music".to_string()), ..play_data(&filepath) }).await.unwrap();
+        play(&mut engine, ids[2], play_data(&filepath)).await.unwrap();
+        assert_eq!(engine.buses.len(), 1);
+        assert_eq!(engine.manager.as_ref().unwrap().num_sub_tracks(), 1);
+
+        // 出力デバイスを切り替えた後は、同じ音量でバスを作り直す
+        for id in ids {
+            engine.handle_stop(id, Duration::ZERO, Easing::Linear).await.unwrap();
+        }
+        process(&mut engine);
+        engine.sweep_states().await;
+        engine.handle_set_output_device(Some("Main Out".to_string())).unwrap();
+        assert!(engine.buses.is_empty());
+        play(&mut engine, Uuid::now_v7(), PlayCommandData { output_bus: Some("Music".to_string()), ..play_data(&filepath) }).await.unwrap();
+        assert_eq!(engine.buses.len(), 1);
+        assert_eq!(engine.bus_volumes["Music"], -6.0);
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[tokio::test]
+    async fn scrub_preview_while_paused() {
+        let (engine, _audio_tx, mut event_rx, filepath) = setup_engine(1000, 2000);
+        let mut engine = engine.with_scrub_preview(true);
+        let id = Uuid::now_v7();
+        play(&mut engine, id, play_data(&filepath)).await.unwrap();
+        process(&mut engine);
+        engine.handle_pause(id).await.unwrap();
+        process(&mut engine);
+        assert_eq!(engine.playing_sounds[&id].handle.state(), PlaybackState::Paused);
+
+        for position in [0.5, 0.8, 1.2] {
+            engine.handle_seek(id, position).unwrap();
+        }
+        assert_eq!(engine.scrub_previews.len(), 3);
+
+        // プレビューは短時間で停止し、キューは一時停止したまま移動先の位置にとどまる
+        process(&mut engine);
+        process(&mut engine);
+        engine.sweep_states().await;
+        assert!(engine.scrub_previews.is_empty());
+        assert_eq!(engine.playing_sounds[&id].handle.state(), PlaybackState::Paused);
+        while let Ok(event) = event_rx.try_recv() {
+            assert!(!matches!(event, EngineEvent::Audio(AudioEngineEvent::Completed { .. })));
+        }
+
+        // 一時停止中の位置は再開後に反映される。移動した区間は再生した時間に含めない
+        engine.handle_resume(id).await.unwrap();
+        process(&mut engine);
+        engine.sweep_states().await;
+        let playing_sound = &engine.playing_sounds[&id];
+        assert!((1.2..1.4).contains(&playing_sound.handle.position()), "position {}", playing_sound.handle.position());
+        assert!(playing_sound.tracker.played_duration < 0.5, "played {}s", playing_sound.tracker.played_duration);
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    /// 最初の読み込みで失敗するデコーダ。再生中にメディアが外された状況を模します。
+    struct FailingDecoder;
+
+    impl Decoder for FailingDecoder {
+        type Error = FromFileError;
+
+        fn sample_rate(&self) -> u32 {
+            48000
+        }
+
+        fn num_frames(&self) -> usize {
+            48000
+        }
+
+        fn decode(&mut self) -> Result<Vec<Frame>, Self::Error> {
+            Err(FromFileError::IoError(std::io::Error::other("media removed")))
+        }
+
+        fn seek(&mut self, index: usize) -> Result<usize, Self::Error> {
+            Ok(index)
+        }
+    }
+
+    #[tokio::test]
+    async fn streaming_read_error() {
+        let (mut engine, _audio_tx, mut event_rx, filepath) = setup_engine(48000, 48000);
+        let mut data = play_data(&filepath);
+        data.streaming = true;
+
+        let id = Uuid::now_v7();
+        let sound = LoadedSound::Streaming(StreamingSoundData::from_decoder(FailingDecoder));
+        engine.pending_loads.insert(id, PendingLoad { cancel_token: CancellationToken::new(), deferred_commands: Vec::new() });
+        engine.finish_load(LoadedMessage { id, data, result: Ok((sound, 0.0)) }).await.unwrap();
+        assert!(matches!(event_rx.recv().await, Some(EngineEvent::Audio(AudioEngineEvent::Started { .. }))));
+
+        // デコードは別のスレッドで行われるため、エラーが届くまで繰り返す
+        let mut error_kind = None;
+        for _ in 0..100 {
+            process(&mut engine);
+            engine.sweep_states().await;
+            if let Ok(EngineEvent::Audio(AudioEngineEvent::Error { instance_id, kind, .. })) = event_rx.try_recv() {
+                assert_eq!(instance_id, id);
+                error_kind = Some(kind);
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(error_kind, Some(CueErrorKind::MediaUnavailable));
+        assert!(!engine.playing_sounds.contains_key(&id));
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[tokio::test]
+    async fn streaming_finite_loop_count() {
+        // 先読みするバッファ(16384フレーム)よりループ領域を長くする
+        let (mut engine, _audio_tx, mut event_rx, filepath) = setup_engine(48000, 48000);
+        let mut data = play_data(&filepath);
+        data.streaming = true;
+        data.loop_region = Some(Region { start: PlaybackPosition::Seconds(0.5), end: EndPosition::EndOfAudio });
+        data.loop_count = Some(2);
+
+        let id = Uuid::now_v7();
+        play(&mut engine, id, data).await.unwrap();
+        assert!(matches!(engine.playing_sounds[&id].handle, SoundHandle::Streaming(_)));
+        // ストリーミング再生ではファイル全体を読み込まない
+        assert!(engine.playing_sounds[&id].tracker.sound_data.is_none());
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let elapsed = run_until_completed(&mut engine, &mut event_rx, 48000, 5.0).await.unwrap();
+
+        // 読み込みを待つ間は再生が進まないため、経過時間は実際の再生時間より長くなる場合がある
+        assert!((1.5..2.5).contains(&elapsed), "completed after {}s", elapsed);
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[tokio::test]
+    async fn streaming_seek() {
+        let (engine, _audio_tx, _event_rx, filepath) = setup_engine(48000, 96000);
+        let mut engine = engine.with_scrub_preview(true);
+        let mut data = play_data(&filepath);
+        data.streaming = true;
+        let id = Uuid::now_v7();
+        play(&mut engine, id, data).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        process(&mut engine);
+        engine.handle_pause(id).await.unwrap();
+        while engine.playing_sounds[&id].handle.state() != PlaybackState::Paused {
+            process(&mut engine);
+        }
+
+        // メモリに読み込んでいないため、一時停止中でもプレビューは再生しない
+        engine.handle_seek(id, 1.2).unwrap();
+        assert!(engine.scrub_previews.is_empty());
+        engine.handle_resume(id).await.unwrap();
+        // 先読みした分を再生した後に移動先から再生する
+        let mut position = 0.0;
+        for _ in 0..400 {
+            process(&mut engine);
+            std::thread::sleep(Duration::from_micros(100));
+            position = engine.playing_sounds[&id].handle.position();
+            if position >= 1.2 {
+                break;
+            }
+        }
+        assert!((1.2..1.5).contains(&position), "position is {}s", position);
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[tokio::test]
+    async fn seek_without_scrub_preview() {
+        let (mut engine, _audio_tx, _event_rx, filepath) = setup_engine(1000, 2000);
+        let id = Uuid::now_v7();
+        play(&mut engine, id, play_data(&filepath)).await.unwrap();
+        process(&mut engine);
+        engine.handle_pause(id).await.unwrap();
+        process(&mut engine);
+
+        engine.handle_seek(id, 1.2).unwrap();
+        assert!(engine.scrub_previews.is_empty());
+        assert!(engine.handle_seek(Uuid::now_v7(), 1.2).is_err());
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[tokio::test]
+    async fn playback_report_on_completion() {
+        let (mut engine, _audio_tx, mut event_rx, filepath) = setup_engine(1000, 1000);
+        let mut data = play_data(&filepath);
+        data.levels = AudioCueLevels { master: -6.0 };
+        data.fade_out_param = None;
+        let id = Uuid::now_v7();
+        play(&mut engine, id, data).await.unwrap();
+
+        let mut report = None;
+        for _ in 0..20 {
+            process(&mut engine);
+            engine.sweep_states().await;
+            while let Ok(event) = event_rx.try_recv() {
+                if let EngineEvent::Audio(AudioEngineEvent::Completed { report: completed_report, .. }) = event {
+                    report = Some(completed_report);
+                }
+            }
+        }
+
+        let report = report.unwrap();
+        assert!((report.played_duration - 1.0).abs() < 0.01, "played {}s", report.played_duration);
+        assert!((report.peak_level + 12.0).abs() < 0.1, "peak {}dB", report.peak_level);
+        assert!(!report.was_stopped_manually);
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[tokio::test]
+    async fn playback_report_on_stop() {
+        let (mut engine, _audio_tx, mut event_rx, filepath) = setup_engine(1000, 1000);
+        let id = Uuid::now_v7();
+        play(&mut engine, id, play_data(&filepath)).await.unwrap();
+        for _ in 0..3 {
+            process(&mut engine);
+            engine.sweep_states().await;
+        }
+
+        engine.handle_stop(id, Duration::ZERO, Easing::Linear).await.unwrap();
+        process(&mut engine);
+        engine.sweep_states().await;
+
+        let mut report = None;
+        while let Ok(event) = event_rx.try_recv() {
+            if let EngineEvent::Audio(AudioEngineEvent::Completed { report: completed_report, .. }) = event {
+                report = Some(completed_report);
+            }
+        }
+        let report = report.unwrap();
+        assert!(report.played_duration > 0.3 && report.played_duration < 0.6, "played {}s", report.played_duration);
+        assert!(report.was_stopped_manually);
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[tokio::test]
+    async fn play_preloaded_sound() {
+        let (mut engine, _audio_tx, mut event_rx, filepath) = setup_engine(1000, 1000);
+        let preload_id = Uuid::now_v7();
+        let id = Uuid::now_v7();
+
+        engine.handle_preload(preload_id, play_data(&filepath));
+        while engine.preload_cache.get(&filepath, None).is_none() {
+            time::sleep(Duration::from_millis(5)).await;
+        }
+
+        // 読み込みを待たずに再生を開始する
+        engine.handle_play(id, play_data(&filepath)).await.unwrap();
+        assert!(engine.playing_sounds.contains_key(&id));
+        assert!(matches!(event_rx.try_recv(), Ok(EngineEvent::Audio(AudioEngineEvent::Started { instance_id })) if instance_id == id));
+
+        // ラウドネスの目標が異なる場合は先読みを使わない
+        let other_id = Uuid::now_v7();
+        let data = PlayCommandData { normalize_to: Some(-23.0), ..play_data(&filepath) };
+        engine.handle_play(other_id, data).await.unwrap();
+        assert!(engine.pending_loads.contains_key(&other_id));
+
+        engine.handle_command(AudioCommand::EvictPreload { id: preload_id }).await;
+        assert!(engine.preload_cache.get(&filepath, None).is_none());
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[test]
+    fn preload_cache_budget() {
+        let sound = |num_frames: usize| StaticSoundData {
+            sample_rate: 1000,
+            frames: vec![kira::Frame::ZERO; num_frames].into(),
+            settings: StaticSoundSettings::default(),
+            slice: None,
+        };
+        let frame_size = std::mem::size_of::<kira::Frame>();
+        let cache = PreloadCache::new(200 * frame_size);
+        let ids: Vec<Uuid> = (0..3).map(|_| Uuid::now_v7()).collect();
+
+        assert!(cache.insert(ids[0], PathBuf::from("a.wav"), None, sound(100), 0.0));
+        assert!(cache.insert(ids[1], PathBuf::from("b.wav"), None, sound(100), 0.0));
+        // 最近再生したサウンドは残し、最も長く使われていないサウンドを解放する
+        assert!(cache.get(Path::new("a.wav"), None).is_some());
+        assert!(cache.insert(ids[2], PathBuf::from("c.wav"), None, sound(100), 0.0));
+        assert!(cache.get(Path::new("a.wav"), None).is_some());
+        assert!(cache.get(Path::new("b.wav"), None).is_none());
+        assert!(cache.get(Path::new("c.wav"), None).is_some());
+
+        assert!(!cache.insert(Uuid::now_v7(), PathBuf::from("d.wav"), None, sound(201), 0.0));
+        assert!(cache.remove(&ids[0]));
+        assert!(!cache.remove(&ids[1]));
+    }
+
+    #[tokio::test]
+    async fn voice_stealing() {
+        let (mut engine, _audio_tx, mut event_rx, filepath) = setup_engine(1000, 1000);
+        let first_id = Uuid::now_v7();
+        let second_id = Uuid::now_v7();
+        engine.handle_command(AudioCommand::SetMaxVoices { limit: Some(1) }).await;
+
+        play(&mut engine, first_id, play_data(&filepath)).await.unwrap();
+        process(&mut engine);
+        play(&mut engine, second_id, play_data(&filepath)).await.unwrap();
+
+        let mut stolen = Vec::new();
+        while let Ok(event) = event_rx.try_recv() {
+            if let EngineEvent::Audio(AudioEngineEvent::VoiceStolen { instance_id }) = event {
+                stolen.push(instance_id);
+            }
+        }
+        assert_eq!(stolen, vec![first_id]);
+        assert!(engine.playing_sounds[&first_id].tracker.stopped_manually);
+        assert!(!engine.playing_sounds[&second_id].tracker.stopped_manually);
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[tokio::test]
+    async fn replace_media_keeps_position() {
+        let (mut engine, _audio_tx, mut event_rx, filepath) = setup_engine(1000, 1000);
+        let new_filepath = std::env::temp_dir().join(format!("sbsp_test_{}.wav", Uuid::new_v4()));
+        write_test_wav(&new_filepath, 1000, 1000);
+        let id = Uuid::now_v7();
+        play(&mut engine, id, play_data(&filepath)).await.unwrap();
+        for _ in 0..4 {
+            process(&mut engine);
+            engine.sweep_states().await;
+        }
+        let position = engine.playing_sounds[&id].handle.position();
+        assert!(position > 0.3);
+
+        // 読み込み中は元のサウンドの再生を続ける
+        engine.handle_replace_media(id, new_filepath.clone()).unwrap();
+        assert_eq!(engine.playing_sounds[&id].data.filepath, filepath);
+        let replaced = engine.replaced_rx.recv().await.unwrap();
+        engine.finish_replace(replaced).await.unwrap();
+        process(&mut engine);
+        engine.sweep_states().await;
+
+        let playing_sound = &engine.playing_sounds[&id];
+        assert_eq!(playing_sound.data.filepath, new_filepath);
+        assert!((playing_sound.handle.position() - (position + 0.128)).abs() < 0.01);
+        assert_eq!(engine.manager.as_mut().unwrap().main_track().num_sounds(), 1);
+        // 差し替えによるCompletedは送信されない
+        while let Ok(event) = event_rx.try_recv() {
+            assert!(!matches!(event, EngineEvent::Audio(AudioEngineEvent::Completed { .. })));
+        }
+        std::fs::remove_file(filepath).unwrap();
+        std::fs::remove_file(new_filepath).unwrap();
+    }
+
+    #[tokio::test]
+    async fn replace_media_superseded_while_loading() {
+        let (mut engine, _audio_tx, _event_rx, filepath) = setup_engine(1000, 1000);
+        let first_filepath = std::env::temp_dir().join(format!("sbsp_test_{}.wav", Uuid::new_v4()));
+        let second_filepath = std::env::temp_dir().join(format!("sbsp_test_{}.wav", Uuid::new_v4()));
+        write_test_wav(&first_filepath, 1000, 1000);
+        write_test_wav(&second_filepath, 1000, 1000);
+        let id = Uuid::now_v7();
+        play(&mut engine, id, play_data(&filepath)).await.unwrap();
+
+        // 先の差し替えが読み込み中に置き換えられた場合は、後の差し替えだけが適用される
+        engine.handle_replace_media(id, first_filepath.clone()).unwrap();
+        let first = engine.replaced_rx.recv().await.unwrap();
+        engine.handle_replace_media(id, second_filepath.clone()).unwrap();
+        engine.finish_replace(first).await.unwrap();
+        assert_eq!(engine.playing_sounds[&id].data.filepath, filepath);
+        let second = engine.replaced_rx.recv().await.unwrap();
+        engine.finish_replace(second).await.unwrap();
+        assert_eq!(engine.playing_sounds[&id].data.filepath, second_filepath);
+
+        // 停止した場合は読み込み中の差し替えを破棄する
+        engine.handle_replace_media(id, first_filepath.clone()).unwrap();
+        let replaced = engine.replaced_rx.recv().await.unwrap();
+        engine.handle_stop(id, Duration::ZERO, Easing::Linear).await.unwrap();
+        engine.finish_replace(replaced).await.unwrap();
+        assert_eq!(engine.playing_sounds[&id].data.filepath, second_filepath);
+        assert!(engine.pending_replacements.is_empty());
+        std::fs::remove_file(filepath).unwrap();
+        std::fs::remove_file(first_filepath).unwrap();
+        std::fs::remove_file(second_filepath).unwrap();
+    }
+
+    #[tokio::test]
+    async fn cancel_fade_in() {
+        let (mut engine, _audio_tx, _event_rx, filepath) = setup_engine(48000, 48000);
+        let mut data = play_data(&filepath);
+        data.fade_in_param = Some(AudioCueFadeParam { duration: 10.0, easing: Easing::Linear.into() });
+        let id = Uuid::now_v7();
+        play(&mut engine, id, data).await.unwrap();
+        process(&mut engine);
+
+        engine.handle_cancel_fade(id).unwrap();
+        process(&mut engine);
+        assert_eq!(engine.playing_sounds[&id].handle.state(), PlaybackState::Resuming);
+
+        // 10秒のフェードインの途中でも、10ms程度でフェードが完了する
+        for _ in 0..5 {
+            process(&mut engine);
+        }
+        assert_eq!(engine.playing_sounds[&id].handle.state(), PlaybackState::Playing);
+        assert!(engine.playing_sounds[&id].handle.position() < 1.0);
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[tokio::test]
+    async fn breakpoint_fade_stages() {
+        let (mut engine, _audio_tx, _event_rx, filepath) = setup_engine(48000, 48000);
+        let mut data = play_data(&filepath);
+        data.fade_in_param = Some(AudioCueFadeParam { duration: 0.5, easing: FadeCurve::Breakpoints(vec![(0.5, 0.8)]) });
+        let id = Uuid::now_v7();
+        play(&mut engine, id, data).await.unwrap();
+
+        // 開始時に0.25秒までの区間が指示され、残りの区間は再生位置に応じて指示される
+        let stages = &engine.playing_sounds[&id].fade_stages;
+        assert_eq!(stages.len(), 1);
+        assert_eq!((stages[0].position, stages[0].end_position), (0.25, 0.5));
+        while engine.playing_sounds[&id].handle.position() < 0.25 {
+            process(&mut engine);
+        }
+        engine.sweep_states().await;
+        assert!(engine.playing_sounds[&id].fade_stages.is_empty());
+
+        // 再生中のレベル変更も、現在の再生位置からの区間に分割される
+        engine
+            .handle_set_levels(id, AudioCueLevels { master: -12.0 }, 0.2, FadeCurve::Breakpoints(vec![(0.5, 0.5)]))
+            .unwrap();
+        let position = engine.playing_sounds[&id].handle.position();
+        let stages = &engine.playing_sounds[&id].fade_stages;
+        assert_eq!(stages.len(), 1);
+        assert!((stages[0].position - (position + 0.1)).abs() < 1e-9);
+        assert_eq!(stages[0].volume, Decibels(-12.0));
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[tokio::test]
+    async fn automation_envelope() {
+        let (mut engine, _audio_tx, _event_rx, filepath) = setup_engine(48000, 48000);
+        let mut data = play_data(&filepath);
+        data.levels = AudioCueLevels { master: -3.0 };
+        data.automation = Some(vec![(0.2, -6.0), (0.4, -6.0), (0.6, 0.0), (0.8, 3.0)]);
+        data.fade_out_param = Some(AudioCueFadeParam { duration: 0.5, easing: Easing::Linear.into() });
+        let id = Uuid::now_v7();
+        play(&mut engine, id, data).await.unwrap();
+
+        // 最初の区間は開始時に指示され、フェードアウトの開始後に始まる区間はフェードアウトに置き換わる
+        let stages: Vec<_> = engine.playing_sounds[&id]
+            .fade_stages
+            .iter()
+            .map(|stage| (stage.position, stage.end_position, stage.volume))
+            .collect();
+        assert_eq!(
+            stages,
+            vec![(0.2, 0.4, Decibels(-9.0)), (0.4, 0.6, Decibels(-3.0)), (0.5, 1.0, Decibels::SILENCE)]
+        );
+        while engine.playing_sounds[&id].handle.position() < 0.2 {
+            process(&mut engine);
+        }
+        engine.sweep_states().await;
+        assert_eq!(engine.playing_sounds[&id].fade_stages.len(), 2);
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[tokio::test]
+    async fn media_unavailable() {
+        let (mut engine, audio_tx, mut event_rx, filepath) = setup_engine(48000, 48000);
+        // ドライブが取り外された状態を再現する
+        engine.sound_loader = |_| Err(FromFileError::IoError(std::io::Error::from(std::io::ErrorKind::NotFound)));
+        let cancel_token = CancellationToken::new();
+        let handle = tokio::spawn(engine.run(cancel_token.clone()));
+
+        let id = Uuid::now_v7();
+        audio_tx.send(AudioCommand::Play { id, data: play_data(&filepath) }).await.unwrap();
+
+        assert!(matches!(event_rx.recv().await, Some(EngineEvent::Audio(AudioEngineEvent::Loading { instance_id })) if instance_id == id));
+        let event = event_rx.recv().await.unwrap();
+        if let EngineEvent::Audio(AudioEngineEvent::Error { instance_id, kind, .. }) = event {
+            assert_eq!(instance_id, id);
+            assert_eq!(kind, CueErrorKind::FileNotFound);
+        } else {
+            panic!("Wrong Engine Event emitted: {:?}", event);
+        }
+
+        // 失敗したサウンドはエンジンに登録されず、その後のイベントも送信されない
+        cancel_token.cancel();
+        handle.await.unwrap();
+        assert!(event_rx.try_recv().is_err());
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[test]
+    fn error_kind_of_load_error() {
+        let load_error = |error: FromFileError| anyhow::Error::new(error).context("Failed to load sound data");
+        assert_eq!(error_kind(&load_error(FromFileError::NoDefaultTrack)), CueErrorKind::UnsupportedFormat);
+        assert_eq!(
+            error_kind(&load_error(FromFileError::SymphoniaError(SymphoniaError::Unsupported("core (probe): no suitable format reader found")))),
+            CueErrorKind::UnsupportedFormat
+        );
+        assert_eq!(
+            error_kind(&load_error(FromFileError::SymphoniaError(SymphoniaError::DecodeError("invalid main_data offset")))),
+            CueErrorKind::DecodeFailure
+        );
+        assert_eq!(
+            error_kind(&load_error(FromFileError::IoError(std::io::Error::from(std::io::ErrorKind::NotFound)))),
+            CueErrorKind::FileNotFound
+        );
+        assert_eq!(
+            error_kind(&load_error(FromFileError::SymphoniaError(SymphoniaError::IoError(std::io::Error::other("device removed"))))),
+            CueErrorKind::MediaUnavailable
+        );
+
+        let error = anyhow::anyhow!("No output devices").context(OutputDeviceUnavailable(Some("Speakers".to_string())));
+        assert_eq!(error_kind(&error), CueErrorKind::DeviceUnavailable);
+        assert_eq!(error_kind(&anyhow::anyhow!("Sound limit reached")), CueErrorKind::Playback);
+    }
+
+    #[tokio::test]
+    async fn meter_levels() {
+        let (mut engine, _audio_tx, _event_rx, filepath) = setup_engine(1000, 1000);
+        let mut data = play_data(&filepath);
+        data.levels = AudioCueLevels { master: -6.0 };
+        let id = Uuid::now_v7();
+        play(&mut engine, id, data).await.unwrap();
+        for _ in 0..2 {
+            process(&mut engine);
+        }
+
+        let playing_sound = &engine.playing_sounds[&id];
+        let (peak, rms) = playing_sound.tracker.meter(playing_sound.handle.position(), &playing_sound.levels).unwrap();
+        // 一定の振幅(約-6dBFS)のため、ピークとRMSはどちらも約-12dBFSになる
+        assert!((peak + 12.0).abs() < 0.1, "peak {}dB", peak);
+        assert!((rms + 12.0).abs() < 0.1, "rms {}dB", rms);
+
+        let (peak, _) = playing_sound.tracker.meter(0.0, &playing_sound.levels).unwrap();
+        assert_eq!(peak, Decibels::SILENCE.0 as f64);
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[tokio::test]
+    async fn normalize_to_loudness() {
+        let (mut engine, _audio_tx, _event_rx, filepath) = setup_engine(48000, 0);
+        // 997Hz、振幅0.5の正弦波(約-9.03LUFS)
+        let samples: Vec<i16> = (0..48000)
+            .map(|index| ((index as f64 * 997.0 / 48000.0 * std::f64::consts::TAU).sin() * TEST_AMPLITUDE as f64) as i16)
+            .collect();
+        write_wav_samples(&filepath, 48000, &samples);
+        let id = Uuid::now_v7();
+        play(&mut engine, id, PlayCommandData { normalize_to: Some(-23.0), ..play_data(&filepath) }).await.unwrap();
+        for _ in 0..40 {
+            process(&mut engine);
+        }
+
+        let playing_sound = &engine.playing_sounds[&id];
+        assert!((playing_sound.gain_offset + 13.97).abs() < 0.1, "gain offset {}dB", playing_sound.gain_offset);
+        // キューのレベルは変更しない
+        assert_eq!(playing_sound.levels, AudioCueLevels { master: 0.0 });
+        let (peak, _) = playing_sound.tracker.meter(playing_sound.handle.position(), &playing_sound.output_levels()).unwrap();
+        assert!((peak + 20.0).abs() < 0.2, "peak {}dB", peak);
+
+        // 補正はレベルの変更後も維持する
+        engine.handle_set_levels(id, AudioCueLevels { master: -6.0 }, 0.0, FadeCurve::default()).unwrap();
+        assert!((engine.playing_sounds[&id].output_levels().master + 19.97).abs() < 0.1);
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[tokio::test]
+    async fn stop_while_loading() {
+        let (mut engine, audio_tx, mut event_rx, filepath) = setup_engine(48000, 48000);
+        // 大きなファイルの読み込みを再現する
+        engine.sound_loader = |path| {
+            std::thread::sleep(Duration::from_millis(100));
+            StaticSoundData::from_file(path)
+        };
+        let cancel_token = CancellationToken::new();
+        let handle = tokio::spawn(engine.run(cancel_token.clone()));
+
+        let id = Uuid::now_v7();
+        audio_tx.send(AudioCommand::Play { id, data: play_data(&filepath) }).await.unwrap();
+        audio_tx.send(AudioCommand::Stop { id, fade_out: Duration::ZERO, easing: Easing::Linear }).await.unwrap();
+
+        assert!(matches!(event_rx.recv().await, Some(EngineEvent::Audio(AudioEngineEvent::Loading { instance_id })) if instance_id == id));
+        let event = event_rx.recv().await.unwrap();
+        let EngineEvent::Audio(AudioEngineEvent::Completed { instance_id, report }) = event else {
+            panic!("Wrong Engine Event emitted: {:?}", event);
+        };
+        assert_eq!(instance_id, id);
+        assert!(report.was_stopped_manually);
+        assert_eq!(report.played_duration, 0.0);
+
+        // 読み込みが終わっても再生を開始しない
+        time::sleep(Duration::from_millis(200)).await;
+        assert!(event_rx.try_recv().is_err());
+        cancel_token.cancel();
+        handle.await.unwrap();
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[tokio::test]
+    async fn commands_deferred_while_loading() {
+        let (mut engine, _audio_tx, mut event_rx, filepath) = setup_engine(48000, 48000);
+        let id = Uuid::now_v7();
+
+        engine.handle_play(id, play_data(&filepath)).await.unwrap();
+        engine.handle_command(AudioCommand::Pause { id }).await;
+        assert!(!engine.playing_sounds.contains_key(&id));
+
+        let loaded = engine.loaded_rx.recv().await.unwrap();
+        engine.finish_load(loaded).await.unwrap();
+        process(&mut engine);
+
+        assert!(matches!(event_rx.recv().await, Some(EngineEvent::Audio(AudioEngineEvent::Loading { .. }))));
+        assert!(matches!(event_rx.recv().await, Some(EngineEvent::Audio(AudioEngineEvent::Started { .. }))));
+        assert!(matches!(event_rx.recv().await, Some(EngineEvent::Audio(AudioEngineEvent::Paused { .. }))));
+        assert!(matches!(engine.playing_sounds[&id].handle.state(), PlaybackState::Pausing | PlaybackState::Paused));
+        assert!(engine.pending_loads.is_empty());
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[tokio::test]
+    async fn release_loop() {
+        let (mut engine, _audio_tx, mut event_rx, filepath) = setup_engine(1000, 2000);
+        let mut data = play_data(&filepath);
+        data.end_time = Some(1.5);
+        data.loop_region = Some(Region { start: PlaybackPosition::Seconds(0.5), end: EndPosition::Custom(PlaybackPosition::Seconds(1.0)) });
+        let id = Uuid::now_v7();
+        play(&mut engine, id, data).await.unwrap();
+        assert_eq!(run_until_completed(&mut engine, &mut event_rx, 1000, 2.0).await, None);
+
+        let position = engine.playing_sounds[&id].handle.position();
+        assert!((0.5..1.0).contains(&position), "position {}s", position);
+        engine.handle_release_loop(id).unwrap();
+        let elapsed = run_until_completed(&mut engine, &mut event_rx, 1000, 5.0).await.unwrap();
+
+        // ループ領域を抜けてend_timeまで再生する
+        let remaining = 1.5 - position;
+        assert!((remaining..remaining + 0.3).contains(&elapsed), "completed after {}s, {}s remaining", elapsed, remaining);
+        std::fs::remove_file(filepath).unwrap();
+    }
+}
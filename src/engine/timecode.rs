@@ -0,0 +1,47 @@
+/// 外部タイムコード(LTC/MTCなど)の現在位置を提供するソース
+pub trait TimecodeSource: Send + Sync {
+    /// 1秒あたりのフレーム数
+    fn frame_rate(&self) -> f64;
+    /// 現在のフレーム番号
+    fn current_frame(&self) -> u64;
+}
+
+/// 指定したフレームに到達するまでの秒数を返します。既に過ぎている場合は0を返します。
+pub fn seconds_until_frame(source: &dyn TimecodeSource, target_frame: u64) -> f64 {
+    let current_frame = source.current_frame();
+    if target_frame <= current_frame {
+        return 0.0;
+    }
+    (target_frame - current_frame) as f64 / source.frame_rate()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use super::*;
+
+    pub struct MockTimecodeSource {
+        pub frame_rate: f64,
+        pub frame: AtomicU64,
+    }
+
+    impl TimecodeSource for MockTimecodeSource {
+        fn frame_rate(&self) -> f64 {
+            self.frame_rate
+        }
+
+        fn current_frame(&self) -> u64 {
+            self.frame.load(Ordering::Relaxed)
+        }
+    }
+
+    #[test]
+    fn seconds_until_target_frame() {
+        let source = MockTimecodeSource { frame_rate: 25.0, frame: AtomicU64::new(100) };
+
+        assert_eq!(seconds_until_frame(&source, 150), 2.0);
+        assert_eq!(seconds_until_frame(&source, 100), 0.0);
+        assert_eq!(seconds_until_frame(&source, 50), 0.0);
+    }
+}
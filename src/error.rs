@@ -0,0 +1,31 @@
+use tokio::sync::{mpsc, oneshot};
+
+use crate::event::UiError;
+
+/// ライブラリの公開APIが返すエラー。組み込む側で失敗の種類ごとに処理できるようにする
+#[derive(Debug, thiserror::Error)]
+pub enum BackendError {
+    /// オーディオ出力を初期化できなかった
+    #[error("Failed to initialize audio engine: {0}")]
+    AudioInit(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// 送信先のタスクが終了している
+    #[error("Backend channel is closed.")]
+    ChannelClosed,
+    /// ショーモデルの操作に失敗した
+    #[error(transparent)]
+    Model(#[from] UiError),
+}
+
+impl<T> From<mpsc::error::SendError<T>> for BackendError {
+    fn from(_: mpsc::error::SendError<T>) -> Self {
+        Self::ChannelClosed
+    }
+}
+
+impl From<oneshot::error::RecvError> for BackendError {
+    fn from(_: oneshot::error::RecvError) -> Self {
+        Self::ChannelClosed
+    }
+}
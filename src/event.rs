@@ -3,12 +3,16 @@ use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::{executor::ExecutorEvent, model::cue::Cue};
+use crate::{executor::ExecutorEvent, model::{cue::Cue, settings::ShowSettings}};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(tag = "type", content = "param", rename_all = "camelCase", rename_all_fields = "camelCase")]
 pub enum UiEvent {
     // Cue Status Events
+    CueLoading {
+        cue_id: Uuid,
+    },
     CueStarted {
         cue_id: Uuid,
     },
@@ -21,9 +25,37 @@ pub enum UiEvent {
     CueCompleted {
         cue_id: Uuid,
     },
+    CueMeter {
+        cue_id: Uuid,
+        peak: f64, // dBFS
+        rms: f64,  // dBFS
+    },
+    CuePlaybackReport {
+        cue_id: Uuid,
+        played_duration: f64,
+        peak_level: f64,
+        was_stopped_manually: bool,
+    },
     CueError {
         cue_id: Uuid,
         error: String,
+        kind: CueErrorKind,
+    },
+    /// 同時再生数の上限を超えたため、キューのサウンドが停止された
+    CueVoiceStolen {
+        cue_id: Uuid,
+    },
+    /// 試聴を開始した。再生中のキューには含まれない
+    CueAuditionStarted {
+        cue_id: Uuid,
+    },
+    CueAuditionCompleted {
+        cue_id: Uuid,
+    },
+    /// GOや自動実行でキューを実行しなかった
+    CueSkipped {
+        cue_id: Uuid,
+        reason: CueSkipReason,
     },
 
     // System Events
@@ -34,9 +66,28 @@ pub enum UiEvent {
     ShowModelLoaded {
         path: PathBuf
     },
+    ShowModelCreated,
+    /// 読み込み直したショーに存在しないため停止したキュー
+    VanishedCuesStopped {
+        cue_ids: Vec<Uuid>,
+    },
+    /// パニック停止で、停止の完了を待たずに取り除いた再生中のキュー
+    PanicTriggered {
+        cue_ids: Vec<Uuid>,
+    },
+    /// 未保存の変更があるため、ショーを閉じるコマンドを保留した。ConfirmCloseで実行、CancelCloseで取り消す
+    UnsavedChanges,
     ShowModelSaved {
         path: PathBuf,
     },
+    /// 未保存の変更を自動保存ファイルに書き出した。ショーファイルは変更されない
+    ShowModelAutoSaved {
+        path: PathBuf,
+    },
+    AssetCollectProgress {
+        collected: usize, // 処理済みのオーディオキューの数
+        total: usize,
+    },
     CueUpdated {
         cue: Cue,
     },
@@ -50,6 +101,33 @@ pub enum UiEvent {
     CueMoved {
         cue_id: Uuid,
         to_index: usize,
+        order: f64, // 移動したキューの新しい並び順
+    },
+    /// 並び順を振り直した全てのキューと、その新しい並び順。リストの順に並ぶ
+    CuesReordered {
+        orders: Vec<(Uuid, f64)>,
+    },
+    /// RenumberCuesで番号が変わったキューと、その新しい番号
+    CuesRenumbered {
+        numbers: Vec<(Uuid, String)>,
+    },
+    /// Batchで適用したコマンド、または1つのコマンドによる複数の変更のイベント。適用した順に並ぶ
+    BatchApplied {
+        events: Vec<UiEvent>,
+    },
+    CueArmChanged {
+        cue_id: Uuid,
+        armed: bool,
+    },
+    CueValidationWarning {
+        cue_id: Uuid,
+        message: String,
+    },
+    SettingsUpdated {
+        settings: ShowSettings,
+    },
+    LiveLockChanged {
+        enabled: bool,
     },
 
     OperationFailed {
@@ -58,6 +136,7 @@ pub enum UiEvent {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(tag = "type", rename_all="camelCase", rename_all_fields = "camelCase")]
 pub enum UiError {
     FileSave {
@@ -72,17 +151,78 @@ pub enum UiError {
         cue_id: Uuid,
         message: String,
     },
+    SettingsEdit {
+        message: String,
+    },
+    Renumber {
+        message: String,
+    },
+    Batch {
+        message: String,
+    },
+    LiveLocked {
+        cue_id: Uuid,
+    },
+    UnknownTemplate {
+        template_name: String,
+    },
+}
+
+impl std::fmt::Display for UiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UiError::FileSave { path, message } => write!(f, "Failed to save '{}': {}", path.display(), message),
+            UiError::FileLoad { path, message } => write!(f, "Failed to load '{}': {}", path.display(), message),
+            UiError::CueEdit { cue_id, message } => write!(f, "Failed to edit cue '{}': {}", cue_id, message),
+            UiError::SettingsEdit { message } => write!(f, "Failed to edit settings: {}", message),
+            UiError::Renumber { message } => write!(f, "Failed to renumber cues: {}", message),
+            UiError::Batch { message } => write!(f, "Failed to apply batch: {}", message),
+            UiError::LiveLocked { cue_id } => write!(f, "Cue '{}' can't be changed while live lock is enabled.", cue_id),
+            UiError::UnknownTemplate { template_name } => write!(f, "Cue template '{}' doesn't exist.", template_name),
+        }
+    }
+}
+
+impl std::error::Error for UiError {}
+
+/// キューのエラーの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum CueErrorKind {
+    FileNotFound,      // 音声ファイルが存在しない
+    MediaUnavailable,  // 音声ファイルを読み込めない(ドライブの取り外し、アクセス権など)
+    UnsupportedFormat, // 対応していないコンテナ・コーデック・チャンネル構成
+    DecodeFailure,     // ファイルが壊れているなどの理由でデコードできない
+    DeviceUnavailable, // 出力デバイスを使用できない
+    Playback,
+}
+
+/// キューを実行しなかった理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum CueSkipReason {
+    Disarmed,  // キューが無効化されている
+    EndOfList, // キューリストの最後のキューから次のキューへ進もうとした
+    Removed,   // キューがショーから削除されている
+    AudioHeld, // HoldAudioで全体の音声を一時停止中
 }
 
 impl From<ExecutorEvent> for UiEvent {
     fn from(value: ExecutorEvent) -> Self {
         match value {
+            ExecutorEvent::Loading { cue_id } => UiEvent::CueLoading { cue_id },
             ExecutorEvent::Started { cue_id } => UiEvent::CueStarted { cue_id },
             ExecutorEvent::Paused { cue_id, .. } => UiEvent::CuePaused { cue_id },
             ExecutorEvent::Resumed { cue_id } => UiEvent::CueResumed { cue_id },
-            ExecutorEvent::Completed { cue_id } => UiEvent::CueCompleted { cue_id },
+            ExecutorEvent::Completed { cue_id, .. } => UiEvent::CueCompleted { cue_id },
             ExecutorEvent::Progress { .. } => unreachable!(),
-            ExecutorEvent::Error { cue_id, error } => UiEvent::CueError { cue_id, error },
+            ExecutorEvent::Meter { cue_id, peak, rms } => UiEvent::CueMeter { cue_id, peak, rms },
+            ExecutorEvent::Error { cue_id, error, kind } => UiEvent::CueError { cue_id, error, kind },
+            ExecutorEvent::VoiceStolen { cue_id } => UiEvent::CueVoiceStolen { cue_id },
+            ExecutorEvent::AuditionStarted { cue_id } => UiEvent::CueAuditionStarted { cue_id },
+            ExecutorEvent::AuditionCompleted { cue_id } => UiEvent::CueAuditionCompleted { cue_id },
         }
     }
 }
\ No newline at end of file
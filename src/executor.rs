@@ -1,21 +1,103 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::{HashMap, HashSet}, path::PathBuf, sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex}, time::Duration};
 
-use tokio::sync::{RwLock, mpsc};
+use kira::Easing;
+use tokio::{sync::{RwLock, broadcast, mpsc, oneshot, watch}, time::Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
 use uuid::Uuid;
 
 use crate::{
-    engine::audio_engine::{AudioCommand, AudioEngineEvent, PlayCommandData},
+    engine::{
+        audio_engine::{AudioCommand, AudioEngineEvent, PlayCommandData},
+        timecode::{TimecodeSource, seconds_until_frame},
+    },
+    event::CueErrorKind,
+    input::InputTrigger,
     manager::ShowModelHandle,
-    model::cue::{Cue, CueParam},
+    model::{cue::{AudioCueFadeParam, AudioCueLevels, Cue, CueParam}, easing::FadeCurve, settings::ShowSettings},
 };
 
+/// 外部からのトリガーを受信するチャネルの容量
+const INPUT_TRIGGER_CAPACITY: usize = 64;
+
 #[derive(Debug)]
 pub enum ExecutorCommand {
     ExecuteCue(Uuid), // cue_id
+    StopCue(Uuid),    // cue_id
+    StopAll,
+    /// フェードを使わずに全てのキューをすぐに停止します。AudioEngineからの停止の通知は待ちません。
+    Panic,
+    ExecuteCueAtFrame {
+        cue_id: Uuid,
+        frame: u64,
+    },
+    PauseAll,
+    ResumeAll,
+    PauseCue(Uuid),  // cue_id
+    ResumeCue(Uuid), // cue_id
+    SetLevelsMany {
+        changes: Vec<(Uuid, AudioCueLevels, f64, FadeCurve)>, // (cue_id, levels, duration, easing)
+    },
+    ReplaceMedia {
+        cue_id: Uuid,
+        new_target: PathBuf,
+    },
+    CancelFade(Uuid), // cue_id
+    Seek {
+        cue_id: Uuid,
+        position: f64,
+    },
+    ReleaseLoop(Uuid), // cue_id
+    SetMasterVolume {
+        db: f64,
+        duration: f64,
+        easing: Easing,
+    },
+    SetBusVolume {
+        bus: String,
+        db: f64,
+        duration: f64,
+        easing: Easing,
+    },
+    /// プレビューモードを切り替えます。プレビュー中はオーディオキューを再生せず、キューの長さだけ待って終了させます。
+    SetPreviewMode(bool),
+    /// 再生中の全てのサウンドを同じカーブで無音までフェードし、フェードが終わったら停止します。
+    /// フェード中に次のキューが実行された場合は、フェードを打ち切ってすぐに停止します。
+    FadeOutAll {
+        duration: f64,
+        easing: FadeCurve,
+    },
+    /// `from_cue_id`のキューをフェードして停止し、`to_cue_id`のキューを同じ長さのフェードインで開始します。
+    Crossfade {
+        from_cue_id: Uuid,
+        to_cue_id: Uuid,
+        duration: f64,
+        easing: FadeCurve,
+    },
+    /// オーディオキューを下げた音量で短時間だけ試聴します。再生中のキューとしては扱いません。
+    AuditionCue(Uuid), // cue_id
+    /// オーディオキューの音声ファイルを先に読み込み、次の実行ですぐに再生を開始できるようにします。
+    Preload(Uuid),      // cue_id
+    EvictPreload(Uuid), // cue_id
+    /// 再生中のインスタンスの一覧(instance_id → cue_id)を返します。デバッグ用で、状態は変更しません。
+    QueryActive {
+        reply: oneshot::Sender<HashMap<Uuid, Uuid>>,
+    },
 }
 
+/// プレビュー中のオーディオキューが再生位置を通知する間隔
+const PREVIEW_PROGRESS_INTERVAL: Duration = Duration::from_millis(50);
+/// 試聴を開始してから、キューのフェードアウトで停止するまでの時間
+const AUDITION_DURATION: Duration = Duration::from_secs(5);
+/// 試聴する際にキューのレベルから下げる音量(dB)
+const AUDITION_LEVEL_OFFSET: f64 = -12.0;
+
 #[derive(Debug, Clone)]
 pub enum ExecutorEvent {
+    /// オーディオキューの音声ファイルを読み込んでいる
+    Loading {
+        cue_id: Uuid,
+    },
     Started {
         cue_id: Uuid,
     },
@@ -30,16 +112,246 @@ pub enum ExecutorEvent {
         position: f64,
         duration: f64,
     },
+    Meter {
+        cue_id: Uuid,
+        peak: f64, // dBFS
+        rms: f64,  // dBFS
+    },
     Resumed {
         cue_id: Uuid,
     },
     Completed {
         cue_id: Uuid,
+        report: PlaybackReport,
     },
     Error {
         cue_id: Uuid,
         error: String,
+        kind: CueErrorKind,
     },
+    /// 同時再生数の上限を超えたため、最も古いサウンドが停止された
+    VoiceStolen {
+        cue_id: Uuid,
+    },
+    /// 試聴を開始した
+    AuditionStarted {
+        cue_id: Uuid,
+    },
+    /// 試聴が終了した。エラーで再生できなかった場合も含む
+    AuditionCompleted {
+        cue_id: Uuid,
+    },
+}
+
+impl ExecutorEvent {
+    pub fn cue_id(&self) -> Uuid {
+        match self {
+            Self::Loading { cue_id } => *cue_id,
+            Self::Started { cue_id } => *cue_id,
+            Self::Progress { cue_id, .. } => *cue_id,
+            Self::Paused { cue_id, .. } => *cue_id,
+            Self::Meter { cue_id, .. } => *cue_id,
+            Self::Resumed { cue_id } => *cue_id,
+            Self::Completed { cue_id, .. } => *cue_id,
+            Self::Error { cue_id, .. } => *cue_id,
+            Self::VoiceStolen { cue_id } => *cue_id,
+            Self::AuditionStarted { cue_id } => *cue_id,
+            Self::AuditionCompleted { cue_id } => *cue_id,
+        }
+    }
+}
+
+/// キュー終了時の再生のまとめ
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaybackReport {
+    pub played_duration: f64, // 一時停止中を除いて再生した時間(秒)
+    pub peak_level: f64,      // 再生した範囲のピークレベル(dBFS)。キューのレベルを含み、フェードは含まない
+    pub was_stopped_manually: bool,
+}
+
+impl PlaybackReport {
+    pub(crate) fn empty() -> Self {
+        Self {
+            played_duration: 0.0,
+            peak_level: kira::Decibels::SILENCE.0 as f64,
+            was_stopped_manually: false,
+        }
+    }
+
+    /// 子キューのレポートをグループのレポートに集約します。
+    fn merge(&mut self, other: &PlaybackReport) {
+        self.played_duration = self.played_duration.max(other.played_duration);
+        self.peak_level = self.peak_level.max(other.peak_level);
+        self.was_stopped_manually |= other.was_stopped_manually;
+    }
+}
+
+/// 実行中のグループキュー。全ての子キューが終了するとグループも終了します。
+struct ActiveGroup {
+    pending_children: HashSet<Uuid>,
+    report: PlaybackReport,
+}
+
+#[derive(Clone, Default)]
+struct ActiveGroups(Arc<RwLock<HashMap<Uuid, ActiveGroup>>>);
+
+impl ActiveGroups {
+    /// キューの終了を記録し、それによって終了したグループの完了イベントを内側から順に返します。
+    async fn complete_child(&self, cue_id: Uuid, report: PlaybackReport) -> Vec<ExecutorEvent> {
+        let mut groups = self.0.write().await;
+        let mut events = Vec::new();
+        let (mut finished_id, mut finished_report) = (cue_id, report);
+        while let Some((group_id, group)) = groups
+            .iter_mut()
+            .find(|(_, group)| group.pending_children.contains(&finished_id))
+        {
+            group.pending_children.remove(&finished_id);
+            group.report.merge(&finished_report);
+            if !group.pending_children.is_empty() {
+                break;
+            }
+            let group_id = *group_id;
+            let group = groups.remove(&group_id).unwrap();
+            events.push(ExecutorEvent::Completed { cue_id: group_id, report: group.report.clone() });
+            (finished_id, finished_report) = (group_id, group.report);
+        }
+        events
+    }
+
+    /// イベントを送信し、キューの終了によってグループが終了した場合はその完了イベントも送信します。
+    async fn send_event(&self, event_tx: &mpsc::Sender<ExecutorEvent>, event: ExecutorEvent) -> Result<(), anyhow::Error> {
+        let finished = match &event {
+            ExecutorEvent::Completed { cue_id, report } => Some((*cue_id, report.clone())),
+            ExecutorEvent::Error { cue_id, .. } => Some((*cue_id, PlaybackReport::empty())),
+            _ => None,
+        };
+        event_tx.send(event).await?;
+        if let Some((cue_id, report)) = finished {
+            for group_event in self.complete_child(cue_id, report).await {
+                event_tx.send(group_event).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// AudioEngineを使わずに実行中のインスタンス(待機中のキューやプレビュー)。一時停止と停止の指示をタスクに届けます。
+struct TimerInstance {
+    cue_id: Uuid,
+    paused_tx: watch::Sender<bool>,
+    stop_token: CancellationToken,
+}
+
+#[derive(Clone, Default)]
+struct TimerInstances(Arc<Mutex<HashMap<Uuid, TimerInstance>>>);
+
+impl TimerInstances {
+    /// インスタンスを登録し、一時停止の指示の受信側、停止の指示、破棄すると登録を解除するガードを返します。
+    /// 停止の指示は`stop_token`(StopAllなど)が取り消された場合にも届きます。
+    fn register(&self, instance_id: Uuid, cue_id: Uuid, stop_token: &CancellationToken) -> (watch::Receiver<bool>, CancellationToken, TimerGuard) {
+        let (paused_tx, paused_rx) = watch::channel(false);
+        let stop_token = stop_token.child_token();
+        self.0.lock().unwrap().insert(instance_id, TimerInstance { cue_id, paused_tx, stop_token: stop_token.clone() });
+        (paused_rx, stop_token, TimerGuard { instances: self.clone(), instance_id })
+    }
+
+    /// キューのインスタンスに停止を指示し、指示したインスタンスの数を返します。
+    fn stop(&self, cue_id: Uuid) -> usize {
+        let instances = self.0.lock().unwrap();
+        let targets: Vec<&TimerInstance> = instances.values().filter(|instance| instance.cue_id == cue_id).collect();
+        for instance in &targets {
+            instance.stop_token.cancel();
+        }
+        targets.len()
+    }
+
+    /// キューのインスタンス(`None`の場合は全てのインスタンス)に一時停止・再開を指示し、指示したインスタンスの数を返します。
+    fn set_paused(&self, cue_id: Option<Uuid>, paused: bool) -> usize {
+        let instances = self.0.lock().unwrap();
+        let targets: Vec<&TimerInstance> = instances
+            .values()
+            .filter(|instance| cue_id.is_none_or(|cue_id| instance.cue_id == cue_id))
+            .collect();
+        for instance in &targets {
+            instance.paused_tx.send_replace(paused);
+        }
+        targets.len()
+    }
+}
+
+/// タスクの終了時に、TimerInstancesからインスタンスの登録を解除します。
+struct TimerGuard {
+    instances: TimerInstances,
+    instance_id: Uuid,
+}
+
+impl Drop for TimerGuard {
+    fn drop(&mut self) {
+        self.instances.0.lock().unwrap().remove(&self.instance_id);
+    }
+}
+
+/// タイムコード同期や`pre_wait`による開始までの時間を待ちます。
+/// 開始前に停止された場合は、グループが終了できるよう完了を通知してfalseを返します。取り消された場合もfalseを返します。
+async fn wait_until_start(
+    cue_id: Uuid,
+    start_delay: f64,
+    stop_token: &CancellationToken,
+    cancel_token: &CancellationToken,
+    active_groups: &ActiveGroups,
+    event_tx: &mpsc::Sender<ExecutorEvent>,
+) -> bool {
+    tokio::select! {
+        _ = tokio::time::sleep(Duration::from_secs_f64(start_delay)) => true,
+        _ = stop_token.cancelled() => {
+            let report = PlaybackReport { was_stopped_manually: true, ..PlaybackReport::empty() };
+            if let Err(e) = active_groups.send_event(event_tx, ExecutorEvent::Completed { cue_id, report }).await {
+                tracing::error!(error = %e, "Failed to send Completed event for cue stopped before start.");
+            }
+            false
+        }
+        _ = cancel_token.cancelled() => false,
+    }
+}
+
+/// 一時停止中の時間を除いて経過時間を測ります。
+struct PausableClock {
+    started_at: Instant,
+    paused_at: Option<Instant>,
+    paused_duration: Duration,
+}
+
+impl PausableClock {
+    fn start() -> Self {
+        Self { started_at: Instant::now(), paused_at: None, paused_duration: Duration::ZERO }
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    fn elapsed(&self) -> Duration {
+        let now = self.paused_at.unwrap_or_else(Instant::now);
+        now.duration_since(self.started_at).saturating_sub(self.paused_duration)
+    }
+
+    /// 一時停止の指示に合わせて時計を止め(または再開し)、CueControllerに通知します。
+    /// 再生位置は`duration`秒までに丸めます。送信に失敗した場合はfalseを返します。
+    async fn follow(&mut self, paused: bool, cue_id: Uuid, duration: f64, event_tx: &mpsc::Sender<ExecutorEvent>) -> bool {
+        if paused == self.is_paused() {
+            return true;
+        }
+        let event = if paused {
+            self.paused_at = Some(Instant::now());
+            ExecutorEvent::Paused { cue_id, position: self.elapsed().as_secs_f64().min(duration), duration }
+        } else {
+            if let Some(paused_at) = self.paused_at.take() {
+                self.paused_duration += paused_at.elapsed();
+            }
+            ExecutorEvent::Resumed { cue_id }
+        };
+        event_tx.send(event).await.is_ok()
+    }
 }
 
 #[derive(Debug)]
@@ -58,6 +370,17 @@ pub struct Executor {
     engine_event_rx: mpsc::Receiver<EngineEvent>,   // 各エンジンからのイベント受信用
 
     active_instances: Arc<RwLock<HashMap<Uuid, Uuid>>>,
+    audition_instances: Arc<RwLock<HashMap<Uuid, Uuid>>>, // 試聴中のインスタンス(instance_id → cue_id)
+    active_groups: ActiveGroups,
+    timer_instances: TimerInstances, // 待機中のキューやプレビューなど、AudioEngineを使わずに実行中のインスタンス
+    timecode_source: Option<Arc<dyn TimecodeSource>>,
+    wait_cancel_token: CancellationToken, // 実行中のWaitキューを終了させる
+    wait_stop_token: Mutex<CancellationToken>, // StopAllで待機中のキューを停止させる。停止するたびに作り直す
+    input_trigger_tx: broadcast::Sender<InputTrigger>, // MIDI・OSCの受信部から届くトリガー
+    output_device: RwLock<Option<String>>, // AudioEngineに最後に指定した出力デバイス
+    max_voices: RwLock<Option<usize>>,     // AudioEngineに最後に指定した同時再生数の上限
+    preview: AtomicBool, // プレビューモード。オーディオキューをAudioEngineに送らない
+    fade_out_token: Mutex<CancellationToken>, // FadeOutAllのフェードを打ち切る。フェードのたびに作り直す
 }
 
 impl Executor {
@@ -76,14 +399,37 @@ impl Executor {
             playback_event_tx,
             engine_event_rx,
             active_instances: Arc::new(RwLock::new(HashMap::new())),
+            audition_instances: Arc::new(RwLock::new(HashMap::new())),
+            active_groups: ActiveGroups::default(),
+            timer_instances: TimerInstances::default(),
+            timecode_source: None,
+            wait_cancel_token: CancellationToken::new(),
+            wait_stop_token: Mutex::new(CancellationToken::new()),
+            input_trigger_tx: broadcast::channel(INPUT_TRIGGER_CAPACITY).0,
+            output_device: RwLock::new(None),
+            max_voices: RwLock::new(None),
+            preview: AtomicBool::new(false),
+            fade_out_token: Mutex::new(CancellationToken::new()),
         }
     }
 
+    /// タイムコード同期に使用するソースを設定します。
+    pub fn with_timecode_source(mut self, source: Arc<dyn TimecodeSource>) -> Self {
+        self.timecode_source = Some(source);
+        self
+    }
+
+    /// MIDI・OSCの受信部がWaitForInputキューにトリガーを届けるための送信側を返します。
+    pub fn input_trigger_sender(&self) -> broadcast::Sender<InputTrigger> {
+        self.input_trigger_tx.clone()
+    }
+
     /// Executorのメインループ。指示を待ち受け、処理します。
-    pub async fn run(mut self) {
+    pub async fn run(mut self, cancel_token: CancellationToken) {
         log::info!("Executor run loop started.");
         loop {
             tokio::select! {
+                _ = cancel_token.cancelled() => break,
                 Some(command) = self.command_rx.recv() => {
                     log::debug!("Executor received command: {:?}", command);
                     if let Err(e) = self.process_command(command).await {
@@ -98,6 +444,13 @@ impl Executor {
                 else => break,
             }
         }
+        // 各エンジンが終了するまで残りのイベントを転送する
+        while let Some(event) = self.engine_event_rx.recv().await {
+            if let Err(e) = self.handle_engine_event(event).await {
+                log::error!("Error handling engine event: {:?}", e);
+            }
+        }
+        self.wait_cancel_token.cancel();
         log::info!("Executor run loop finished.");
     }
 
@@ -107,228 +460,1634 @@ impl Executor {
             ExecutorCommand::ExecuteCue(cue_id) => {
                 // ShowModelからIDでキューの詳細データを取得
                 if let Some(cue) = self.model_handle.get_cue_by_id(&cue_id).await {
+                    if !cue.armed {
+                        tracing::info!(%cue_id, "Skipping disarmed cue.");
+                        return Ok(());
+                    }
+                    self.fade_out_token.lock().unwrap().cancel();
                     // キューのタイプに応じて処理を振り分け
-                    self.dispatch_cue(&cue).await?;
+                    self.dispatch_cue(&cue, 0.0).await?;
+                } else {
+                    tracing::error!(%cue_id, "Cannot execute cue: Cue not found.");
+                }
+            }
+            ExecutorCommand::ExecuteCueAtFrame { cue_id, frame } => {
+                let Some(timecode_source) = &self.timecode_source else {
+                    return Err(anyhow::anyhow!("Cannot execute cue at frame {}: no timecode source is set.", frame));
+                };
+                if let Some(cue) = self.model_handle.get_cue_by_id(&cue_id).await {
+                    if !cue.armed {
+                        tracing::info!(%cue_id, "Skipping disarmed cue.");
+                        return Ok(());
+                    }
+                    self.fade_out_token.lock().unwrap().cancel();
+                    let start_delay = seconds_until_frame(timecode_source.as_ref(), frame);
+                    self.dispatch_cue(&cue, start_delay).await?;
+                } else {
+                    tracing::error!(%cue_id, "Cannot execute cue: Cue not found.");
+                }
+            }
+            ExecutorCommand::StopCue(cue_id) => self.stop_cue(cue_id).await?,
+            ExecutorCommand::StopAll => {
+                // 待機中のWait・WaitForInputキューを停止する
+                std::mem::replace(&mut *self.wait_stop_token.lock().unwrap(), CancellationToken::new()).cancel();
+                let cue_ids: HashSet<Uuid> = self.active_instances.read().await.values().copied().collect();
+                for cue_id in cue_ids {
+                    self.stop_cue(cue_id).await?;
+                }
+            }
+            ExecutorCommand::Panic => {
+                std::mem::replace(&mut *self.wait_stop_token.lock().unwrap(), CancellationToken::new()).cancel();
+                self.active_groups.0.write().await.clear();
+                // 停止後にAudioEngineから届くイベントは、不明なインスタンスとして破棄する
+                let mut instance_ids: Vec<Uuid> = self.active_instances.write().await.drain().map(|(instance_id, _)| instance_id).collect();
+                // 試聴は終了のイベントを届けるため、管理から外さずに停止する
+                instance_ids.extend(self.audition_instances.read().await.keys().copied());
+                for instance_id in instance_ids {
+                    self.audio_tx
+                        .send(AudioCommand::Stop { id: instance_id, fade_out: Duration::ZERO, easing: Easing::Linear })
+                        .await?;
+                }
+            }
+            ExecutorCommand::FadeOutAll { duration, easing } => self.fade_out_all(duration, easing).await?,
+            ExecutorCommand::Crossfade { from_cue_id, to_cue_id, duration, easing } => {
+                self.crossfade(from_cue_id, to_cue_id, duration, easing).await?
+            }
+            ExecutorCommand::AuditionCue(cue_id) => self.audition_cue(cue_id).await?,
+            ExecutorCommand::Preload(cue_id) => {
+                let Some(cue) = self.model_handle.get_cue_by_id(&cue_id).await else {
+                    tracing::error!(%cue_id, "Cannot preload cue: Cue not found.");
+                    return Ok(());
+                };
+                let settings = self.model_handle.get_settings().await;
+                // 先読みはキューのIDで識別し、再生時はファイルとラウドネスの目標が同じサウンドを使う
+                let data = self.play_command_data(&cue, &settings, 0.0).await?;
+                self.audio_tx.send(AudioCommand::Preload { id: cue_id, data }).await?;
+            }
+            ExecutorCommand::EvictPreload(cue_id) => self.audio_tx.send(AudioCommand::EvictPreload { id: cue_id }).await?,
+            ExecutorCommand::QueryActive { reply } => {
+                let active_instances = self.active_instances.read().await.clone();
+                if reply.send(active_instances).is_err() {
+                    log::warn!("QueryActive: Requester dropped before reply.");
+                }
+            }
+            ExecutorCommand::SetPreviewMode(enabled) => {
+                log::info!("Preview mode {}.", if enabled { "enabled" } else { "disabled" });
+                self.preview.store(enabled, Ordering::Relaxed);
+            }
+            ExecutorCommand::PauseAll => {
+                self.timer_instances.set_paused(None, true);
+                let instance_ids: Vec<Uuid> = self.active_instances.read().await.keys().copied().collect();
+                for instance_id in instance_ids {
+                    self.audio_tx.send(AudioCommand::Pause { id: instance_id }).await?;
+                }
+            }
+            ExecutorCommand::ResumeAll => {
+                self.timer_instances.set_paused(None, false);
+                let instance_ids: Vec<Uuid> = self.active_instances.read().await.keys().copied().collect();
+                for instance_id in instance_ids {
+                    self.audio_tx.send(AudioCommand::Resume { id: instance_id }).await?;
+                }
+            }
+            ExecutorCommand::PauseCue(cue_id) => {
+                let instance_ids = self.instances_of(&cue_id).await;
+                let timer_count = self.timer_instances.set_paused(Some(cue_id), true);
+                if instance_ids.is_empty() && timer_count == 0 {
+                    tracing::warn!(%cue_id, "Cannot pause: Cue is not active.");
+                }
+                for instance_id in instance_ids {
+                    self.audio_tx.send(AudioCommand::Pause { id: instance_id }).await?;
+                }
+            }
+            ExecutorCommand::ResumeCue(cue_id) => {
+                let instance_ids = self.instances_of(&cue_id).await;
+                let timer_count = self.timer_instances.set_paused(Some(cue_id), false);
+                if instance_ids.is_empty() && timer_count == 0 {
+                    tracing::warn!(%cue_id, "Cannot resume: Cue is not active.");
+                }
+                for instance_id in instance_ids {
+                    self.audio_tx.send(AudioCommand::Resume { id: instance_id }).await?;
+                }
+            }
+            ExecutorCommand::CancelFade(cue_id) => {
+                let instance_ids = self.instances_of(&cue_id).await;
+                if instance_ids.is_empty() {
+                    tracing::warn!(%cue_id, "Cannot cancel fade: Cue is not active.");
+                }
+                for instance_id in instance_ids {
+                    self.audio_tx.send(AudioCommand::CancelFade { id: instance_id }).await?;
+                }
+            }
+            ExecutorCommand::Seek { cue_id, position } => {
+                let instance_ids = self.instances_of(&cue_id).await;
+                if instance_ids.is_empty() {
+                    tracing::warn!(%cue_id, "Cannot seek: Cue is not active.");
+                }
+                for instance_id in instance_ids {
+                    self.audio_tx.send(AudioCommand::Seek { id: instance_id, position }).await?;
+                }
+            }
+            ExecutorCommand::ReleaseLoop(cue_id) => {
+                let instance_ids = self.instances_of(&cue_id).await;
+                if instance_ids.is_empty() {
+                    tracing::warn!(%cue_id, "Cannot release loop: Cue is not active.");
+                }
+                for instance_id in instance_ids {
+                    self.audio_tx.send(AudioCommand::ReleaseLoop { id: instance_id }).await?;
+                }
+            }
+            ExecutorCommand::SetMasterVolume { db, duration, easing } => {
+                self.audio_tx.send(AudioCommand::SetMasterVolume { db, duration, easing }).await?;
+            }
+            ExecutorCommand::SetBusVolume { bus, db, duration, easing } => {
+                self.audio_tx.send(AudioCommand::SetBusVolume { bus, db, duration, easing }).await?;
+            }
+            ExecutorCommand::ReplaceMedia { cue_id, new_target } => {
+                let instance_ids = self.instances_of(&cue_id).await;
+                if instance_ids.is_empty() {
+                    tracing::warn!(%cue_id, "Cannot replace media: Cue is not active.");
+                }
+                let filepath = self.model_handle.resolve_path(&new_target).await;
+                for instance_id in instance_ids {
+                    self.audio_tx
+                        .send(AudioCommand::ReplaceMedia {
+                            id: instance_id,
+                            filepath: filepath.clone(),
+                        })
+                        .await?;
+                }
+            }
+            ExecutorCommand::SetLevelsMany { changes } => {
+                // フェードが揃うように、全てのインスタンスへの変更を1つのコマンドにまとめて送る
+                let active_instances = self.active_instances.read().await;
+                let mut instance_changes = Vec::new();
+                for (cue_id, levels, duration, easing) in changes {
+                    let mut found = false;
+                    for (instance_id, _) in active_instances.iter().filter(|(_, id)| cue_id.eq(*id)) {
+                        instance_changes.push((*instance_id, levels.clone(), duration, easing.clone()));
+                        found = true;
+                    }
+                    if !found {
+                        tracing::warn!(%cue_id, "Cannot set levels: Cue is not active.");
+                    }
+                }
+                drop(active_instances);
+                if !instance_changes.is_empty() {
+                    self.audio_tx
+                        .send(AudioCommand::SetLevelsMany { changes: instance_changes })
+                        .await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// キューを解釈し、適切なエンジンにコマンドを送信します。`start_delay`秒にキューの`pre_wait`を加えた時間の後に再生を開始します。
+    async fn dispatch_cue(&self, cue: &Cue, start_delay: f64) -> Result<(), anyhow::Error> {
+        if let CueParam::Group { .. } = &cue.param {
+            self.dispatch_group(cue, start_delay).await
+        } else {
+            self.dispatch_single_cue(cue, start_delay + cue.pre_wait).await
+        }
+    }
+
+    /// グループキューの子キューを入れ子のグループも含めて全て実行します。
+    /// 同じキューは一度だけ実行し、自分自身を含むような循環した参照は無視します。
+    /// 子キューは、外側のグループから順に全ての`pre_wait`を加えた時間の後に開始します。
+    async fn dispatch_group(&self, group_cue: &Cue, start_delay: f64) -> Result<(), anyhow::Error> {
+        let mut visited = HashSet::from([group_cue.id]);
+        let mut queue = vec![(group_cue.clone(), start_delay)];
+        let mut groups = Vec::new();
+        let mut leaves = Vec::new();
+        while let Some((cue, parent_delay)) = queue.pop() {
+            let delay = parent_delay + cue.pre_wait;
+            let CueParam::Group { children } = &cue.param else {
+                leaves.push((cue, delay));
+                continue;
+            };
+            let mut pending_children = HashSet::new();
+            for child_id in children {
+                if !visited.insert(*child_id) {
+                    tracing::warn!(group_id = %cue.id, %child_id, "Group cue: skipping already dispatched or cyclic child.");
+                    continue;
+                }
+                if let Some(child) = self.model_handle.get_cue_by_id(child_id).await {
+                    if !child.armed {
+                        tracing::info!(group_id = %cue.id, %child_id, "Group cue: skipping disarmed child.");
+                        continue;
+                    }
+                    pending_children.insert(child.id);
+                    queue.push((child, delay));
+                } else {
+                    tracing::error!(group_id = %cue.id, %child_id, "Group cue: child cue not found.");
+                }
+            }
+            groups.push((cue.id, pending_children));
+        }
+
+        let mut empty_groups = Vec::new();
+        {
+            let mut active_groups = self.active_groups.0.write().await;
+            for (group_id, pending_children) in &groups {
+                if pending_children.is_empty() {
+                    empty_groups.push(*group_id);
                 } else {
-                    log::error!("Cannot execute cue: Cue with id '{}' not found.", cue_id);
+                    active_groups.insert(*group_id, ActiveGroup {
+                        pending_children: pending_children.clone(),
+                        report: PlaybackReport::empty(),
+                    });
                 }
             }
         }
+        for (group_id, _) in &groups {
+            self.playback_event_tx.send(ExecutorEvent::Started { cue_id: *group_id }).await?;
+        }
+        for (leaf, delay) in &leaves {
+            self.dispatch_single_cue(leaf, *delay).await?;
+        }
+        // 子キューの無いグループはすぐに終了する。内側のグループから終了させる
+        for group_id in empty_groups.into_iter().rev() {
+            let event = ExecutorEvent::Completed { cue_id: group_id, report: PlaybackReport::empty() };
+            self.active_groups.send_event(&self.playback_event_tx, event).await?;
+        }
         Ok(())
     }
 
-    /// キューを解釈し、適切なエンジンにコマンドを送信します。
-    async fn dispatch_cue(&self, cue: &Cue) -> Result<(), anyhow::Error> {
+    async fn dispatch_single_cue(&self, cue: &Cue, start_delay: f64) -> Result<(), anyhow::Error> {
         let instance_id = Uuid::now_v7();
-        log::info!(
-            "Dispatching cue '{}' with new instance_id '{}'",
-            cue.name,
-            instance_id
-        );
+        // このインスタンスのログには、キューとインスタンスのIDを付ける
+        let span = tracing::info_span!("cue", cue_id = %cue.id, %instance_id);
+        self.dispatch_instance(cue, instance_id, start_delay).instrument(span).await
+    }
+
+    async fn dispatch_instance(&self, cue: &Cue, instance_id: Uuid, start_delay: f64) -> Result<(), anyhow::Error> {
+        tracing::info!(name = %cue.name, start_delay, "Dispatching cue.");
 
         match &cue.param {
-            CueParam::Audio {
-                target,
-                start_time,
-                fade_in_param,
-                end_time,
-                fade_out_param,
-                levels,
-                loop_region,
-            } => {
+            CueParam::Audio { .. } if self.preview.load(Ordering::Relaxed) => {
+                let duration = self.model_handle.cue_duration(cue).await.unwrap_or_else(|| {
+                    tracing::warn!("Preview: duration of cue is unknown. Completing immediately.");
+                    0.0
+                });
+                self.spawn_preview_playback(cue.id, instance_id, duration, start_delay);
+            }
+            CueParam::Audio { .. } => {
+                let settings = self.model_handle.get_settings().await;
+                self.sync_output_device(settings.general.output_device.clone()).await?;
+                self.sync_max_voices(settings.general.max_voices).await?;
+                // 同じキューの再生中のインスタンスは、新しいインスタンスに置き換える
+                self.retire_instances(cue.id).await?;
                 // AudioEngineが理解できるAudioCommandに変換
-                let audio_command = AudioCommand::Play {
-                    id: instance_id,
-                    data: PlayCommandData {
-                        filepath: target.clone(),
-                        levels: levels.clone(),
-                        start_time: *start_time,
-                        fade_in_param: *fade_in_param,
-                        end_time: *end_time,
-                        fade_out_param: *fade_out_param,
-                        loop_region: *loop_region,
-                    },
-                };
+                let data = self.play_command_data(cue, &settings, start_delay).await?;
+                let audio_command = AudioCommand::Play { id: instance_id, data };
                 // AudioEngineにコマンドを送信
                 self.audio_tx.send(audio_command).await?;
+                self.active_instances
+                    .write()
+                    .await
+                    .insert(instance_id, cue.id);
             }
             CueParam::Wait { duration } => {
                 // イベント送信用チャネルのクローンを新しいタスクに渡す
                 let event_tx = self.playback_event_tx.clone();
+                let active_groups = self.active_groups.clone();
                 let cue_id = cue.id;
                 let wait_duration = *duration;
+                let cancel_token = self.wait_cancel_token.clone();
+                let stop_token = self.wait_stop_token.lock().unwrap().clone();
+                let (mut paused_rx, stop_token, timer_guard) = self.timer_instances.register(instance_id, cue_id, &stop_token);
 
                 // 待機処理を別の非同期タスクとして実行
                 tokio::spawn(async move {
+                    let _timer_guard = timer_guard;
+                    // 0. タイムコード同期やpre_waitの場合は開始時刻まで待機
+                    if !wait_until_start(cue_id, start_delay, &stop_token, &cancel_token, &active_groups, &event_tx).await {
+                        return;
+                    }
+
                     // 1. 開始イベントを送信
                     if let Err(e) = event_tx.send(ExecutorEvent::Started { cue_id }).await {
-                        log::error!("Failed to send Started event for Wait cue: {}", e);
+                        tracing::error!(error = %e, "Failed to send Started event for Wait cue.");
                         return; // 送信に失敗したらタスク終了
                     }
 
-                    // 2. 指定された時間だけ待機
-                    tokio::time::sleep(std::time::Duration::from_secs_f64(wait_duration)).await;
+                    // 2. 指定された時間だけ待機。一時停止中は待機時間を進めない
+                    let mut clock = PausableClock::start();
+                    let mut pause_closed = false;
+                    let was_stopped_manually = loop {
+                        let remaining = Duration::from_secs_f64(wait_duration).saturating_sub(clock.elapsed());
+                        tokio::select! {
+                            _ = tokio::time::sleep(remaining), if !clock.is_paused() => break false,
+                            result = paused_rx.changed(), if !pause_closed => {
+                                if result.is_err() {
+                                    pause_closed = true;
+                                    continue;
+                                }
+                                let paused = *paused_rx.borrow_and_update();
+                                if !clock.follow(paused, cue_id, wait_duration, &event_tx).await {
+                                    return;
+                                }
+                            }
+                            _ = stop_token.cancelled() => break true,
+                            _ = cancel_token.cancelled() => return,
+                        }
+                    };
 
                     // 3. 完了イベントを送信
-                    if let Err(e) = event_tx.send(ExecutorEvent::Completed { cue_id }).await {
-                        log::error!("Failed to send Completed event for Wait cue: {}", e);
+                    let report = PlaybackReport {
+                        played_duration: clock.elapsed().as_secs_f64().min(wait_duration),
+                        peak_level: kira::Decibels::SILENCE.0 as f64,
+                        was_stopped_manually,
+                    };
+                    if let Err(e) = active_groups.send_event(&event_tx, ExecutorEvent::Completed { cue_id, report }).await {
+                        tracing::error!(error = %e, "Failed to send Completed event for Wait cue.");
                     }
-                });
+                }.in_current_span());
+            }
+            CueParam::WaitForInput { source } => {
+                let event_tx = self.playback_event_tx.clone();
+                let active_groups = self.active_groups.clone();
+                let cue_id = cue.id;
+                let source = source.clone();
+                let cancel_token = self.wait_cancel_token.clone();
+                let stop_token = self.wait_stop_token.lock().unwrap().clone();
+                let input_trigger_tx = self.input_trigger_tx.clone();
+                let (mut paused_rx, stop_token, timer_guard) = self.timer_instances.register(instance_id, cue_id, &stop_token);
+
+                tokio::spawn(async move {
+                    let _timer_guard = timer_guard;
+                    if !wait_until_start(cue_id, start_delay, &stop_token, &cancel_token, &active_groups, &event_tx).await {
+                        return;
+                    }
+
+                    // 開始前に届いたトリガーには反応しない
+                    let mut trigger_rx = input_trigger_tx.subscribe();
+                    drop(input_trigger_tx);
+                    if let Err(e) = event_tx.send(ExecutorEvent::Started { cue_id }).await {
+                        tracing::error!(error = %e, "Failed to send Started event for WaitForInput cue.");
+                        return;
+                    }
+
+                    let mut clock = PausableClock::start();
+                    let mut pause_closed = false;
+                    let was_stopped_manually = loop {
+                        tokio::select! {
+                            result = trigger_rx.recv() => match result {
+                                // 一時停止中に届いたトリガーには反応しない
+                                Ok(trigger) if trigger.matches(&source) && !clock.is_paused() => break false,
+                                Ok(_) => {}
+                                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                                    tracing::warn!(skipped, "WaitForInput cue skipped input triggers.");
+                                }
+                                Err(broadcast::error::RecvError::Closed) => return,
+                            },
+                            result = paused_rx.changed(), if !pause_closed => {
+                                if result.is_err() {
+                                    pause_closed = true;
+                                    continue;
+                                }
+                                let paused = *paused_rx.borrow_and_update();
+                                if !clock.follow(paused, cue_id, 0.0, &event_tx).await {
+                                    return;
+                                }
+                            }
+                            _ = stop_token.cancelled() => break true,
+                            _ = cancel_token.cancelled() => return,
+                        }
+                    };
+
+                    let report = PlaybackReport {
+                        played_duration: clock.elapsed().as_secs_f64(),
+                        peak_level: kira::Decibels::SILENCE.0 as f64,
+                        was_stopped_manually,
+                    };
+                    if let Err(e) = active_groups.send_event(&event_tx, ExecutorEvent::Completed { cue_id, report }).await {
+                        tracing::error!(error = %e, "Failed to send Completed event for WaitForInput cue.");
+                    }
+                }.in_current_span());
+            }
+            CueParam::Group { .. } => {
+                tracing::error!("Group cue must be dispatched as a group.");
+            }
+        }
+        Ok(())
+    }
+
+    /// プレビュー中のオーディオキューの代わりに、音を出さずに`duration`秒の再生を模したイベントを送信します。
+    fn spawn_preview_playback(&self, cue_id: Uuid, instance_id: Uuid, duration: f64, start_delay: f64) {
+        let event_tx = self.playback_event_tx.clone();
+        let active_groups = self.active_groups.clone();
+        let cancel_token = self.wait_cancel_token.clone();
+        let stop_token = self.wait_stop_token.lock().unwrap().clone();
+        // StopCueで止められるよう、待機中のキューと同じくインスタンスを登録する
+        let (mut paused_rx, stop_token, timer_guard) = self.timer_instances.register(instance_id, cue_id, &stop_token);
+
+        tokio::spawn(async move {
+            let _timer_guard = timer_guard;
+            if !wait_until_start(cue_id, start_delay, &stop_token, &cancel_token, &active_groups, &event_tx).await {
+                return;
+            }
+            if let Err(e) = event_tx.send(ExecutorEvent::Started { cue_id }).await {
+                tracing::error!(error = %e, "Failed to send Started event for preview.");
+                return;
+            }
+
+            let mut clock = PausableClock::start();
+            let mut pause_closed = false;
+            let mut progress_timer = tokio::time::interval(PREVIEW_PROGRESS_INTERVAL);
+            let was_stopped_manually = loop {
+                let remaining = Duration::from_secs_f64(duration).saturating_sub(clock.elapsed());
+                tokio::select! {
+                    _ = tokio::time::sleep(remaining), if !clock.is_paused() => break false,
+                    result = paused_rx.changed(), if !pause_closed => {
+                        if result.is_err() {
+                            pause_closed = true;
+                            continue;
+                        }
+                        let paused = *paused_rx.borrow_and_update();
+                        if !clock.follow(paused, cue_id, duration, &event_tx).await {
+                            return;
+                        }
+                    }
+                    _ = stop_token.cancelled() => break true,
+                    _ = cancel_token.cancelled() => return,
+                    _ = progress_timer.tick(), if !clock.is_paused() => {
+                        let position = clock.elapsed().as_secs_f64().min(duration);
+                        if event_tx.send(ExecutorEvent::Progress { cue_id, position, duration }).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+            };
+
+            let report = PlaybackReport {
+                played_duration: clock.elapsed().as_secs_f64().min(duration),
+                peak_level: kira::Decibels::SILENCE.0 as f64,
+                was_stopped_manually,
+            };
+            if let Err(e) = active_groups.send_event(&event_tx, ExecutorEvent::Completed { cue_id, report }).await {
+                tracing::error!(error = %e, "Failed to send Completed event for preview.");
+            }
+        }.in_current_span());
+    }
+
+    /// 再生中の全てのサウンドを無音までフェードし、`duration`秒後に停止します。待機中のキューはすぐに停止します。
+    async fn fade_out_all(&self, duration: f64, easing: FadeCurve) -> Result<(), anyhow::Error> {
+        std::mem::replace(&mut *self.wait_stop_token.lock().unwrap(), CancellationToken::new()).cancel();
+        let fade_out_token = CancellationToken::new();
+        // 前のフェードが終わっていない場合は、そのサウンドをすぐに停止する
+        std::mem::replace(&mut *self.fade_out_token.lock().unwrap(), fade_out_token.clone()).cancel();
+
+        let instance_ids: Vec<Uuid> = self.active_instances.read().await.keys().copied().collect();
+        if instance_ids.is_empty() {
+            return Ok(());
+        }
+        let silence = AudioCueLevels { master: kira::Decibels::SILENCE.0 as f64 };
+        let changes = instance_ids.iter().map(|instance_id| (*instance_id, silence.clone(), duration, easing.clone())).collect();
+        self.audio_tx.send(AudioCommand::SetLevelsMany { changes }).await?;
+
+        let audio_tx = self.audio_tx.clone();
+        let active_instances = self.active_instances.clone();
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_secs_f64(duration)) => {}
+                _ = fade_out_token.cancelled() => log::info!("Fade out of all cues was interrupted."),
+            }
+            // フェード中に終了したサウンドは停止しない
+            let instance_ids: Vec<Uuid> = {
+                let active_instances = active_instances.read().await;
+                instance_ids.into_iter().filter(|instance_id| active_instances.contains_key(instance_id)).collect()
+            };
+            for instance_id in instance_ids {
+                let stop = AudioCommand::Stop { id: instance_id, fade_out: Duration::ZERO, easing: Easing::Linear };
+                if let Err(e) = audio_tx.send(stop).await {
+                    log::error!("Failed to stop sound after fade out: {}", e);
+                    return;
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// `from_cue_id`のキューを`duration`秒かけて無音までフェードして停止し、`to_cue_id`のキューをキューのフェードインの代わりに同じ長さ・カーブのフェードインで開始します。
+    /// `from_cue_id`のキューが再生中でない場合は、`to_cue_id`のキューのフェードインのみ行います。
+    async fn crossfade(&self, from_cue_id: Uuid, to_cue_id: Uuid, duration: f64, easing: FadeCurve) -> Result<(), anyhow::Error> {
+        let Some(mut to_cue) = self.model_handle.get_cue_by_id(&to_cue_id).await else {
+            tracing::error!(%to_cue_id, "Cannot crossfade: Cue not found.");
+            return Ok(());
+        };
+        let CueParam::Audio { fade_in_param, .. } = &mut to_cue.param else {
+            return Err(anyhow::anyhow!("Cannot crossfade to cue '{}': Only audio cues can be faded in.", to_cue_id));
+        };
+        if !to_cue.armed {
+            tracing::info!(%to_cue_id, "Skipping crossfade to disarmed cue.");
+            return Ok(());
+        }
+        *fade_in_param = Some(AudioCueFadeParam { duration, easing: easing.clone() });
+
+        let instance_ids = self.instances_of(&from_cue_id).await;
+        if instance_ids.is_empty() {
+            tracing::info!(%from_cue_id, "Crossfade: Cue is not active. Only fading in.");
+        }
+        for instance_id in instance_ids {
+            self.audio_tx
+                .send(AudioCommand::Stop { id: instance_id, fade_out: Duration::from_secs_f64(duration), easing: easing.easing() })
+                .await?;
+        }
+        self.fade_out_token.lock().unwrap().cancel();
+        self.dispatch_cue(&to_cue, 0.0).await
+    }
+
+    /// オーディオキューの再生の指定を作成します。フェードがキューで指定されていない場合はショーの既定値を使います。
+    async fn play_command_data(&self, cue: &Cue, settings: &ShowSettings, start_delay: f64) -> Result<PlayCommandData, anyhow::Error> {
+        let CueParam::Audio {
+            target,
+            start_time,
+            fade_in_param,
+            end_time,
+            fade_out_param,
+            levels,
+            loop_region,
+            loop_count,
+            output_bus,
+            streaming,
+            normalize_to,
+            automation,
+            ..
+        } = &cue.param
+        else {
+            return Err(anyhow::anyhow!("Cue '{}' is not an audio cue.", cue.id));
+        };
+        Ok(PlayCommandData {
+            filepath: self.model_handle.resolve_path(target).await,
+            levels: levels.clone(),
+            start_time: *start_time,
+            fade_in_param: fade_in_param.clone().or(settings.general.default_fade_in_param.clone()),
+            end_time: *end_time,
+            fade_out_param: fade_out_param.clone().or(settings.general.default_fade_out_param.clone()),
+            loop_region: *loop_region,
+            loop_count: *loop_count,
+            start_delay,
+            output_bus: output_bus.clone(),
+            streaming: *streaming,
+            normalize_to: *normalize_to,
+            automation: automation.clone(),
+        })
+    }
+
+    /// オーディオキューをキューのレベルから`AUDITION_LEVEL_OFFSET`下げた音量で再生し、`AUDITION_DURATION`後にキューのフェードアウトで停止します。
+    /// 試聴中のインスタンスは再生中のキューとは別に管理し、AudioEngineからのイベントは試聴のイベントとして送信します。
+    async fn audition_cue(&self, cue_id: Uuid) -> Result<(), anyhow::Error> {
+        let Some(cue) = self.model_handle.get_cue_by_id(&cue_id).await else {
+            tracing::error!(%cue_id, "Cannot audition cue: Cue not found.");
+            return Ok(());
+        };
+        let settings = self.model_handle.get_settings().await;
+        let mut data = self.play_command_data(&cue, &settings, 0.0).await?;
+        data.levels.master += AUDITION_LEVEL_OFFSET;
+        data.loop_region = None;
+        data.loop_count = None;
+        self.sync_output_device(settings.general.output_device).await?;
+        // 同じキューの試聴中のインスタンスは、新しいインスタンスに置き換える
+        let previous_ids: Vec<Uuid> = {
+            let mut audition_instances = self.audition_instances.write().await;
+            let previous_ids = audition_instances.iter().filter(|(_, id)| cue_id.eq(*id)).map(|(instance_id, _)| *instance_id).collect();
+            audition_instances.retain(|_, id| !cue_id.eq(id));
+            previous_ids
+        };
+        for instance_id in previous_ids {
+            self.audio_tx
+                .send(AudioCommand::Stop { id: instance_id, fade_out: Duration::ZERO, easing: Easing::Linear })
+                .await?;
+        }
+
+        let instance_id = Uuid::now_v7();
+        let fade_out = data.fade_out_param.clone().unwrap_or(AudioCueFadeParam { duration: 0.0, easing: Easing::Linear.into() });
+        self.audio_tx.send(AudioCommand::Play { id: instance_id, data }).await?;
+        self.audition_instances.write().await.insert(instance_id, cue_id);
+
+        let audio_tx = self.audio_tx.clone();
+        let audition_instances = self.audition_instances.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(AUDITION_DURATION).await;
+            // 既に終了または置き換えられた試聴は停止しない
+            if !audition_instances.read().await.contains_key(&instance_id) {
+                return;
+            }
+            let stop_command = AudioCommand::Stop {
+                id: instance_id,
+                fade_out: Duration::from_secs_f64(fade_out.duration),
+                easing: fade_out.easing.easing(),
+            };
+            if let Err(e) = audio_tx.send(stop_command).await {
+                tracing::error!(error = %e, "Failed to stop audition.");
+            }
+        }.in_current_span());
+        Ok(())
+    }
+
+    /// 試聴中のインスタンスのイベントを、試聴のイベントとしてCueControllerに送信します。
+    async fn handle_audition_event(&self, cue_id: Uuid, audio_event: AudioEngineEvent) -> Result<(), anyhow::Error> {
+        let playback_event = match audio_event {
+            AudioEngineEvent::Started { .. } => ExecutorEvent::AuditionStarted { cue_id },
+            AudioEngineEvent::Completed { instance_id, .. } => {
+                self.audition_instances.write().await.remove(&instance_id);
+                ExecutorEvent::AuditionCompleted { cue_id }
+            }
+            AudioEngineEvent::Error { instance_id, error, .. } => {
+                tracing::warn!(%cue_id, %error, "Audition failed.");
+                self.audition_instances.write().await.remove(&instance_id);
+                ExecutorEvent::AuditionCompleted { cue_id }
+            }
+            _ => return Ok(()),
+        };
+        self.playback_event_tx.send(playback_event).await?;
+        Ok(())
+    }
+
+    /// 再生中のキューを、キューまたはショーの既定の停止フェードで停止します。
+    /// 実行中のグループキューの場合は、終了していない子キューを再帰的に停止します。
+    async fn stop_cue(&self, cue_id: Uuid) -> Result<(), anyhow::Error> {
+        let mut stopped = false;
+        for target_id in self.active_group_members(cue_id).await {
+            let instance_ids = self.instances_of(&target_id).await;
+            stopped |= !instance_ids.is_empty() || self.timer_instances.stop(target_id) > 0;
+            if instance_ids.is_empty() {
+                continue;
+            }
+            let stop_fade = self.stop_fade_param(&target_id).await;
+            for instance_id in instance_ids {
+                self.audio_tx
+                    .send(AudioCommand::Stop {
+                        id: instance_id,
+                        fade_out: Duration::from_secs_f64(stop_fade.duration),
+                        easing: stop_fade.easing.easing(),
+                    })
+                    .await?;
             }
         }
+        if !stopped {
+            tracing::warn!(%cue_id, "Cannot stop cue: Cue is not active.");
+        }
+        Ok(())
+    }
+
+    /// キュー自身と、実行中のグループキューの場合は終了していない子孫のキューのIDを返します。
+    async fn active_group_members(&self, cue_id: Uuid) -> Vec<Uuid> {
+        let groups = self.active_groups.0.read().await;
+        let mut members = vec![cue_id];
+        let mut queue = vec![cue_id];
+        // 循環するグループは実行時に除かれているため、同じキューを二度たどることはない
+        while let Some(member_id) = queue.pop() {
+            if let Some(group) = groups.get(&member_id) {
+                members.extend(&group.pending_children);
+                queue.extend(&group.pending_children);
+            }
+        }
+        members
+    }
+
+    /// キューを停止する際のフェード。キューで指定されていない場合はショーの既定値を使い、どちらもない場合はすぐに停止します。
+    async fn stop_fade_param(&self, cue_id: &Uuid) -> AudioCueFadeParam {
+        let cue_stop_fade = match self.model_handle.get_cue_by_id(cue_id).await {
+            Some(Cue { param: CueParam::Audio { stop_fade_param, .. }, .. }) => stop_fade_param,
+            _ => None,
+        };
+        cue_stop_fade
+            .or(self.model_handle.get_settings().await.general.default_stop_fade_param)
+            .unwrap_or(AudioCueFadeParam { duration: 0.0, easing: Easing::Linear.into() })
+    }
+
+    /// ショー設定の出力デバイスが変更されていれば、AudioEngineに切り替えを指示します。
+    async fn sync_output_device(&self, device: Option<String>) -> Result<(), anyhow::Error> {
+        let mut output_device = self.output_device.write().await;
+        if device.ne(&*output_device) {
+            self.audio_tx
+                .send(AudioCommand::SetOutputDevice { device: device.clone() })
+                .await?;
+            *output_device = device;
+        }
+        Ok(())
+    }
+
+    /// ショー設定の同時再生数の上限が変更されていれば、AudioEngineに指定します。
+    async fn sync_max_voices(&self, limit: Option<usize>) -> Result<(), anyhow::Error> {
+        let mut max_voices = self.max_voices.write().await;
+        if limit.ne(&*max_voices) {
+            self.audio_tx.send(AudioCommand::SetMaxVoices { limit }).await?;
+            *max_voices = limit;
+        }
+        Ok(())
+    }
+
+    /// キューの再生中のインスタンスのIDを返します。
+    async fn instances_of(&self, cue_id: &Uuid) -> Vec<Uuid> {
+        self.active_instances
+            .read()
+            .await
+            .iter()
+            .filter(|(_, id)| cue_id.eq(*id))
+            .map(|(instance_id, _)| *instance_id)
+            .collect()
+    }
+
+    /// キューの再生中のインスタンスをフェードなしで停止します。
+    /// 停止後にAudioEngineから届くイベントは、不明なインスタンスとして破棄します。
+    async fn retire_instances(&self, cue_id: Uuid) -> Result<(), anyhow::Error> {
+        let instance_ids: Vec<Uuid> = {
+            let mut active_instances = self.active_instances.write().await;
+            let instance_ids = active_instances.iter().filter(|(_, id)| cue_id.eq(*id)).map(|(instance_id, _)| *instance_id).collect();
+            active_instances.retain(|_, id| !cue_id.eq(id));
+            instance_ids
+        };
+        for instance_id in instance_ids {
+            tracing::info!(%cue_id, %instance_id, "Retriggered cue. Stopping previous instance.");
+            self.audio_tx
+                .send(AudioCommand::Stop { id: instance_id, fade_out: Duration::ZERO, easing: Easing::Linear })
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn handle_engine_event(&self, event: EngineEvent) -> Result<(), anyhow::Error> {
+        match event {
+            EngineEvent::Audio(audio_event) => {
+                let instance_id = audio_event.instance_id();
+                let audition_cue_id = self.audition_instances.read().await.get(&instance_id).copied();
+                if let Some(cue_id) = audition_cue_id {
+                    return self.handle_audition_event(cue_id, audio_event).await;
+                }
+
+                let instances = self.active_instances.read().await;
+                let Some(cue_id) = instances.get(&instance_id).cloned() else {
+                    tracing::warn!(%instance_id, "Received event for unknown instance_id.");
+                    return Ok(());
+                };
+
+                let playback_event = match audio_event {
+                    AudioEngineEvent::Loading { .. } => ExecutorEvent::Loading { cue_id },
+                    AudioEngineEvent::Started { .. } => ExecutorEvent::Started { cue_id },
+                    AudioEngineEvent::Progress {
+                        position, duration, ..
+                    } => ExecutorEvent::Progress {
+                        cue_id,
+                        position,
+                        duration,
+                    },
+                    AudioEngineEvent::Paused {
+                        position, duration, ..
+                    } => ExecutorEvent::Paused {
+                        cue_id,
+                        position,
+                        duration,
+                    },
+                    AudioEngineEvent::Meter { peak, rms, .. } => ExecutorEvent::Meter { cue_id, peak, rms },
+                    AudioEngineEvent::Resumed { .. } => ExecutorEvent::Resumed { cue_id },
+                    AudioEngineEvent::VoiceStolen { .. } => ExecutorEvent::VoiceStolen { cue_id },
+                    AudioEngineEvent::Completed { report, .. } => {
+                        drop(instances);
+                        self.active_instances.write().await.remove(&instance_id);
+                        ExecutorEvent::Completed { cue_id, report }
+                    }
+                    AudioEngineEvent::Error { error, kind, .. } => {
+                        drop(instances);
+                        self.active_instances.write().await.remove(&instance_id);
+                        ExecutorEvent::Error { cue_id, error, kind }
+                    }
+                };
+
+                self.active_groups.send_event(&self.playback_event_tx, playback_event).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{path::PathBuf, sync::atomic::AtomicU64};
+
+    use kira::sound::Region;
+    use tokio::sync::{broadcast, mpsc::{self, Receiver, Sender}};
+    use uuid::Uuid;
+
+    use crate::{
+        engine::{audio_engine::{AudioCommand, AudioEngineEvent}, timecode::tests::MockTimecodeSource}, event::UiEvent, manager::ShowModelManager, model::{
+            self,
+            cue::{AudioCueFadeParam, AudioCueLevels, Cue, RetriggerMode},
+        }
+    };
+
+    async fn setup_executor(cue_id: Uuid) -> (ShowModelManager, Sender<ExecutorCommand>, Receiver<AudioCommand>, Sender<EngineEvent>, Receiver<ExecutorEvent>) {
+        let (exec_tx, exec_rx) = mpsc::channel::<ExecutorCommand>(32);
+        let (audio_tx, audio_rx) = mpsc::channel::<AudioCommand>(32);
+        let (playback_event_tx, playback_event_rx) = mpsc::channel::<ExecutorEvent>(32);
+        let (engine_event_tx, engine_event_rx) = mpsc::channel::<EngineEvent>(32);
+        let (event_tx, _) = broadcast::channel::<UiEvent>(32);
+
+        let (manager, handle) = ShowModelManager::new(event_tx.clone());
+        manager
+            .write_with(|model| {
+                model.name = "TestShowModel".to_string();
+                model.cues.push(Cue {
+                    id: cue_id,
+                    number: "1".to_string(),
+                    name: "Play IGY".to_string(),
+                    notes: "".to_string(),
+                    pre_wait: 0.0,
+                    post_wait: 0.0,
+                    sequence: model::cue::CueSequence::DoNotContinue,
+                    follow_offset: None,
+                    armed: true,
+                    retrigger_mode: RetriggerMode::Restart,
+                    color: None,
+                    order: 0.0,
+                    schedule: None,
+                    param: model::cue::CueParam::Audio {
+                        target: PathBuf::from("./I.G.Y.flac"),
+                    start_time: Some(5.0),
+                    fade_in_param: Some(AudioCueFadeParam {
+                        duration: 2.0,
+                        easing: kira::Easing::Linear.into(),
+                    }),
+                    end_time: Some(50.0),
+                    fade_out_param: Some(AudioCueFadeParam {
+                        duration: 5.0,
+                        easing: kira::Easing::InPowi(2).into(),
+                    }),
+                    levels: AudioCueLevels { master: 0.0 },
+                    loop_region: Some(Region { start: kira::sound::PlaybackPosition::Seconds(2.0), end: kira::sound::EndPosition::EndOfAudio }),
+                    loop_count: None,
+                    stop_fade_param: None,
+                    output_bus: None,
+                    streaming: false,
+                    normalize_to: None,
+                    automation: None,
+                    },
+                });
+                cue_id
+            })
+            .await;
+
+        let executor = Executor::new(
+            handle.clone(),
+            exec_rx,
+            audio_tx,
+            playback_event_tx,
+            engine_event_rx,
+        );
+
+        tokio::spawn(executor.run(CancellationToken::new()));
+
+        (manager, exec_tx, audio_rx, engine_event_tx, playback_event_rx)
+    }
+    
+
+    #[tokio::test]
+    async fn play_command() {
+        let cue_id = Uuid::new_v4();
+
+        let (_, exec_tx, mut audio_rx, _, _) = setup_executor(cue_id).await;
+
+        let old_id = Uuid::now_v7();
+
+        exec_tx
+            .send(ExecutorCommand::ExecuteCue(cue_id))
+            .await
+            .unwrap();
+
+        let command = audio_rx.recv().await.unwrap();
+
+        if let AudioCommand::Play { id, data } = command {
+            assert!(id > old_id);
+            let now_id = Uuid::now_v7();
+            assert!(id < now_id);
+            assert_eq!(data.filepath, PathBuf::from("./I.G.Y.flac"));
+            assert_eq!(data.levels, AudioCueLevels { master: 0.0 });
+            assert_eq!(data.start_time, Some(5.0));
+            assert_eq!(data.fade_in_param, Some(AudioCueFadeParam { duration: 2.0, easing: kira::Easing::Linear.into() }));
+            assert_eq!(data.end_time, Some(50.0));
+            assert_eq!(data.fade_out_param, Some(AudioCueFadeParam { duration: 5.0, easing: kira::Easing::InPowi(2).into() }));
+            assert_eq!(data.loop_region, Some(Region { start: kira::sound::PlaybackPosition::Seconds(2.0), end: kira::sound::EndPosition::EndOfAudio }));
+        } else {
+            unreachable!();
+        }
+    }
+
+    #[tokio::test]
+    async fn play_at_frame_command() {
+        let cue_id = Uuid::new_v4();
+        let (exec_tx, exec_rx) = mpsc::channel::<ExecutorCommand>(32);
+        let (audio_tx, mut audio_rx) = mpsc::channel::<AudioCommand>(32);
+        let (playback_event_tx, _playback_event_rx) = mpsc::channel::<ExecutorEvent>(32);
+        let (_engine_event_tx, engine_event_rx) = mpsc::channel::<EngineEvent>(32);
+        let (event_tx, _) = broadcast::channel::<UiEvent>(32);
+        let (manager, handle) = ShowModelManager::new(event_tx);
+        manager
+            .write_with(|model| {
+                model.cues.push(Cue {
+                    id: cue_id,
+                    number: "1".to_string(),
+                    name: "Play IGY".to_string(),
+                    notes: "".to_string(),
+                    pre_wait: 0.0,
+                    post_wait: 0.0,
+                    sequence: model::cue::CueSequence::DoNotContinue,
+                    follow_offset: None,
+                    armed: true,
+                    retrigger_mode: RetriggerMode::Restart,
+                    color: None,
+                    order: 0.0,
+                    schedule: None,
+                    param: model::cue::CueParam::Audio {
+                        target: PathBuf::from("./I.G.Y.flac"),
+                        start_time: None,
+                        fade_in_param: None,
+                        end_time: None,
+                        fade_out_param: None,
+                        levels: AudioCueLevels { master: 0.0 },
+                        loop_region: None,
+                        loop_count: None,
+                        stop_fade_param: None,
+                        output_bus: None,
+                        streaming: false,
+                        normalize_to: None,
+                        automation: None,
+                    },
+                });
+            })
+            .await;
+
+        let timecode_source = Arc::new(MockTimecodeSource { frame_rate: 25.0, frame: AtomicU64::new(1000) });
+        let executor = Executor::new(handle, exec_rx, audio_tx, playback_event_tx, engine_event_rx)
+            .with_timecode_source(timecode_source);
+        tokio::spawn(executor.run(CancellationToken::new()));
+
+        exec_tx
+            .send(ExecutorCommand::ExecuteCueAtFrame { cue_id, frame: 1050 })
+            .await
+            .unwrap();
+
+        if let Some(AudioCommand::Play { data, .. }) = audio_rx.recv().await {
+            assert_eq!(data.start_delay, 2.0);
+        } else {
+            unreachable!();
+        }
+    }
+
+    #[tokio::test]
+    async fn pause_n_resume_all_command() {
+        let cue_id = Uuid::new_v4();
+
+        let (_, exec_tx, mut audio_rx, _, _) = setup_executor(cue_id).await;
+
+        exec_tx
+            .send(ExecutorCommand::ExecuteCue(cue_id))
+            .await
+            .unwrap();
+
+        let instance_id = if let Some(AudioCommand::Play { id, .. }) = audio_rx.recv().await {
+            id
+        } else {
+            unreachable!();
+        };
+
+        exec_tx.send(ExecutorCommand::PauseAll).await.unwrap();
+
+        if let Some(AudioCommand::Pause { id }) = audio_rx.recv().await {
+            assert_eq!(id, instance_id);
+        } else {
+            panic!("Wrong Audio Command emitted.");
+        }
+
+        exec_tx.send(ExecutorCommand::ResumeAll).await.unwrap();
+
+        if let Some(AudioCommand::Resume { id }) = audio_rx.recv().await {
+            assert_eq!(id, instance_id);
+        } else {
+            panic!("Wrong Audio Command emitted.");
+        }
+    }
+
+    #[tokio::test]
+    async fn pause_n_resume_cue_command() {
+        let cue_id = Uuid::new_v4();
+
+        let (_, exec_tx, mut audio_rx, _, _) = setup_executor(cue_id).await;
+
+        exec_tx.send(ExecutorCommand::ExecuteCue(cue_id)).await.unwrap();
+        let Some(AudioCommand::Play { id: instance_id, .. }) = audio_rx.recv().await else {
+            unreachable!();
+        };
+
+        exec_tx.send(ExecutorCommand::PauseCue(cue_id)).await.unwrap();
+        assert!(matches!(audio_rx.recv().await, Some(AudioCommand::Pause { id }) if id == instance_id));
+
+        exec_tx.send(ExecutorCommand::ResumeCue(cue_id)).await.unwrap();
+        assert!(matches!(audio_rx.recv().await, Some(AudioCommand::Resume { id }) if id == instance_id));
+
+        // 再生していないキューは無視する
+        exec_tx.send(ExecutorCommand::PauseCue(Uuid::new_v4())).await.unwrap();
+        exec_tx.send(ExecutorCommand::StopCue(cue_id)).await.unwrap();
+        assert!(matches!(audio_rx.recv().await, Some(AudioCommand::Stop { id, .. }) if id == instance_id));
+    }
+
+    #[tokio::test]
+    async fn default_fade_params_from_settings() {
+        let cue_id = Uuid::new_v4();
+        let plain_cue_id = Uuid::new_v4();
+
+        let (manager, exec_tx, mut audio_rx, _, _) = setup_executor(cue_id).await;
+        let default_fade_in_param = AudioCueFadeParam { duration: 0.5, easing: kira::Easing::Linear.into() };
+        let default_fade_out_param = AudioCueFadeParam { duration: 1.5, easing: kira::Easing::OutPowi(2).into() };
+        manager
+            .write_with(|model| {
+                let mut cue = model.cues[0].clone();
+                cue.id = plain_cue_id;
+                if let model::cue::CueParam::Audio { fade_in_param, fade_out_param, .. } = &mut cue.param {
+                    *fade_in_param = None;
+                    *fade_out_param = None;
+                }
+                model.cues.push(cue);
+                model.settings.general.default_fade_in_param = Some(default_fade_in_param.clone());
+                model.settings.general.default_fade_out_param = Some(default_fade_out_param.clone());
+            })
+            .await;
+
+        exec_tx.send(ExecutorCommand::ExecuteCue(plain_cue_id)).await.unwrap();
+        if let Some(AudioCommand::Play { data, .. }) = audio_rx.recv().await {
+            assert_eq!(data.fade_in_param, Some(default_fade_in_param));
+            assert_eq!(data.fade_out_param, Some(default_fade_out_param));
+        } else {
+            panic!("Wrong Audio Command emitted.");
+        }
+
+        // キューで指定したフェードが優先される
+        exec_tx.send(ExecutorCommand::ExecuteCue(cue_id)).await.unwrap();
+        if let Some(AudioCommand::Play { data, .. }) = audio_rx.recv().await {
+            assert_eq!(data.fade_in_param, Some(AudioCueFadeParam { duration: 2.0, easing: kira::Easing::Linear.into() }));
+            assert_eq!(data.fade_out_param, Some(AudioCueFadeParam { duration: 5.0, easing: kira::Easing::InPowi(2).into() }));
+        } else {
+            panic!("Wrong Audio Command emitted.");
+        }
+    }
+
+    #[tokio::test]
+    async fn stop_fade_from_cue_or_settings() {
+        let cue_id = Uuid::new_v4();
+
+        let (manager, exec_tx, mut audio_rx, _, _) = setup_executor(cue_id).await;
+        let default_stop_fade_param = AudioCueFadeParam { duration: 3.0, easing: kira::Easing::OutPowi(2).into() };
+        manager
+            .write_with(|model| model.settings.general.default_stop_fade_param = Some(default_stop_fade_param))
+            .await;
+
+        exec_tx.send(ExecutorCommand::ExecuteCue(cue_id)).await.unwrap();
+        assert!(matches!(audio_rx.recv().await, Some(AudioCommand::Play { .. })));
+        exec_tx.send(ExecutorCommand::StopCue(cue_id)).await.unwrap();
+        if let Some(AudioCommand::Stop { fade_out, easing, .. }) = audio_rx.recv().await {
+            assert_eq!((fade_out, easing), (Duration::from_secs(3), kira::Easing::OutPowi(2)));
+        } else {
+            panic!("Wrong Audio Command emitted.");
+        }
+
+        // キューで指定したフェードが優先される
+        manager
+            .write_with(|model| {
+                if let model::cue::CueParam::Audio { stop_fade_param, .. } = &mut model.cues[0].param {
+                    *stop_fade_param = Some(AudioCueFadeParam { duration: 1.5, easing: kira::Easing::Linear.into() });
+                }
+            })
+            .await;
+        exec_tx.send(ExecutorCommand::StopCue(cue_id)).await.unwrap();
+        if let Some(AudioCommand::Stop { fade_out, easing, .. }) = audio_rx.recv().await {
+            assert_eq!((fade_out, easing), (Duration::from_secs_f64(1.5), kira::Easing::Linear));
+        } else {
+            panic!("Wrong Audio Command emitted.");
+        }
+    }
+
+    #[tokio::test]
+    async fn output_device_from_settings() {
+        let cue_id = Uuid::new_v4();
+
+        let (manager, exec_tx, mut audio_rx, _, _) = setup_executor(cue_id).await;
+        manager
+            .write_with(|model| model.settings.general.output_device = Some("Main Out".to_string()))
+            .await;
+
+        for _ in 0..2 {
+            exec_tx
+                .send(ExecutorCommand::ExecuteCue(cue_id))
+                .await
+                .unwrap();
+        }
+
+        if let Some(AudioCommand::SetOutputDevice { device }) = audio_rx.recv().await {
+            assert_eq!(device, Some("Main Out".to_string()));
+        } else {
+            panic!("Wrong Audio Command emitted.");
+        }
+        assert!(matches!(audio_rx.recv().await, Some(AudioCommand::Play { .. })));
+        // 2回目は前のインスタンスを停止してから再生し、出力デバイスは切り替えない
+        assert!(matches!(audio_rx.recv().await, Some(AudioCommand::Stop { .. })));
+        assert!(matches!(audio_rx.recv().await, Some(AudioCommand::Play { .. })));
+    }
+
+    #[tokio::test]
+    async fn max_voices_from_settings() {
+        let cue_id = Uuid::new_v4();
+
+        let (manager, exec_tx, mut audio_rx, _, _) = setup_executor(cue_id).await;
+        manager
+            .write_with(|model| model.settings.general.max_voices = Some(4))
+            .await;
+
+        exec_tx.send(ExecutorCommand::ExecuteCue(cue_id)).await.unwrap();
+
+        if let Some(AudioCommand::SetMaxVoices { limit }) = audio_rx.recv().await {
+            assert_eq!(limit, Some(4));
+        } else {
+            panic!("Wrong Audio Command emitted.");
+        }
+        assert!(matches!(audio_rx.recv().await, Some(AudioCommand::Play { .. })));
+    }
+
+    #[tokio::test]
+    async fn cancel_fade_command() {
+        let cue_id = Uuid::new_v4();
+
+        let (_, exec_tx, mut audio_rx, _, _) = setup_executor(cue_id).await;
+
+        exec_tx.send(ExecutorCommand::ExecuteCue(cue_id)).await.unwrap();
+        let instance_id = if let Some(AudioCommand::Play { id, .. }) = audio_rx.recv().await {
+            id
+        } else {
+            unreachable!();
+        };
+
+        exec_tx.send(ExecutorCommand::CancelFade(cue_id)).await.unwrap();
+
+        if let Some(AudioCommand::CancelFade { id }) = audio_rx.recv().await {
+            assert_eq!(id, instance_id);
+        } else {
+            panic!("Wrong Audio Command emitted.");
+        }
+    }
+
+    #[tokio::test]
+    async fn release_loop_command() {
+        let cue_id = Uuid::new_v4();
+
+        let (_, exec_tx, mut audio_rx, _, _) = setup_executor(cue_id).await;
+
+        exec_tx.send(ExecutorCommand::ExecuteCue(cue_id)).await.unwrap();
+        let Some(AudioCommand::Play { id: instance_id, .. }) = audio_rx.recv().await else {
+            unreachable!();
+        };
+
+        exec_tx.send(ExecutorCommand::ReleaseLoop(cue_id)).await.unwrap();
+        assert!(matches!(audio_rx.recv().await, Some(AudioCommand::ReleaseLoop { id }) if id == instance_id));
+    }
+
+    #[tokio::test]
+    async fn replace_media_command() {
+        let cue_id = Uuid::new_v4();
+
+        let (_, exec_tx, mut audio_rx, _, _) = setup_executor(cue_id).await;
+
+        exec_tx.send(ExecutorCommand::ExecuteCue(cue_id)).await.unwrap();
+        let instance_id = if let Some(AudioCommand::Play { id, .. }) = audio_rx.recv().await {
+            id
+        } else {
+            unreachable!();
+        };
+
+        exec_tx
+            .send(ExecutorCommand::ReplaceMedia { cue_id, new_target: PathBuf::from("./I.G.Y_v2.flac") })
+            .await
+            .unwrap();
+
+        if let Some(AudioCommand::ReplaceMedia { id, filepath }) = audio_rx.recv().await {
+            assert_eq!(id, instance_id);
+            assert_eq!(filepath, PathBuf::from("./I.G.Y_v2.flac"));
+        } else {
+            panic!("Wrong Audio Command emitted.");
+        }
+    }
+
+    #[tokio::test]
+    async fn set_levels_many_command() {
+        let cue_id = Uuid::new_v4();
+
+        let (_, exec_tx, mut audio_rx, _, _) = setup_executor(cue_id).await;
+
+        // 同じキューを2回実行すると、後のインスタンスだけが残る
+        let mut instance_ids = Vec::new();
+        for _ in 0..2 {
+            exec_tx
+                .send(ExecutorCommand::ExecuteCue(cue_id))
+                .await
+                .unwrap();
+            loop {
+                match audio_rx.recv().await {
+                    Some(AudioCommand::Play { id, .. }) => {
+                        instance_ids = vec![id];
+                        break;
+                    }
+                    Some(AudioCommand::Stop { .. }) => {}
+                    command => panic!("Wrong Audio Command emitted: {:?}", command),
+                }
+            }
+        }
+
+        exec_tx
+            .send(ExecutorCommand::SetLevelsMany {
+                changes: vec![
+                    (cue_id, AudioCueLevels { master: -6.0 }, 3.0, kira::Easing::Linear.into()),
+                    (Uuid::new_v4(), AudioCueLevels { master: -12.0 }, 3.0, kira::Easing::Linear.into()),
+                ],
+            })
+            .await
+            .unwrap();
+
+        if let Some(AudioCommand::SetLevelsMany { changes }) = audio_rx.recv().await {
+            let mut ids: Vec<Uuid> = changes.iter().map(|(id, ..)| *id).collect();
+            ids.sort();
+            assert_eq!(ids, instance_ids);
+            for (_, levels, duration, easing) in changes {
+                assert_eq!(levels, AudioCueLevels { master: -6.0 });
+                assert_eq!(duration, 3.0);
+                assert_eq!(easing, kira::Easing::Linear.into());
+            }
+        } else {
+            panic!("Wrong Audio Command emitted.");
+        }
+    }
+
+    #[tokio::test]
+    async fn shutdown_flushes_engine_events() {
+        let cue_id = Uuid::new_v4();
+        let (exec_tx, exec_rx) = mpsc::channel::<ExecutorCommand>(32);
+        let (audio_tx, mut audio_rx) = mpsc::channel::<AudioCommand>(32);
+        let (playback_event_tx, mut playback_event_rx) = mpsc::channel::<ExecutorEvent>(32);
+        let (engine_event_tx, engine_event_rx) = mpsc::channel::<EngineEvent>(32);
+        let (event_tx, _) = broadcast::channel::<UiEvent>(32);
+        let (manager, handle) = ShowModelManager::new(event_tx);
+        manager
+            .write_with(|model| {
+                model.cues.push(Cue {
+                    id: cue_id,
+                    number: "1".to_string(),
+                    name: "Play IGY".to_string(),
+                    notes: "".to_string(),
+                    pre_wait: 0.0,
+                    post_wait: 0.0,
+                    sequence: model::cue::CueSequence::DoNotContinue,
+                    follow_offset: None,
+                    armed: true,
+                    retrigger_mode: RetriggerMode::Restart,
+                    color: None,
+                    order: 0.0,
+                    schedule: None,
+                    param: model::cue::CueParam::Audio {
+                        target: PathBuf::from("./I.G.Y.flac"),
+                        start_time: None,
+                        fade_in_param: None,
+                        end_time: None,
+                        fade_out_param: None,
+                        levels: AudioCueLevels { master: 0.0 },
+                        loop_region: None,
+                        loop_count: None,
+                        stop_fade_param: None,
+                        output_bus: None,
+                        streaming: false,
+                        normalize_to: None,
+                        automation: None,
+                    },
+                });
+            })
+            .await;
+
+        let cancel_token = CancellationToken::new();
+        let executor = Executor::new(handle, exec_rx, audio_tx, playback_event_tx, engine_event_rx);
+        let task = tokio::spawn(executor.run(cancel_token.clone()));
+
+        exec_tx
+            .send(ExecutorCommand::ExecuteCue(cue_id))
+            .await
+            .unwrap();
+        let instance_id = if let Some(AudioCommand::Play { id, .. }) = audio_rx.recv().await {
+            id
+        } else {
+            unreachable!();
+        };
+
+        cancel_token.cancel();
+        let report = PlaybackReport { played_duration: 1.0, peak_level: -6.0, was_stopped_manually: true };
+        engine_event_tx.send(EngineEvent::Audio(AudioEngineEvent::Completed { instance_id, report })).await.unwrap();
+        drop(engine_event_tx);
+
+        task.await.unwrap();
+        assert!(matches!(playback_event_rx.recv().await, Some(ExecutorEvent::Completed { cue_id: id, .. }) if id == cue_id));
+        assert!(playback_event_rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn stop_command() {
+        let cue_id = Uuid::new_v4();
+
+        let (_, exec_tx, mut audio_rx, _, _) = setup_executor(cue_id).await;
+
+        exec_tx
+            .send(ExecutorCommand::ExecuteCue(cue_id))
+            .await
+            .unwrap();
+
+        let instance_id = if let Some(AudioCommand::Play { id, .. }) = audio_rx.recv().await {
+            id
+        } else {
+            unreachable!();
+        };
+
+        exec_tx
+            .send(ExecutorCommand::StopCue(cue_id))
+            .await
+            .unwrap();
+
+        if let Some(AudioCommand::Stop { id, .. }) = audio_rx.recv().await {
+            assert_eq!(id, instance_id);
+        } else {
+            panic!("Wrong Audio Command emitted.");
+        }
+    }
+
+    #[tokio::test]
+    async fn panic_command() {
+        let cue_id = Uuid::new_v4();
+
+        let (_, exec_tx, mut audio_rx, engine_event_tx, mut playback_event_rx) = setup_executor(cue_id).await;
+
+        exec_tx.send(ExecutorCommand::ExecuteCue(cue_id)).await.unwrap();
+        let Some(AudioCommand::Play { id: instance_id, .. }) = audio_rx.recv().await else {
+            panic!("Wrong Audio Command emitted.");
+        };
+
+        // 停止フェードが設定されていてもすぐに停止する
+        exec_tx.send(ExecutorCommand::Panic).await.unwrap();
+        if let Some(AudioCommand::Stop { id, fade_out, .. }) = audio_rx.recv().await {
+            assert_eq!(id, instance_id);
+            assert_eq!(fade_out, Duration::ZERO);
+        } else {
+            panic!("Wrong Audio Command emitted.");
+        }
+
+        // 停止後に届いた完了の通知は転送しない
+        let report = PlaybackReport { played_duration: 1.0, peak_level: -6.0, was_stopped_manually: true };
+        engine_event_tx
+            .send(EngineEvent::Audio(AudioEngineEvent::Completed { instance_id, report }))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(playback_event_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn retrigger_restarts_cue() {
+        let cue_id = Uuid::new_v4();
+
+        let (_, exec_tx, mut audio_rx, engine_event_tx, mut playback_event_rx) = setup_executor(cue_id).await;
+
+        exec_tx.send(ExecutorCommand::ExecuteCue(cue_id)).await.unwrap();
+        let Some(AudioCommand::Play { id: first_id, .. }) = audio_rx.recv().await else {
+            panic!("Wrong Audio Command emitted.");
+        };
+
+        // 再生中に同じキューを実行すると、前のインスタンスをすぐに停止してから再生する
+        exec_tx.send(ExecutorCommand::ExecuteCue(cue_id)).await.unwrap();
+        if let Some(AudioCommand::Stop { id, fade_out, .. }) = audio_rx.recv().await {
+            assert_eq!(id, first_id);
+            assert_eq!(fade_out, Duration::ZERO);
+        } else {
+            panic!("Wrong Audio Command emitted.");
+        }
+        let Some(AudioCommand::Play { id: second_id, .. }) = audio_rx.recv().await else {
+            panic!("Wrong Audio Command emitted.");
+        };
+        assert_ne!(first_id, second_id);
+
+        // 前のインスタンスのイベントは転送しない
+        let report = PlaybackReport { played_duration: 1.0, peak_level: -6.0, was_stopped_manually: true };
+        engine_event_tx
+            .send(EngineEvent::Audio(AudioEngineEvent::Completed { instance_id: first_id, report }))
+            .await
+            .unwrap();
+        engine_event_tx.send(EngineEvent::Audio(AudioEngineEvent::Started { instance_id: second_id })).await.unwrap();
+        assert!(matches!(playback_event_rx.recv().await, Some(ExecutorEvent::Started { cue_id: id }) if id == cue_id));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(playback_event_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn query_active_instances() {
+        let cue_id = Uuid::new_v4();
+
+        let (_, exec_tx, mut audio_rx, _, _) = setup_executor(cue_id).await;
+
+        exec_tx.send(ExecutorCommand::ExecuteCue(cue_id)).await.unwrap();
+        let Some(AudioCommand::Play { id: instance_id, .. }) = audio_rx.recv().await else {
+            panic!("Wrong Audio Command emitted.");
+        };
+
+        let (reply_tx, reply_rx) = oneshot::channel();
+        exec_tx.send(ExecutorCommand::QueryActive { reply: reply_tx }).await.unwrap();
+        assert_eq!(reply_rx.await.unwrap(), HashMap::from([(instance_id, cue_id)]));
+        // 問い合わせではAudioEngineにコマンドを送らない
+        assert!(audio_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn crossfade_command() {
+        let from_cue_id = Uuid::new_v4();
+        let to_cue_id = Uuid::new_v4();
+
+        let (manager, exec_tx, mut audio_rx, _, _) = setup_executor(from_cue_id).await;
+        manager
+            .write_with(|model| {
+                let to_cue = Cue { id: to_cue_id, ..model.cues[0].clone() };
+                model.cues.push(to_cue);
+            })
+            .await;
+
+        // 再生中でない場合は、フェードインのみ行う
+        exec_tx.send(ExecutorCommand::Crossfade { from_cue_id, to_cue_id, duration: 3.0, easing: Easing::Linear.into() }).await.unwrap();
+        let Some(AudioCommand::Play { id: to_instance_id, data }) = audio_rx.recv().await else {
+            panic!("Wrong Audio Command emitted.");
+        };
+        assert_eq!(data.fade_in_param, Some(AudioCueFadeParam { duration: 3.0, easing: Easing::Linear.into() }));
 
-        self.active_instances
-            .write()
-            .await
-            .insert(instance_id, cue.id);
-        Ok(())
+        exec_tx.send(ExecutorCommand::Crossfade { from_cue_id: to_cue_id, to_cue_id: from_cue_id, duration: 1.5, easing: Easing::OutPowi(2).into() }).await.unwrap();
+        if let Some(AudioCommand::Stop { id, fade_out, easing }) = audio_rx.recv().await {
+            assert_eq!(id, to_instance_id);
+            assert_eq!(fade_out, Duration::from_secs_f64(1.5));
+            assert_eq!(easing, Easing::OutPowi(2));
+        } else {
+            panic!("Wrong Audio Command emitted.");
+        }
+        let Some(AudioCommand::Play { data, .. }) = audio_rx.recv().await else {
+            panic!("Wrong Audio Command emitted.");
+        };
+        assert_eq!(data.fade_in_param, Some(AudioCueFadeParam { duration: 1.5, easing: Easing::OutPowi(2).into() }));
     }
 
-    async fn handle_engine_event(&self, event: EngineEvent) -> Result<(), anyhow::Error> {
-        match event {
-            EngineEvent::Audio(audio_event) => {
-                let instance_id = audio_event.instance_id();
+    #[tokio::test]
+    async fn audition_cue_command() {
+        let cue_id = Uuid::new_v4();
 
-                let instances = self.active_instances.read().await;
-                let Some(cue_id) = instances.get(&instance_id).cloned() else {
-                    log::warn!("Received event for unknown instance_id: {}", instance_id);
-                    return Ok(());
-                };
+        let (_, exec_tx, mut audio_rx, engine_event_tx, mut playback_event_rx) = setup_executor(cue_id).await;
 
-                let playback_event = match audio_event {
-                    AudioEngineEvent::Started { .. } => ExecutorEvent::Started { cue_id },
-                    AudioEngineEvent::Progress {
-                        position, duration, ..
-                    } => ExecutorEvent::Progress {
-                        cue_id,
-                        position,
-                        duration,
-                    },
-                    AudioEngineEvent::Paused {
-                        position, duration, ..
-                    } => ExecutorEvent::Paused {
-                        cue_id,
-                        position,
-                        duration,
-                    },
-                    AudioEngineEvent::Resumed { .. } => ExecutorEvent::Resumed { cue_id },
-                    AudioEngineEvent::Completed { .. } => {
-                        drop(instances);
-                        self.active_instances.write().await.remove(&instance_id);
-                        ExecutorEvent::Completed { cue_id }
-                    }
-                    AudioEngineEvent::Error { error, .. } => {
-                        drop(instances);
-                        self.active_instances.write().await.remove(&instance_id);
-                        ExecutorEvent::Error { cue_id, error }
-                    }
-                };
+        exec_tx.send(ExecutorCommand::AuditionCue(cue_id)).await.unwrap();
+        let Some(AudioCommand::Play { id: instance_id, data }) = audio_rx.recv().await else {
+            panic!("Wrong Audio Command emitted.");
+        };
+        assert_eq!(data.levels, AudioCueLevels { master: AUDITION_LEVEL_OFFSET });
+        assert_eq!(data.loop_region, None);
 
-                self.playback_event_tx.send(playback_event).await?;
-            }
-        }
-        Ok(())
+        engine_event_tx.send(EngineEvent::Audio(AudioEngineEvent::Started { instance_id })).await.unwrap();
+        assert!(matches!(playback_event_rx.recv().await, Some(ExecutorEvent::AuditionStarted { cue_id: id }) if id == cue_id));
+
+        // 試聴は再生中のインスタンスに含めない
+        let (reply, reply_rx) = oneshot::channel();
+        exec_tx.send(ExecutorCommand::QueryActive { reply }).await.unwrap();
+        assert!(reply_rx.await.unwrap().is_empty());
+
+        let report = PlaybackReport { played_duration: 5.0, peak_level: -12.0, was_stopped_manually: true };
+        engine_event_tx.send(EngineEvent::Audio(AudioEngineEvent::Completed { instance_id, report })).await.unwrap();
+        assert!(matches!(playback_event_rx.recv().await, Some(ExecutorEvent::AuditionCompleted { cue_id: id }) if id == cue_id));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::{path::PathBuf};
+    #[tokio::test]
+    async fn fade_out_all_command() {
+        let cue_id = Uuid::new_v4();
 
-    use kira::sound::Region;
-    use tokio::sync::{broadcast, mpsc::{self, Receiver, Sender}};
-    use uuid::Uuid;
+        let (_, exec_tx, mut audio_rx, _, _) = setup_executor(cue_id).await;
 
-    use crate::{
-        engine::audio_engine::{AudioCommand, AudioEngineEvent}, event::UiEvent, manager::ShowModelManager, model::{
-            self,
-            cue::{AudioCueFadeParam, AudioCueLevels, Cue},
+        exec_tx.send(ExecutorCommand::ExecuteCue(cue_id)).await.unwrap();
+        let Some(AudioCommand::Play { id: instance_id, .. }) = audio_rx.recv().await else {
+            panic!("Wrong Audio Command emitted.");
+        };
+
+        exec_tx.send(ExecutorCommand::FadeOutAll { duration: 0.05, easing: Easing::Linear.into() }).await.unwrap();
+        if let Some(AudioCommand::SetLevelsMany { changes }) = audio_rx.recv().await {
+            assert_eq!(changes.len(), 1);
+            assert_eq!(changes[0].0, instance_id);
+            assert_eq!(changes[0].1.master, kira::Decibels::SILENCE.0 as f64);
+            assert_eq!(changes[0].2, 0.05);
+        } else {
+            panic!("Wrong Audio Command emitted.");
         }
-    };
+        // フェードが終わったら停止する
+        if let Some(AudioCommand::Stop { id, fade_out, .. }) = audio_rx.recv().await {
+            assert_eq!(id, instance_id);
+            assert_eq!(fade_out, Duration::ZERO);
+        } else {
+            panic!("Wrong Audio Command emitted.");
+        }
+    }
 
-    async fn setup_executor(cue_id: Uuid) -> (ShowModelManager, Sender<ExecutorCommand>, Receiver<AudioCommand>, Sender<EngineEvent>, Receiver<ExecutorEvent>) {
-        let (exec_tx, exec_rx) = mpsc::channel::<ExecutorCommand>(32);
-        let (audio_tx, audio_rx) = mpsc::channel::<AudioCommand>(32);
-        let (playback_event_tx, playback_event_rx) = mpsc::channel::<ExecutorEvent>(32);
-        let (engine_event_tx, engine_event_rx) = mpsc::channel::<EngineEvent>(32);
-        let (event_tx, _) = broadcast::channel::<UiEvent>(32);
+    #[tokio::test]
+    async fn go_interrupts_fade_out_all() {
+        let cue_id = Uuid::new_v4();
 
-        let (manager, handle) = ShowModelManager::new(event_tx.clone());
+        let (manager, exec_tx, mut audio_rx, _, _) = setup_executor(cue_id).await;
+        let next_cue_id = Uuid::new_v4();
         manager
             .write_with(|model| {
-                model.name = "TestShowModel".to_string();
-                model.cues.push(Cue {
-                    id: cue_id,
-                    number: "1".to_string(),
-                    name: "Play IGY".to_string(),
-                    notes: "".to_string(),
-                    pre_wait: 0.0,
-                    post_wait: 0.0,
-                    sequence: model::cue::CueSequence::DoNotContinue,
-                    param: model::cue::CueParam::Audio {
-                        target: PathBuf::from("./I.G.Y.flac"),
-                    start_time: Some(5.0),
-                    fade_in_param: Some(AudioCueFadeParam {
-                        duration: 2.0,
-                        easing: kira::Easing::Linear,
-                    }),
-                    end_time: Some(50.0),
-                    fade_out_param: Some(AudioCueFadeParam {
-                        duration: 5.0,
-                        easing: kira::Easing::InPowi(2),
-                    }),
-                    levels: AudioCueLevels { master: 0.0 },
-                    loop_region: Some(Region { start: kira::sound::PlaybackPosition::Seconds(2.0), end: kira::sound::EndPosition::EndOfAudio }),
-                    },
-                });
-                cue_id
+                let mut next_cue = model.cues[0].clone();
+                next_cue.id = next_cue_id;
+                model.cues.push(next_cue);
             })
             .await;
 
-        let executor = Executor::new(
-            handle.clone(),
-            exec_rx,
-            audio_tx,
-            playback_event_tx,
-            engine_event_rx,
-        );
-
-        tokio::spawn(executor.run());
-
-        (manager, exec_tx, audio_rx, engine_event_tx, playback_event_rx)
+        exec_tx.send(ExecutorCommand::ExecuteCue(cue_id)).await.unwrap();
+        let Some(AudioCommand::Play { id: instance_id, .. }) = audio_rx.recv().await else {
+            panic!("Wrong Audio Command emitted.");
+        };
+        exec_tx.send(ExecutorCommand::FadeOutAll { duration: 60.0, easing: Easing::Linear.into() }).await.unwrap();
+        assert!(matches!(audio_rx.recv().await, Some(AudioCommand::SetLevelsMany { .. })));
+
+        // 次のキューを実行すると、フェード中のサウンドだけをすぐに停止する
+        exec_tx.send(ExecutorCommand::ExecuteCue(next_cue_id)).await.unwrap();
+        let mut stopped = Vec::new();
+        let mut played = Vec::new();
+        for _ in 0..2 {
+            match tokio::time::timeout(Duration::from_secs(1), audio_rx.recv()).await.unwrap() {
+                Some(AudioCommand::Stop { id, .. }) => stopped.push(id),
+                Some(AudioCommand::Play { id, .. }) => played.push(id),
+                command => panic!("Wrong Audio Command emitted: {:?}", command),
+            }
+        }
+        assert_eq!(stopped, vec![instance_id]);
+        assert_eq!(played.len(), 1);
+        assert_ne!(played[0], instance_id);
     }
-    
 
     #[tokio::test]
-    async fn play_command() {
+    async fn preview_mode_does_not_play_audio() {
         let cue_id = Uuid::new_v4();
 
-        let (_, exec_tx, mut audio_rx, _, _) = setup_executor(cue_id).await;
+        let (_, exec_tx, mut audio_rx, _, mut playback_event_rx) = setup_executor(cue_id).await;
 
-        let old_id = Uuid::now_v7();
+        exec_tx.send(ExecutorCommand::SetPreviewMode(true)).await.unwrap();
+        exec_tx.send(ExecutorCommand::ExecuteCue(cue_id)).await.unwrap();
 
-        exec_tx
-            .send(ExecutorCommand::ExecuteCue(cue_id))
-            .await
-            .unwrap();
+        assert!(matches!(playback_event_rx.recv().await, Some(ExecutorEvent::Started { cue_id: id }) if id == cue_id));
+        // 音声ファイルの長さが分からない場合はすぐに終了する
+        loop {
+            match playback_event_rx.recv().await {
+                Some(ExecutorEvent::Progress { .. }) => {}
+                Some(ExecutorEvent::Completed { cue_id: id, report }) => {
+                    assert_eq!(id, cue_id);
+                    assert!(!report.was_stopped_manually);
+                    break;
+                }
+                event => panic!("Wrong Playback Event emitted: {:?}", event),
+            }
+        }
+        assert!(audio_rx.try_recv().is_err());
 
-        let command = audio_rx.recv().await.unwrap();
+        exec_tx.send(ExecutorCommand::SetPreviewMode(false)).await.unwrap();
+        exec_tx.send(ExecutorCommand::ExecuteCue(cue_id)).await.unwrap();
+        assert!(matches!(audio_rx.recv().await, Some(AudioCommand::Play { .. })));
+    }
 
-        if let AudioCommand::Play { id, data } = command {
-            assert!(id > old_id);
-            let now_id = Uuid::now_v7();
-            assert!(id < now_id);
-            assert_eq!(data.filepath, PathBuf::from("./I.G.Y.flac"));
-            assert_eq!(data.levels, AudioCueLevels { master: 0.0 });
-            assert_eq!(data.start_time, Some(5.0));
-            assert_eq!(data.fade_in_param, Some(AudioCueFadeParam { duration: 2.0, easing: kira::Easing::Linear }));
-            assert_eq!(data.end_time, Some(50.0));
-            assert_eq!(data.fade_out_param, Some(AudioCueFadeParam { duration: 5.0, easing: kira::Easing::InPowi(2) }));
-            assert_eq!(data.loop_region, Some(Region { start: kira::sound::PlaybackPosition::Seconds(2.0), end: kira::sound::EndPosition::EndOfAudio }));
-        } else {
-            unreachable!();
+    #[tokio::test]
+    async fn stop_preview_playback() {
+        let (exec_tx, exec_rx) = mpsc::channel::<ExecutorCommand>(32);
+        let (audio_tx, mut audio_rx) = mpsc::channel::<AudioCommand>(32);
+        let (playback_event_tx, mut playback_event_rx) = mpsc::channel::<ExecutorEvent>(32);
+        let (_engine_event_tx, engine_event_rx) = mpsc::channel::<EngineEvent>(32);
+        let (_, handle) = ShowModelManager::new(broadcast::channel::<UiEvent>(32).0);
+        let cue_id = Uuid::new_v4();
+        let executor = Executor::new(handle, exec_rx, audio_tx, playback_event_tx, engine_event_rx);
+        executor.spawn_preview_playback(cue_id, Uuid::now_v7(), 10.0, 0.0);
+        tokio::spawn(executor.run(CancellationToken::new()));
+
+        assert!(matches!(playback_event_rx.recv().await, Some(ExecutorEvent::Started { cue_id: id }) if id == cue_id));
+        exec_tx.send(ExecutorCommand::StopCue(cue_id)).await.unwrap();
+        loop {
+            match playback_event_rx.recv().await {
+                Some(ExecutorEvent::Progress { .. }) => {}
+                Some(ExecutorEvent::Completed { cue_id: id, report }) => {
+                    assert_eq!(id, cue_id);
+                    assert!(report.was_stopped_manually);
+                    break;
+                }
+                event => panic!("Wrong Playback Event emitted: {:?}", event),
+            }
         }
+        assert!(audio_rx.try_recv().is_err());
     }
 
     #[tokio::test]
@@ -350,6 +2109,9 @@ mod tests {
             unreachable!();
         };
 
+        engine_event_tx.send(EngineEvent::Audio(AudioEngineEvent::Loading { instance_id })).await.unwrap();
+        assert!(matches!(playback_event_rx.recv().await, Some(ExecutorEvent::Loading { cue_id }) if cue_id == orig_cue_id));
+
         engine_event_tx.send(EngineEvent::Audio(AudioEngineEvent::Started { instance_id })).await.unwrap();
 
         if let Some(event) = playback_event_rx.recv().await {
@@ -482,11 +2244,13 @@ mod tests {
             unreachable!();
         };
 
-        engine_event_tx.send(EngineEvent::Audio(AudioEngineEvent::Completed { instance_id })).await.unwrap();
+        let report = PlaybackReport { played_duration: 45.0, peak_level: -6.0, was_stopped_manually: false };
+        engine_event_tx.send(EngineEvent::Audio(AudioEngineEvent::Completed { instance_id, report: report.clone() })).await.unwrap();
 
         if let Some(event) = playback_event_rx.recv().await {
-            if let ExecutorEvent::Completed {cue_id } = event {
+            if let ExecutorEvent::Completed { cue_id, report: received_report } = event {
                 assert_eq!(cue_id, orig_cue_id);
+                assert_eq!(received_report, report);
             } else {
                 panic!("Wrong Playback Event emitted.");
             }
@@ -514,17 +2278,326 @@ mod tests {
             unreachable!();
         };
 
-        engine_event_tx.send(EngineEvent::Audio(AudioEngineEvent::Error { instance_id, error: "Error".to_string() })).await.unwrap();
+        engine_event_tx.send(EngineEvent::Audio(AudioEngineEvent::Error { instance_id, error: "Error".to_string(), kind: CueErrorKind::MediaUnavailable })).await.unwrap();
 
         if let Some(event) = playback_event_rx.recv().await {
-            if let ExecutorEvent::Error {cue_id, error } = event {
+            if let ExecutorEvent::Error {cue_id, error, kind } = event {
                 assert_eq!(cue_id, orig_cue_id);
                 assert_eq!(error, "Error".to_string());
+                assert_eq!(kind, CueErrorKind::MediaUnavailable);
+            } else {
+                panic!("Wrong Playback Event emitted.");
+            }
+        } else {
+            unreachable!();
+        }
+    }
+
+    fn make_cue(cue_id: Uuid, param: model::cue::CueParam) -> Cue {
+        Cue {
+            id: cue_id,
+            number: "".to_string(),
+            name: "".to_string(),
+            notes: "".to_string(),
+            pre_wait: 0.0,
+            post_wait: 0.0,
+            sequence: model::cue::CueSequence::DoNotContinue,
+            follow_offset: None,
+            armed: true,
+            retrigger_mode: RetriggerMode::Restart,
+            color: None,
+            order: 0.0,
+            schedule: None,
+            param,
+        }
+    }
+
+    #[tokio::test]
+    async fn group_cue() {
+        let audio_cue_id = Uuid::new_v4();
+        let group_id = Uuid::new_v4();
+        let inner_group_id = Uuid::new_v4();
+        let wait_cue_id = Uuid::new_v4();
+
+        let (manager, exec_tx, mut audio_rx, engine_event_tx, mut playback_event_rx) = setup_executor(audio_cue_id).await;
+        manager
+            .write_with(|model| {
+                model.cues.push(make_cue(group_id, model::cue::CueParam::Group { children: vec![audio_cue_id, inner_group_id] }));
+                // 外側のグループへの循環参照は無視される
+                model.cues.push(make_cue(inner_group_id, model::cue::CueParam::Group { children: vec![wait_cue_id, group_id] }));
+                model.cues.push(make_cue(wait_cue_id, model::cue::CueParam::Wait { duration: 0.01 }));
+            })
+            .await;
+
+        exec_tx.send(ExecutorCommand::ExecuteCue(group_id)).await.unwrap();
+
+        let instance_id = if let Some(AudioCommand::Play { id, .. }) = audio_rx.recv().await {
+            id
+        } else {
+            unreachable!();
+        };
+        let mut started = HashSet::new();
+        for _ in 0..3 {
+            if let Some(ExecutorEvent::Started { cue_id }) = playback_event_rx.recv().await {
+                started.insert(cue_id);
             } else {
                 panic!("Wrong Playback Event emitted.");
             }
+        }
+        assert_eq!(started, HashSet::from([group_id, inner_group_id, wait_cue_id]));
+
+        // Waitキューの終了で内側のグループが終了する
+        assert!(matches!(playback_event_rx.recv().await, Some(ExecutorEvent::Completed { cue_id, .. }) if cue_id == wait_cue_id));
+        assert!(matches!(playback_event_rx.recv().await, Some(ExecutorEvent::Completed { cue_id, .. }) if cue_id == inner_group_id));
+
+        let report = PlaybackReport { played_duration: 45.0, peak_level: -6.0, was_stopped_manually: false };
+        engine_event_tx.send(EngineEvent::Audio(AudioEngineEvent::Completed { instance_id, report })).await.unwrap();
+
+        assert!(matches!(playback_event_rx.recv().await, Some(ExecutorEvent::Completed { cue_id, .. }) if cue_id == audio_cue_id));
+        if let Some(ExecutorEvent::Completed { cue_id, report }) = playback_event_rx.recv().await {
+            assert_eq!(cue_id, group_id);
+            assert_eq!(report.played_duration, 45.0);
+            assert_eq!(report.peak_level, -6.0);
+        } else {
+            panic!("Wrong Playback Event emitted.");
+        }
+    }
+
+    #[tokio::test]
+    async fn pre_wait_delays_start() {
+        let audio_cue_id = Uuid::new_v4();
+        let group_id = Uuid::new_v4();
+        let wait_cue_id = Uuid::new_v4();
+
+        let (manager, exec_tx, mut audio_rx, _, mut playback_event_rx) = setup_executor(audio_cue_id).await;
+        manager
+            .write_with(|model| {
+                model.cues[0].pre_wait = 0.5;
+                let mut group_cue = make_cue(group_id, model::cue::CueParam::Group { children: vec![audio_cue_id] });
+                group_cue.pre_wait = 0.25;
+                model.cues.push(group_cue);
+                let mut wait_cue = make_cue(wait_cue_id, model::cue::CueParam::Wait { duration: 0.01 });
+                wait_cue.pre_wait = 10.0;
+                model.cues.push(wait_cue);
+            })
+            .await;
+
+        exec_tx.send(ExecutorCommand::ExecuteCue(audio_cue_id)).await.unwrap();
+        let Some(AudioCommand::Play { data, .. }) = audio_rx.recv().await else {
+            unreachable!();
+        };
+        assert_eq!(data.start_delay, 0.5);
+
+        // グループの子キューは、グループと子キュー自身のpre_waitの後に開始する
+        exec_tx.send(ExecutorCommand::ExecuteCue(group_id)).await.unwrap();
+        // 再生中のインスタンスは先に停止される
+        assert!(matches!(audio_rx.recv().await, Some(AudioCommand::Stop { .. })));
+        let Some(AudioCommand::Play { data, .. }) = audio_rx.recv().await else {
+            unreachable!();
+        };
+        assert_eq!(data.start_delay, 0.75);
+        assert!(matches!(playback_event_rx.recv().await, Some(ExecutorEvent::Started { cue_id }) if cue_id == group_id));
+
+        // pre_waitの間に停止したキューは、開始せずに終了する
+        exec_tx.send(ExecutorCommand::ExecuteCue(wait_cue_id)).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(playback_event_rx.try_recv().is_err());
+        exec_tx.send(ExecutorCommand::StopCue(wait_cue_id)).await.unwrap();
+        if let Some(ExecutorEvent::Completed { cue_id, report }) = playback_event_rx.recv().await {
+            assert_eq!(cue_id, wait_cue_id);
+            assert!(report.was_stopped_manually);
         } else {
+            panic!("Wrong Playback Event emitted.");
+        }
+    }
+
+    #[tokio::test]
+    async fn stop_group_cue() {
+        let audio_cue_id = Uuid::new_v4();
+        let group_id = Uuid::new_v4();
+        let inner_group_id = Uuid::new_v4();
+        let wait_cue_id = Uuid::new_v4();
+
+        let (manager, exec_tx, mut audio_rx, engine_event_tx, mut playback_event_rx) = setup_executor(audio_cue_id).await;
+        manager
+            .write_with(|model| {
+                model.cues.push(make_cue(group_id, model::cue::CueParam::Group { children: vec![audio_cue_id, inner_group_id] }));
+                model.cues.push(make_cue(inner_group_id, model::cue::CueParam::Group { children: vec![wait_cue_id] }));
+                model.cues.push(make_cue(wait_cue_id, model::cue::CueParam::Wait { duration: 10.0 }));
+            })
+            .await;
+
+        exec_tx.send(ExecutorCommand::ExecuteCue(group_id)).await.unwrap();
+        let Some(AudioCommand::Play { id: instance_id, .. }) = audio_rx.recv().await else {
             unreachable!();
+        };
+        for _ in 0..3 {
+            assert!(matches!(playback_event_rx.recv().await, Some(ExecutorEvent::Started { .. })));
+        }
+
+        // グループを停止すると、内側のグループを含む全ての子キューが停止する
+        exec_tx.send(ExecutorCommand::StopCue(group_id)).await.unwrap();
+        assert!(matches!(audio_rx.recv().await, Some(AudioCommand::Stop { id, .. }) if id == instance_id));
+        if let Some(ExecutorEvent::Completed { cue_id, report }) = playback_event_rx.recv().await {
+            assert_eq!(cue_id, wait_cue_id);
+            assert!(report.was_stopped_manually);
+        } else {
+            panic!("Wrong Playback Event emitted.");
+        }
+        assert!(matches!(playback_event_rx.recv().await, Some(ExecutorEvent::Completed { cue_id, .. }) if cue_id == inner_group_id));
+
+        let report = PlaybackReport { played_duration: 1.0, peak_level: -6.0, was_stopped_manually: true };
+        engine_event_tx.send(EngineEvent::Audio(AudioEngineEvent::Completed { instance_id, report })).await.unwrap();
+        assert!(matches!(playback_event_rx.recv().await, Some(ExecutorEvent::Completed { cue_id, .. }) if cue_id == audio_cue_id));
+        if let Some(ExecutorEvent::Completed { cue_id, report }) = playback_event_rx.recv().await {
+            assert_eq!(cue_id, group_id);
+            assert!(report.was_stopped_manually);
+        } else {
+            panic!("Wrong Playback Event emitted.");
+        }
+    }
+
+    #[tokio::test]
+    async fn wait_for_input_and_stop_all() {
+        let (exec_tx, exec_rx) = mpsc::channel::<ExecutorCommand>(32);
+        let (audio_tx, _audio_rx) = mpsc::channel::<AudioCommand>(32);
+        let (playback_event_tx, mut playback_event_rx) = mpsc::channel::<ExecutorEvent>(32);
+        let (_engine_event_tx, engine_event_rx) = mpsc::channel::<EngineEvent>(32);
+        let (manager, handle) = ShowModelManager::new(broadcast::channel::<UiEvent>(32).0);
+        let note_cue_id = Uuid::new_v4();
+        let osc_cue_id = Uuid::new_v4();
+        manager
+            .write_with(|model| {
+                let source = model::cue::TriggerSource::MidiNote { channel: 1, note: 60 };
+                model.cues.push(make_cue(note_cue_id, model::cue::CueParam::WaitForInput { source }));
+                let source = model::cue::TriggerSource::Osc { address: "/sbsp/next".to_string() };
+                model.cues.push(make_cue(osc_cue_id, model::cue::CueParam::WaitForInput { source }));
+            })
+            .await;
+        let executor = Executor::new(handle, exec_rx, audio_tx, playback_event_tx, engine_event_rx);
+        let input_trigger_tx = executor.input_trigger_sender();
+        tokio::spawn(executor.run(CancellationToken::new()));
+
+        exec_tx.send(ExecutorCommand::ExecuteCue(note_cue_id)).await.unwrap();
+        exec_tx.send(ExecutorCommand::ExecuteCue(osc_cue_id)).await.unwrap();
+        for _ in 0..2 {
+            assert!(matches!(playback_event_rx.recv().await, Some(ExecutorEvent::Started { .. })));
+        }
+
+        // 一致しないトリガーでは終了せず、一致したキューのみ終了する
+        input_trigger_tx.send(InputTrigger::MidiNoteOn { channel: 1, note: 61, velocity: 100 }).unwrap();
+        input_trigger_tx.send(InputTrigger::MidiNoteOn { channel: 1, note: 60, velocity: 100 }).unwrap();
+        if let Some(ExecutorEvent::Completed { cue_id, report }) = playback_event_rx.recv().await {
+            assert_eq!(cue_id, note_cue_id);
+            assert!(!report.was_stopped_manually);
+        } else {
+            panic!("Wrong Playback Event emitted.");
+        }
+
+        // StopAllで待機中のキューを停止する
+        exec_tx.send(ExecutorCommand::StopAll).await.unwrap();
+        if let Some(ExecutorEvent::Completed { cue_id, report }) = playback_event_rx.recv().await {
+            assert_eq!(cue_id, osc_cue_id);
+            assert!(report.was_stopped_manually);
+        } else {
+            panic!("Wrong Playback Event emitted.");
+        }
+        input_trigger_tx.send(InputTrigger::Osc { address: "/sbsp/next".to_string() }).ok();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(playback_event_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn pause_n_resume_waiting_cues() {
+        let (exec_tx, exec_rx) = mpsc::channel::<ExecutorCommand>(32);
+        let (audio_tx, _audio_rx) = mpsc::channel::<AudioCommand>(32);
+        let (playback_event_tx, mut playback_event_rx) = mpsc::channel::<ExecutorEvent>(32);
+        let (_engine_event_tx, engine_event_rx) = mpsc::channel::<EngineEvent>(32);
+        let (manager, handle) = ShowModelManager::new(broadcast::channel::<UiEvent>(32).0);
+        let wait_cue_id = Uuid::new_v4();
+        let input_cue_id = Uuid::new_v4();
+        manager
+            .write_with(|model| {
+                model.cues.push(make_cue(wait_cue_id, model::cue::CueParam::Wait { duration: 0.1 }));
+                let source = model::cue::TriggerSource::MidiNote { channel: 1, note: 60 };
+                model.cues.push(make_cue(input_cue_id, model::cue::CueParam::WaitForInput { source }));
+            })
+            .await;
+        let executor = Executor::new(handle, exec_rx, audio_tx, playback_event_tx, engine_event_rx);
+        let input_trigger_tx = executor.input_trigger_sender();
+        tokio::spawn(executor.run(CancellationToken::new()));
+
+        exec_tx.send(ExecutorCommand::ExecuteCue(wait_cue_id)).await.unwrap();
+        exec_tx.send(ExecutorCommand::ExecuteCue(input_cue_id)).await.unwrap();
+        for _ in 0..2 {
+            assert!(matches!(playback_event_rx.recv().await, Some(ExecutorEvent::Started { .. })));
+        }
+
+        // 一時停止中は待機時間が進まず、トリガーにも反応しない
+        exec_tx.send(ExecutorCommand::PauseAll).await.unwrap();
+        for _ in 0..2 {
+            assert!(matches!(playback_event_rx.recv().await, Some(ExecutorEvent::Paused { .. })));
+        }
+        input_trigger_tx.send(InputTrigger::MidiNoteOn { channel: 1, note: 60, velocity: 100 }).unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(playback_event_rx.try_recv().is_err());
+
+        exec_tx.send(ExecutorCommand::ResumeCue(wait_cue_id)).await.unwrap();
+        assert!(matches!(playback_event_rx.recv().await, Some(ExecutorEvent::Resumed { cue_id }) if cue_id == wait_cue_id));
+        if let Some(ExecutorEvent::Completed { cue_id, report }) = playback_event_rx.recv().await {
+            assert_eq!(cue_id, wait_cue_id);
+            assert!(!report.was_stopped_manually);
+        } else {
+            panic!("Wrong Playback Event emitted.");
+        }
+
+        exec_tx.send(ExecutorCommand::ResumeCue(input_cue_id)).await.unwrap();
+        assert!(matches!(playback_event_rx.recv().await, Some(ExecutorEvent::Resumed { cue_id }) if cue_id == input_cue_id));
+        input_trigger_tx.send(InputTrigger::MidiNoteOn { channel: 1, note: 60, velocity: 100 }).unwrap();
+        assert!(matches!(playback_event_rx.recv().await, Some(ExecutorEvent::Completed { cue_id, .. }) if cue_id == input_cue_id));
+    }
+
+    #[tokio::test]
+    async fn disarmed_cue_is_ignored() {
+        let cue_id = Uuid::new_v4();
+        let armed_cue_id = Uuid::new_v4();
+        let (manager, exec_tx, mut audio_rx, _, _) = setup_executor(cue_id).await;
+        manager
+            .write_with(|model| {
+                let mut armed_cue = model.cues[0].clone();
+                armed_cue.id = armed_cue_id;
+                if let model::cue::CueParam::Audio { target, .. } = &mut armed_cue.param {
+                    *target = PathBuf::from("./armed.flac");
+                }
+                model.cues.push(armed_cue);
+                model.cues[0].armed = false;
+            })
+            .await;
+
+        exec_tx.send(ExecutorCommand::ExecuteCue(cue_id)).await.unwrap();
+        exec_tx.send(ExecutorCommand::ExecuteCue(armed_cue_id)).await.unwrap();
+
+        // 無効化されたキューは実行されない
+        if let Some(AudioCommand::Play { data, .. }) = audio_rx.recv().await {
+            assert_eq!(data.filepath, PathBuf::from("./armed.flac"));
+        } else {
+            panic!("Wrong Audio Command emitted.");
         }
     }
+
+    #[tokio::test]
+    async fn empty_group_cue() {
+        let group_id = Uuid::new_v4();
+        let (manager, exec_tx, _, _, mut playback_event_rx) = setup_executor(Uuid::new_v4()).await;
+        manager
+            .write_with(|model| {
+                model.cues.push(make_cue(group_id, model::cue::CueParam::Group { children: vec![Uuid::new_v4()] }));
+            })
+            .await;
+
+        exec_tx.send(ExecutorCommand::ExecuteCue(group_id)).await.unwrap();
+
+        assert!(matches!(playback_event_rx.recv().await, Some(ExecutorEvent::Started { cue_id }) if cue_id == group_id));
+        assert!(matches!(playback_event_rx.recv().await, Some(ExecutorEvent::Completed { cue_id, .. }) if cue_id == group_id));
+    }
 }
@@ -0,0 +1,176 @@
+use std::{collections::HashMap, sync::{Arc, Mutex}, time::{Duration, Instant}};
+
+use uuid::Uuid;
+
+use crate::{controller::ControllerCommand, model::cue::TriggerSource};
+
+/// コマンドの入力元
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputSource {
+    WebSocket,
+    Rest,
+    Osc,
+    Midi,
+}
+
+impl InputSource {
+    /// 入力元ごとの既定の再送防止時間。手動操作のWebSocketとRESTは連続したGOを許可する
+    fn default_window(&self) -> Duration {
+        match self {
+            InputSource::WebSocket => Duration::ZERO,
+            InputSource::Rest => Duration::ZERO,
+            InputSource::Osc => Duration::from_millis(100),
+            InputSource::Midi => Duration::from_millis(100),
+        }
+    }
+}
+
+/// MIDI・OSCの受信部から届く外部からのトリガー
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputTrigger {
+    MidiNoteOn {
+        channel: u8, // 1〜16
+        note: u8,
+        velocity: u8,
+    },
+    Osc {
+        address: String,
+    },
+}
+
+impl InputTrigger {
+    /// WaitForInputキューが待機しているトリガーと一致する場合はtrueを返します。
+    /// ベロシティ0のノートオンはノートオフとして扱い、一致しません。
+    pub fn matches(&self, source: &TriggerSource) -> bool {
+        match (self, source) {
+            (InputTrigger::MidiNoteOn { channel, note, velocity }, TriggerSource::MidiNote { channel: source_channel, note: source_note }) => {
+                *velocity > 0 && channel == source_channel && note == source_note
+            }
+            (InputTrigger::Osc { address }, TriggerSource::Osc { address: source_address }) => address == source_address,
+            _ => false,
+        }
+    }
+}
+
+/// 同じトリガーとみなすコマンドの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum TriggerKey {
+    Go,
+    GoFromCue(Uuid),
+    GoMultiple(usize),
+    ToggleCue(Uuid),
+    GoAtTimecode(Uuid),
+}
+
+impl TriggerKey {
+    fn from_command(command: &ControllerCommand) -> Option<Self> {
+        match command {
+            ControllerCommand::Go => Some(Self::Go),
+            ControllerCommand::GoFromCue { cue_id } => Some(Self::GoFromCue(*cue_id)),
+            ControllerCommand::GoMultiple { count } => Some(Self::GoMultiple(*count)),
+            ControllerCommand::ToggleCue { cue_id } => Some(Self::ToggleCue(*cue_id)),
+            ControllerCommand::GoAtTimecode { cue_id, .. } => Some(Self::GoAtTimecode(*cue_id)),
+            _ => None,
+        }
+    }
+}
+
+/// 入力元ごとに、再送防止時間内に届いた同じトリガーを破棄します。
+///
+/// 再送防止時間は入力元ごとに独立しているため、ある入力元の重複は他の入力元からのトリガーに影響しません。
+#[derive(Clone)]
+pub struct ReplayGuard {
+    windows: HashMap<InputSource, Duration>,
+    last_triggers: Arc<Mutex<HashMap<(InputSource, TriggerKey), Instant>>>,
+}
+
+impl Default for ReplayGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReplayGuard {
+    pub fn new() -> Self {
+        Self {
+            windows: HashMap::new(),
+            last_triggers: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// 入力元の再送防止時間を設定します。
+    pub fn with_window(mut self, source: InputSource, window: Duration) -> Self {
+        self.windows.insert(source, window);
+        self
+    }
+
+    pub fn window(&self, source: InputSource) -> Duration {
+        self.windows.get(&source).copied().unwrap_or_else(|| source.default_window())
+    }
+
+    /// コマンドを実行してよい場合はtrueを返します。トリガー以外のコマンドは常に実行します。
+    pub fn check(&self, source: InputSource, command: &ControllerCommand) -> bool {
+        self.check_at(source, command, Instant::now())
+    }
+
+    fn check_at(&self, source: InputSource, command: &ControllerCommand, now: Instant) -> bool {
+        let Some(trigger_key) = TriggerKey::from_command(command) else {
+            return true;
+        };
+        let window = self.window(source);
+        let mut last_triggers = self.last_triggers.lock().unwrap();
+        if let Some(last) = last_triggers.get(&(source, trigger_key))
+            && now.duration_since(*last) < window
+        {
+            log::info!("Ignored replayed {:?} from {:?}.", trigger_key, source);
+            return false;
+        }
+        last_triggers.insert((source, trigger_key), now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_per_source() {
+        let guard = ReplayGuard::new().with_window(InputSource::Osc, Duration::from_millis(200));
+        let now = Instant::now();
+
+        assert!(guard.check_at(InputSource::Osc, &ControllerCommand::Go, now));
+        assert!(!guard.check_at(InputSource::Osc, &ControllerCommand::Go, now + Duration::from_millis(50)));
+        // 同じ瞬間でも、他の入力元からのGOは実行する
+        assert!(guard.check_at(InputSource::WebSocket, &ControllerCommand::Go, now + Duration::from_millis(50)));
+        assert!(guard.check_at(InputSource::WebSocket, &ControllerCommand::Go, now + Duration::from_millis(50)));
+
+        assert!(guard.check_at(InputSource::Osc, &ControllerCommand::Go, now + Duration::from_millis(250)));
+        assert!(guard.check_at(InputSource::Osc, &ControllerCommand::StopAll, now + Duration::from_millis(260)));
+        assert!(guard.check_at(InputSource::Osc, &ControllerCommand::StopAll, now + Duration::from_millis(260)));
+    }
+
+    #[test]
+    fn different_cues_are_not_replays() {
+        let guard = ReplayGuard::new();
+        let now = Instant::now();
+        let first = ControllerCommand::ToggleCue { cue_id: Uuid::new_v4() };
+        let second = ControllerCommand::ToggleCue { cue_id: Uuid::new_v4() };
+
+        assert!(guard.check_at(InputSource::Midi, &first, now));
+        assert!(guard.check_at(InputSource::Midi, &second, now));
+        assert!(!guard.check_at(InputSource::Midi, &first, now));
+    }
+
+    #[test]
+    fn input_trigger_matches_source() {
+        let note = TriggerSource::MidiNote { channel: 1, note: 60 };
+        assert!(InputTrigger::MidiNoteOn { channel: 1, note: 60, velocity: 100 }.matches(&note));
+        assert!(!InputTrigger::MidiNoteOn { channel: 1, note: 60, velocity: 0 }.matches(&note));
+        assert!(!InputTrigger::MidiNoteOn { channel: 2, note: 60, velocity: 100 }.matches(&note));
+
+        let osc = TriggerSource::Osc { address: "/sbsp/next".to_string() };
+        assert!(InputTrigger::Osc { address: "/sbsp/next".to_string() }.matches(&osc));
+        assert!(!InputTrigger::Osc { address: "/sbsp/next".to_string() }.matches(&note));
+    }
+}
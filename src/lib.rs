@@ -1,40 +1,117 @@
-use tokio::sync::{broadcast, mpsc, watch};
+use std::{net::SocketAddr, path::PathBuf, time::Duration};
 
-use crate::{controller::{ControllerCommand, CueController, ShowState}, engine::audio_engine::{AudioCommand, AudioEngine}, event::UiEvent, executor::{EngineEvent, Executor, ExecutorCommand, ExecutorEvent}, manager::{ShowModelHandle, ShowModelManager}};
+use tokio::{sync::{broadcast, mpsc, watch}, task::JoinHandle};
+use tokio_util::sync::CancellationToken;
 
-mod event;
-mod controller;
-mod engine;
-mod executor;
-mod manager;
-mod model;
+use crate::{controller::{ControllerCommand, CueController, CueHistory, ShowState}, diagnostics::ChannelMonitor, error::BackendError, engine::audio_engine::{AudioCommand, AudioEngine}, event::UiEvent, input::InputTrigger, executor::{EngineEvent, Executor, ExecutorCommand, ExecutorEvent}, manager::{ShowModelHandle, ShowModelManager}, midi::MidiListener, osc::OscListener};
+
+pub mod event;
+pub mod controller;
+pub mod diagnostics;
+pub mod engine;
+pub mod error;
+pub mod executor;
+pub mod input;
+pub mod manager;
+pub mod media;
+pub mod midi;
+pub mod model;
+pub mod osc;
+#[cfg(feature = "schema")]
+pub mod schema;
+#[cfg(test)]
+mod test_util;
+
+/// バックエンドの起動設定
+#[derive(Debug, Clone)]
+pub struct BackendConfig {
+    pub channel_capacity: usize,       // 内部チャネルの容量
+    pub event_capacity: usize,         // UIイベントのブロードキャストの容量。頻度の高いイベントで遅いクライアントが取りこぼさないよう大きめにする
+    pub poll_interval: Duration,       // AudioEngineが再生位置を通知する間隔
+    pub output_device: Option<String>, // ショー設定で指定されるまで使用する出力デバイス。Noneの場合はシステムの既定のデバイス
+    pub bind_address: SocketAddr,      // APIサーバーの待ち受けアドレス
+    pub ws_ping_interval: Duration,    // WebSocketのクライアントにPingを送信する間隔
+    pub ws_pong_timeout: Duration,     // Pingを送信してからPongを待つ時間。応答がない場合は切断する
+    pub media_root: PathBuf,           // APIから情報を取得できる音声ファイルのディレクトリ
+}
+
+impl Default for BackendConfig {
+    fn default() -> Self {
+        Self {
+            channel_capacity: 32,
+            event_capacity: 256,
+            poll_interval: Duration::from_millis(50),
+            output_device: None,
+            bind_address: SocketAddr::from(([0, 0, 0, 0], 8888)),
+            ws_ping_interval: Duration::from_secs(15),
+            ws_pong_timeout: Duration::from_secs(10),
+            media_root: PathBuf::from("."),
+        }
+    }
+}
 
 pub struct BackendHandle {
     pub model_handle: ShowModelHandle,
 
     pub controller_tx: mpsc::Sender<ControllerCommand>,
+    pub exec_tx: mpsc::Sender<ExecutorCommand>, // デバッグ用の問い合わせに使う
     pub state_rx: watch::Receiver<ShowState>,
-    pub event_rx: broadcast::Receiver<UiEvent>
+    pub event_tx: broadcast::Sender<UiEvent>,
+    pub event_rx: broadcast::Receiver<UiEvent>,
+    pub channel_monitor: ChannelMonitor,
+    pub history: CueHistory,
+    pub input_trigger_tx: broadcast::Sender<InputTrigger>, // MIDI・OSCの受信部からWaitForInputキューにトリガーを届ける
+
+    cancel_token: CancellationToken,
+    tasks: Vec<JoinHandle<()>>,
 }
 
-pub async fn start_backend() -> BackendHandle {
-    let (controller_tx, controller_rx) = mpsc::channel::<ControllerCommand>(32);
-    let (exec_tx, exec_rx) = mpsc::channel::<ExecutorCommand>(32);
-    let (audio_tx, audio_rx) = mpsc::channel::<AudioCommand>(32);
-    let (executor_event_tx, executor_event_rx) = mpsc::channel::<ExecutorEvent>(32);
-    let (engine_event_tx, engine_event_rx) = mpsc::channel::<EngineEvent>(32);
+impl BackendHandle {
+    /// 再生中のサウンドをフェードアウトし、残りのイベントを送信した上で全てのタスクを終了します。
+    pub async fn shutdown(self) {
+        self.cancel_token.cancel();
+        for task in self.tasks {
+            if let Err(e) = task.await {
+                log::error!("Backend task failed during shutdown: {:?}", e);
+            }
+        }
+        log::info!("Backend shut down.");
+    }
+}
+
+/// 既定の設定でバックエンドを起動します。
+pub async fn start_backend_default() -> Result<BackendHandle, BackendError> {
+    start_backend(BackendConfig::default()).await
+}
+
+/// バックエンドを起動します。オーディオ出力を初期化できない場合は`BackendError::AudioInit`を返します。
+pub async fn start_backend(config: BackendConfig) -> Result<BackendHandle, BackendError> {
+    let capacity = config.channel_capacity;
+    let (controller_tx, controller_rx) = mpsc::channel::<ControllerCommand>(capacity);
+    let (exec_tx, exec_rx) = mpsc::channel::<ExecutorCommand>(capacity);
+    let (audio_tx, audio_rx) = mpsc::channel::<AudioCommand>(capacity);
+    let (executor_event_tx, executor_event_rx) = mpsc::channel::<ExecutorEvent>(capacity);
+    let (engine_event_tx, engine_event_rx) = mpsc::channel::<EngineEvent>(capacity);
     let (state_tx, state_rx) = watch::channel::<ShowState>(ShowState::new());
-    let (event_tx, event_rx) = broadcast::channel::<UiEvent>(32);
+    let (event_tx, event_rx) = broadcast::channel::<UiEvent>(config.event_capacity);
+    let channel_monitor = ChannelMonitor::new()
+        .with_channel("controller", &controller_tx)
+        .with_channel("executor", &exec_tx)
+        .with_channel("audio", &audio_tx)
+        .with_channel("executor_event", &executor_event_tx)
+        .with_channel("engine_event", &engine_event_tx);
 
     let (model_manager, model_handle) = ShowModelManager::new(event_tx.clone());
+    let model_manager = model_manager.with_show_state(state_rx.clone());
     let controller = CueController::new(
         model_handle.clone(),
-        exec_tx,
+        exec_tx.clone(),
         controller_rx,
         executor_event_rx,
         state_tx,
         event_tx.clone(),
     ).await;
+    let history = controller.history();
 
     let executor = Executor::new(
         model_handle.clone(),
@@ -43,13 +120,25 @@ pub async fn start_backend() -> BackendHandle {
         executor_event_tx,
         engine_event_rx,
     );
+    let input_trigger_tx = executor.input_trigger_sender();
+    let midi_listener = MidiListener::new(model_handle.clone(), controller_tx.clone(), input_trigger_tx.clone(), event_tx.subscribe());
+    let osc_listener = OscListener::new(model_handle.clone(), controller_tx.clone(), input_trigger_tx.clone(), event_tx.subscribe());
 
-    let audio_engine = AudioEngine::new(audio_rx, engine_event_tx).unwrap();
+    let audio_engine = AudioEngine::with_output_device(audio_rx, engine_event_tx, config.output_device)
+        .map_err(|e| BackendError::AudioInit(format!("{:#}", e)))?
+        .with_poll_interval(config.poll_interval)
+        .with_scrub_preview(true);
 
-    tokio::spawn(model_manager.run());
-    tokio::spawn(controller.run());
-    tokio::spawn(executor.run());
-    tokio::spawn(audio_engine.run());
+    let cancel_token = CancellationToken::new();
+    let tasks = vec![
+        tokio::spawn(audio_engine.run(cancel_token.clone())),
+        tokio::spawn(executor.run(cancel_token.clone())),
+        tokio::spawn(controller.run(cancel_token.clone())),
+        tokio::spawn(model_manager.run(cancel_token.clone())),
+        tokio::spawn(channel_monitor.clone().run(cancel_token.clone())),
+        tokio::spawn(osc_listener.run(cancel_token.clone())),
+        tokio::spawn(midi_listener.run(cancel_token.clone())),
+    ];
 
-    BackendHandle { model_handle, controller_tx, state_rx, event_rx }
-}
\ No newline at end of file
+    Ok(BackendHandle { model_handle, controller_tx, exec_tx, state_rx, event_tx, event_rx, channel_monitor, history, input_trigger_tx, cancel_token, tasks })
+}
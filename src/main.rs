@@ -1,58 +1,42 @@
 mod apiserver;
-mod event;
-mod controller;
-mod engine;
-mod executor;
-mod manager;
-mod model;
+#[cfg(test)]
+mod test_util;
 
-use tokio::sync::{broadcast, mpsc, watch};
-
-use crate::{
-    controller::{ControllerCommand, CueController, ShowState}, engine::audio_engine::{AudioCommand, AudioEngine}, event::UiEvent, executor::{EngineEvent, Executor, ExecutorCommand, ExecutorEvent}, manager::ShowModelManager,
-};
+use sbsp_backend::{input::ReplayGuard, start_backend, BackendConfig};
 
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
     env_logger::init();
 
-    let (ctrl_tx, ctrl_rx) = mpsc::channel::<ControllerCommand>(32);
-    let (exec_tx, exec_rx) = mpsc::channel::<ExecutorCommand>(32);
-    let (audio_tx, audio_rx) = mpsc::channel::<AudioCommand>(32);
-    let (executor_event_tx, executor_event_rx) = mpsc::channel::<ExecutorEvent>(32);
-    let (engine_event_tx, engine_event_rx) = mpsc::channel::<EngineEvent>(32);
-    let (state_tx, state_rx) = watch::channel::<ShowState>(ShowState::new());
-    let (event_tx, _) = broadcast::channel::<UiEvent>(32);
-
-    let (model_manager, model_handle) = ShowModelManager::new(event_tx.clone());
-
-    let controller = CueController::new(
-        model_handle.clone(),
-        exec_tx,
-        ctrl_rx,
-        executor_event_rx,
-        state_tx,
-        event_tx.clone(),
-    ).await;
-
-    let executor = Executor::new(
-        model_handle.clone(),
-        exec_rx,
-        audio_tx,
-        executor_event_tx,
-        engine_event_rx,
-    );
-
-    let audio_engine = AudioEngine::new(audio_rx, engine_event_tx)?;
-
-    tokio::spawn(model_manager.run());
-    tokio::spawn(controller.run());
-    tokio::spawn(executor.run());
-    tokio::spawn(audio_engine.run());
-
-    let app = apiserver::create_api_router(ctrl_tx.clone(), state_rx, event_tx, model_handle.clone()).await;
-
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:8888").await?;
+    // `--export-schema [dir]`が指定された場合は、APIの型のJSON Schemaを書き出して終了する
+    #[cfg(feature = "schema")]
+    if let Some(index) = std::env::args().position(|arg| arg == "--export-schema") {
+        let dir = std::env::args().nth(index + 1).unwrap_or_else(|| "schema".to_string());
+        return apiserver::export_schema(std::path::Path::new(&dir));
+    }
+
+    let config = BackendConfig::default();
+    let bind_address = config.bind_address;
+    let heartbeat = apiserver::WsHeartbeat { interval: config.ws_ping_interval, timeout: config.ws_pong_timeout };
+    let options = apiserver::ApiOptions::default()
+        .with_replay_guard(ReplayGuard::new())
+        .with_heartbeat(heartbeat)
+        .with_media_root(config.media_root.clone());
+    let backend = start_backend(config).await?;
+    let options = options.with_executor(backend.exec_tx.clone());
+
+    let app = apiserver::create_api_router(
+        backend.controller_tx.clone(),
+        backend.state_rx.clone(),
+        backend.event_tx.clone(),
+        backend.model_handle.clone(),
+        backend.channel_monitor.clone(),
+        backend.history.clone(),
+        options,
+    )
+    .await;
+
+    let listener = tokio::net::TcpListener::bind(bind_address).await?;
     log::info!("ApiServer listening on {}", listener.local_addr()?);
     axum::serve(listener, app).await.unwrap();
 
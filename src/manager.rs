@@ -1,19 +1,34 @@
-use std::{path::{Path, PathBuf}, sync::Arc};
+use std::{collections::{HashMap, HashSet}, hash::{DefaultHasher, Hasher}, io::Read, path::{Path, PathBuf}, sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex}, time::{Duration, SystemTime}};
+
+use anyhow::Context;
 
 use serde::{Deserialize, Serialize};
-use tokio::sync::{broadcast, mpsc, RwLock};
+use tokio::{sync::{broadcast, mpsc, oneshot, watch, RwLock}, time::{self, Instant}};
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
-use crate::{event::{UiError, UiEvent}, model::{cue::Cue, ShowModel}};
+use crate::{controller::ShowState, error::BackendError, media::DurationCache, event::{UiError, UiEvent}, model::{cue::{Cue, CueKind, CueParam, CuePatch}, settings::ShowSettings, timing::{cue_sheet, CueSheetEntry}, ShowModel}};
 
 #[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(tag = "command", content = "params", rename_all = "camelCase", rename_all_fields = "camelCase")]
 pub enum ModelCommand {
     UpdateCue(Cue),
+    /// キューの指定したフィールドのみを変更します。
+    PatchCue {
+        cue_id: Uuid,
+        changes: CuePatch,
+    },
     AddCue {
         cue: Cue,
         at_index: usize,
     },
+    /// 設定のテンプレートの既定値で`target`を再生するオーディオキューを作成し、追加します。
+    AddCueFromTemplate {
+        template_name: String,
+        target: PathBuf,
+        at_index: usize,
+    },
     RemoveCue {
         cue_id: Uuid,
     },
@@ -21,18 +36,140 @@ pub enum ModelCommand {
         cue_id: Uuid,
         to_index: usize,
     },
+    DuplicateCue {
+        cue_id: Uuid,
+    },
+    SetArmed {
+        cue_id: Uuid,
+        armed: bool,
+    },
+    /// キューのメモの末尾に、時刻を付けた1行を追記します。
+    AppendNote {
+        cue_id: Uuid,
+        text: String,
+    },
+    /// キューリストの順にキュー番号を`start`から`increment`ずつ振り直します。
+    /// `preserve_existing`がtrueの場合は番号が空のキューのみ振り直し、既存の番号と重複する番号は飛ばします。
+    RenumberCues {
+        start: f64,
+        increment: f64,
+        #[serde(default)]
+        preserve_existing: bool,
+    },
+    UpdateSettings(ShowSettings),
+    /// ショーの内容を変更する複数のコマンドをまとめて適用します。いずれかが失敗した場合は何も変更しません。
+    Batch(Vec<ModelCommand>),
+    SetLiveLock {
+        enabled: bool,
+    },
+    /// ライブロックを無視してコマンドを実行します。
+    Override(Box<ModelCommand>),
+    /// 未保存の変更を破棄して、ショーを閉じるコマンドを確認なしで実行します。
+    DiscardChanges(Box<ModelCommand>),
+    /// 未保存の変更があるため保留したコマンドを、変更を破棄して実行します。
+    ConfirmClose,
+    /// 保留したコマンドを取り消します。
+    CancelClose,
 
     Save,
+    /// ショーファイルが読み込み後に他から変更されていても上書き保存します。
+    SaveForce,
     SaveToFile(PathBuf),
     LoadFromFile(PathBuf),
+    /// 空のショーを作成します。
+    NewShow,
+    /// 参照している全ての音声ファイルを`dest_dir`のメディアフォルダにコピーし、ショーを`dest_dir`に保存します。
+    CollectAssets {
+        dest_dir: PathBuf,
+    },
 }
 
+/// CollectAssetsで音声ファイルをコピーするフォルダ名
+const ASSET_DIR_NAME: &str = "media";
+/// ショーファイルが未保存の場合にCollectAssetsで使用するファイル名
+const DEFAULT_SHOW_FILE_NAME: &str = "show.sbsp";
+/// 自動保存ファイルの、ショーファイル名に付ける拡張子
+const AUTOSAVE_EXTENSION: &str = "autosave";
+
+/// コマンドと、処理結果を返す場合はその送信先
+type ModelRequest = (ModelCommand, Option<oneshot::Sender<Result<(), UiError>>>);
+
 pub struct ShowModelManager {
     model: Arc<RwLock<ShowModel>>,
-    command_rx: mpsc::Receiver<ModelCommand>,
+    command_rx: mpsc::Receiver<ModelRequest>,
     event_tx: broadcast::Sender<UiEvent>,
 
     show_model_path: Arc<RwLock<Option<PathBuf>>>,
+    live_lock: Arc<AtomicBool>, // 再生中のキューの追加・削除・移動を拒否する
+    state_rx: Option<watch::Receiver<ShowState>>,
+    dirty: AtomicBool, // 最後に保存・読み込みしてから変更があるかどうか
+    auto_save_pending: AtomicBool, // 最後に自動保存してから変更があるかどうか
+    file_modified: Mutex<Option<SystemTime>>, // 最後に読み込み・保存した時点のショーファイルの更新日時
+    pending_close: Mutex<Option<ModelCommand>>, // 確認を待っているショーを閉じるコマンド
+}
+
+impl ModelCommand {
+    /// キューリストの構造を変更するコマンドの場合、対象のキューIDを返します。
+    fn structural_cue_id(&self) -> Option<Uuid> {
+        match self {
+            ModelCommand::AddCue { cue, .. } => Some(cue.id),
+            ModelCommand::RemoveCue { cue_id }
+            | ModelCommand::MoveCue { cue_id, .. }
+            | ModelCommand::DuplicateCue { cue_id } => Some(*cue_id),
+            ModelCommand::Batch(commands) => commands.iter().find_map(ModelCommand::structural_cue_id),
+            _ => None,
+        }
+    }
+
+    /// UIから送信された、追加・更新するキューを返します。
+    fn submitted_cues(&self) -> Vec<&Cue> {
+        match self {
+            ModelCommand::UpdateCue(cue) | ModelCommand::AddCue { cue, .. } => vec![cue],
+            ModelCommand::Batch(commands) => commands.iter().flat_map(ModelCommand::submitted_cues).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// ショーの内容を変更するコマンドかどうかを返します。
+    fn edits_model(&self) -> bool {
+        matches!(
+            self,
+            ModelCommand::UpdateCue(_)
+                | ModelCommand::PatchCue { .. }
+                | ModelCommand::AddCue { .. }
+                | ModelCommand::RemoveCue { .. }
+                | ModelCommand::MoveCue { .. }
+                | ModelCommand::DuplicateCue { .. }
+                | ModelCommand::SetArmed { .. }
+                | ModelCommand::AppendNote { .. }
+                | ModelCommand::RenumberCues { .. }
+                | ModelCommand::UpdateSettings(_)
+                | ModelCommand::Batch(_)
+        )
+    }
+
+    /// テンプレートからキューを追加するコマンドを、作成したキューを追加するAddCueに置き換えます。
+    fn resolve_templates(self, settings: &ShowSettings) -> Result<ModelCommand, UiError> {
+        match self {
+            ModelCommand::AddCueFromTemplate { template_name, target, at_index } => {
+                let Some(template) = settings.templates.get(&template_name) else {
+                    return Err(UiError::UnknownTemplate { template_name });
+                };
+                Ok(ModelCommand::AddCue { cue: template.to_cue(target, &settings.general), at_index })
+            }
+            ModelCommand::Batch(commands) => commands
+                .into_iter()
+                .map(|command| command.resolve_templates(settings))
+                .collect::<Result<_, _>>()
+                .map(ModelCommand::Batch),
+            command => Ok(command),
+        }
+    }
+
+    /// 現在のショーを閉じるコマンドかどうかを返します。
+    fn closes_show(&self) -> bool {
+        matches!(self, ModelCommand::LoadFromFile(_) | ModelCommand::NewShow)
+    }
 }
 
 impl ShowModelManager {
@@ -40,76 +177,221 @@ impl ShowModelManager {
         let (command_tx, command_rx) = mpsc::channel(32);
         let model = Arc::new(RwLock::new(ShowModel::default()));
         let show_model_path = Arc::new(RwLock::new(None));
+        let live_lock = Arc::new(AtomicBool::new(false));
         let manager = Self {
             model: model.clone(),
             command_rx,
             event_tx,
             show_model_path: show_model_path.clone(),
+            live_lock: live_lock.clone(),
+            state_rx: None,
+            dirty: AtomicBool::new(false),
+            auto_save_pending: AtomicBool::new(false),
+            file_modified: Mutex::new(None),
+            pending_close: Mutex::new(None),
         };
         let handle = ShowModelHandle {
             model,
             command_tx,
             show_model_path,
+            live_lock,
+            duration_cache: DurationCache::default(),
         };
 
         (manager, handle)
     }
 
-    pub async fn run(mut self) {
-        while let Some(command) = self.command_rx.recv().await {
-            let event = self.process_command(command).await;
-            if let Some(event) = event {
-                self.event_tx.send(event).ok();
+    /// ライブロックの判定に使用する再生状態を設定します。設定しない場合、ライブロックは常に解除された状態として扱われます。
+    pub fn with_show_state(mut self, state_rx: watch::Receiver<ShowState>) -> Self {
+        self.state_rx = Some(state_rx);
+        self
+    }
+
+    /// ライブロックが有効で、再生中のキューがあるかどうかを返します。
+    fn is_live_locked(&self) -> bool {
+        self.live_lock.load(Ordering::Relaxed)
+            && self.state_rx.as_ref().is_some_and(|state_rx| state_rx.borrow().active_cues.values().any(|active_cue| active_cue.status.is_running()))
+    }
+
+    pub async fn run(mut self, cancel_token: CancellationToken) {
+        let mut last_auto_save = Instant::now();
+        loop {
+            // 設定の変更をすぐに反映するため、毎回間隔を読み直す
+            let auto_save_deadline = self
+                .read()
+                .await
+                .settings
+                .general
+                .auto_save_interval
+                .and_then(|interval| Duration::try_from_secs_f64(interval).ok())
+                .map(|interval| last_auto_save + interval);
+            tokio::select! {
+                _ = cancel_token.cancelled() => break,
+                _ = async { time::sleep_until(auto_save_deadline.unwrap()).await }, if auto_save_deadline.is_some() => {
+                    last_auto_save = Instant::now();
+                    if let Some(event) = self.auto_save().await {
+                        self.event_tx.send(event).ok();
+                    }
+                },
+                Some((command, reply_tx)) = self.command_rx.recv() => {
+                    let event = self.process_command(command).await;
+                    if let Some(reply_tx) = reply_tx {
+                        let result = match &event {
+                            Some(UiEvent::OperationFailed { error }) => Err(error.clone()),
+                            _ => Ok(()),
+                        };
+                        reply_tx.send(result).ok();
+                    }
+                    if let Some(event) = event {
+                        self.event_tx.send(event).ok();
+                    }
+                },
+                else => break,
             }
         }
     }
 
-    async fn process_command(&self, command: ModelCommand) -> Option<UiEvent> {
-        match command {
-            ModelCommand::UpdateCue(cue) => {
-                let mut model = self.model.write().await;
-                if let Some(index) = model.cues.iter().position(|c| c.id == cue.id) {
-                    model.cues[index] = cue.clone();
-                    Some(UiEvent::CueUpdated { cue })
-                } else {
-                    Some(UiEvent::OperationFailed { error: UiError::CueEdit { cue_id: cue.id, message: "Cue doesn't exist.".to_string() } })
+    async fn process_command(&self, mut command: ModelCommand) -> Option<UiEvent> {
+        let mut overridden = false;
+        let mut discard_changes = false;
+        loop {
+            match command {
+                ModelCommand::Override(inner) => {
+                    overridden = true;
+                    command = *inner;
                 }
-            }
-            ModelCommand::AddCue { cue, at_index } => {
-                let mut model = self.model.write().await;
-                if model.cues.iter().any(|c| c.id == cue.id) {
-                    Some(UiEvent::OperationFailed { error: UiError::CueEdit { cue_id: cue.id, message: "Cue already exist.".to_string() } })
-                } else if at_index > model.cues.len() {
-                    Some(UiEvent::OperationFailed { error: UiError::CueEdit { cue_id: cue.id, message: "Insert index is out of list.".to_string() } })
-                } else {
-                    model.cues.insert(at_index, cue.clone());
-                    Some(UiEvent::CueAdded { cue, at_index })
+                ModelCommand::DiscardChanges(inner) => {
+                    discard_changes = true;
+                    command = *inner;
                 }
+                _ => break,
             }
-            ModelCommand::RemoveCue { cue_id } => {
-                let mut model = self.model.write().await;
-                if let Some(index) = model.cues.iter().position(|c| c.id == cue_id) {
-                    model.cues.remove(index);
-                    Some(UiEvent::CueRemoved { cue_id })
-                } else {
-                    Some(UiEvent::OperationFailed { error: UiError::CueEdit { cue_id, message: "Cue doesn't exist.".to_string() } })
-                }
+        }
+        let command = match command.resolve_templates(&self.read().await.settings) {
+            Ok(command) => command,
+            Err(error) => return Some(UiEvent::OperationFailed { error }),
+        };
+        if !overridden
+            && let Some(cue_id) = command.structural_cue_id()
+            && self.is_live_locked()
+        {
+            log::warn!("Live lock rejected an edit of cue '{}'.", cue_id);
+            return Some(UiEvent::OperationFailed { error: UiError::LiveLocked { cue_id } });
+        }
+        let command = match command {
+            ModelCommand::ConfirmClose => {
+                let Some(pending_command) = self.pending_close.lock().unwrap().take() else {
+                    log::warn!("ConfirmClose received, but no command is waiting for confirmation.");
+                    return None;
+                };
+                pending_command
             }
-            ModelCommand::MoveCue { cue_id, to_index } => {
+            ModelCommand::CancelClose => {
+                self.pending_close.lock().unwrap().take();
+                return None;
+            }
+            // 未保存の変更がある場合、ショーを閉じるコマンドは確認されるまで保留する
+            command if command.closes_show() && !discard_changes && self.dirty.load(Ordering::Relaxed) => {
+                log::info!("Show has unsaved changes. Waiting for confirmation to close.");
+                *self.pending_close.lock().unwrap() = Some(command);
+                return Some(UiEvent::UnsavedChanges);
+            }
+            command => command,
+        };
+
+        let edits_model = command.edits_model();
+        let event = self.apply_command(command).await;
+        match &event {
+            Some(UiEvent::OperationFailed { .. }) => {}
+            Some(UiEvent::ShowModelSaved { .. } | UiEvent::ShowModelLoaded { .. } | UiEvent::ShowModelCreated) => {
+                let modified = match &event {
+                    Some(UiEvent::ShowModelSaved { path } | UiEvent::ShowModelLoaded { path }) => file_modified(path).await,
+                    _ => None,
+                };
+                *self.file_modified.lock().unwrap() = modified;
+                self.dirty.store(false, Ordering::Relaxed);
+                self.auto_save_pending.store(false, Ordering::Relaxed);
+                self.pending_close.lock().unwrap().take();
+            }
+            _ if edits_model => {
+                self.dirty.store(true, Ordering::Relaxed);
+                self.auto_save_pending.store(true, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+        event
+    }
+
+    /// 前回の自動保存から変更がある場合、ショーファイルの隣の自動保存ファイルに書き出します。
+    /// ショーファイルのパスが未設定の場合は何もしません。
+    async fn auto_save(&self) -> Option<UiEvent> {
+        if !self.auto_save_pending.load(Ordering::Relaxed) {
+            return None;
+        }
+        let path = auto_save_path(self.show_model_path.read().await.as_ref()?);
+        match self.save_to_file(&path).await {
+            Ok(()) => {
+                self.auto_save_pending.store(false, Ordering::Relaxed);
+                Some(UiEvent::ShowModelAutoSaved { path })
+            }
+            Err(error) => {
+                // 次の間隔で再試行する
+                log::error!("Failed to auto-save show: {}", error);
+                None
+            }
+        }
+    }
+
+    async fn apply_command(&self, command: ModelCommand) -> Option<UiEvent> {
+        match command {
+            command @ (ModelCommand::UpdateCue(_)
+            | ModelCommand::PatchCue { .. }
+            | ModelCommand::AddCue { .. }
+            | ModelCommand::RemoveCue { .. }
+            | ModelCommand::MoveCue { .. }
+            | ModelCommand::DuplicateCue { .. }
+            | ModelCommand::SetArmed { .. }
+            | ModelCommand::AppendNote { .. }
+            | ModelCommand::RenumberCues { .. }
+            | ModelCommand::UpdateSettings(_)
+            | ModelCommand::Batch(_)) => {
+                let checked_cues: Vec<Cue> = command.submitted_cues().into_iter().cloned().collect();
                 let mut model = self.model.write().await;
-                if let Some(index) = model.cues.iter().position(|c| c.id == cue_id) {
-                    let cue = model.cues.remove(index);
-                    model.cues.insert(to_index, cue.clone());
-                    Some(UiEvent::CueMoved { cue_id, to_index })
-                } else if to_index > model.cues.len() {
-                    Some(UiEvent::OperationFailed { error: UiError::CueEdit { cue_id, message: "Insert index is out of list.".to_string() } })
-                } else {
-                    Some(UiEvent::OperationFailed { error: UiError::CueEdit { cue_id, message: "Cue doesn't exist.".to_string() } })
+                let event = match Self::apply_edit(&mut model, command) {
+                    Ok(event) => event,
+                    Err(error) => return Some(UiEvent::OperationFailed { error }),
+                };
+                drop(model);
+                // 追加・更新したキューの警告は、そのキューのイベントの後に送信する
+                if checked_cues.is_empty() {
+                    return Some(event);
+                }
+                self.event_tx.send(event).ok();
+                for cue in checked_cues {
+                    self.check_audio_target(&cue).await;
                 }
+                None
+            }
+            ModelCommand::SetLiveLock { enabled } => {
+                self.live_lock.store(enabled, Ordering::Relaxed);
+                Some(UiEvent::LiveLockChanged { enabled })
             }
-            ModelCommand::Save => {
+            ModelCommand::Override(_)
+            | ModelCommand::DiscardChanges(_)
+            | ModelCommand::ConfirmClose
+            | ModelCommand::CancelClose
+            | ModelCommand::AddCueFromTemplate { .. } => unreachable!(),
+            command @ (ModelCommand::Save | ModelCommand::SaveForce) => {
                 if let Some(path) = self.show_model_path.read().await.as_ref() {
-                    if let Err(error) = self.save_to_file(path.as_path()).await {
+                    let recorded = *self.file_modified.lock().unwrap();
+                    let conflicted = !matches!(command, ModelCommand::SaveForce)
+                        && recorded.is_some()
+                        && file_modified(path).await.is_some_and(|modified| Some(modified) != recorded);
+                    if conflicted {
+                        log::warn!("Show file '{}' was modified after it was loaded. Refusing to overwrite.", path.display());
+                        let message = "The file was modified by someone else after it was loaded. Use SaveForce to overwrite it.".to_string();
+                        Some(UiEvent::OperationFailed { error: UiError::FileSave { path: path.to_path_buf(), message } })
+                    } else if let Err(error) = self.save_to_file(path.as_path()).await {
                         log::error!("Failed to save model file: {}", error);
                         Some(UiEvent::OperationFailed { error: UiError::FileSave { path: path.to_path_buf(), message: error.to_string() } })
                     } else {
@@ -121,15 +403,55 @@ impl ShowModelManager {
                 }
             }
             ModelCommand::SaveToFile(path) => {
-                if let Err(error) = self.save_to_file(path.as_path()).await {
+                let mut model = self.read().await.clone();
+                let old_path = self.show_model_path.read().await.clone();
+                let rebased_cues = match old_path.as_ref() {
+                    Some(old_path) => rebase_media_paths(&mut model.cues, old_path, &path),
+                    None => Vec::new(),
+                };
+                if let Err(error) = Self::write_model_to_file(model, path.as_path()).await {
                     log::error!("Failed to save model file: {}", error);
                     Some(UiEvent::OperationFailed {error: UiError::FileSave { path, message: error.to_string() }})
                 } else {
+                    self.write_with(|model| {
+                        for rebased_cue in &rebased_cues {
+                            if let Some(cue) = model.cues.iter_mut().find(|c| c.id == rebased_cue.id) {
+                                cue.param = rebased_cue.param.clone();
+                            }
+                        }
+                    })
+                    .await;
+                    for cue in rebased_cues {
+                        self.event_tx.send(UiEvent::CueUpdated { cue }).ok();
+                    }
                     let mut show_model_path = self.show_model_path.write().await;
                     *show_model_path = Some(path.clone());
                     Some(UiEvent::ShowModelSaved { path })
                 }
             }
+            ModelCommand::CollectAssets { dest_dir } => {
+                match self.collect_assets(&dest_dir).await {
+                    Ok((path, rewritten_cues)) => {
+                        self.write_with(|model| {
+                            for rewritten_cue in &rewritten_cues {
+                                if let Some(cue) = model.cues.iter_mut().find(|c| c.id == rewritten_cue.id) {
+                                    cue.param = rewritten_cue.param.clone();
+                                }
+                            }
+                        })
+                        .await;
+                        for cue in rewritten_cues {
+                            self.event_tx.send(UiEvent::CueUpdated { cue }).ok();
+                        }
+                        *self.show_model_path.write().await = Some(path.clone());
+                        Some(UiEvent::ShowModelSaved { path })
+                    }
+                    Err(error) => {
+                        log::error!("Failed to collect assets: {:?}", error);
+                        Some(UiEvent::OperationFailed { error: UiError::FileSave { path: dest_dir, message: format!("{:#}", error) } })
+                    }
+                }
+            }
             ModelCommand::LoadFromFile(path) => {
                 if let Err(error) = self.load_from_file(path.as_path()).await {
                     log::error!("Failed to load model file: {}", error);
@@ -140,9 +462,167 @@ impl ShowModelManager {
                     Some(UiEvent::ShowModelLoaded { path })
                 }
             }
+            ModelCommand::NewShow => {
+                self.write_with(|model| *model = ShowModel::default()).await;
+                *self.show_model_path.write().await = None;
+                log::info!("New show created.");
+                Some(UiEvent::ShowModelCreated)
+            }
+        }
+    }
+
+    /// ショーの内容を変更するコマンドを適用し、UIに送信するイベントを返します。
+    /// Batchは全てのコマンドが成功した場合のみ適用し、失敗した場合は適用前の状態に戻します。
+    fn apply_edit(model: &mut ShowModel, command: ModelCommand) -> Result<UiEvent, UiError> {
+        match command {
+            ModelCommand::UpdateCue(cue) => {
+                cue.validate().map_err(|message| UiError::CueEdit { cue_id: cue.id, message })?;
+                let Some(index) = model.cues.iter().position(|c| c.id == cue.id) else {
+                    return Err(UiError::CueEdit { cue_id: cue.id, message: "Cue doesn't exist.".to_string() });
+                };
+                // 並び順はMoveCueでのみ変更する
+                let cue = Cue { order: model.cues[index].order, ..cue };
+                model.cues[index] = cue.clone();
+                Ok(UiEvent::CueUpdated { cue })
+            }
+            ModelCommand::PatchCue { cue_id, changes } => {
+                let Some(cue) = model.cues.iter_mut().find(|c| c.id == cue_id) else {
+                    return Err(UiError::CueEdit { cue_id, message: "Cue doesn't exist.".to_string() });
+                };
+                cue.apply_patch(changes).map_err(|message| UiError::CueEdit { cue_id, message })?;
+                Ok(UiEvent::CueUpdated { cue: cue.clone() })
+            }
+            ModelCommand::AddCue { cue, at_index } => {
+                cue.validate().map_err(|message| UiError::CueEdit { cue_id: cue.id, message })?;
+                if model.cues.iter().any(|c| c.id == cue.id) {
+                    Err(UiError::CueEdit { cue_id: cue.id, message: "Cue already exist.".to_string() })
+                } else if at_index > model.cues.len() {
+                    Err(UiError::CueEdit { cue_id: cue.id, message: "Insert index is out of list.".to_string() })
+                } else {
+                    model.cues.insert(at_index, cue);
+                    let reordered = model.place_cue(at_index);
+                    let event = UiEvent::CueAdded { cue: model.cues[at_index].clone(), at_index };
+                    Ok(Self::with_reordered(model, reordered, event))
+                }
+            }
+            ModelCommand::RemoveCue { cue_id } => {
+                let Some(index) = model.cues.iter().position(|c| c.id == cue_id) else {
+                    return Err(UiError::CueEdit { cue_id, message: "Cue doesn't exist.".to_string() });
+                };
+                model.cues.remove(index);
+                Ok(UiEvent::CueRemoved { cue_id })
+            }
+            ModelCommand::MoveCue { cue_id, to_index } => {
+                let Some(index) = model.cues.iter().position(|c| c.id == cue_id) else {
+                    return Err(UiError::CueEdit { cue_id, message: "Cue doesn't exist.".to_string() });
+                };
+                if to_index >= model.cues.len() {
+                    return Err(UiError::CueEdit { cue_id, message: "Insert index is out of list.".to_string() });
+                }
+                let cue = model.cues.remove(index);
+                model.cues.insert(to_index, cue);
+                let reordered = model.place_cue(to_index);
+                let event = UiEvent::CueMoved { cue_id, to_index, order: model.cues[to_index].order };
+                Ok(Self::with_reordered(model, reordered, event))
+            }
+            ModelCommand::DuplicateCue { cue_id } => {
+                let Some(index) = model.cues.iter().position(|c| c.id == cue_id) else {
+                    return Err(UiError::CueEdit { cue_id, message: "Cue doesn't exist.".to_string() });
+                };
+                let mut cue = model.cues[index].clone();
+                cue.id = Uuid::new_v4();
+                cue.name = format!("{} copy", cue.name);
+                cue.number = Self::next_free_number(&model.cues, &cue.number);
+                let at_index = index + 1;
+                model.cues.insert(at_index, cue);
+                let reordered = model.place_cue(at_index);
+                let event = UiEvent::CueAdded { cue: model.cues[at_index].clone(), at_index };
+                Ok(Self::with_reordered(model, reordered, event))
+            }
+            ModelCommand::SetArmed { cue_id, armed } => {
+                let Some(cue) = model.cues.iter_mut().find(|c| c.id == cue_id) else {
+                    return Err(UiError::CueEdit { cue_id, message: "Cue doesn't exist.".to_string() });
+                };
+                cue.armed = armed;
+                Ok(UiEvent::CueArmChanged { cue_id, armed })
+            }
+            ModelCommand::AppendNote { cue_id, text } => {
+                let Some(cue) = model.cues.iter_mut().find(|c| c.id == cue_id) else {
+                    return Err(UiError::CueEdit { cue_id, message: "Cue doesn't exist.".to_string() });
+                };
+                cue.append_note(&text, SystemTime::now()).map_err(|message| UiError::CueEdit { cue_id, message })?;
+                Ok(UiEvent::CueUpdated { cue: cue.clone() })
+            }
+            ModelCommand::RenumberCues { start, increment, preserve_existing } => {
+                if !start.is_finite() || !increment.is_finite() || increment <= 0.0 {
+                    let message = format!("Invalid numbering: start {}, increment {}.", start, increment);
+                    return Err(UiError::Renumber { message });
+                }
+                let numbers = renumber_cues(&mut model.cues, start, increment, preserve_existing);
+                log::info!("Renumbered {} cues.", numbers.len());
+                Ok(UiEvent::CuesRenumbered { numbers })
+            }
+            ModelCommand::UpdateSettings(settings) => {
+                settings.validate().map_err(|message| UiError::SettingsEdit { message })?;
+                model.settings = settings.clone();
+                Ok(UiEvent::SettingsUpdated { settings })
+            }
+            ModelCommand::Batch(commands) => {
+                let snapshot = model.clone();
+                let mut events = Vec::with_capacity(commands.len());
+                for command in commands {
+                    let result = if command.edits_model() {
+                        Self::apply_edit(model, command)
+                    } else {
+                        Err(UiError::Batch { message: "Only commands that edit the show can be batched.".to_string() })
+                    };
+                    match result {
+                        Ok(event) => events.push(event),
+                        Err(error) => {
+                            *model = snapshot;
+                            return Err(error);
+                        }
+                    }
+                }
+                Ok(UiEvent::BatchApplied { events })
+            }
+            _ => Err(UiError::Batch { message: "Only commands that edit the show can be batched.".to_string() }),
+        }
+    }
+
+    /// オーディオキューの対象ファイルが読み込めない場合、警告イベントを送信します。キュー自体は拒否しません。
+    async fn check_audio_target(&self, cue: &Cue) {
+        let CueParam::Audio { target, .. } = &cue.param else {
+            return;
+        };
+        let target = resolve_media_path(self.show_model_path.read().await.as_deref(), target);
+        if let Err(error) = check_readable_file(&target).await {
+            let message = format!("Audio file '{}' is not readable: {}", target.display(), error);
+            log::warn!("{}", message);
+            self.event_tx.send(UiEvent::CueValidationWarning { cue_id: cue.id, message }).ok();
         }
     }
 
+    /// 全てのキューの並び順を振り直した場合、UIが並び順を揃えられるよう、全てのキューの並び順を`event`に続けて送信します。
+    fn with_reordered(model: &ShowModel, reordered: bool, event: UiEvent) -> UiEvent {
+        if !reordered {
+            return event;
+        }
+        let orders = model.cues.iter().map(|cue| (cue.id, cue.order)).collect();
+        UiEvent::BatchApplied { events: vec![event, UiEvent::CuesReordered { orders }] }
+    }
+
+    /// 既存のキュー番号と衝突しない番号を生成します。空の番号はそのまま返します。
+    fn next_free_number(cues: &[Cue], number: &str) -> String {
+        if number.is_empty() {
+            return String::new();
+        }
+        (1..)
+            .map(|suffix| format!("{}.{}", number, suffix))
+            .find(|candidate| !cues.iter().any(|c| c.number.eq(candidate)))
+            .unwrap()
+    }
+
     pub async fn read(&self) -> tokio::sync::RwLockReadGuard<'_, ShowModel> {
         self.model.read().await
     }
@@ -155,11 +635,10 @@ impl ShowModelManager {
         updater(&mut guard)
     }
 
-    pub async fn load_from_file(&self, path: &Path) -> Result<(), anyhow::Error> {
+    pub(crate) async fn load_from_file(&self, path: &Path) -> Result<(), anyhow::Error> {
         let content = tokio::fs::read_to_string(path).await?;
 
-        let new_model: ShowModel =
-            tokio::task::spawn_blocking(move || serde_json::from_str(&content)).await??;
+        let new_model = tokio::task::spawn_blocking(move || ShowModel::from_json(&content)).await??;
 
         self.write_with(|state| {
             *state = new_model;
@@ -170,12 +649,65 @@ impl ShowModelManager {
         Ok(())
     }
 
-    pub async fn save_to_file(&self, path: &Path) -> Result<(), anyhow::Error> {
+    pub(crate) async fn save_to_file(&self, path: &Path) -> Result<(), anyhow::Error> {
         let state_guard = self.read().await;
 
         let model_clone = state_guard.clone();
         drop(state_guard); // Readロックを明示的に解放
 
+        Self::write_model_to_file(model_clone, path).await
+    }
+
+    /// 音声ファイルを`dest_dir`にコピーしてショーを保存し、保存先と書き換えたキューを返します。
+    /// 内容が同じファイルは1つにまとめ、ファイル名が重複する場合は番号を付けます。
+    async fn collect_assets(&self, dest_dir: &Path) -> Result<(PathBuf, Vec<Cue>), anyhow::Error> {
+        let show_model_path = self.show_model_path.read().await.clone();
+        let mut model = self.read().await.clone();
+        tokio::fs::create_dir_all(dest_dir.join(ASSET_DIR_NAME)).await?;
+
+        let total = model.cues.iter().filter(|cue| matches!(cue.param, CueParam::Audio { .. })).count();
+        let mut collected_files: HashMap<u64, Vec<(PathBuf, PathBuf)>> = HashMap::new(); // 内容のハッシュ -> (コピー元, コピー先の相対パス)
+        let mut used_names = HashSet::new();
+        let mut rewritten_cues = Vec::new();
+        let mut collected = 0;
+        for cue in model.cues.iter_mut() {
+            let CueParam::Audio { target, .. } = &mut cue.param else {
+                continue;
+            };
+            let source = resolve_media_path(show_model_path.as_deref(), target);
+            let hash = hash_file(source.clone())
+                .await
+                .with_context(|| format!("Failed to read '{}'", source.display()))?;
+            let candidates = collected_files.entry(hash).or_default();
+            let relative = match find_same_file(candidates, &source).await? {
+                Some(relative) => relative,
+                None => {
+                    let relative = Path::new(ASSET_DIR_NAME).join(unique_file_name(&source, &mut used_names));
+                    tokio::fs::copy(&source, dest_dir.join(&relative))
+                        .await
+                        .with_context(|| format!("Failed to copy '{}'", source.display()))?;
+                    candidates.push((source.clone(), relative.clone()));
+                    relative
+                }
+            };
+            if *target != relative {
+                *target = relative;
+                rewritten_cues.push(cue.clone());
+            }
+            collected += 1;
+            self.event_tx.send(UiEvent::AssetCollectProgress { collected, total }).ok();
+        }
+
+        let file_name = show_model_path
+            .as_deref()
+            .and_then(Path::file_name)
+            .unwrap_or(DEFAULT_SHOW_FILE_NAME.as_ref());
+        let path = dest_dir.join(file_name);
+        Self::write_model_to_file(model, &path).await?;
+        Ok((path, rewritten_cues))
+    }
+
+    async fn write_model_to_file(model_clone: ShowModel, path: &Path) -> Result<(), anyhow::Error> {
         let content =
             tokio::task::spawn_blocking(move || serde_json::to_string_pretty(&model_clone))
                 .await??;
@@ -186,51 +718,329 @@ impl ShowModelManager {
     }
 }
 
+/// ファイルの更新日時を返します。ファイルが存在しない場合はNoneを返します。
+async fn file_modified(path: &Path) -> Option<SystemTime> {
+    tokio::fs::metadata(path).await.and_then(|metadata| metadata.modified()).ok()
+}
+
+/// ショーファイルに対応する自動保存ファイルのパスを返します。
+fn auto_save_path(show_model_path: &Path) -> PathBuf {
+    let mut file_name = show_model_path.file_name().unwrap_or(DEFAULT_SHOW_FILE_NAME.as_ref()).to_os_string();
+    file_name.push(".");
+    file_name.push(AUTOSAVE_EXTENSION);
+    show_model_path.with_file_name(file_name)
+}
+
+/// 音声ファイルとして読み込めるかを確認します。ディレクトリは開けても再生できないため、通常のファイルに限ります。
+async fn check_readable_file(path: &Path) -> std::io::Result<()> {
+    let file = tokio::fs::File::open(path).await?;
+    if !file.metadata().await?.is_file() {
+        return Err(std::io::Error::other("not a regular file"));
+    }
+    Ok(())
+}
+
+/// ハッシュが一致したコピー済みのファイルのうち、`source`と内容が同じもののコピー先を返します。
+/// ハッシュは衝突し得るため、長さと内容を比べて確かめます。
+async fn find_same_file(candidates: &[(PathBuf, PathBuf)], source: &Path) -> Result<Option<PathBuf>, anyhow::Error> {
+    for (collected_source, relative) in candidates {
+        if files_equal(collected_source.clone(), source.to_path_buf())
+            .await
+            .with_context(|| format!("Failed to compare '{}'", source.display()))?
+        {
+            return Ok(Some(relative.clone()));
+        }
+    }
+    Ok(None)
+}
+
+/// 2つのファイルの内容が同じかどうかを返します。
+async fn files_equal(a: PathBuf, b: PathBuf) -> Result<bool, anyhow::Error> {
+    tokio::task::spawn_blocking(move || {
+        let (mut file_a, mut file_b) = (std::fs::File::open(a)?, std::fs::File::open(b)?);
+        if file_a.metadata()?.len() != file_b.metadata()?.len() {
+            return Ok(false);
+        }
+        let (mut buffer_a, mut buffer_b) = (vec![0; 64 * 1024], vec![0; 64 * 1024]);
+        loop {
+            let read = file_a.read(&mut buffer_a)?;
+            if read == 0 {
+                return Ok(true);
+            }
+            file_b.read_exact(&mut buffer_b[..read])?;
+            if buffer_a[..read] != buffer_b[..read] {
+                return Ok(false);
+            }
+        }
+    })
+    .await?
+}
+
+/// ファイルの内容のハッシュを求めます。
+async fn hash_file(path: PathBuf) -> Result<u64, anyhow::Error> {
+    tokio::task::spawn_blocking(move || {
+        let mut file = std::fs::File::open(path)?;
+        let mut hasher = DefaultHasher::new();
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let read = file.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.write(&buffer[..read]);
+        }
+        Ok(hasher.finish())
+    })
+    .await?
+}
+
+/// 使用済みの名前と重複しないファイル名を返します。重複する場合は拡張子の前に番号を付けます。
+fn unique_file_name(source: &Path, used_names: &mut HashSet<PathBuf>) -> PathBuf {
+    let stem = source.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = source.extension().map(|extension| format!(".{}", extension.to_string_lossy())).unwrap_or_default();
+    let file_name = (0..)
+        .map(|suffix| match suffix {
+            0 => PathBuf::from(format!("{}{}", stem, extension)),
+            suffix => PathBuf::from(format!("{}-{}{}", stem, suffix, extension)),
+        })
+        .find(|file_name| !used_names.contains(file_name))
+        .unwrap();
+    used_names.insert(file_name.clone());
+    file_name
+}
+
+/// キューリストの順にキュー番号を振り直し、番号が変わったキューのIDと新しい番号を返します。
+fn renumber_cues(cues: &mut [Cue], start: f64, increment: f64, preserve_existing: bool) -> Vec<(Uuid, String)> {
+    let reserved: HashSet<String> = if preserve_existing {
+        cues.iter().filter(|cue| !cue.number.is_empty()).map(|cue| cue.number.clone()).collect()
+    } else {
+        HashSet::new()
+    };
+    let mut candidates = (0..)
+        .map(|step| format_cue_number(start + step as f64 * increment))
+        .filter(|number| !reserved.contains(number));
+    let mut numbers = Vec::new();
+    for cue in cues.iter_mut() {
+        if preserve_existing && !cue.number.is_empty() {
+            continue;
+        }
+        let number = candidates.next().unwrap();
+        if cue.number != number {
+            cue.number = number.clone();
+            numbers.push((cue.id, number));
+        }
+    }
+    numbers
+}
+
+/// キュー番号を、小数点以下の不要な0を除いて文字列にします。
+fn format_cue_number(number: f64) -> String {
+    // 増分の加算による誤差を丸める
+    let formatted = format!("{:.6}", number);
+    formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// 相対パスのメディアを、ショーファイルのあるディレクトリからのパスとして解決します。
+/// ショーファイルが未保存の場合は、作業ディレクトリからの相対パスのまま返します。
+fn resolve_media_path(show_model_path: Option<&Path>, target: &Path) -> PathBuf {
+    match show_model_path.and_then(Path::parent) {
+        Some(show_dir) if target.is_relative() => show_dir.join(target),
+        _ => target.to_path_buf(),
+    }
+}
+
+/// 保存先のディレクトリが変わる場合に、相対パスのメディアを新しいディレクトリからの相対パスに書き換えます。
+/// 書き換えたキューを返します。
+fn rebase_media_paths(cues: &mut [Cue], old_show_path: &Path, new_show_path: &Path) -> Vec<Cue> {
+    let (Ok(old_dir), Ok(new_dir)) = (
+        std::path::absolute(old_show_path.parent().unwrap_or(Path::new(""))),
+        std::path::absolute(new_show_path.parent().unwrap_or(Path::new(""))),
+    ) else {
+        return Vec::new();
+    };
+    let (old_dir, new_dir) = (normalize_path(&old_dir), normalize_path(&new_dir));
+    if old_dir == new_dir {
+        return Vec::new();
+    }
+
+    let mut rebased_cues = Vec::new();
+    for cue in cues.iter_mut() {
+        if let CueParam::Audio { target, .. } = &mut cue.param
+            && target.is_relative()
+        {
+            *target = relative_path(&new_dir, &normalize_path(&old_dir.join(&*target)));
+            rebased_cues.push(cue.clone());
+        }
+    }
+    rebased_cues
+}
+
+/// `.`と`..`を字句的に取り除きます。
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push("..");
+                }
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// 正規化済みの絶対パス`base`から`target`への相対パスを求めます。
+fn relative_path(base: &Path, target: &Path) -> PathBuf {
+    let base_components: Vec<_> = base.components().collect();
+    let target_components: Vec<_> = target.components().collect();
+    let common = base_components
+        .iter()
+        .zip(&target_components)
+        .take_while(|(a, b)| a == b)
+        .count();
+    if common == 0 {
+        // ドライブが異なる場合などは相対パスにできない
+        return target.to_path_buf();
+    }
+
+    let mut relative = PathBuf::new();
+    for _ in common..base_components.len() {
+        relative.push("..");
+    }
+    for component in &target_components[common..] {
+        relative.push(component);
+    }
+    relative
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum ValidationSeverity {
+    Warning,
+    Error,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationIssue {
+    pub cue_id: Uuid,
+    pub severity: ValidationSeverity,
+    pub message: String,
+}
 
 #[derive(Clone)]
 pub struct ShowModelHandle {
     model: Arc<RwLock<ShowModel>>,
-    command_tx: mpsc::Sender<ModelCommand>,
+    command_tx: mpsc::Sender<ModelRequest>,
     show_model_path: Arc<RwLock<Option<PathBuf>>>,
+    live_lock: Arc<AtomicBool>,
+    duration_cache: DurationCache,
 }
 
 impl ShowModelHandle {
-    pub async fn send_command(&self, command: ModelCommand) -> anyhow::Result<()> {
-        self.command_tx.send(command).await?;
+    pub async fn send_command(&self, command: ModelCommand) -> Result<(), BackendError> {
+        self.command_tx.send((command, None)).await?;
         Ok(())
     }
 
-    pub async fn update_cue(&self, cue: Cue) -> anyhow::Result<()> {
+    /// コマンドを送信し、処理が終わるまで待ちます。処理に失敗した場合は`UiError`を返します。
+    pub async fn execute_command(&self, command: ModelCommand) -> Result<(), BackendError> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.command_tx.send((command, Some(reply_tx))).await?;
+        reply_rx.await??;
+        Ok(())
+    }
+
+    pub async fn update_cue(&self, cue: Cue) -> Result<(), BackendError> {
         self.send_command(ModelCommand::UpdateCue(cue)).await?;
         Ok(())
     }
     
-    pub async fn add_cue(&self, cue: Cue, at_index: usize) -> anyhow::Result<()> {
+    pub async fn patch_cue(&self, cue_id: Uuid, changes: CuePatch) -> Result<(), BackendError> {
+        self.send_command(ModelCommand::PatchCue { cue_id, changes }).await?;
+        Ok(())
+    }
+
+    pub async fn add_cue(&self, cue: Cue, at_index: usize) -> Result<(), BackendError> {
         self.send_command(ModelCommand::AddCue { cue, at_index }).await?;
         Ok(())
     }
 
-    pub async fn remove_cue(&self, cue_id: Uuid) -> anyhow::Result<()> {
+    pub async fn add_cue_from_template(&self, template_name: String, target: PathBuf, at_index: usize) -> Result<(), BackendError> {
+        self.send_command(ModelCommand::AddCueFromTemplate { template_name, target, at_index }).await?;
+        Ok(())
+    }
+
+    pub async fn remove_cue(&self, cue_id: Uuid) -> Result<(), BackendError> {
         self.send_command(ModelCommand::RemoveCue { cue_id }).await?;
         Ok(())
     }
 
-    pub async fn move_cue(&self, cue_id: Uuid, to_index: usize) -> anyhow::Result<()> {
+    pub async fn move_cue(&self, cue_id: Uuid, to_index: usize) -> Result<(), BackendError> {
         self.send_command(ModelCommand::MoveCue { cue_id, to_index }).await?;
         Ok(())
     }
 
-    pub async fn save(&self) -> anyhow::Result<()> {
+    pub async fn duplicate_cue(&self, cue_id: Uuid) -> Result<(), BackendError> {
+        self.send_command(ModelCommand::DuplicateCue { cue_id }).await?;
+        Ok(())
+    }
+
+    pub async fn set_armed(&self, cue_id: Uuid, armed: bool) -> Result<(), BackendError> {
+        self.send_command(ModelCommand::SetArmed { cue_id, armed }).await?;
+        Ok(())
+    }
+
+    pub async fn append_note(&self, cue_id: Uuid, text: String) -> Result<(), BackendError> {
+        self.send_command(ModelCommand::AppendNote { cue_id, text }).await?;
+        Ok(())
+    }
+
+    pub async fn set_live_lock(&self, enabled: bool) -> Result<(), BackendError> {
+        self.send_command(ModelCommand::SetLiveLock { enabled }).await?;
+        Ok(())
+    }
+
+    pub fn is_live_lock_enabled(&self) -> bool {
+        self.live_lock.load(Ordering::Relaxed)
+    }
+
+    pub async fn update_settings(&self, settings: ShowSettings) -> Result<(), BackendError> {
+        self.send_command(ModelCommand::UpdateSettings(settings)).await?;
+        Ok(())
+    }
+
+    /// 再生カーソルの位置を記録し、次の保存時にショーファイルに含めます。
+    /// 再生の操作で変わる値のため、未保存の変更としては扱いません。
+    pub async fn set_last_cursor(&self, cursor: Option<Uuid>) {
+        self.model.write().await.last_cursor = cursor;
+    }
+
+    pub async fn get_settings(&self) -> ShowSettings {
+        self.read().await.settings.clone()
+    }
+
+    pub async fn save(&self) -> Result<(), BackendError> {
         self.send_command(ModelCommand::Save).await?;
         Ok(())
     }
 
-    pub async fn save_as(&self, path: PathBuf) -> anyhow::Result<()> {
+    pub async fn save_force(&self) -> Result<(), BackendError> {
+        self.send_command(ModelCommand::SaveForce).await?;
+        Ok(())
+    }
+
+    pub async fn save_as(&self, path: PathBuf) -> Result<(), BackendError> {
         self.send_command(ModelCommand::SaveToFile(path)).await?;
         Ok(())
     }
 
-    pub async fn load_from_file(&self, path: PathBuf) -> anyhow::Result<()> {
+    pub async fn load_from_file(&self, path: PathBuf) -> Result<(), BackendError> {
         self.send_command(ModelCommand::LoadFromFile(path)).await?;
         Ok(())
     }
@@ -244,6 +1054,114 @@ impl ShowModelHandle {
             .cloned()
     }
 
+    /// キュー番号が一致する最初のキューを返します。
+    pub async fn get_cue_by_number(&self, number: &str) -> Option<Cue> {
+        self.read()
+            .await
+            .cues
+            .iter()
+            .find(|c| c.number == number)
+            .cloned()
+    }
+
+    /// オーディオキューとWaitキューの長さ(秒)を返します。オーディオキューは再生範囲(start_time〜end_time)の長さで、ループは含めません。
+    /// 音声ファイルを読み込めないキューは含めません。
+    pub async fn cue_durations(&self) -> HashMap<Uuid, f64> {
+        let cues = self.read().await.cues.clone();
+        let mut durations = HashMap::new();
+        for cue in cues {
+            if let Some(duration) = self.cue_duration(&cue).await {
+                durations.insert(cue.id, duration);
+            }
+        }
+        durations
+    }
+
+    /// オーディオキュー・Waitキューの長さ(秒)を返します。長さが決まらないキューの場合はNoneを返します。
+    pub async fn cue_duration(&self, cue: &Cue) -> Option<f64> {
+        match &cue.param {
+            CueParam::Audio { target, start_time, end_time, .. } => {
+                let file_duration = self.duration_cache.duration(&self.resolve_path(target).await).await?;
+                let start = start_time.unwrap_or(0.0).clamp(0.0, file_duration);
+                let end = end_time.map_or(file_duration, |end_time| end_time.min(file_duration));
+                Some((end - start).max(0.0))
+            }
+            CueParam::Wait { duration } => Some(*duration),
+            CueParam::Group { .. } | CueParam::WaitForInput { .. } => None,
+        }
+    }
+
+    pub async fn get_cues_referencing(&self, cue_id: &Uuid) -> Vec<Uuid> {
+        self.read()
+            .await
+            .cues
+            .iter()
+            .filter(|c| c.param.target_cue_ids().contains(cue_id))
+            .map(|c| c.id)
+            .collect()
+    }
+
+    /// キューの名前・番号・メモに`query`を含むキューのIDを、キューリストの順に返します。
+    /// 大文字と小文字は区別しません。`kind`を指定した場合はその種類のキューのみを返します。
+    pub async fn search_cues(&self, query: &str, kind: Option<CueKind>) -> Vec<Uuid> {
+        let query = query.to_lowercase();
+        self.read()
+            .await
+            .cues
+            .iter()
+            .filter(|c| kind.is_none_or(|kind| c.param.kind() == kind))
+            .filter(|c| [&c.name, &c.number, &c.notes].iter().any(|field| field.to_lowercase().contains(&query)))
+            .map(|c| c.id)
+            .collect()
+    }
+
+    /// キューリスト全体を検証し、問題点の一覧を返します。モデルは変更しません。
+    pub async fn validate_show(&self) -> Vec<ValidationIssue> {
+        let cues = self.read().await.cues.clone();
+        let mut issues = Vec::new();
+        for cue in &cues {
+            let mut report = |severity, message| {
+                issues.push(ValidationIssue { cue_id: cue.id, severity, message });
+            };
+            if let Err(message) = cue.validate() {
+                report(ValidationSeverity::Error, message);
+            }
+            if let CueParam::Audio { target, start_time, end_time, automation, .. } = &cue.param {
+                if let Err(error) = check_readable_file(&self.resolve_path(target).await).await {
+                    report(ValidationSeverity::Error, format!("Audio file '{}' is not readable: {}", target.display(), error));
+                }
+                // 終了位置を指定していないキューの長さは、ファイルを読み込んで確認する
+                if let Some(last_time) = automation.as_ref().and_then(|points| points.last()).map(|(time, _)| *time)
+                    && let Some(duration) = self.cue_duration(cue).await
+                    && last_time > duration
+                {
+                    report(ValidationSeverity::Warning, format!("Automation breakpoint at {} seconds exceeds the cue duration ({} seconds).", last_time, duration));
+                }
+                if let (Some(start_time), Some(end_time)) = (start_time, end_time)
+                    && start_time >= end_time
+                {
+                    report(ValidationSeverity::Error, format!("Start time ({}) must be before end time ({}).", start_time, end_time));
+                }
+            }
+            for target_id in cue.param.target_cue_ids() {
+                if !cues.iter().any(|c| c.id == target_id) {
+                    report(ValidationSeverity::Error, format!("Target cue '{}' doesn't exist.", target_id));
+                }
+            }
+        }
+        issues
+    }
+
+    /// 各キューのショー開始からの開始時刻を計算したキューシートを返します。
+    pub async fn timing_sheet(&self) -> Vec<CueSheetEntry> {
+        cue_sheet(&self.read().await.cues)
+    }
+
+    /// キューのメディアのパスを、ショーファイルの場所を基準に解決します。
+    pub async fn resolve_path(&self, target: &Path) -> PathBuf {
+        resolve_media_path(self.show_model_path.read().await.as_deref(), target)
+    }
+
     pub async fn get_current_file_path(&self) -> Option<PathBuf> {
         self.show_model_path.read().await.clone()
     }
@@ -252,3 +1170,876 @@ impl ShowModelHandle {
         self.model.read().await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    use crate::{controller::{ActiveCue, PlaybackStatus}, model::{cue::{AudioCueFadeParam, AudioCueLevels, CueSequence, RetriggerMode}, settings::CueTemplate}};
+
+    fn make_cue(id: Uuid, number: &str) -> Cue {
+        Cue {
+            id,
+            number: number.to_string(),
+            name: "Play IGY".to_string(),
+            notes: "".to_string(),
+            pre_wait: 0.0,
+            post_wait: 0.0,
+            sequence: CueSequence::DoNotContinue,
+            follow_offset: None,
+            armed: true,
+            retrigger_mode: RetriggerMode::Restart,
+            color: None,
+            order: 0.0,
+            schedule: None,
+            param: CueParam::Audio {
+                target: PathBuf::from("./I.G.Y.flac"),
+                start_time: None,
+                fade_in_param: None,
+                end_time: None,
+                fade_out_param: None,
+                levels: AudioCueLevels { master: 0.0 },
+                loop_region: None,
+                loop_count: None,
+                stop_fade_param: None,
+                output_bus: None,
+                streaming: false,
+                normalize_to: None,
+                automation: None,
+            },
+        }
+    }
+
+    async fn setup_manager(cues: Vec<Cue>) -> (ShowModelManager, broadcast::Receiver<UiEvent>) {
+        let (event_tx, event_rx) = broadcast::channel::<UiEvent>(32);
+        let (manager, _) = ShowModelManager::new(event_tx);
+        manager
+            .write_with(|model| {
+                model.name = "TestShowModel".to_string();
+                model.cues = cues;
+            })
+            .await;
+        (manager, event_rx)
+    }
+
+    #[tokio::test]
+    async fn duplicate_cue() {
+        let cue_id = Uuid::new_v4();
+        let next_id = Uuid::new_v4();
+        let cues = vec![Cue { order: 1.0, ..make_cue(cue_id, "1") }, Cue { order: 2.0, ..make_cue(next_id, "1.1") }];
+        let (manager, _) = setup_manager(cues).await;
+
+        let event = manager.process_command(ModelCommand::DuplicateCue { cue_id }).await;
+
+        let Some(UiEvent::CueAdded { cue, at_index }) = event else {
+            panic!("Wrong UiEvent emitted.");
+        };
+        assert_eq!(at_index, 1);
+        assert_ne!(cue.id, cue_id);
+        assert_eq!(cue.name, "Play IGY copy");
+        assert_eq!(cue.number, "1.2");
+
+        let model = manager.read().await;
+        assert_eq!(model.cues.len(), 3);
+        assert_eq!(model.cues[1], cue);
+        assert_eq!(model.cues[2].id, next_id);
+    }
+
+    #[tokio::test]
+    async fn add_cue_with_missing_file() {
+        let (manager, mut event_rx) = setup_manager(vec![]).await;
+        let cue = Cue { order: 1.0, ..make_cue(Uuid::new_v4(), "1") };
+
+        let event = manager.process_command(ModelCommand::AddCue { cue: cue.clone(), at_index: 0 }).await;
+
+        assert!(event.is_none());
+        assert_eq!(event_rx.recv().await.unwrap(), UiEvent::CueAdded { cue: cue.clone(), at_index: 0 });
+        assert!(matches!(event_rx.recv().await.unwrap(), UiEvent::CueValidationWarning { cue_id, .. } if cue_id == cue.id));
+        assert_eq!(manager.read().await.cues, vec![cue.clone()]);
+
+        // ディレクトリは開けても再生できないため警告する
+        let mut directory_cue = Cue { order: 2.0, ..make_cue(Uuid::new_v4(), "2") };
+        if let CueParam::Audio { target, .. } = &mut directory_cue.param {
+            *target = std::env::temp_dir();
+        }
+        manager.process_command(ModelCommand::AddCue { cue: directory_cue.clone(), at_index: 1 }).await;
+        assert!(matches!(event_rx.recv().await.unwrap(), UiEvent::CueAdded { .. }));
+        assert!(matches!(event_rx.recv().await.unwrap(), UiEvent::CueValidationWarning { cue_id, .. } if cue_id == directory_cue.id));
+    }
+
+    #[tokio::test]
+    async fn update_cue_with_invalid_easing() {
+        let cue_id = Uuid::new_v4();
+        let (manager, _) = setup_manager(vec![make_cue(cue_id, "1")]).await;
+
+        let mut cue = make_cue(cue_id, "1");
+        if let CueParam::Audio { fade_in_param, .. } = &mut cue.param {
+            *fade_in_param = Some(AudioCueFadeParam { duration: 2.0, easing: kira::Easing::InPowi(0).into() });
+        }
+        let event = manager.process_command(ModelCommand::UpdateCue(cue)).await;
+
+        assert!(matches!(event, Some(UiEvent::OperationFailed { error: UiError::CueEdit { cue_id: id, .. } }) if id == cue_id));
+        assert_eq!(manager.read().await.cues[0], make_cue(cue_id, "1"));
+    }
+
+    #[tokio::test]
+    async fn patch_cue() {
+        let cue_id = Uuid::new_v4();
+        let mut cue = make_cue(cue_id, "1");
+        if let CueParam::Audio { start_time, .. } = &mut cue.param {
+            *start_time = Some(1.0);
+        }
+        let (manager, _) = setup_manager(vec![cue]).await;
+        let changes: CuePatch = serde_json::from_value(serde_json::json!({
+            "name": "Renamed",
+            "levels": { "master": -6.0 },
+            "startTime": null,
+        }))
+        .unwrap();
+
+        let event = manager.process_command(ModelCommand::PatchCue { cue_id, changes }).await;
+
+        let Some(UiEvent::CueUpdated { cue }) = event else {
+            panic!("Wrong UiEvent emitted.");
+        };
+        assert_eq!(cue.name, "Renamed");
+        assert_eq!(cue.number, "1");
+        let CueParam::Audio { levels, start_time, .. } = &cue.param else {
+            unreachable!();
+        };
+        assert_eq!(levels.master, -6.0);
+        assert_eq!(*start_time, None);
+        assert_eq!(manager.read().await.cues[0], cue);
+
+        // 適用できない変更は全体を拒否する
+        let mut wait_cue = make_cue(Uuid::new_v4(), "2");
+        wait_cue.param = CueParam::Wait { duration: 1.0 };
+        manager.write_with(|model| model.cues.push(wait_cue.clone())).await;
+        let changes = CuePatch { name: Some("Wait".to_string()), levels: Some(AudioCueLevels { master: 0.0 }), ..Default::default() };
+        let event = manager.process_command(ModelCommand::PatchCue { cue_id: wait_cue.id, changes }).await;
+        assert!(matches!(event, Some(UiEvent::OperationFailed { error: UiError::CueEdit { .. } })));
+        assert_eq!(manager.read().await.cues[1], wait_cue);
+    }
+
+    #[tokio::test]
+    async fn patch_cue_automation() {
+        let cue_id = Uuid::new_v4();
+        let mut cue = make_cue(cue_id, "1");
+        if let CueParam::Audio { start_time, end_time, .. } = &mut cue.param {
+            *start_time = Some(1.0);
+            *end_time = Some(5.0);
+        }
+        let (manager, _) = setup_manager(vec![cue]).await;
+
+        let changes: CuePatch = serde_json::from_value(serde_json::json!({ "automation": [[1.0, -10.0], [3.0, 2.0]] })).unwrap();
+        let event = manager.process_command(ModelCommand::PatchCue { cue_id, changes }).await;
+        let Some(UiEvent::CueUpdated { cue }) = event else {
+            panic!("Wrong UiEvent emitted.");
+        };
+        let CueParam::Audio { automation, .. } = &cue.param else {
+            unreachable!();
+        };
+        assert_eq!(*automation, Some(vec![(1.0, -10.0), (3.0, 2.0)]));
+
+        // 時刻順でないブレークポイントと、キューの長さ(4秒)を超えるブレークポイントは拒否する
+        for automation in [vec![(2.0, 0.0), (1.0, 0.0)], vec![(4.5, 0.0)]] {
+            let changes = CuePatch { automation: Some(Some(automation)), ..Default::default() };
+            let event = manager.process_command(ModelCommand::PatchCue { cue_id, changes }).await;
+            assert!(matches!(event, Some(UiEvent::OperationFailed { error: UiError::CueEdit { .. } })));
+        }
+        assert_eq!(manager.read().await.cues[0], cue);
+    }
+
+    #[tokio::test]
+    async fn patch_cue_color() {
+        let cue_id = Uuid::new_v4();
+        let (manager, _) = setup_manager(vec![make_cue(cue_id, "1")]).await;
+
+        let changes: CuePatch = serde_json::from_value(serde_json::json!({ "color": "#00aa3F" })).unwrap();
+        let event = manager.process_command(ModelCommand::PatchCue { cue_id, changes }).await;
+        let Some(UiEvent::CueUpdated { cue }) = event else {
+            panic!("Wrong UiEvent emitted.");
+        };
+        assert_eq!(cue.color.as_deref(), Some("#00aa3F"));
+
+        // 形式が違う色は拒否する
+        for color in ["green", "#00aa3", "#00aa3g", "00aa3f0"] {
+            let changes = CuePatch { color: Some(Some(color.to_string())), ..Default::default() };
+            let event = manager.process_command(ModelCommand::PatchCue { cue_id, changes }).await;
+            assert!(matches!(event, Some(UiEvent::OperationFailed { error: UiError::CueEdit { .. } })), "{color}");
+        }
+        assert_eq!(manager.read().await.cues[0].color.as_deref(), Some("#00aa3F"));
+
+        let changes: CuePatch = serde_json::from_value(serde_json::json!({ "color": null })).unwrap();
+        manager.process_command(ModelCommand::PatchCue { cue_id, changes }).await;
+        assert_eq!(manager.read().await.cues[0].color, None);
+    }
+
+    #[tokio::test]
+    async fn move_cue() {
+        let cue_ids = [Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4()];
+        let cues = cue_ids.iter().enumerate().map(|(index, id)| Cue { order: index as f64 + 1.0, ..make_cue(*id, "") }).collect();
+        let (manager, _) = setup_manager(cues).await;
+
+        let event = manager.process_command(ModelCommand::MoveCue { cue_id: cue_ids[0], to_index: 2 }).await;
+
+        assert_eq!(event, Some(UiEvent::CueMoved { cue_id: cue_ids[0], to_index: 2, order: 4.0 }));
+        let order: Vec<Uuid> = manager.read().await.cues.iter().map(|c| c.id).collect();
+        assert_eq!(order, vec![cue_ids[1], cue_ids[2], cue_ids[0]]);
+
+        // 移動したキュー以外の並び順は変わらない
+        let event = manager.process_command(ModelCommand::MoveCue { cue_id: cue_ids[0], to_index: 1 }).await;
+        assert_eq!(event, Some(UiEvent::CueMoved { cue_id: cue_ids[0], to_index: 1, order: 2.5 }));
+        let orders: Vec<f64> = manager.read().await.cues.iter().map(|c| c.order).collect();
+        assert_eq!(orders, vec![2.0, 2.5, 3.0]);
+    }
+
+    #[tokio::test]
+    async fn move_cue_between_same_order() {
+        let cue_ids = [Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4()];
+        let (manager, _) = setup_manager(cue_ids.iter().map(|id| make_cue(*id, "")).collect()).await;
+
+        // 前後のキューの並び順が同じ場合は、全てのキューの並び順を振り直して送信する
+        let event = manager.process_command(ModelCommand::MoveCue { cue_id: cue_ids[2], to_index: 1 }).await;
+
+        let orders = vec![(cue_ids[0], 1.0), (cue_ids[2], 2.0), (cue_ids[1], 3.0)];
+        let expected = vec![
+            UiEvent::CueMoved { cue_id: cue_ids[2], to_index: 1, order: 2.0 },
+            UiEvent::CuesReordered { orders: orders.clone() },
+        ];
+        assert_eq!(event, Some(UiEvent::BatchApplied { events: expected }));
+        let cues: Vec<(Uuid, f64)> = manager.read().await.cues.iter().map(|c| (c.id, c.order)).collect();
+        assert_eq!(cues, orders);
+    }
+
+    #[tokio::test]
+    async fn move_cue_out_of_list() {
+        let cue_ids = [Uuid::new_v4(), Uuid::new_v4()];
+        let (manager, _) = setup_manager(cue_ids.iter().map(|id| make_cue(*id, "")).collect()).await;
+
+        let event = manager.process_command(ModelCommand::MoveCue { cue_id: cue_ids[0], to_index: 5 }).await;
+
+        assert!(matches!(event, Some(UiEvent::OperationFailed { error: UiError::CueEdit { cue_id, .. } }) if cue_id == cue_ids[0]));
+        let order: Vec<Uuid> = manager.read().await.cues.iter().map(|c| c.id).collect();
+        assert_eq!(order, cue_ids.to_vec());
+    }
+
+    #[tokio::test]
+    async fn cues_referencing() {
+        let cue_id = Uuid::new_v4();
+        let (event_tx, _) = broadcast::channel::<UiEvent>(32);
+        let (manager, handle) = ShowModelManager::new(event_tx);
+        manager
+            .write_with(|model| {
+                model.cues = vec![make_cue(cue_id, "1"), make_cue(Uuid::new_v4(), "2")];
+            })
+            .await;
+
+        assert!(handle.get_cues_referencing(&cue_id).await.is_empty());
+
+        let group_id = Uuid::new_v4();
+        let mut group_cue = make_cue(group_id, "3");
+        group_cue.param = CueParam::Group { children: vec![cue_id] };
+        manager.write_with(|model| model.cues.push(group_cue)).await;
+
+        assert_eq!(handle.get_cues_referencing(&cue_id).await, vec![group_id]);
+    }
+
+    #[tokio::test]
+    async fn resolve_relative_media_path() {
+        let (event_tx, _) = broadcast::channel::<UiEvent>(32);
+        let (manager, handle) = ShowModelManager::new(event_tx);
+        let root = std::env::temp_dir().join("sbsp_resolve_test");
+
+        assert_eq!(handle.resolve_path(Path::new("media/IGY.flac")).await, PathBuf::from("media/IGY.flac"));
+
+        *manager.show_model_path.write().await = Some(root.join("show").join("show.sbsp"));
+        assert_eq!(
+            handle.resolve_path(Path::new("media/IGY.flac")).await,
+            root.join("show").join("media/IGY.flac")
+        );
+        assert_eq!(handle.resolve_path(&root.join("IGY.flac")).await, root.join("IGY.flac"));
+    }
+
+    #[tokio::test]
+    async fn execute_command() {
+        let cue_id = Uuid::new_v4();
+        let (event_tx, mut event_rx) = broadcast::channel::<UiEvent>(32);
+        let (manager, handle) = ShowModelManager::new(event_tx);
+        manager.write_with(|model| model.cues = vec![make_cue(cue_id, "1")]).await;
+        tokio::spawn(manager.run(CancellationToken::new()));
+
+        handle.execute_command(ModelCommand::RemoveCue { cue_id }).await.unwrap();
+        assert_eq!(event_rx.recv().await.unwrap(), UiEvent::CueRemoved { cue_id });
+
+        let error = handle.execute_command(ModelCommand::RemoveCue { cue_id }).await.unwrap_err();
+        assert!(matches!(
+            error,
+            BackendError::Model(UiError::CueEdit { cue_id: id, message }) if id == cue_id && message == "Cue doesn't exist."
+        ));
+        // 失敗は他のクライアントにも通知される
+        assert!(matches!(event_rx.recv().await.unwrap(), UiEvent::OperationFailed { .. }));
+    }
+
+    #[tokio::test]
+    async fn validate_show() {
+        let missing_id = Uuid::new_v4();
+        let reversed_id = Uuid::new_v4();
+        let wait_id = Uuid::new_v4();
+        let mut reversed_cue = make_cue(reversed_id, "2");
+        reversed_cue.param = CueParam::Audio {
+            target: PathBuf::from("Cargo.toml"),
+            start_time: Some(10.0),
+            fade_in_param: None,
+            end_time: Some(5.0),
+            fade_out_param: None,
+            levels: AudioCueLevels { master: 0.0 },
+            loop_region: None,
+            loop_count: None,
+            stop_fade_param: None,
+            output_bus: None,
+            streaming: false,
+            normalize_to: None,
+            automation: None,
+        };
+        let mut wait_cue = make_cue(wait_id, "3");
+        wait_cue.param = CueParam::Wait { duration: 1.0 };
+
+        let (event_tx, _) = broadcast::channel::<UiEvent>(32);
+        let (manager, handle) = ShowModelManager::new(event_tx);
+        manager
+            .write_with(|model| {
+                model.cues = vec![make_cue(missing_id, "1"), reversed_cue, wait_cue];
+            })
+            .await;
+
+        let issues = handle.validate_show().await;
+
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].cue_id, missing_id);
+        assert_eq!(issues[0].severity, ValidationSeverity::Error);
+        assert_eq!(issues[1].cue_id, reversed_id);
+        assert_eq!(issues[1].severity, ValidationSeverity::Error);
+    }
+
+    #[tokio::test]
+    async fn collected_file_hash_collision() {
+        let root = std::env::temp_dir().join(format!("sbsp_test_{}", Uuid::new_v4()));
+        tokio::fs::create_dir_all(root.join("a")).await.unwrap();
+        tokio::fs::create_dir_all(root.join("b")).await.unwrap();
+        tokio::fs::create_dir_all(root.join("c")).await.unwrap();
+        tokio::fs::write(root.join("a").join("IGY.flac"), b"IGY").await.unwrap();
+        tokio::fs::write(root.join("b").join("IGY.flac"), b"IGZ").await.unwrap();
+        tokio::fs::write(root.join("c").join("IGY.flac"), b"IGY").await.unwrap();
+
+        // ハッシュが衝突した場合を想定し、同じ名前・長さでも内容が異なるファイルはまとめない
+        let candidates = vec![(root.join("a").join("IGY.flac"), PathBuf::from("media/IGY.flac"))];
+        assert_eq!(find_same_file(&candidates, &root.join("b").join("IGY.flac")).await.unwrap(), None);
+        assert_eq!(
+            find_same_file(&candidates, &root.join("c").join("IGY.flac")).await.unwrap(),
+            Some(PathBuf::from("media/IGY.flac"))
+        );
+        tokio::fs::remove_dir_all(root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn save_to_other_directory_rebases_media() {
+        let root = std::env::temp_dir().join(format!("sbsp_test_{}", Uuid::new_v4()));
+        let old_path = root.join("a").join("show.json");
+        let new_path = root.join("b").join("sub").join("show.json");
+        tokio::fs::create_dir_all(new_path.parent().unwrap()).await.unwrap();
+
+        let cue_id = Uuid::new_v4();
+        let absolute_id = Uuid::new_v4();
+        let mut cue = make_cue(cue_id, "1");
+        if let CueParam::Audio { target, .. } = &mut cue.param {
+            *target = PathBuf::from("./media/IGY.flac");
+        }
+        let mut absolute_cue = make_cue(absolute_id, "2");
+        if let CueParam::Audio { target, .. } = &mut absolute_cue.param {
+            *target = root.join("IGY.flac");
+        }
+        let (manager, _) = setup_manager(vec![cue, absolute_cue]).await;
+        *manager.show_model_path.write().await = Some(old_path);
+
+        let event = manager.process_command(ModelCommand::SaveToFile(new_path.clone())).await;
+
+        assert_eq!(event, Some(UiEvent::ShowModelSaved { path: new_path.clone() }));
+        let expected = PathBuf::from("../../a/media/IGY.flac");
+        let saved: ShowModel = serde_json::from_str(&tokio::fs::read_to_string(&new_path).await.unwrap()).unwrap();
+        for model in [&saved, &*manager.read().await] {
+            let CueParam::Audio { target, .. } = &model.cues[0].param else { unreachable!() };
+            assert_eq!(target, &expected);
+            let CueParam::Audio { target, .. } = &model.cues[1].param else { unreachable!() };
+            assert_eq!(target, &root.join("IGY.flac"));
+        }
+
+        tokio::fs::remove_dir_all(&root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn collect_assets() {
+        let root = std::env::temp_dir().join(format!("sbsp_test_{}", Uuid::new_v4()));
+        let show_path = root.join("show").join("show.sbsp");
+        let dest_dir = root.join("package");
+        tokio::fs::create_dir_all(root.join("show").join("media")).await.unwrap();
+        tokio::fs::create_dir_all(root.join("other")).await.unwrap();
+        tokio::fs::write(root.join("show").join("media").join("IGY.flac"), b"IGY").await.unwrap();
+        tokio::fs::write(root.join("show").join("IGY copy.flac"), b"IGY").await.unwrap();
+        tokio::fs::write(root.join("other").join("IGY.flac"), b"IGY remix").await.unwrap();
+
+        let targets = [PathBuf::from("media/IGY.flac"), PathBuf::from("IGY copy.flac"), root.join("other").join("IGY.flac")];
+        let cues: Vec<Cue> = targets
+            .iter()
+            .enumerate()
+            .map(|(index, path)| {
+                let mut cue = make_cue(Uuid::new_v4(), &index.to_string());
+                if let CueParam::Audio { target, .. } = &mut cue.param {
+                    *target = path.clone();
+                }
+                cue
+            })
+            .collect();
+        let (manager, mut event_rx) = setup_manager(cues).await;
+        *manager.show_model_path.write().await = Some(show_path);
+
+        let event = manager.process_command(ModelCommand::CollectAssets { dest_dir: dest_dir.clone() }).await;
+
+        let new_path = dest_dir.join("show.sbsp");
+        assert_eq!(event, Some(UiEvent::ShowModelSaved { path: new_path.clone() }));
+        // 内容が同じファイルは1つにまとめ、名前が重複する別のファイルには番号を付ける
+        let expected = [PathBuf::from("media/IGY.flac"), PathBuf::from("media/IGY.flac"), PathBuf::from("media/IGY-1.flac")];
+        let saved: ShowModel = serde_json::from_str(&tokio::fs::read_to_string(&new_path).await.unwrap()).unwrap();
+        for model in [&saved, &*manager.read().await] {
+            let collected_targets: Vec<&PathBuf> = model
+                .cues
+                .iter()
+                .map(|cue| match &cue.param {
+                    CueParam::Audio { target, .. } => target,
+                    _ => unreachable!(),
+                })
+                .collect();
+            assert_eq!(collected_targets, expected.iter().collect::<Vec<_>>());
+        }
+        assert_eq!(tokio::fs::read(dest_dir.join("media/IGY-1.flac")).await.unwrap(), b"IGY remix");
+        assert_eq!(std::fs::read_dir(dest_dir.join("media")).unwrap().count(), 2);
+        assert_eq!(manager.show_model_path.read().await.as_ref(), Some(&new_path));
+
+        let mut progress = Vec::new();
+        while let Ok(event) = event_rx.try_recv() {
+            if let UiEvent::AssetCollectProgress { collected, total } = event {
+                progress.push((collected, total));
+            }
+        }
+        assert_eq!(progress, vec![(1, 3), (2, 3), (3, 3)]);
+
+        tokio::fs::remove_dir_all(&root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn duplicate_missing_cue() {
+        let (manager, _) = setup_manager(vec![make_cue(Uuid::new_v4(), "1")]).await;
+        let cue_id = Uuid::new_v4();
+
+        let event = manager.process_command(ModelCommand::DuplicateCue { cue_id }).await;
+
+        assert!(matches!(event, Some(UiEvent::OperationFailed { error: UiError::CueEdit { cue_id: id, .. } }) if id == cue_id));
+        assert_eq!(manager.read().await.cues.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn renumber_cues() {
+        let ids: Vec<Uuid> = (0..4).map(|_| Uuid::new_v4()).collect();
+        let (manager, _) = setup_manager(vec![
+            make_cue(ids[0], "1"),
+            make_cue(ids[1], "1.1"),
+            make_cue(ids[2], ""),
+            make_cue(ids[3], "7"),
+        ])
+        .await;
+
+        let event = manager.process_command(ModelCommand::RenumberCues { start: 1.0, increment: 0.5, preserve_existing: false }).await;
+
+        // 番号が変わらないキューは含めない
+        let expected = vec![(ids[1], "1.5".to_string()), (ids[2], "2".to_string()), (ids[3], "2.5".to_string())];
+        assert_eq!(event, Some(UiEvent::CuesRenumbered { numbers: expected }));
+        let numbers: Vec<String> = manager.read().await.cues.iter().map(|cue| cue.number.clone()).collect();
+        assert_eq!(numbers, vec!["1", "1.5", "2", "2.5"]);
+        assert_eq!(format_cue_number(0.1 + 0.2), "0.3");
+    }
+
+    #[tokio::test]
+    async fn renumber_preserves_existing_numbers() {
+        let ids: Vec<Uuid> = (0..4).map(|_| Uuid::new_v4()).collect();
+        let (manager, _) = setup_manager(vec![
+            make_cue(ids[0], ""),
+            make_cue(ids[1], "2"),
+            make_cue(ids[2], ""),
+            make_cue(ids[3], ""),
+        ])
+        .await;
+
+        let event = manager.process_command(ModelCommand::RenumberCues { start: 1.0, increment: 1.0, preserve_existing: true }).await;
+
+        // 既存の番号と重複する番号は飛ばす
+        let expected = vec![(ids[0], "1".to_string()), (ids[2], "3".to_string()), (ids[3], "4".to_string())];
+        assert_eq!(event, Some(UiEvent::CuesRenumbered { numbers: expected }));
+        assert_eq!(manager.read().await.cues[1].number, "2");
+
+        let event = manager.process_command(ModelCommand::RenumberCues { start: 1.0, increment: 0.0, preserve_existing: false }).await;
+        assert!(matches!(event, Some(UiEvent::OperationFailed { error: UiError::Renumber { .. } })));
+    }
+
+    #[tokio::test]
+    async fn batch_applies_all_commands() {
+        let cue_id = Uuid::new_v4();
+        let (manager, mut event_rx) = setup_manager(vec![make_cue(cue_id, "1")]).await;
+        let pasted = [Cue { order: 1.0, ..make_cue(Uuid::new_v4(), "2") }, Cue { order: 2.0, ..make_cue(Uuid::new_v4(), "3") }];
+
+        let event = manager
+            .process_command(ModelCommand::Batch(vec![
+                ModelCommand::AddCue { cue: pasted[0].clone(), at_index: 1 },
+                ModelCommand::AddCue { cue: pasted[1].clone(), at_index: 2 },
+                ModelCommand::SetArmed { cue_id, armed: false },
+            ]))
+            .await;
+
+        // 音声ファイルの警告はまとめたイベントの後に送信する
+        assert!(event.is_none());
+        assert_eq!(
+            event_rx.recv().await.unwrap(),
+            UiEvent::BatchApplied {
+                events: vec![
+                    UiEvent::CueAdded { cue: pasted[0].clone(), at_index: 1 },
+                    UiEvent::CueAdded { cue: pasted[1].clone(), at_index: 2 },
+                    UiEvent::CueArmChanged { cue_id, armed: false },
+                ]
+            }
+        );
+        assert!(matches!(event_rx.recv().await.unwrap(), UiEvent::CueValidationWarning { cue_id, .. } if cue_id == pasted[0].id));
+        let model = manager.read().await;
+        assert_eq!(model.cues.len(), 3);
+        assert!(!model.cues[0].armed);
+    }
+
+    #[tokio::test]
+    async fn batch_rolls_back_on_failure() {
+        let cue_id = Uuid::new_v4();
+        let missing_id = Uuid::new_v4();
+        let (manager, _) = setup_manager(vec![make_cue(cue_id, "1")]).await;
+
+        let event = manager
+            .process_command(ModelCommand::Batch(vec![
+                ModelCommand::AddCue { cue: make_cue(Uuid::new_v4(), "2"), at_index: 1 },
+                ModelCommand::RemoveCue { cue_id: missing_id },
+            ]))
+            .await;
+
+        assert!(matches!(event, Some(UiEvent::OperationFailed { error: UiError::CueEdit { cue_id: id, .. } }) if id == missing_id));
+        assert_eq!(manager.read().await.cues.len(), 1);
+
+        // ショーの内容を変更しないコマンドはまとめられない
+        let event = manager
+            .process_command(ModelCommand::Batch(vec![ModelCommand::RemoveCue { cue_id }, ModelCommand::Save]))
+            .await;
+        assert!(matches!(event, Some(UiEvent::OperationFailed { error: UiError::Batch { .. } })));
+        assert_eq!(manager.read().await.cues.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn update_settings_round_trip() {
+        let (manager, _) = setup_manager(vec![make_cue(Uuid::new_v4(), "1")]).await;
+        let mut settings = ShowSettings::default();
+        settings.general.output_device = Some("Main Out".to_string());
+        settings.general.default_fade_in_param = Some(AudioCueFadeParam { duration: 1.5, easing: kira::Easing::Linear.into() });
+        settings.general.default_pre_wait = 0.5;
+
+        let event = manager.process_command(ModelCommand::UpdateSettings(settings.clone())).await;
+        assert_eq!(event, Some(UiEvent::SettingsUpdated { settings: settings.clone() }));
+
+        let path = std::env::temp_dir().join(format!("sbsp_test_{}.sbsp", Uuid::new_v4()));
+        let event = manager.process_command(ModelCommand::SaveToFile(path.clone())).await;
+        assert!(matches!(event, Some(UiEvent::ShowModelSaved { .. })));
+
+        let (loaded_manager, _) = setup_manager(vec![]).await;
+        let event = loaded_manager.process_command(ModelCommand::LoadFromFile(path.clone())).await;
+        assert!(matches!(event, Some(UiEvent::ShowModelLoaded { .. })));
+        assert_eq!(loaded_manager.read().await.settings, settings);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn update_invalid_settings() {
+        let (manager, _) = setup_manager(vec![]).await;
+        let mut settings = ShowSettings::default();
+        settings.general.default_fade_out_param = Some(AudioCueFadeParam { duration: -1.0, easing: kira::Easing::Linear.into() });
+
+        let event = manager.process_command(ModelCommand::UpdateSettings(settings)).await;
+
+        assert!(matches!(event, Some(UiEvent::OperationFailed { error: UiError::SettingsEdit { .. } })));
+        assert_eq!(manager.read().await.settings, ShowSettings::default());
+    }
+
+    #[tokio::test]
+    async fn live_lock() {
+        let cue_id = Uuid::new_v4();
+        let playing_cue_id = Uuid::new_v4();
+        let (manager, _) = setup_manager(vec![make_cue(cue_id, "1"), make_cue(playing_cue_id, "2")]).await;
+        let (state_tx, state_rx) = watch::channel(ShowState::new());
+        let manager = manager.with_show_state(state_rx);
+
+        let event = manager.process_command(ModelCommand::SetLiveLock { enabled: true }).await;
+        assert_eq!(event, Some(UiEvent::LiveLockChanged { enabled: true }));
+
+        state_tx.send_modify(|state| {
+            state.active_cues.insert(playing_cue_id, ActiveCue::new(playing_cue_id, PlaybackStatus::Playing, 0));
+        });
+        let event = manager.process_command(ModelCommand::RemoveCue { cue_id }).await;
+        assert_eq!(event, Some(UiEvent::OperationFailed { error: UiError::LiveLocked { cue_id } }));
+        assert_eq!(manager.read().await.cues.len(), 2);
+
+        state_tx.send_modify(|state| state.active_cues.clear());
+        let event = manager.process_command(ModelCommand::RemoveCue { cue_id }).await;
+        assert_eq!(event, Some(UiEvent::CueRemoved { cue_id }));
+        assert_eq!(manager.read().await.cues.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn live_lock_override() {
+        let cue_id = Uuid::new_v4();
+        let (manager, _) = setup_manager(vec![make_cue(cue_id, "1")]).await;
+        let mut show_state = ShowState::new();
+        show_state.active_cues.insert(cue_id, ActiveCue::new(cue_id, PlaybackStatus::Playing, 0));
+        let (_state_tx, state_rx) = watch::channel(show_state);
+        let manager = manager.with_show_state(state_rx);
+        manager.process_command(ModelCommand::SetLiveLock { enabled: true }).await;
+
+        let event = manager
+            .process_command(ModelCommand::Override(Box::new(ModelCommand::RemoveCue { cue_id })))
+            .await;
+
+        assert_eq!(event, Some(UiEvent::CueRemoved { cue_id }));
+        assert!(manager.read().await.cues.is_empty());
+    }
+
+    #[tokio::test]
+    async fn set_armed() {
+        let cue_id = Uuid::new_v4();
+        let (manager, _) = setup_manager(vec![make_cue(cue_id, "1")]).await;
+
+        let event = manager.process_command(ModelCommand::SetArmed { cue_id, armed: false }).await;
+
+        assert_eq!(event, Some(UiEvent::CueArmChanged { cue_id, armed: false }));
+        assert!(!manager.read().await.cues[0].armed);
+    }
+
+    #[tokio::test]
+    async fn append_note() {
+        let cue_id = Uuid::new_v4();
+        let mut cue = make_cue(cue_id, "1");
+        cue.notes = "Check levels".to_string();
+        let (manager, _) = setup_manager(vec![cue]).await;
+
+        let event = manager.process_command(ModelCommand::AppendNote { cue_id, text: " Late GO\n".to_string() }).await;
+
+        let Some(UiEvent::CueUpdated { cue }) = event else {
+            panic!("Unexpected event: {:?}", event);
+        };
+        let (original, appended) = cue.notes.split_once('\n').unwrap();
+        assert_eq!(original, "Check levels");
+        // "[YYYY-MM-DD HH:MM:SS] "の後に追記した内容が続く
+        assert_eq!(appended.len(), "[2026-01-01 00:00:00] Late GO".len());
+        assert!(appended.starts_with('['));
+        assert!(appended.ends_with("] Late GO"));
+        assert_eq!(manager.read().await.cues[0].notes, cue.notes);
+
+        let event = manager.process_command(ModelCommand::AppendNote { cue_id, text: "  ".to_string() }).await;
+        assert!(matches!(event, Some(UiEvent::OperationFailed { error: UiError::CueEdit { .. } })));
+
+        let mut cue = make_cue(Uuid::new_v4(), "2");
+        let timestamp = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_709_210_096);
+        cue.append_note("Mic 3 dropout", timestamp).unwrap();
+        assert_eq!(cue.notes, "[2024-02-29 12:34:56] Mic 3 dropout");
+    }
+
+    #[tokio::test]
+    async fn add_cue_from_template() {
+        let (manager, mut event_rx) = setup_manager(vec![make_cue(Uuid::new_v4(), "1")]).await;
+        let fade_param = AudioCueFadeParam { duration: 2.0, easing: kira::Easing::Linear.into() };
+        let template = CueTemplate {
+            post_wait: Some(1.0),
+            sequence: CueSequence::AutoContinue,
+            fade_out_param: Some(fade_param.clone()),
+            levels: AudioCueLevels { master: -6.0 },
+            ..Default::default()
+        };
+        manager
+            .write_with(|model| {
+                model.settings.general.default_pre_wait = 0.5;
+                model.settings.general.default_post_wait = 2.0;
+                model.settings.templates.insert("SFX".to_string(), template)
+            })
+            .await;
+
+        let command = ModelCommand::AddCueFromTemplate { template_name: "SFX".to_string(), target: PathBuf::from("door.wav"), at_index: 1 };
+        manager.process_command(command).await;
+
+        let event = event_rx.try_recv().ok();
+        let Some(UiEvent::CueAdded { cue, at_index: 1 }) = event else {
+            panic!("Unexpected event: {:?}", event);
+        };
+        assert_eq!(cue.name, "door");
+        // テンプレートで指定していないプリウェイトはショー設定の既定値になる
+        assert_eq!(cue.pre_wait, 0.5);
+        assert_eq!(cue.post_wait, 1.0);
+        assert_eq!(cue.sequence, CueSequence::AutoContinue);
+        let CueParam::Audio { target, fade_out_param, levels, .. } = &cue.param else {
+            panic!("Unexpected param: {:?}", cue.param);
+        };
+        assert_eq!(target, &PathBuf::from("door.wav"));
+        assert_eq!(fade_out_param, &Some(fade_param));
+        assert_eq!(levels.master, -6.0);
+        assert_eq!(manager.read().await.cues[1], cue);
+
+        let command = ModelCommand::AddCueFromTemplate { template_name: "Music".to_string(), target: PathBuf::from("song.wav"), at_index: 0 };
+        let event = manager.process_command(command).await;
+        assert_eq!(event, Some(UiEvent::OperationFailed { error: UiError::UnknownTemplate { template_name: "Music".to_string() } }));
+        assert_eq!(manager.read().await.cues.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn save_conflict() {
+        let path = std::env::temp_dir().join(format!("sbsp_test_{}.sbsp", Uuid::new_v4()));
+        let (manager, _) = setup_manager(vec![make_cue(Uuid::new_v4(), "1")]).await;
+        manager.process_command(ModelCommand::SaveToFile(path.clone())).await;
+        let event = manager.process_command(ModelCommand::Save).await;
+        assert_eq!(event, Some(UiEvent::ShowModelSaved { path: path.clone() }));
+
+        // 他の端末がショーファイルを書き換えた
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(10)).unwrap();
+        drop(file);
+        let event = manager.process_command(ModelCommand::Save).await;
+        assert!(matches!(event, Some(UiEvent::OperationFailed { error: UiError::FileSave { .. } })));
+
+        let event = manager.process_command(ModelCommand::SaveForce).await;
+        assert_eq!(event, Some(UiEvent::ShowModelSaved { path: path.clone() }));
+        // 上書きした後は、通常の保存ができる
+        let event = manager.process_command(ModelCommand::Save).await;
+        assert_eq!(event, Some(UiEvent::ShowModelSaved { path: path.clone() }));
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[tokio::test]
+    async fn auto_save() {
+        let path = std::env::temp_dir().join(format!("sbsp_test_{}.sbsp", Uuid::new_v4()));
+        let (manager, _) = setup_manager(vec![]).await;
+
+        // パスが未設定の場合は自動保存しない
+        manager.process_command(ModelCommand::AddCue { cue: make_cue(Uuid::new_v4(), "1"), at_index: 0 }).await;
+        assert_eq!(manager.auto_save().await, None);
+
+        manager.process_command(ModelCommand::SaveToFile(path.clone())).await;
+        assert_eq!(manager.auto_save().await, None);
+
+        manager.process_command(ModelCommand::AddCue { cue: make_cue(Uuid::new_v4(), "2"), at_index: 1 }).await;
+        let auto_save_path = path.with_extension("sbsp.autosave");
+        assert_eq!(manager.auto_save().await, Some(UiEvent::ShowModelAutoSaved { path: auto_save_path.clone() }));
+        // 変更がなければ再度書き出さない
+        assert_eq!(manager.auto_save().await, None);
+
+        // 自動保存ではショーファイルは保存済みにならない
+        assert!(manager.dirty.load(Ordering::Relaxed));
+        let saved: ShowModel = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let auto_saved: ShowModel = serde_json::from_str(&std::fs::read_to_string(&auto_save_path).unwrap()).unwrap();
+        assert_eq!(saved.cues.len(), 1);
+        assert_eq!(auto_saved.cues.len(), 2);
+
+        std::fs::remove_file(path).ok();
+        std::fs::remove_file(auto_save_path).ok();
+    }
+
+    #[tokio::test]
+    async fn load_while_dirty_waits_for_confirmation() {
+        let path = std::env::temp_dir().join(format!("sbsp_test_{}.json", Uuid::new_v4()));
+        let saved_cue_id = Uuid::new_v4();
+        let (manager, _) = setup_manager(vec![make_cue(saved_cue_id, "1")]).await;
+        manager.process_command(ModelCommand::SaveToFile(path.clone())).await;
+
+        let added_cue_id = Uuid::new_v4();
+        manager.process_command(ModelCommand::AddCue { cue: make_cue(added_cue_id, "2"), at_index: 1 }).await;
+        let event = manager.process_command(ModelCommand::LoadFromFile(path.clone())).await;
+
+        // 確認されるまで読み込まず、編集内容を保持する
+        assert_eq!(event, Some(UiEvent::UnsavedChanges));
+        assert_eq!(manager.read().await.cues.len(), 2);
+
+        let event = manager.process_command(ModelCommand::ConfirmClose).await;
+        assert_eq!(event, Some(UiEvent::ShowModelLoaded { path: path.clone() }));
+        let cue_ids: Vec<Uuid> = manager.read().await.cues.iter().map(|cue| cue.id).collect();
+        assert_eq!(cue_ids, vec![saved_cue_id]);
+
+        // 読み込んだ直後は未保存の変更がないため、確認なしで閉じられる
+        assert_eq!(manager.process_command(ModelCommand::NewShow).await, Some(UiEvent::ShowModelCreated));
+        assert!(manager.read().await.cues.is_empty());
+        tokio::fs::remove_file(path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn discard_changes_and_cancel_close() {
+        let cue_id = Uuid::new_v4();
+        let (manager, _) = setup_manager(vec![]).await;
+        manager.process_command(ModelCommand::AddCue { cue: make_cue(cue_id, "1"), at_index: 0 }).await;
+
+        assert_eq!(manager.process_command(ModelCommand::NewShow).await, Some(UiEvent::UnsavedChanges));
+        assert_eq!(manager.process_command(ModelCommand::CancelClose).await, None);
+        assert_eq!(manager.process_command(ModelCommand::ConfirmClose).await, None);
+        assert_eq!(manager.read().await.cues.len(), 1);
+
+        let event = manager
+            .process_command(ModelCommand::DiscardChanges(Box::new(ModelCommand::NewShow)))
+            .await;
+        assert_eq!(event, Some(UiEvent::ShowModelCreated));
+        assert!(manager.read().await.cues.is_empty());
+    }
+
+    #[tokio::test]
+    async fn load_older_show_file() {
+        let (manager, _) = setup_manager(vec![]).await;
+        let path = std::env::temp_dir().join(format!("sbsp_test_{}.json", Uuid::new_v4()));
+        let mut value = serde_json::to_value(ShowModel { name: "Old".to_string(), cues: vec![make_cue(Uuid::new_v4(), "1")], ..Default::default() }).unwrap();
+        value.as_object_mut().unwrap().remove("version");
+        tokio::fs::write(&path, value.to_string()).await.unwrap();
+
+        manager.load_from_file(&path).await.unwrap();
+        let model = manager.read().await;
+        assert_eq!(model.version, crate::model::SHOW_MODEL_VERSION);
+        assert_eq!(model.name, "Old");
+        assert_eq!(model.cues.len(), 1);
+        assert_eq!(model.cues[0].order, 1.0);
+        drop(model);
+
+        // 新しいバージョンのファイルは読み込まない
+        value["version"] = (crate::model::SHOW_MODEL_VERSION + 1).into();
+        tokio::fs::write(&path, value.to_string()).await.unwrap();
+        let event = manager.process_command(ModelCommand::LoadFromFile(path.clone())).await;
+        assert!(matches!(event, Some(UiEvent::OperationFailed { error: UiError::FileLoad { message, .. } }) if message.contains("newer")));
+        assert_eq!(manager.read().await.name, "Old");
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+
+    #[test]
+    fn load_cue_without_armed() {
+        let mut value = serde_json::to_value(make_cue(Uuid::new_v4(), "1")).unwrap();
+        value.as_object_mut().unwrap().remove("armed");
+        value.as_object_mut().unwrap().remove("color");
+
+        let cue: Cue = serde_json::from_value(value).unwrap();
+
+        assert!(cue.armed);
+        assert_eq!(cue.color, None);
+    }
+}
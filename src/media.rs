@@ -0,0 +1,331 @@
+use std::{collections::HashMap, fs::File, path::{Path, PathBuf}, sync::{Arc, Mutex}, time::SystemTime};
+
+use anyhow::Context;
+use kira::sound::static_sound::StaticSoundData;
+use serde::Serialize;
+use symphonia::core::{audio::SampleBuffer, codecs::DecoderOptions, errors::Error as SymphoniaError, formats::FormatOptions, io::MediaSourceStream, meta::MetadataOptions, probe::{Hint, ProbeResult}};
+
+/// 読み込んだ時点のファイルの更新日時と、ファイルの長さ
+type CachedDuration = (SystemTime, Option<f64>);
+
+/// 音声ファイルの長さ(秒)のキャッシュ。ファイルの更新日時が変わった場合は読み込み直します。
+#[derive(Clone, Default)]
+pub struct DurationCache(Arc<Mutex<HashMap<PathBuf, CachedDuration>>>);
+
+impl DurationCache {
+    /// 音声ファイルの長さを返します。読み込めないファイルの場合はNoneを返します。
+    pub async fn duration(&self, path: &Path) -> Option<f64> {
+        let modified = tokio::fs::metadata(path).await.and_then(|metadata| metadata.modified()).ok()?;
+        if let Some((cached_modified, duration)) = self.0.lock().unwrap().get(path)
+            && *cached_modified == modified
+        {
+            return *duration;
+        }
+        let owned_path = path.to_path_buf();
+        let duration = match tokio::task::spawn_blocking(move || probe_duration(&owned_path)).await {
+            Ok(Ok(duration)) => Some(duration),
+            Ok(Err(e)) => {
+                log::warn!("Failed to read duration of '{}': {:?}", path.display(), e);
+                None
+            }
+            Err(e) => {
+                log::error!("Duration probe task failed: {:?}", e);
+                return None;
+            }
+        };
+        // 読み込めなかったファイルも、更新されるまで読み込み直さない
+        self.0.lock().unwrap().insert(path.to_path_buf(), (modified, duration));
+        duration
+    }
+}
+
+/// 読み込んだ時点のファイルの更新日時と、ファイルのラウドネス
+type CachedLoudness = (SystemTime, Option<f64>);
+
+/// 音声ファイルのラウドネス(LUFS)のキャッシュ。ファイルの更新日時が変わった場合は測定し直します。
+#[derive(Clone, Default)]
+pub struct LoudnessCache(Arc<Mutex<HashMap<PathBuf, CachedLoudness>>>);
+
+impl LoudnessCache {
+    /// 音声ファイルの統合ラウドネスを返します。読み込めないファイルや無音のファイルの場合はNoneを返します。
+    pub async fn loudness(&self, path: &Path) -> Option<f64> {
+        let modified = tokio::fs::metadata(path).await.and_then(|metadata| metadata.modified()).ok()?;
+        if let Some((cached_modified, loudness)) = self.0.lock().unwrap().get(path)
+            && *cached_modified == modified
+        {
+            return *loudness;
+        }
+        let owned_path = path.to_path_buf();
+        let loudness = match tokio::task::spawn_blocking(move || scan_loudness(&owned_path)).await {
+            Ok(Ok(loudness)) => loudness,
+            Ok(Err(e)) => {
+                log::warn!("Failed to scan loudness of '{}': {:?}", path.display(), e);
+                None
+            }
+            Err(e) => {
+                log::error!("Loudness scan task failed: {:?}", e);
+                return None;
+            }
+        };
+        self.0.lock().unwrap().insert(path.to_path_buf(), (modified, loudness));
+        loudness
+    }
+}
+
+/// ITU-R BS.1770の統合ラウドネスの測定
+///
+/// 100msごとのK特性フィルター後のエネルギーを集計し、400msのブロックにゲートをかけて平均します。
+/// チャンネルの重みは全て1.0として扱います。
+struct LoudnessMeter {
+    filters: Vec<[Biquad; 2]>, // チャンネルごとの(高域シェルフ, ハイパス)
+    step_frames: usize,
+    step_energy: f64,
+    step_position: usize,
+    steps: Vec<f64>, // 100msごとのエネルギーの平均
+}
+
+impl LoudnessMeter {
+    fn new(sample_rate: f64, channels: usize) -> Self {
+        Self {
+            filters: (0..channels).map(|_| [Biquad::k_shelf(sample_rate), Biquad::k_high_pass(sample_rate)]).collect(),
+            step_frames: ((sample_rate * 0.1) as usize).max(1),
+            step_energy: 0.0,
+            step_position: 0,
+            steps: Vec::new(),
+        }
+    }
+
+    fn push_interleaved(&mut self, samples: &[f32]) {
+        for frame in samples.chunks_exact(self.filters.len()) {
+            for (sample, [shelf, high_pass]) in frame.iter().zip(self.filters.iter_mut()) {
+                let filtered = high_pass.process(shelf.process(*sample as f64));
+                self.step_energy += filtered * filtered;
+            }
+            self.step_position += 1;
+            if self.step_position == self.step_frames {
+                self.steps.push(self.step_energy / self.step_frames as f64);
+                self.step_energy = 0.0;
+                self.step_position = 0;
+            }
+        }
+    }
+
+    /// 統合ラウドネス(LUFS)を返します。ゲートを通過するブロックがない場合はNoneを返します。
+    fn integrated(self) -> Option<f64> {
+        let blocks: Vec<f64> = if self.steps.len() >= 4 {
+            self.steps.windows(4).map(|window| window.iter().sum::<f64>() / 4.0).collect()
+        } else {
+            // 400msに満たないファイルは、全体を1つのブロックとして扱う
+            let frames = self.steps.len() * self.step_frames + self.step_position;
+            if frames == 0 {
+                return None;
+            }
+            vec![(self.steps.iter().sum::<f64>() * self.step_frames as f64 + self.step_energy) / frames as f64]
+        };
+        let to_lufs = |energy: f64| -0.691 + 10.0 * energy.log10();
+        let mean = |blocks: &[f64]| (!blocks.is_empty()).then(|| blocks.iter().sum::<f64>() / blocks.len() as f64);
+        let absolute_gated: Vec<f64> = blocks.into_iter().filter(|energy| to_lufs(*energy) > -70.0).collect();
+        let relative_threshold = to_lufs(mean(&absolute_gated)?) - 10.0;
+        let relative_gated: Vec<f64> = absolute_gated.into_iter().filter(|energy| to_lufs(*energy) > relative_threshold).collect();
+        mean(&relative_gated).map(to_lufs)
+    }
+}
+
+/// 双2次フィルター
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2], // a0で正規化したa1, a2
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 3]) -> Self {
+        Self { b: b.map(|b| b / a[0]), a: [a[1] / a[0], a[2] / a[0]], x: [0.0; 2], y: [0.0; 2] }
+    }
+
+    /// K特性の1段目。頭部の影響を模した高域シェルフ。係数はサンプルレートに合わせてBS.1770の48kHzの係数から求める
+    fn k_shelf(sample_rate: f64) -> Self {
+        let (f0, gain, q) = (1_681.974_450_955_533, 3.999_843_853_973_347, 0.707_175_236_955_419_6);
+        let k = (std::f64::consts::PI * f0 / sample_rate).tan();
+        let vh = 10f64.powf(gain / 20.0);
+        let vb = vh.powf(0.499_666_774_154_541_6);
+        Self::new(
+            [vh + vb * k / q + k * k, 2.0 * (k * k - vh), vh - vb * k / q + k * k],
+            [1.0 + k / q + k * k, 2.0 * (k * k - 1.0), 1.0 - k / q + k * k],
+        )
+    }
+
+    /// K特性の2段目。低域を除くハイパス
+    fn k_high_pass(sample_rate: f64) -> Self {
+        let (f0, q) = (38.135_470_876_024_44, 0.500_327_037_323_877_3);
+        let k = (std::f64::consts::PI * f0 / sample_rate).tan();
+        let a0 = 1.0 + k / q + k * k;
+        Self::new([a0, -2.0 * a0, a0], [a0, 2.0 * (k * k - 1.0), 1.0 - k / q + k * k])
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.b[1] * self.x[0] + self.b[2] * self.x[1] - self.a[0] * self.y[0] - self.a[1] * self.y[1];
+        self.x = [x, self.x[0]];
+        self.y = [y, self.y[0]];
+        y
+    }
+}
+
+/// 音声ファイル全体をデコードして統合ラウドネスを測定します。時間がかかるため、非同期処理の中ではspawn_blockingで呼び出します。
+fn scan_loudness(path: &Path) -> anyhow::Result<Option<f64>> {
+    let mut probed = probe_format(path)?;
+    let track = probed.format.default_track().context("No audio track found.")?;
+    let track_id = track.id;
+    let mut decoder = symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+    let mut meter = None;
+    let mut sample_buffer: Option<SampleBuffer<f32>> = None;
+    loop {
+        let packet = match probed.format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // 壊れたパケットは飛ばす
+            Err(SymphoniaError::DecodeError(e)) => {
+                log::debug!("Skipping undecodable packet in '{}': {}", path.display(), e);
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let spec = *decoded.spec();
+        let meter = meter.get_or_insert_with(|| LoudnessMeter::new(spec.rate as f64, spec.channels.count()));
+        if sample_buffer.as_ref().is_none_or(|buffer| buffer.capacity() < decoded.capacity() * spec.channels.count()) {
+            sample_buffer = Some(SampleBuffer::new(decoded.capacity() as u64, spec));
+        }
+        let buffer = sample_buffer.as_mut().unwrap();
+        buffer.copy_interleaved_ref(decoded);
+        meter.push_interleaved(buffer.samples());
+    }
+    Ok(meter.and_then(LoudnessMeter::integrated))
+}
+
+/// ヘッダーから読み取った音声ファイルの情報
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct AudioInfo {
+    pub duration: Option<f64>, // 秒。ヘッダーにフレーム数がない形式の場合はNone
+    pub sample_rate: Option<u32>,
+    pub channels: Option<usize>,
+    pub format: String, // コーデックの短い名前(pcm_s16le, flac, mp3など)
+}
+
+/// 音声ファイルのヘッダーのみを読み込み、情報を返します。ファイルの読み込みを行うため、非同期処理の中ではspawn_blockingで呼び出します。
+pub fn probe_audio_info(path: &Path) -> anyhow::Result<AudioInfo> {
+    let probed = probe_format(path)?;
+    let track = probed.format.default_track().context("No audio track found.")?;
+    let params = &track.codec_params;
+    let format = symphonia::default::get_codecs()
+        .get_codec(params.codec)
+        .map_or_else(|| "unknown".to_string(), |codec| codec.short_name.to_string());
+    Ok(AudioInfo {
+        duration: params.n_frames.zip(params.sample_rate).map(|(n_frames, sample_rate)| n_frames as f64 / sample_rate as f64),
+        sample_rate: params.sample_rate,
+        channels: params.channels.map(|channels| channels.count()),
+        format,
+    })
+}
+
+fn probe_format(path: &Path) -> anyhow::Result<ProbeResult> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|extension| extension.to_str()) {
+        hint.with_extension(extension);
+    }
+    Ok(symphonia::default::get_probe().format(
+        &hint,
+        MediaSourceStream::new(Box::new(file), Default::default()),
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    )?)
+}
+
+/// 音声ファイルの長さを求めます。ヘッダーにフレーム数がない形式は、全体をデコードして求めます。
+fn probe_duration(path: &Path) -> anyhow::Result<f64> {
+    let probed = probe_format(path)?;
+    let frames = probed.format.default_track().and_then(|track| {
+        let params = &track.codec_params;
+        params.n_frames.zip(params.sample_rate)
+    });
+    if let Some((n_frames, sample_rate)) = frames {
+        return Ok(n_frames as f64 / sample_rate as f64);
+    }
+    Ok(StaticSoundData::from_file(path)?.duration().as_secs_f64())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::write_wav_samples;
+
+    use super::*;
+
+    fn write_wav(path: &Path, sample_rate: u32, frames: u32) {
+        write_wav_samples(path, sample_rate, &vec![0; frames as usize]);
+    }
+
+    #[tokio::test]
+    async fn duration_is_cached_until_modified() {
+        let path = std::env::temp_dir().join(format!("sbsp_duration_{}.wav", uuid::Uuid::new_v4()));
+        write_wav(&path, 8000, 16000);
+        let cache = DurationCache::default();
+
+        assert_eq!(cache.duration(&path).await, Some(2.0));
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+        cache.0.lock().unwrap().insert(path.clone(), (modified, Some(99.0)));
+        assert_eq!(cache.duration(&path).await, Some(99.0));
+
+        // 更新日時が変わると読み込み直す
+        write_wav(&path, 8000, 4000);
+        let file = File::options().write(true).open(&path).unwrap();
+        file.set_modified(modified + std::time::Duration::from_secs(1)).unwrap();
+        assert_eq!(cache.duration(&path).await, Some(0.5));
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(cache.duration(&path).await, None);
+        assert_eq!(cache.duration(Path::new("Cargo.toml")).await, None);
+    }
+
+    #[test]
+    fn audio_info() {
+        let path = std::env::temp_dir().join(format!("sbsp_info_{}.wav", uuid::Uuid::new_v4()));
+        write_wav(&path, 8000, 4000);
+
+        let info = probe_audio_info(&path).unwrap();
+        assert_eq!(info, AudioInfo { duration: Some(0.5), sample_rate: Some(8000), channels: Some(1), format: "pcm_s16le".to_string() });
+        std::fs::remove_file(&path).unwrap();
+        assert!(probe_audio_info(Path::new("Cargo.toml")).is_err());
+    }
+
+    #[tokio::test]
+    async fn loudness_of_sine() {
+        let path = std::env::temp_dir().join(format!("sbsp_loudness_{}.wav", uuid::Uuid::new_v4()));
+        // 997Hz、振幅0.5(約-6dBFS)の正弦波。1チャンネルの0dBFSの正弦波は-3.01LUFSになる
+        let samples: Vec<i16> = (0..96000)
+            .map(|index| ((index as f64 * 997.0 / 48000.0 * std::f64::consts::TAU).sin() * 16384.0) as i16)
+            .collect();
+        write_wav_samples(&path, 48000, &samples);
+        let cache = LoudnessCache::default();
+
+        let loudness = cache.loudness(&path).await.unwrap();
+        assert!((loudness + 9.03).abs() < 0.1, "loudness {}LUFS", loudness);
+
+        // 無音のファイルはゲートを通過するブロックがない
+        write_wav(&path, 48000, 96000);
+        let file = File::options().write(true).open(&path).unwrap();
+        file.set_modified(std::fs::metadata(&path).unwrap().modified().unwrap() + std::time::Duration::from_secs(1)).unwrap();
+        assert_eq!(cache.loudness(&path).await, None);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(cache.loudness(Path::new("Cargo.toml")).await, None);
+    }
+}
@@ -0,0 +1,274 @@
+use anyhow::Context;
+use midir::{MidiInput, MidiInputConnection};
+use tokio::sync::{broadcast, mpsc};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    controller::ControllerCommand,
+    event::UiEvent,
+    input::{InputSource, InputTrigger, ReplayGuard},
+    manager::ShowModelHandle,
+    model::settings::{MSC_ALL_CALL_DEVICE_ID, MidiSettings},
+};
+
+/// MIDIの受信スレッドから受け取るメッセージのチャネルの容量
+const MIDI_MESSAGE_CAPACITY: usize = 64;
+
+/// MIDI Show Controlのコマンド。`cue_number`はQ_numberで、省略された場合はNone
+#[derive(Debug, Clone, PartialEq)]
+pub enum MscCommand {
+    Go { cue_number: Option<String> },
+    Stop { cue_number: Option<String> },
+    Resume { cue_number: Option<String> },
+}
+
+/// MIDI Show ControlのSysExを解析します。
+/// `device_id`宛てとオールコール以外のメッセージ、および対応していないコマンドの場合はNoneを返します。
+pub fn parse_msc(message: &[u8], device_id: u8) -> Option<MscCommand> {
+    let [0xF0, 0x7F, target_id, 0x02, _command_format, command, data @ .., 0xF7] = message else {
+        return None;
+    };
+    if *target_id != device_id && *target_id != MSC_ALL_CALL_DEVICE_ID {
+        return None;
+    }
+    // データはQ_number 00 Q_list 00 Q_pathの順で、いずれも省略できる
+    let cue_number = data
+        .split(|byte| *byte == 0x00)
+        .next()
+        .filter(|cue_number| !cue_number.is_empty())
+        .map(|cue_number| String::from_utf8_lossy(cue_number).into_owned());
+    match command {
+        0x01 => Some(MscCommand::Go { cue_number }),
+        0x02 => Some(MscCommand::Stop { cue_number }),
+        0x03 => Some(MscCommand::Resume { cue_number }),
+        _ => None,
+    }
+}
+
+/// MIDIの入力デバイスからMIDI Show Controlを受信し、CueControllerにコマンドを送信します。
+///
+/// ノートオンはWaitForInputキューのトリガーとして転送します。
+/// 入力デバイスはショー設定から取得し、設定が変更された場合は接続し直します。
+pub struct MidiListener {
+    model_handle: ShowModelHandle,
+    controller_tx: mpsc::Sender<ControllerCommand>,
+    input_trigger_tx: broadcast::Sender<InputTrigger>,
+    event_rx: broadcast::Receiver<UiEvent>,
+    replay_guard: ReplayGuard,
+}
+
+impl MidiListener {
+    pub fn new(
+        model_handle: ShowModelHandle,
+        controller_tx: mpsc::Sender<ControllerCommand>,
+        input_trigger_tx: broadcast::Sender<InputTrigger>,
+        event_rx: broadcast::Receiver<UiEvent>,
+    ) -> Self {
+        Self {
+            model_handle,
+            controller_tx,
+            input_trigger_tx,
+            event_rx,
+            replay_guard: ReplayGuard::new(),
+        }
+    }
+
+    pub async fn run(mut self, cancel_token: CancellationToken) {
+        log::info!("MidiListener run loop started.");
+        let mut settings = self.model_handle.get_settings().await.midi;
+        'connect: loop {
+            let (message_tx, mut message_rx) = mpsc::channel::<Vec<u8>>(MIDI_MESSAGE_CAPACITY);
+            // 接続を保持している間だけメッセージを受信する
+            let _connection = if settings.enabled {
+                match connect_input(settings.input_device.as_deref(), message_tx) {
+                    Ok(connection) => Some(connection),
+                    Err(e) => {
+                        log::error!("Failed to connect MIDI input: {:?}", e);
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+            loop {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => break 'connect,
+                    result = self.event_rx.recv() => match result {
+                        Ok(UiEvent::SettingsUpdated { .. } | UiEvent::BatchApplied { .. } | UiEvent::ShowModelLoaded { .. } | UiEvent::ShowModelCreated)
+                        | Err(broadcast::error::RecvError::Lagged(_)) => {
+                            let new_settings = self.model_handle.get_settings().await.midi;
+                            if new_settings != settings {
+                                settings = new_settings;
+                                continue 'connect;
+                            }
+                        }
+                        Ok(_) => {}
+                        Err(broadcast::error::RecvError::Closed) => break 'connect,
+                    },
+                    Some(message) = message_rx.recv() => self.handle_message(&message, &settings).await,
+                }
+            }
+        }
+        log::info!("MidiListener run loop finished.");
+    }
+
+    async fn handle_message(&self, message: &[u8], settings: &MidiSettings) {
+        match message {
+            [0xF0, ..] => {
+                let Some(msc_command) = parse_msc(message, settings.msc_device_id) else {
+                    log::trace!("Ignored unrecognized SysEx: {:02X?}", message);
+                    return;
+                };
+                if let Some(command) = self.controller_command(msc_command).await
+                    && self.replay_guard.check(InputSource::Midi, &command)
+                    && let Err(e) = self.controller_tx.send(command).await
+                {
+                    log::error!("Failed to send MSC command to CueController: {:?}", e);
+                }
+            }
+            [status @ 0x90..=0x9F, note, velocity] => {
+                let trigger = InputTrigger::MidiNoteOn { channel: (status & 0x0F) + 1, note: *note, velocity: *velocity };
+                if self.input_trigger_tx.send(trigger).is_err() {
+                    log::trace!("No WaitForInput cues are listening to input triggers.");
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// MSCのコマンドをCueControllerのコマンドに変換します。
+    /// STOPとRESUMEは全体の一時停止・再開として扱い、キュー番号は使用しません。
+    async fn controller_command(&self, msc_command: MscCommand) -> Option<ControllerCommand> {
+        match msc_command {
+            MscCommand::Go { cue_number: None } => Some(ControllerCommand::Go),
+            MscCommand::Go { cue_number: Some(cue_number) } => {
+                let Some(cue) = self.model_handle.get_cue_by_number(&cue_number).await else {
+                    log::warn!("MSC GO: Cue number '{}' not found.", cue_number);
+                    return None;
+                };
+                Some(ControllerCommand::GoFromCue { cue_id: cue.id })
+            }
+            // キュー番号がない場合は全体の音声を一時停止・再開する
+            MscCommand::Stop { cue_number: None } => Some(ControllerCommand::HoldAudio),
+            MscCommand::Resume { cue_number: None } => Some(ControllerCommand::ReleaseAudio),
+            MscCommand::Stop { cue_number: Some(cue_number) } => {
+                let Some(cue) = self.model_handle.get_cue_by_number(&cue_number).await else {
+                    log::warn!("MSC STOP: Cue number '{}' not found.", cue_number);
+                    return None;
+                };
+                Some(ControllerCommand::PauseCue { cue_id: cue.id })
+            }
+            MscCommand::Resume { cue_number: Some(cue_number) } => {
+                let Some(cue) = self.model_handle.get_cue_by_number(&cue_number).await else {
+                    log::warn!("MSC RESUME: Cue number '{}' not found.", cue_number);
+                    return None;
+                };
+                Some(ControllerCommand::ResumeCue { cue_id: cue.id })
+            }
+        }
+    }
+}
+
+/// MIDIの入力デバイスに接続し、受信したメッセージを`message_tx`に送ります。
+fn connect_input(device: Option<&str>, message_tx: mpsc::Sender<Vec<u8>>) -> anyhow::Result<MidiInputConnection<()>> {
+    let midi_input = MidiInput::new("sbsp").context("Failed to initialize MIDI input")?;
+    let ports = midi_input.ports();
+    let port = match device {
+        Some(device) => ports.iter().find(|port| midi_input.port_name(port).is_ok_and(|name| name == device)),
+        None => ports.first(),
+    }
+    .with_context(|| format!("MIDI input device {:?} not found.", device))?;
+    let port_name = midi_input.port_name(port)?;
+    let connection = midi_input
+        .connect(
+            port,
+            "sbsp-input",
+            move |_, message, _| {
+                if message_tx.try_send(message.to_vec()).is_err() {
+                    log::warn!("Dropped incoming MIDI message: {:02X?}", message);
+                }
+            },
+            (),
+        )
+        .map_err(|e| anyhow::anyhow!("Failed to connect to MIDI input '{}': {}", port_name, e))?;
+    log::info!("MidiListener connected to '{}'.", port_name);
+    Ok(connection)
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::{manager::ShowModelManager, model::cue::{Cue, CueParam, CueSequence, RetriggerMode}};
+
+    use super::*;
+
+    #[test]
+    fn parse_msc_commands() {
+        let go = [0xF0, 0x7F, 0x01, 0x02, 0x10, 0x01, b'1', b'2', b'.', b'5', 0x00, b'1', 0xF7];
+        assert_eq!(parse_msc(&go, 0x01), Some(MscCommand::Go { cue_number: Some("12.5".to_string()) }));
+        // 他のデバイス宛てのメッセージは無視し、オールコールには応答する
+        assert_eq!(parse_msc(&go, 0x02), None);
+        let stop = [0xF0, 0x7F, 0x7F, 0x02, 0x10, 0x02, 0xF7];
+        assert_eq!(parse_msc(&stop, 0x02), Some(MscCommand::Stop { cue_number: None }));
+        let resume = [0xF0, 0x7F, 0x7F, 0x02, 0x10, 0x03, 0xF7];
+        assert_eq!(parse_msc(&resume, 0x02), Some(MscCommand::Resume { cue_number: None }));
+
+        // 対応していないコマンドや、MSC以外のSysExは無視する
+        assert_eq!(parse_msc(&[0xF0, 0x7F, 0x7F, 0x02, 0x10, 0x0A, 0xF7], 0x00), None);
+        assert_eq!(parse_msc(&[0xF0, 0x7E, 0x7F, 0x06, 0x01, 0xF7], 0x00), None);
+    }
+
+    #[tokio::test]
+    async fn msc_and_notes_to_commands() {
+        let (event_tx, event_rx) = broadcast::channel::<UiEvent>(32);
+        let (controller_tx, mut controller_rx) = mpsc::channel::<ControllerCommand>(32);
+        let (input_trigger_tx, mut input_trigger_rx) = broadcast::channel::<InputTrigger>(32);
+        let (manager, handle) = ShowModelManager::new(event_tx);
+        let cue_id = Uuid::new_v4();
+        manager
+            .write_with(|model| {
+                model.cues.push(Cue {
+                    id: cue_id,
+                    number: "12.5".to_string(),
+                    name: "".to_string(),
+                    notes: "".to_string(),
+                    pre_wait: 0.0,
+                    post_wait: 0.0,
+                    sequence: CueSequence::DoNotContinue,
+                    follow_offset: None,
+                    armed: true,
+                    retrigger_mode: RetriggerMode::Restart,
+                    color: None,
+                    order: 0.0,
+                    schedule: None,
+                    param: CueParam::Wait { duration: 1.0 },
+                });
+            })
+            .await;
+        let listener = MidiListener::new(handle, controller_tx, input_trigger_tx, event_rx);
+        let settings = MidiSettings { enabled: true, input_device: None, msc_device_id: 0x01 };
+
+        listener.handle_message(&[0xF0, 0x7F, 0x01, 0x02, 0x10, 0x01, 0xF7], &settings).await;
+        assert!(matches!(controller_rx.try_recv(), Ok(ControllerCommand::Go)));
+        listener.handle_message(&[0xF0, 0x7F, 0x01, 0x02, 0x10, 0x01, b'1', b'2', b'.', b'5', 0xF7], &settings).await;
+        assert!(matches!(controller_rx.try_recv(), Ok(ControllerCommand::GoFromCue { cue_id: id }) if id == cue_id));
+        listener.handle_message(&[0xF0, 0x7F, 0x01, 0x02, 0x10, 0x02, 0xF7], &settings).await;
+        assert!(matches!(controller_rx.try_recv(), Ok(ControllerCommand::HoldAudio)));
+        listener.handle_message(&[0xF0, 0x7F, 0x01, 0x02, 0x10, 0x03, 0xF7], &settings).await;
+        assert!(matches!(controller_rx.try_recv(), Ok(ControllerCommand::ReleaseAudio)));
+        // キュー番号を指定した場合はそのキューだけを一時停止・再開する
+        listener.handle_message(&[0xF0, 0x7F, 0x01, 0x02, 0x10, 0x02, b'1', b'2', b'.', b'5', 0xF7], &settings).await;
+        assert!(matches!(controller_rx.try_recv(), Ok(ControllerCommand::PauseCue { cue_id: id }) if id == cue_id));
+        listener.handle_message(&[0xF0, 0x7F, 0x01, 0x02, 0x10, 0x03, b'1', b'2', b'.', b'5', 0xF7], &settings).await;
+        assert!(matches!(controller_rx.try_recv(), Ok(ControllerCommand::ResumeCue { cue_id: id }) if id == cue_id));
+
+        // 存在しないキュー番号や認識できないSysExは何もしない
+        listener.handle_message(&[0xF0, 0x7F, 0x01, 0x02, 0x10, 0x01, b'9', 0xF7], &settings).await;
+        listener.handle_message(&[0xF0, 0x43, 0x10, 0x4C, 0xF7], &settings).await;
+        assert!(controller_rx.try_recv().is_err());
+
+        listener.handle_message(&[0x91, 60, 100], &settings).await;
+        assert_eq!(input_trigger_rx.try_recv().unwrap(), InputTrigger::MidiNoteOn { channel: 2, note: 60, velocity: 100 });
+    }
+}
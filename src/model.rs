@@ -1,14 +1,116 @@
+use anyhow::Context;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use uuid::Uuid;
 
 use crate::model::{cue::Cue, settings::ShowSettings};
 
 pub mod cue;
-mod settings;
+pub mod easing;
+pub mod settings;
+pub mod timing;
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+/// ショーファイルの形式を1つ前のバージョンから変換する処理。`MIGRATIONS[n]`はバージョンnからn+1に変換する
+type Migration = fn(&mut Map<String, Value>);
+
+const MIGRATIONS: &[Migration] = &[
+    // 0 -> 1: versionのない形式。versionの追加以外に変更はない
+    |_| {},
+    // 1 -> 2: キューの並び順をリストの位置から求める
+    |object| {
+        if let Some(Value::Array(cues)) = object.get_mut("cues") {
+            for (index, cue) in cues.iter_mut().enumerate() {
+                if let Value::Object(cue) = cue {
+                    cue.insert("order".to_string(), (index as f64 + 1.0).into());
+                }
+            }
+        }
+    },
+];
+
+/// 現在のショーファイルの形式のバージョン
+pub const SHOW_MODEL_VERSION: u32 = MIGRATIONS.len() as u32;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct ShowModel {
+    pub version: u32,
     pub name: String,
     pub cues: Vec<Cue>,
+    #[serde(default)]
     pub settings: ShowSettings,
+    #[serde(default)]
+    pub last_cursor: Option<Uuid>, // 最後の再生カーソルの位置。読み込み時にカーソルを復元する
+}
+
+impl Default for ShowModel {
+    fn default() -> Self {
+        Self {
+            version: SHOW_MODEL_VERSION,
+            name: String::default(),
+            cues: Vec::default(),
+            settings: ShowSettings::default(),
+            last_cursor: None,
+        }
+    }
+}
+
+impl ShowModel {
+    /// ショーファイルの内容を読み込みます。以前のバージョンの形式は現在の形式に変換してから読み込みます。
+    pub fn from_json(content: &str) -> anyhow::Result<Self> {
+        let mut value: Value = serde_json::from_str(content)?;
+        let object = value.as_object_mut().context("Show file must be a JSON object.")?;
+        let version = match object.get("version") {
+            None => 0,
+            Some(version) => version
+                .as_u64()
+                .and_then(|version| u32::try_from(version).ok())
+                .with_context(|| format!("Invalid show file version: {}", version))?,
+        };
+        if version > SHOW_MODEL_VERSION {
+            anyhow::bail!(
+                "Show file version {} is newer than the supported version {}. Please update the application.",
+                version,
+                SHOW_MODEL_VERSION
+            );
+        }
+        for migration in &MIGRATIONS[version as usize..] {
+            migration(object);
+        }
+        object.insert("version".to_string(), SHOW_MODEL_VERSION.into());
+        let mut model: Self = serde_json::from_value(value)?;
+        model.cues.sort_by(|a, b| a.order.total_cmp(&b.order));
+        if model.cues.windows(2).any(|pair| pair[0].order >= pair[1].order) || model.cues.iter().any(|cue| !cue.order.is_finite()) {
+            model.reset_order();
+        }
+        Ok(model)
+    }
+
+    /// `index`のキューの並び順を、前後のキューの間の値にします。他のキューの並び順は変更しません。
+    /// 間に表せる値がない場合のみ、全てのキューの並び順をリストの位置から振り直し、trueを返します。
+    pub fn place_cue(&mut self, index: usize) -> bool {
+        let prev = index.checked_sub(1).map(|prev_index| self.cues[prev_index].order);
+        let next = self.cues.get(index + 1).map(|cue| cue.order);
+        let order = match (prev, next) {
+            (None, None) => 1.0,
+            (Some(prev), None) => prev + 1.0,
+            (None, Some(next)) => next - 1.0,
+            (Some(prev), Some(next)) => prev + (next - prev) / 2.0,
+        };
+        let fits = order.is_finite() && prev.is_none_or(|prev| prev < order) && next.is_none_or(|next| order < next);
+        if !fits {
+            self.reset_order();
+            return true;
+        }
+        self.cues[index].order = order;
+        false
+    }
+
+    /// 全てのキューの並び順を、リストの位置に合わせて1から振り直します。
+    fn reset_order(&mut self) {
+        for (index, cue) in self.cues.iter_mut().enumerate() {
+            cue.order = index as f64 + 1.0;
+        }
+    }
 }
@@ -1,23 +1,44 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, time::{SystemTime, UNIX_EPOCH}};
 
-use kira::{Easing, sound::Region};
-use serde::{Deserialize, Serialize};
+use kira::sound::Region;
+use serde::{Deserialize, Deserializer, Serialize};
 use uuid::Uuid;
 
+use crate::model::easing::{validate_fade_curve, FadeCurve};
+
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct Cue {
     pub id: Uuid,
     pub number: String,
     pub name: String,
     pub notes: String,
-    pub pre_wait: f64,
-    pub post_wait: f64,
+    pub pre_wait: f64,  // GOから動作を開始するまでの秒数
+    pub post_wait: f64, // AutoContinueでは動作の開始から、AutoFollowでは終了から次のキューを実行するまでの秒数
     pub sequence: CueSequence,
+    #[serde(default)]
+    pub follow_offset: Option<f64>, // AutoFollowで次のキューを実行する、キューの終了からの秒数。負の値は終了前。オーディオキューのみ有効
+    #[serde(default = "default_armed")]
+    pub armed: bool, // falseの場合、GOや自動実行で実行されない
+    #[serde(default)]
+    pub retrigger_mode: RetriggerMode,
+    #[serde(default)]
+    pub color: Option<String>, // UIで表示する色。"#RRGGBB"形式。バックエンドは保存と中継のみ行う
+    /// キューリストでの並び順。キューリストは常にこの値の昇順に並べ、追加・移動したキューのみ前後のキューの間の値に変更する
+    #[serde(default)]
+    pub order: f64,
+    #[serde(default)]
+    pub schedule: Option<u64>, // 自動で実行する時刻(UNIXエポックからのミリ秒)。Noneの場合は時刻では実行しない
     pub param: CueParam,
 }
 
+fn default_armed() -> bool {
+    true
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub enum CueSequence {
     #[default]
@@ -27,6 +48,7 @@ pub enum CueSequence {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(tag = "type", content = "params", rename_all = "camelCase")]
 pub enum CueParam {
     Audio {
@@ -36,22 +58,344 @@ pub enum CueParam {
         end_time: Option<f64>,
         fade_out_param: Option<AudioCueFadeParam>,
         levels: AudioCueLevels,
-        loop_region: Option<Region>,
+        #[cfg_attr(feature = "schema", schemars(with = "Option<crate::schema::RegionDef>"))]
+        loop_region: Option<Region>, // 再生範囲(start_time〜end_time)内の位置。end_timeを超える部分は切り詰める
+        loop_count: Option<u32>, // ループ領域の再生回数。Noneの場合は無限ループ
+        stop_fade_param: Option<AudioCueFadeParam>, // 停止コマンドで止める際のフェード
+        #[serde(default)]
+        output_bus: Option<String>, // 再生するバスの名前。Noneの場合はメイントラックで再生する
+        /// trueの場合、ファイル全体を読み込まずに再生しながら読み込む。長い環境音などに使う。
+        /// シークとループ回数の変更は先読みした分(44.1kHzで約0.4秒)遅れて反映され、再生レポートのピークレベルとメーターは求めない。
+        #[serde(default)]
+        streaming: bool,
+        /// 目標のラウドネス(LUFS)。指定した場合、ファイルのラウドネスとの差をlevels.masterに加えて再生する
+        #[serde(default)]
+        normalize_to: Option<f64>,
+        /// 音量のオートメーション。(キューの開始からの秒数, levels.masterに加える音量(dB))のブレークポイントを時刻順に並べる。
+        /// ブレークポイントの間は直線的に変化し、最後のブレークポイント以降はその音量を保つ。フェードアウトが始まると打ち切る
+        #[serde(default)]
+        automation: Option<Vec<(f64, f64)>>,
     },
     Wait {
         duration: f64,
-    }, // TODO midi, osc wait, group cue
+    },
+    Group {
+        children: Vec<Uuid>, // 同時に実行する子キューのID
+    },
+    /// 外部からのMIDI・OSCのトリガーを受信するまで待機します。
+    WaitForInput {
+        source: TriggerSource,
+    },
+}
+
+/// 再生中のキューを再びGOした場合の動作
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum RetriggerMode {
+    /// 再生中のキューをすぐに停止し、最初から実行し直す
+    #[default]
+    Restart,
+    /// 再生中は何もしない
+    Ignore,
 }
 
+/// キューの種類。`CueParam`のタグと同じ名前を使う
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum CueKind {
+    Audio,
+    Wait,
+    Group,
+    WaitForInput,
+}
+
+/// WaitForInputキューが待機するトリガー
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum TriggerSource {
+    MidiNote {
+        channel: u8, // 1〜16
+        note: u8,    // 0〜127
+    },
+    Osc {
+        address: String, // "/"から始まるOSCアドレス
+    },
+}
+
+impl TriggerSource {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            TriggerSource::MidiNote { channel, note } => {
+                if !(1..=16).contains(channel) {
+                    return Err(format!("MIDI channel must be between 1 and 16, but got {}.", channel));
+                }
+                if *note > 127 {
+                    return Err(format!("MIDI note must be between 0 and 127, but got {}.", note));
+                }
+            }
+            TriggerSource::Osc { address } => {
+                if !address.starts_with('/') {
+                    return Err(format!("OSC address must start with '/', but got '{}'.", address));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct AudioCueLevels {
     pub master: f64, // decibels
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct AudioCueFadeParam {
     pub duration: f64,
-    pub easing: Easing,
+    pub easing: FadeCurve,
+}
+
+/// キューの部分的な変更。省略したフィールドは変更しない
+///
+/// `Option`を値に持つフィールドは、`null`を指定すると値を消去します。
+/// `levels`などの音声のパラメータは、Audioキュー以外には指定できません。
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default, rename_all = "camelCase")]
+pub struct CuePatch {
+    pub number: Option<String>,
+    pub name: Option<String>,
+    pub notes: Option<String>,
+    pub pre_wait: Option<f64>,
+    pub post_wait: Option<f64>,
+    pub sequence: Option<CueSequence>,
+    #[serde(deserialize_with = "deserialize_nullable")]
+    pub follow_offset: Option<Option<f64>>,
+    pub armed: Option<bool>,
+    #[serde(deserialize_with = "deserialize_nullable")]
+    pub color: Option<Option<String>>,
+    #[serde(deserialize_with = "deserialize_nullable")]
+    pub schedule: Option<Option<u64>>,
+    pub levels: Option<AudioCueLevels>,
+    #[serde(deserialize_with = "deserialize_nullable")]
+    pub start_time: Option<Option<f64>>,
+    #[serde(deserialize_with = "deserialize_nullable")]
+    pub end_time: Option<Option<f64>>,
+    #[serde(deserialize_with = "deserialize_nullable")]
+    pub fade_in_param: Option<Option<AudioCueFadeParam>>,
+    #[serde(deserialize_with = "deserialize_nullable")]
+    pub fade_out_param: Option<Option<AudioCueFadeParam>>,
+    #[serde(deserialize_with = "deserialize_nullable")]
+    pub stop_fade_param: Option<Option<AudioCueFadeParam>>,
+    #[serde(deserialize_with = "deserialize_nullable")]
+    pub automation: Option<Option<Vec<(f64, f64)>>>,
+}
+
+/// `null`を`Some(None)`として読み込みます。フィールドが省略された場合は`#[serde(default)]`によりNoneになる
+fn deserialize_nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+impl Cue {
+    /// 保存前にキューのパラメータを検証します。
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(follow_offset) = self.follow_offset
+            && !(follow_offset.is_finite() && follow_offset <= 0.0)
+        {
+            return Err(format!("Follow offset must be zero or negative, but got {}.", follow_offset));
+        }
+        if let Some(color) = &self.color
+            && !is_color_hex(color)
+        {
+            return Err(format!("Cue color must be in #RRGGBB format, but got '{}'.", color));
+        }
+        if let CueParam::Audio { fade_in_param, fade_out_param, stop_fade_param, .. } = &self.param {
+            for fade_param in [fade_in_param, fade_out_param, stop_fade_param].into_iter().flatten() {
+                validate_fade_curve(&fade_param.easing)?;
+            }
+        }
+        if let CueParam::Audio { normalize_to: Some(normalize_to), .. } = &self.param
+            && !(normalize_to.is_finite() && (-70.0..=0.0).contains(normalize_to))
+        {
+            return Err(format!("Normalization target must be between -70 and 0 LUFS, but got {}.", normalize_to));
+        }
+        if let CueParam::Audio { automation: Some(automation), start_time, end_time, .. } = &self.param {
+            validate_automation(automation, end_time.map(|end_time| end_time - start_time.unwrap_or(0.0)))?;
+        }
+        if let CueParam::Audio { output_bus: Some(output_bus), .. } = &self.param
+            && output_bus.is_empty()
+        {
+            return Err("Output bus name must not be empty.".to_string());
+        }
+        if let CueParam::WaitForInput { source } = &self.param {
+            source.validate()?;
+        }
+        Ok(())
+    }
+
+    /// 部分的な変更を適用します。適用できない変更が含まれる場合は何も変更せずにエラーを返します。
+    pub fn apply_patch(&mut self, patch: CuePatch) -> Result<(), String> {
+        let mut patched = self.clone();
+        let audio_patched = patch.levels.is_some()
+            || patch.start_time.is_some()
+            || patch.end_time.is_some()
+            || patch.fade_in_param.is_some()
+            || patch.fade_out_param.is_some()
+            || patch.stop_fade_param.is_some()
+            || patch.automation.is_some();
+        if audio_patched {
+            let CueParam::Audio { levels, start_time, end_time, fade_in_param, fade_out_param, stop_fade_param, automation, .. } = &mut patched.param else {
+                return Err(format!("Audio parameters can't be applied to a {:?} cue.", self.param.kind()));
+            };
+            if let Some(new_levels) = patch.levels {
+                *levels = new_levels;
+            }
+            if let Some(new_start_time) = patch.start_time {
+                *start_time = new_start_time;
+            }
+            if let Some(new_end_time) = patch.end_time {
+                *end_time = new_end_time;
+            }
+            if let Some(new_fade_in_param) = patch.fade_in_param {
+                *fade_in_param = new_fade_in_param;
+            }
+            if let Some(new_fade_out_param) = patch.fade_out_param {
+                *fade_out_param = new_fade_out_param;
+            }
+            if let Some(new_stop_fade_param) = patch.stop_fade_param {
+                *stop_fade_param = new_stop_fade_param;
+            }
+            if let Some(new_automation) = patch.automation {
+                *automation = new_automation;
+            }
+        }
+        if let Some(number) = patch.number {
+            patched.number = number;
+        }
+        if let Some(name) = patch.name {
+            patched.name = name;
+        }
+        if let Some(notes) = patch.notes {
+            patched.notes = notes;
+        }
+        if let Some(pre_wait) = patch.pre_wait {
+            patched.pre_wait = pre_wait;
+        }
+        if let Some(post_wait) = patch.post_wait {
+            patched.post_wait = post_wait;
+        }
+        if let Some(sequence) = patch.sequence {
+            patched.sequence = sequence;
+        }
+        if let Some(follow_offset) = patch.follow_offset {
+            patched.follow_offset = follow_offset;
+        }
+        if let Some(armed) = patch.armed {
+            patched.armed = armed;
+        }
+        if let Some(color) = patch.color {
+            patched.color = color;
+        }
+        if let Some(schedule) = patch.schedule {
+            patched.schedule = schedule;
+        }
+        patched.validate()?;
+        *self = patched;
+        Ok(())
+    }
+
+    /// メモの末尾に、UTCの時刻を付けた1行を追記します。
+    pub fn append_note(&mut self, text: &str, timestamp: SystemTime) -> Result<(), String> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err("Note text is empty.".to_string());
+        }
+        if !self.notes.is_empty() && !self.notes.ends_with('\n') {
+            self.notes.push('\n');
+        }
+        let line = text.lines().map(str::trim).collect::<Vec<_>>().join(" ");
+        self.notes.push_str(&format!("[{}] {}", format_utc_timestamp(timestamp), line));
+        Ok(())
+    }
+}
+
+/// 時刻を"YYYY-MM-DD HH:MM:SS"形式のUTCの文字列にします。
+fn format_utc_timestamp(timestamp: SystemTime) -> String {
+    let secs = timestamp.duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+    let (days, secs_of_day) = (secs / 86400, secs % 86400);
+    // 1970-01-01からの日数をグレゴリオ暦の日付に変換する
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+/// 音量のオートメーションのブレークポイントが時刻順に並び、キューの長さ`duration`(秒)に収まっているかを検証します。
+/// `duration`がNoneの場合は、長さはファイルを読み込むまで分からないため検証しません。
+fn validate_automation(points: &[(f64, f64)], duration: Option<f64>) -> Result<(), String> {
+    let mut last_time = 0.0;
+    for (time, db) in points {
+        if !(time.is_finite() && db.is_finite()) || *time < 0.0 {
+            return Err(format!("Automation breakpoint ({}, {}) must have a non-negative time and a finite level.", time, db));
+        }
+        if *time < last_time {
+            return Err("Automation breakpoints must be sorted by time.".to_string());
+        }
+        if let Some(duration) = duration
+            && *time > duration
+        {
+            return Err(format!("Automation breakpoint at {} seconds exceeds the cue duration ({} seconds).", time, duration));
+        }
+        last_time = *time;
+    }
+    Ok(())
+}
+
+/// "#RRGGBB"形式の色かどうかを返します。
+fn is_color_hex(color: &str) -> bool {
+    color.len() == 7 && color.starts_with('#') && color[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+impl CueParam {
+    pub fn kind(&self) -> CueKind {
+        match self {
+            CueParam::Audio { .. } => CueKind::Audio,
+            CueParam::Wait { .. } => CueKind::Wait,
+            CueParam::Group { .. } => CueKind::Group,
+            CueParam::WaitForInput { .. } => CueKind::WaitForInput,
+        }
+    }
+
+    /// このパラメータが参照している他のキューのIDを返します。
+    pub fn target_cue_ids(&self) -> Vec<Uuid> {
+        match self {
+            CueParam::Group { children } => children.clone(),
+            // Fade/Stop/Startキューが実装されたらここで参照先を返す
+            CueParam::Audio { .. } | CueParam::Wait { .. } | CueParam::WaitForInput { .. } => Vec::new(),
+        }
+    }
 }
@@ -0,0 +1,168 @@
+use kira::Easing;
+use serde::{Deserialize, Serialize};
+
+const POWI_RANGE: EasingParamRange = EasingParamRange { min: 1.0, max: 16.0, integer: true };
+const POWF_RANGE: EasingParamRange = EasingParamRange { min: 0.1, max: 16.0, integer: false };
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct EasingParamRange {
+    pub min: f64,
+    pub max: f64,
+    pub integer: bool,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct EasingKind {
+    pub name: &'static str,
+    pub param: Option<EasingParamRange>,
+}
+
+/// クライアントに提示するイージングの一覧を返します。`name`は`kira::Easing`のシリアライズ名と一致します。
+pub fn supported_easings() -> Vec<EasingKind> {
+    vec![
+        EasingKind { name: "Linear", param: None },
+        EasingKind { name: "InPowi", param: Some(POWI_RANGE) },
+        EasingKind { name: "OutPowi", param: Some(POWI_RANGE) },
+        EasingKind { name: "InOutPowi", param: Some(POWI_RANGE) },
+        EasingKind { name: "InPowf", param: Some(POWF_RANGE) },
+        EasingKind { name: "OutPowf", param: Some(POWF_RANGE) },
+        EasingKind { name: "InOutPowf", param: Some(POWF_RANGE) },
+    ]
+}
+
+/// イージングのパラメータが許容範囲内かを検証します。
+pub fn validate_easing(easing: &Easing) -> Result<(), String> {
+    let (name, value, range) = match *easing {
+        Easing::Linear => return Ok(()),
+        Easing::InPowi(power) => ("InPowi", power as f64, POWI_RANGE),
+        Easing::OutPowi(power) => ("OutPowi", power as f64, POWI_RANGE),
+        Easing::InOutPowi(power) => ("InOutPowi", power as f64, POWI_RANGE),
+        Easing::InPowf(power) => ("InPowf", power, POWF_RANGE),
+        Easing::OutPowf(power) => ("OutPowf", power, POWF_RANGE),
+        Easing::InOutPowf(power) => ("InOutPowf", power, POWF_RANGE),
+    };
+    if (range.min..=range.max).contains(&value) {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} parameter must be between {} and {}, got {}.",
+            name, range.min, range.max, value
+        ))
+    }
+}
+
+/// フェードの変化の仕方。kiraのイージング、または折れ線で指定します。
+///
+/// 既存のショーファイルとの互換性のため、イージングはタグを付けずにそのままシリアライズします。
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+pub enum FadeCurve {
+    Easing(#[cfg_attr(feature = "schema", schemars(with = "crate::schema::EasingDef"))] Easing),
+    /// (フェード時間に対する位置, 変化量)の折れ線。どちらも0.0〜1.0で、変化量は振幅の割合
+    /// 始点(0.0, 0.0)と終点(1.0, 1.0)は省略できます。点の間は直線で変化します。
+    Breakpoints(Vec<(f64, f64)>),
+}
+
+impl Default for FadeCurve {
+    fn default() -> Self {
+        FadeCurve::Easing(Easing::Linear)
+    }
+}
+
+impl From<Easing> for FadeCurve {
+    fn from(easing: Easing) -> Self {
+        FadeCurve::Easing(easing)
+    }
+}
+
+impl FadeCurve {
+    /// kiraのTweenで使用するイージングを返します。折れ線は近似として直線になります。
+    pub fn easing(&self) -> Easing {
+        match self {
+            FadeCurve::Easing(easing) => *easing,
+            FadeCurve::Breakpoints(_) => Easing::Linear,
+        }
+    }
+
+    /// 始点と終点を補った折れ線を返します。イージングの場合はNoneを返します。
+    pub fn breakpoints(&self) -> Option<Vec<(f64, f64)>> {
+        let FadeCurve::Breakpoints(points) = self else {
+            return None;
+        };
+        let mut points = points.clone();
+        if points.first().is_none_or(|(time, _)| *time > 0.0) {
+            points.insert(0, (0.0, 0.0));
+        }
+        if points.last().is_some_and(|(time, _)| *time < 1.0) {
+            points.push((1.0, 1.0));
+        }
+        Some(points)
+    }
+}
+
+/// フェードの変化の仕方が有効かを検証します。
+pub fn validate_fade_curve(curve: &FadeCurve) -> Result<(), String> {
+    let points = match curve {
+        FadeCurve::Easing(easing) => return validate_easing(easing),
+        FadeCurve::Breakpoints(points) => points,
+    };
+    let mut last_time = 0.0;
+    for (time, amount) in points {
+        if !(0.0..=1.0).contains(time) || !(0.0..=1.0).contains(amount) {
+            return Err(format!("Fade breakpoint ({}, {}) must be between 0.0 and 1.0.", time, amount));
+        }
+        if *time < last_time {
+            return Err("Fade breakpoints must be sorted by time.".to_string());
+        }
+        last_time = *time;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn easing_list() {
+        let names: Vec<&str> = supported_easings().iter().map(|kind| kind.name).collect();
+        assert_eq!(names, vec!["Linear", "InPowi", "OutPowi", "InOutPowi", "InPowf", "OutPowf", "InOutPowf"]);
+        assert_eq!(supported_easings()[0].param, None);
+        assert_eq!(supported_easings()[1].param, Some(EasingParamRange { min: 1.0, max: 16.0, integer: true }));
+    }
+
+    #[test]
+    fn easing_validation() {
+        assert!(validate_easing(&Easing::Linear).is_ok());
+        assert!(validate_easing(&Easing::InPowi(2)).is_ok());
+        assert!(validate_easing(&Easing::OutPowf(0.5)).is_ok());
+        assert!(validate_easing(&Easing::InPowi(0)).is_err());
+        assert!(validate_easing(&Easing::InOutPowf(-1.0)).is_err());
+        assert!(validate_easing(&Easing::InOutPowf(f64::NAN)).is_err());
+    }
+
+    #[test]
+    fn fade_curve_serialization() {
+        // イージングは以前と同じ形式で読み書きする
+        let curve: FadeCurve = serde_json::from_str(r#"{"InPowi":2}"#).unwrap();
+        assert_eq!(curve, FadeCurve::Easing(Easing::InPowi(2)));
+        assert_eq!(serde_json::to_string(&FadeCurve::Easing(Easing::Linear)).unwrap(), r#""Linear""#);
+
+        let curve: FadeCurve = serde_json::from_str("[[0.5,0.8]]").unwrap();
+        assert_eq!(curve, FadeCurve::Breakpoints(vec![(0.5, 0.8)]));
+        assert_eq!(curve.breakpoints(), Some(vec![(0.0, 0.0), (0.5, 0.8), (1.0, 1.0)]));
+    }
+
+    #[test]
+    fn fade_curve_validation() {
+        assert!(validate_fade_curve(&FadeCurve::Breakpoints(vec![(0.2, 0.5), (0.6, 0.9)])).is_ok());
+        assert!(validate_fade_curve(&FadeCurve::Breakpoints(vec![(0.6, 0.5), (0.2, 0.9)])).is_err());
+        assert!(validate_fade_curve(&FadeCurve::Breakpoints(vec![(0.5, 1.5)])).is_err());
+        assert!(validate_fade_curve(&FadeCurve::Easing(Easing::InPowi(0))).is_err());
+    }
+}
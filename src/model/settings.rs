@@ -1,12 +1,229 @@
+use std::{collections::BTreeMap, net::SocketAddr, path::PathBuf};
+
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::model::{cue::{AudioCueFadeParam, AudioCueLevels, Cue, CueParam, CueSequence, RetriggerMode}, easing::validate_fade_curve};
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct ShowSettings {
+    #[serde(default)]
     pub general: GeneralSettings,
-    // TODO Templates, Audio, Network, MIDI, OSC, Video settings
+    #[serde(default)]
+    pub osc: OscSettings,
+    #[serde(default)]
+    pub midi: MidiSettings,
+    #[serde(default)]
+    pub templates: BTreeMap<String, CueTemplate>, // テンプレート名とオーディオキューの既定値の対応
+    // TODO Audio, Network, Video settings
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default, rename_all = "camelCase")]
+pub struct GeneralSettings {
+    pub output_device: Option<String>, // 出力デバイス名。Noneの場合はシステムの既定のデバイス
+    pub default_fade_in_param: Option<AudioCueFadeParam>,
+    pub default_fade_out_param: Option<AudioCueFadeParam>,
+    pub default_stop_fade_param: Option<AudioCueFadeParam>, // 停止コマンドで止める際のフェード
+    pub default_pre_wait: f64,
+    pub default_post_wait: f64,
+    pub auto_save_interval: Option<f64>, // 自動保存の間隔(秒)。Noneの場合は自動保存しない
+    pub max_voices: Option<usize>,       // 同時に再生できるサウンドの数。超えた場合は最も古いサウンドを停止する。Noneの場合は制限しない
+    pub missed_schedule_policy: MissedSchedulePolicy,
+}
+
+/// 読み込み時や設定時に、キューの実行時刻が既に過ぎていた場合の扱い
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum MissedSchedulePolicy {
+    #[default]
+    Skip,            // 実行しない
+    FireImmediately, // すぐに実行する
+}
+
+/// オーディオキューを追加する際に使用する既定値
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default, rename_all = "camelCase")]
+pub struct CueTemplate {
+    pub pre_wait: Option<f64>,  // Noneの場合はショー設定の既定値を使う
+    pub post_wait: Option<f64>, // Noneの場合はショー設定の既定値を使う
+    pub sequence: CueSequence,
+    pub fade_in_param: Option<AudioCueFadeParam>,
+    pub fade_out_param: Option<AudioCueFadeParam>,
+    pub stop_fade_param: Option<AudioCueFadeParam>,
+    pub levels: AudioCueLevels,
+}
+
+impl CueTemplate {
+    /// テンプレートの既定値で、`target`を再生するオーディオキューを作成します。キュー名はファイル名から付けます。
+    /// テンプレートで指定していないプリウェイト・ポストウェイトは`general`の既定値を使います。
+    pub fn to_cue(&self, target: PathBuf, general: &GeneralSettings) -> Cue {
+        Cue {
+            id: Uuid::new_v4(),
+            number: String::new(),
+            name: target.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default(),
+            notes: String::new(),
+            pre_wait: self.pre_wait.unwrap_or(general.default_pre_wait),
+            post_wait: self.post_wait.unwrap_or(general.default_post_wait),
+            sequence: self.sequence.clone(),
+            follow_offset: None,
+            armed: true,
+            retrigger_mode: RetriggerMode::default(),
+            color: None,
+            order: 0.0, // 追加する位置で決める
+            schedule: None,
+            param: CueParam::Audio {
+                target,
+                start_time: None,
+                fade_in_param: self.fade_in_param.clone(),
+                end_time: None,
+                fade_out_param: self.fade_out_param.clone(),
+                levels: self.levels.clone(),
+                loop_region: None,
+                loop_count: None,
+                stop_fade_param: self.stop_fade_param.clone(),
+                output_bus: None,
+                streaming: false,
+                normalize_to: None,
+                automation: None,
+            },
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+/// OSCで受信したアドレスに対して実行する操作
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
-pub struct GeneralSettings {}
+pub enum OscAction {
+    Go,
+    StopAll,
+    Panic,
+    HoldAudio,
+    ReleaseAudio,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default, rename_all = "camelCase")]
+pub struct OscSettings {
+    pub enabled: bool, // trueの場合、OSCでショーを操作できる
+    pub bind_address: SocketAddr,
+    pub address_map: BTreeMap<String, OscAction>, // OSCアドレスと実行する操作の対応
+}
+
+impl Default for OscSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind_address: SocketAddr::from(([0, 0, 0, 0], 53000)),
+            address_map: BTreeMap::from([
+                ("/sbsp/go".to_string(), OscAction::Go),
+                ("/sbsp/stop".to_string(), OscAction::StopAll),
+            ]),
+        }
+    }
+}
+
+/// MIDI Show Controlでオールコールを表すデバイスID
+pub const MSC_ALL_CALL_DEVICE_ID: u8 = 0x7F;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default, rename_all = "camelCase")]
+pub struct MidiSettings {
+    pub enabled: bool,                // trueの場合、MIDIでショーを操作できる
+    pub input_device: Option<String>, // 入力デバイス名。Noneの場合は最初に見つかったデバイス
+    pub msc_device_id: u8,            // MIDI Show Controlで応答するデバイスID(0〜126)。オールコールには常に応答する
+}
+
+impl ShowSettings {
+    /// 保存前に設定値を検証します。
+    pub fn validate(&self) -> Result<(), String> {
+        let general = &self.general;
+        let template_fade_params = self
+            .templates
+            .values()
+            .flat_map(|template| [&template.fade_in_param, &template.fade_out_param, &template.stop_fade_param]);
+        for fade_param in [&general.default_fade_in_param, &general.default_fade_out_param, &general.default_stop_fade_param]
+            .into_iter()
+            .chain(template_fade_params)
+            .flatten()
+        {
+            if fade_param.duration < 0.0 {
+                return Err(format!("Fade duration must not be negative: {}", fade_param.duration));
+            }
+            validate_fade_curve(&fade_param.easing)?;
+        }
+        if general.default_pre_wait < 0.0 || general.default_post_wait < 0.0 {
+            return Err("Default pre/post wait must not be negative.".to_string());
+        }
+        if let Some(interval) = general.auto_save_interval
+            && !(interval.is_finite() && interval > 0.0)
+        {
+            return Err(format!("Auto-save interval must be positive, but got {}.", interval));
+        }
+        if general.max_voices == Some(0) {
+            return Err("Max voices must be at least 1.".to_string());
+        }
+        if self.midi.msc_device_id >= MSC_ALL_CALL_DEVICE_ID {
+            return Err(format!("MSC device ID must be between 0 and 126, but got {}.", self.midi.msc_device_id));
+        }
+        if let Some(address) = self.osc.address_map.keys().find(|address| !address.starts_with('/')) {
+            return Err(format!("OSC address must start with '/', but got '{}'.", address));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kira::Easing;
+
+    use super::*;
+
+    #[test]
+    fn load_without_general_fields() {
+        let settings: ShowSettings = serde_json::from_str(r#"{"general":{}}"#).unwrap();
+        assert_eq!(settings, ShowSettings::default());
+        let settings: ShowSettings = serde_json::from_str(r#"{}"#).unwrap();
+        assert_eq!(settings, ShowSettings::default());
+    }
+
+    #[test]
+    fn validate() {
+        let mut settings = ShowSettings::default();
+        assert!(settings.validate().is_ok());
+
+        settings.general.default_fade_in_param = Some(AudioCueFadeParam { duration: 2.0, easing: Easing::InPowi(0).into() });
+        assert!(settings.validate().is_err());
+
+        settings.general.default_fade_in_param = Some(AudioCueFadeParam { duration: 2.0, easing: Easing::Linear.into() });
+        settings.general.default_post_wait = -1.0;
+        assert!(settings.validate().is_err());
+
+        settings.general.default_post_wait = 0.0;
+        settings.general.auto_save_interval = Some(0.0);
+        assert!(settings.validate().is_err());
+
+        settings.general.auto_save_interval = Some(60.0);
+        assert!(settings.validate().is_ok());
+        settings.general.max_voices = Some(0);
+        assert!(settings.validate().is_err());
+
+        settings.general.max_voices = Some(8);
+        assert!(settings.validate().is_ok());
+        settings.osc.address_map.insert("sbsp/go".to_string(), OscAction::Go);
+        assert!(settings.validate().is_err());
+
+        settings.osc.address_map.clear();
+        let template = CueTemplate { fade_out_param: Some(AudioCueFadeParam { duration: -1.0, easing: Easing::Linear.into() }), ..Default::default() };
+        settings.templates.insert("SFX".to_string(), template);
+        assert!(settings.validate().is_err());
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::model::cue::{Cue, CueParam, CueSequence};
+
+/// 手動のGOを待つため開始時刻が決まらないキューの表示
+pub const MANUAL_START_LABEL: &str = "—";
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CueSheetEntry {
+    pub cue_id: Uuid,
+    pub number: String,
+    pub name: String,
+    pub absolute_start: Option<f64>, // ショー開始(最初のGO)からの秒数。手動のGOを待つ場合はNone
+    pub start_label: String,         // "mm:ss.ss"形式。開始時刻が決まらない場合は"—"
+}
+
+/// キューリストから、各キューのショー開始からの開始時刻を計算したキューシートを作成します。
+///
+/// 最初のキューをショー開始時刻とし、AutoContinue/AutoFollowで続くキューのみ開始時刻が決まります。
+/// 手動のGOを待つキューと、長さが決まらないキューにAutoFollowで続くキューは"—"になります。
+/// 無効化されたキューは実行時に飛ばされるため"—"とし、次のキューがその開始時刻を引き継ぎます。
+pub fn cue_sheet(cues: &[Cue]) -> Vec<CueSheetEntry> {
+    let mut entries = Vec::with_capacity(cues.len());
+    let mut next_start = Some(0.0);
+    for cue in cues {
+        if !cue.armed {
+            entries.push(CueSheetEntry {
+                cue_id: cue.id,
+                number: cue.number.clone(),
+                name: cue.name.clone(),
+                absolute_start: None,
+                start_label: MANUAL_START_LABEL.to_string(),
+            });
+            continue;
+        }
+        let start = next_start;
+        entries.push(CueSheetEntry {
+            cue_id: cue.id,
+            number: cue.number.clone(),
+            name: cue.name.clone(),
+            absolute_start: start,
+            start_label: start.map_or_else(|| MANUAL_START_LABEL.to_string(), format_time),
+        });
+        next_start = match cue.sequence {
+            CueSequence::DoNotContinue => None,
+            CueSequence::AutoContinue => start.map(|start| start + cue.pre_wait + cue.post_wait),
+            CueSequence::AutoFollow => start
+                .zip(action_duration(cue, cues, &mut HashSet::new()))
+                .map(|(start, duration)| {
+                    // follow_offsetが指定されている場合は、終了前の位置で次のキューが始まる。
+                    // 再生位置を通知するのはオーディオキューだけのため、他のキューは終了時に次のキューが始まる
+                    let follow_offset = match cue.param {
+                        CueParam::Audio { .. } => cue.follow_offset.unwrap_or(0.0),
+                        _ => 0.0,
+                    };
+                    let follow_at = (duration + follow_offset).max(0.0);
+                    start + cue.pre_wait + follow_at + cue.post_wait
+                }),
+        };
+    }
+    entries
+}
+
+/// キューの動作(pre_waitを除く)の長さを返します。ファイルの長さや無限ループ、外部からのトリガーに依存する場合はNoneを返します。
+fn action_duration(cue: &Cue, cues: &[Cue], visited: &mut HashSet<Uuid>) -> Option<f64> {
+    if !visited.insert(cue.id) {
+        return None;
+    }
+    match &cue.param {
+        CueParam::Audio { start_time, end_time, loop_region, .. } => {
+            if loop_region.is_some() {
+                return None;
+            }
+            end_time.map(|end_time| end_time - start_time.unwrap_or(0.0))
+        }
+        CueParam::Wait { duration } => Some(*duration),
+        CueParam::WaitForInput { .. } => None,
+        CueParam::Group { children } => {
+            let mut longest: f64 = 0.0;
+            for child_id in children {
+                let child = cues.iter().find(|c| c.id == *child_id)?;
+                longest = longest.max(child.pre_wait + action_duration(child, cues, visited)?);
+            }
+            Some(longest)
+        }
+    }
+}
+
+fn format_time(seconds: f64) -> String {
+    let minutes = (seconds / 60.0).floor();
+    format!("{:02}:{:05.2}", minutes as u64, seconds - minutes * 60.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::cue::RetriggerMode;
+
+    use super::*;
+
+    fn make_cue(number: &str, sequence: CueSequence, pre_wait: f64, post_wait: f64, param: CueParam) -> Cue {
+        Cue {
+            id: Uuid::new_v4(),
+            number: number.to_string(),
+            name: "".to_string(),
+            notes: "".to_string(),
+            pre_wait,
+            post_wait,
+            sequence,
+            follow_offset: None,
+            armed: true,
+            retrigger_mode: RetriggerMode::Restart,
+            color: None,
+            order: 0.0,
+            schedule: None,
+            param,
+        }
+    }
+
+    #[test]
+    fn auto_continue_chain() {
+        let cues = vec![
+            make_cue("1", CueSequence::AutoContinue, 0.0, 2.0, CueParam::Wait { duration: 10.0 }),
+            make_cue("2", CueSequence::AutoContinue, 1.5, 3.0, CueParam::Wait { duration: 10.0 }),
+            make_cue("3", CueSequence::AutoFollow, 0.5, 1.0, CueParam::Wait { duration: 60.0 }),
+            make_cue("4", CueSequence::DoNotContinue, 0.0, 0.0, CueParam::Wait { duration: 1.0 }),
+            make_cue("5", CueSequence::AutoContinue, 0.0, 0.0, CueParam::Wait { duration: 1.0 }),
+        ];
+
+        let sheet = cue_sheet(&cues);
+
+        let starts: Vec<Option<f64>> = sheet.iter().map(|entry| entry.absolute_start).collect();
+        assert_eq!(starts, vec![Some(0.0), Some(2.0), Some(6.5), Some(68.0), None]);
+        let labels: Vec<&str> = sheet.iter().map(|entry| entry.start_label.as_str()).collect();
+        assert_eq!(labels, vec!["00:00.00", "00:02.00", "00:06.50", "01:08.00", MANUAL_START_LABEL]);
+    }
+
+    fn audio_param(end_time: Option<f64>) -> CueParam {
+        CueParam::Audio {
+            target: "./I.G.Y.flac".into(),
+            start_time: None,
+            fade_in_param: None,
+            end_time,
+            fade_out_param: None,
+            levels: crate::model::cue::AudioCueLevels { master: 0.0 },
+            loop_region: None,
+            loop_count: None,
+            stop_fade_param: None,
+            output_bus: None,
+            streaming: false,
+            normalize_to: None,
+            automation: None,
+        }
+    }
+
+    #[test]
+    fn auto_follow_unknown_duration() {
+        let cues = vec![
+            make_cue("1", CueSequence::AutoFollow, 0.0, 0.0, audio_param(None)),
+            make_cue("2", CueSequence::DoNotContinue, 0.0, 0.0, CueParam::Wait { duration: 1.0 }),
+        ];
+
+        let sheet = cue_sheet(&cues);
+
+        assert_eq!(sheet[0].absolute_start, Some(0.0));
+        assert_eq!(sheet[1].absolute_start, None);
+    }
+
+    #[test]
+    fn auto_follow_group() {
+        let first = make_cue("1.1", CueSequence::DoNotContinue, 0.0, 0.0, CueParam::Wait { duration: 3.0 });
+        let second = make_cue("1.2", CueSequence::DoNotContinue, 2.0, 0.0, CueParam::Wait { duration: 4.0 });
+        let group = make_cue("1", CueSequence::AutoFollow, 0.0, 0.0, CueParam::Group { children: vec![first.id, second.id] });
+        let next = make_cue("2", CueSequence::DoNotContinue, 0.0, 0.0, CueParam::Wait { duration: 1.0 });
+        let next_id = next.id;
+
+        let sheet = cue_sheet(&[group, next, first, second]);
+
+        let entry = sheet.iter().find(|entry| entry.cue_id == next_id).unwrap();
+        assert_eq!(entry.absolute_start, Some(6.0));
+    }
+
+    #[test]
+    fn auto_follow_offset() {
+        let mut first = make_cue("1", CueSequence::AutoFollow, 0.0, 0.0, audio_param(Some(10.0)));
+        first.follow_offset = Some(-3.0);
+        let mut second = make_cue("2", CueSequence::AutoFollow, 0.0, 0.0, audio_param(Some(2.0)));
+        second.follow_offset = Some(-5.0);
+        // 再生位置を通知しないキューのfollow_offsetは使われない
+        let mut third = make_cue("3", CueSequence::AutoFollow, 0.0, 0.0, CueParam::Wait { duration: 4.0 });
+        third.follow_offset = Some(-1.0);
+        let fourth = make_cue("4", CueSequence::DoNotContinue, 0.0, 0.0, CueParam::Wait { duration: 1.0 });
+
+        let sheet = cue_sheet(&[first, second, third, fourth]);
+
+        let starts: Vec<Option<f64>> = sheet.iter().map(|entry| entry.absolute_start).collect();
+        assert_eq!(starts, vec![Some(0.0), Some(7.0), Some(7.0), Some(11.0)]);
+    }
+
+    #[test]
+    fn disarmed_cue_is_skipped() {
+        let first = make_cue("1", CueSequence::AutoContinue, 0.0, 2.0, CueParam::Wait { duration: 10.0 });
+        let mut disarmed = make_cue("2", CueSequence::DoNotContinue, 0.0, 0.0, CueParam::Wait { duration: 1.0 });
+        disarmed.armed = false;
+        let third = make_cue("3", CueSequence::DoNotContinue, 0.0, 0.0, CueParam::Wait { duration: 1.0 });
+
+        let sheet = cue_sheet(&[first, disarmed, third]);
+
+        let starts: Vec<Option<f64>> = sheet.iter().map(|entry| entry.absolute_start).collect();
+        assert_eq!(starts, vec![Some(0.0), None, Some(2.0)]);
+        assert_eq!(sheet[1].start_label, MANUAL_START_LABEL);
+    }
+}
@@ -0,0 +1,197 @@
+use rosc::{OscPacket, decoder::decode_udp};
+use tokio::{net::UdpSocket, sync::{broadcast, mpsc}};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    controller::ControllerCommand,
+    event::UiEvent,
+    input::{InputSource, InputTrigger, ReplayGuard},
+    manager::ShowModelHandle,
+    model::settings::{OscAction, OscSettings},
+};
+
+/// 受信するOSCパケットの最大サイズ
+const MAX_PACKET_SIZE: usize = 65536;
+
+impl From<OscAction> for ControllerCommand {
+    fn from(action: OscAction) -> Self {
+        match action {
+            OscAction::Go => ControllerCommand::Go,
+            OscAction::StopAll => ControllerCommand::StopAll,
+            OscAction::Panic => ControllerCommand::Panic,
+            OscAction::HoldAudio => ControllerCommand::HoldAudio,
+            OscAction::ReleaseAudio => ControllerCommand::ReleaseAudio,
+        }
+    }
+}
+
+/// OSCメッセージを受信し、ショー設定の対応に従ってCueControllerにコマンドを送信します。
+///
+/// 受信したメッセージはWaitForInputキューのトリガーとしても転送します。
+/// 待ち受けアドレスはショー設定から取得し、設定が変更された場合は待ち受け直します。
+pub struct OscListener {
+    model_handle: ShowModelHandle,
+    controller_tx: mpsc::Sender<ControllerCommand>,
+    input_trigger_tx: broadcast::Sender<InputTrigger>,
+    event_rx: broadcast::Receiver<UiEvent>,
+    replay_guard: ReplayGuard,
+}
+
+impl OscListener {
+    pub fn new(
+        model_handle: ShowModelHandle,
+        controller_tx: mpsc::Sender<ControllerCommand>,
+        input_trigger_tx: broadcast::Sender<InputTrigger>,
+        event_rx: broadcast::Receiver<UiEvent>,
+    ) -> Self {
+        Self {
+            model_handle,
+            controller_tx,
+            input_trigger_tx,
+            event_rx,
+            replay_guard: ReplayGuard::new(),
+        }
+    }
+
+    pub async fn run(mut self, cancel_token: CancellationToken) {
+        log::info!("OscListener run loop started.");
+        let mut settings = self.model_handle.get_settings().await.osc;
+        let mut buf = vec![0; MAX_PACKET_SIZE];
+        'bind: loop {
+            let socket = if settings.enabled {
+                match UdpSocket::bind(settings.bind_address).await {
+                    Ok(socket) => {
+                        log::info!("OscListener listening on {}", settings.bind_address);
+                        Some(socket)
+                    }
+                    Err(e) => {
+                        log::error!("Failed to bind OSC listener to {}: {:?}", settings.bind_address, e);
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+            loop {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => break 'bind,
+                    result = self.event_rx.recv() => match result {
+                        Ok(UiEvent::SettingsUpdated { .. } | UiEvent::BatchApplied { .. } | UiEvent::ShowModelLoaded { .. } | UiEvent::ShowModelCreated)
+                        | Err(broadcast::error::RecvError::Lagged(_)) => {
+                            let new_settings = self.model_handle.get_settings().await.osc;
+                            if new_settings != settings {
+                                settings = new_settings;
+                                continue 'bind;
+                            }
+                        }
+                        Ok(_) => {}
+                        Err(broadcast::error::RecvError::Closed) => break 'bind,
+                    },
+                    result = async {
+                        match &socket {
+                            Some(socket) => socket.recv_from(&mut buf).await,
+                            None => std::future::pending().await,
+                        }
+                    } => match result {
+                        Ok((len, _)) => self.handle_packet(&buf[..len], &settings).await,
+                        Err(e) => log::error!("Failed to receive OSC packet: {:?}", e),
+                    },
+                }
+            }
+        }
+        log::info!("OscListener run loop finished.");
+    }
+
+    async fn handle_packet(&self, packet: &[u8], settings: &OscSettings) {
+        let packet = match decode_udp(packet) {
+            Ok((_, packet)) => packet,
+            Err(e) => {
+                log::warn!("Ignored malformed OSC packet: {:?}", e);
+                return;
+            }
+        };
+        // バンドルに含まれるメッセージは順に処理する
+        let mut packets = vec![packet];
+        while let Some(packet) = packets.pop() {
+            match packet {
+                OscPacket::Message(message) => self.handle_address(&message.addr, settings).await,
+                OscPacket::Bundle(bundle) => packets.extend(bundle.content.into_iter().rev()),
+            }
+        }
+    }
+
+    async fn handle_address(&self, address: &str, settings: &OscSettings) {
+        if self.input_trigger_tx.send(InputTrigger::Osc { address: address.to_string() }).is_err() {
+            log::trace!("No WaitForInput cues are listening to input triggers.");
+        }
+        let Some(action) = settings.address_map.get(address) else {
+            log::info!("Ignored unmapped OSC address '{}'.", address);
+            return;
+        };
+        let command = ControllerCommand::from(*action);
+        if !self.replay_guard.check(InputSource::Osc, &command) {
+            return;
+        }
+        if let Err(e) = self.controller_tx.send(command).await {
+            log::error!("Failed to send OSC command to CueController: {:?}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rosc::{OscBundle, OscMessage, OscTime, encoder::encode};
+
+    use crate::manager::ShowModelManager;
+
+    use super::*;
+
+    fn message(address: &str) -> OscPacket {
+        OscPacket::Message(OscMessage { addr: address.to_string(), args: vec![] })
+    }
+
+    #[tokio::test]
+    async fn maps_addresses_to_commands() {
+        let (event_tx, event_rx) = broadcast::channel::<UiEvent>(32);
+        let (controller_tx, mut controller_rx) = mpsc::channel::<ControllerCommand>(32);
+        let (input_trigger_tx, mut input_trigger_rx) = broadcast::channel::<InputTrigger>(32);
+        let (manager, handle) = ShowModelManager::new(event_tx.clone());
+        // 空いているポートを使う
+        let bind_address = UdpSocket::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        manager
+            .write_with(|model| {
+                model.settings.osc.enabled = true;
+                model.settings.osc.bind_address = bind_address;
+            })
+            .await;
+        let cancel_token = CancellationToken::new();
+        tokio::spawn(OscListener::new(handle, controller_tx, input_trigger_tx, event_rx).run(cancel_token.clone()));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let bundle = OscPacket::Bundle(OscBundle {
+            timetag: OscTime { seconds: 0, fractional: 1 },
+            content: vec![message("/unmapped"), message("/sbsp/stop")],
+        });
+        socket.send_to(&encode(&message("/sbsp/go")).unwrap(), bind_address).await.unwrap();
+        socket.send_to(&encode(&bundle).unwrap(), bind_address).await.unwrap();
+
+        assert!(matches!(controller_rx.recv().await, Some(ControllerCommand::Go)));
+        assert!(matches!(controller_rx.recv().await, Some(ControllerCommand::StopAll)));
+        // 対応のないアドレスもWaitForInputキューのトリガーとして転送する
+        for address in ["/sbsp/go", "/unmapped", "/sbsp/stop"] {
+            assert_eq!(input_trigger_rx.recv().await.unwrap(), InputTrigger::Osc { address: address.to_string() });
+        }
+
+        // 設定で無効にすると待ち受けを終了する
+        let mut settings = manager.read().await.settings.clone();
+        settings.osc.enabled = false;
+        manager.write_with(|model| model.settings = settings.clone()).await;
+        event_tx.send(UiEvent::SettingsUpdated { settings }).unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(UdpSocket::bind(bind_address).await.is_ok());
+        cancel_token.cancel();
+    }
+}
@@ -0,0 +1,54 @@
+//! APIの型のJSON Schemaを書き出します。`schema`フィーチャーが有効な場合のみ使用できます。
+
+use std::path::Path;
+
+use anyhow::Context;
+use schemars::{JsonSchema, schema_for};
+
+/// `kira::Easing`のスキーマ。kiraのシリアライズ形式と一致させる
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+#[schemars(rename = "Easing")]
+pub enum EasingDef {
+    Linear,
+    InPowi(i32),
+    OutPowi(i32),
+    InOutPowi(i32),
+    InPowf(f64),
+    OutPowf(f64),
+    InOutPowf(f64),
+}
+
+/// `kira::sound::PlaybackPosition`のスキーマ
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+#[schemars(rename = "PlaybackPosition")]
+pub enum PlaybackPositionDef {
+    Seconds(f64),
+    Samples(usize),
+}
+
+/// `kira::sound::EndPosition`のスキーマ
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+#[schemars(rename = "EndPosition")]
+pub enum EndPositionDef {
+    EndOfAudio,
+    Custom(PlaybackPositionDef),
+}
+
+/// `kira::sound::Region`のスキーマ
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+#[schemars(rename = "Region")]
+pub struct RegionDef {
+    pub start: PlaybackPositionDef,
+    pub end: EndPositionDef,
+}
+
+/// 型のスキーマを`dir`に`<name>.json`として書き出します。
+pub fn write_schema<T: JsonSchema>(dir: &Path, name: &str) -> anyhow::Result<()> {
+    let path = dir.join(format!("{}.json", name));
+    let json = serde_json::to_string_pretty(&schema_for!(T))?;
+    std::fs::write(&path, json + "\n").with_context(|| format!("Failed to write schema to {}", path.display()))
+}